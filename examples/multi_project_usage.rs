@@ -36,7 +36,7 @@ async fn main() -> anyhow::Result<()> {
     println!("📁 项目1管理器创建成功: {}", project1_path);
 
     // 测试项目2管理器创建
-    let project2_manager = server.get_or_create_project(project2_path).await
+    let _project2_manager = server.get_or_create_project(project2_path).await
         .map_err(|e| anyhow::anyhow!("创建项目2管理器失败: {:?}", e))?;
     println!("📁 项目2管理器创建成功: {}", project2_path);
 
@@ -0,0 +1,61 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// 注释默认最大长度（字符数）
+pub const DEFAULT_MAX_COMMENT_LENGTH: usize = 64 * 1024;
+
+/// 默认撤销深度
+pub const DEFAULT_UNDO_DEPTH: usize = 20;
+
+/// 每个数据文件默认保留的备份数量
+pub const DEFAULT_BACKUP_COUNT: usize = 1;
+
+/// 项目配置，对应 `.codenexus/config.json`
+///
+/// 文件不存在或缺少某个字段时使用默认值
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ProjectConfig {
+    /// 注释最大长度（字符数）
+    pub max_comment_length: usize,
+    /// 是否监听数据文件的外部修改并自动重新加载
+    pub watch_data_files: bool,
+    /// 每个项目保留的撤销快照数量，0 表示禁用撤销
+    pub undo_depth: usize,
+    /// 严格模式：拒绝类型或取值中包含查询操作符（独立单词 AND、OR、NOT，以及括号、`*`、`?`、`=[`、`>=[`）的标签
+    pub strict_tag_values: bool,
+    /// 每个源文件最多允许的关联关系数量，未设置时不限制
+    pub max_relations_per_file: Option<usize>,
+    /// 添加关联时，若描述与该文件指向其他目标的关联重复则给出警告
+    pub warn_duplicate_relation_descriptions: bool,
+    /// 关联描述必须完整匹配的正则表达式，未设置时只要求非空
+    pub relation_description_pattern: Option<String>,
+    /// 添加标签或注释时记录文件内容哈希，用于 find_stale_metadata（需要读取文件内容）
+    pub track_content_hashes: bool,
+    /// 将每次修改操作追加记录到数据目录下的 `audit.jsonl`
+    pub audit_log: bool,
+    /// 每个数据文件保留的备份数量：`.json.bak` 为最近一次，更早的依次为 `.json.bak.1`、`.json.bak.2`…，0 表示不备份
+    pub backup_count: usize,
+    /// 将规范化后的文件路径键统一转为小写，用于 macOS、Windows 等大小写不敏感的文件系统。
+    /// 在大小写敏感的文件系统上，仅大小写不同的两个真实文件会被视为同一个文件，它们的元数据会混在一起。
+    /// 启用后可用 fold_path_case 工具折叠已有的键
+    pub path_case_fold: bool,
+}
+
+impl Default for ProjectConfig {
+    fn default() -> Self {
+        Self {
+            max_comment_length: DEFAULT_MAX_COMMENT_LENGTH,
+            watch_data_files: false,
+            undo_depth: DEFAULT_UNDO_DEPTH,
+            strict_tag_values: false,
+            max_relations_per_file: None,
+            warn_duplicate_relation_descriptions: true,
+            relation_description_pattern: None,
+            track_content_hashes: false,
+            audit_log: false,
+            backup_count: DEFAULT_BACKUP_COUNT,
+            path_case_fold: false,
+        }
+    }
+}
@@ -1,39 +1,38 @@
+use crate::i18n::{current_locale, Locale, Message};
+use crate::models::{ErrorBody, ErrorResponse};
+use std::fmt;
 use thiserror::Error;
+use tracing::error;
+
+/// 错误响应本身序列化失败时返回的固定响应
+const SERIALIZATION_ERROR_RESPONSE: &str =
+    r#"{"error":{"code":"SERIALIZATION_ERROR","message":"序列化错误响应失败","suggestion":null}}"#;
 
 /// CodeNexus 错误类型
+///
+/// 显示文本和恢复建议按当前语言（见 `crate::i18n`）输出
 #[derive(Error, Debug)]
 pub enum CodeNexusError {
-    #[error("文件不存在: {0}")]
     FileNotFound(String),
-
-    #[error("标签格式错误: {0}，应为 type:value 格式")]
     InvalidTagFormat(String),
-
-    #[error("查询语法错误: {0}")]
-    InvalidQuerySyntax(String),
-
-    #[error("关联关系已存在: {from} -> {to}")]
+    TagSchemaViolation { tag: String, reason: String },
+    ReservedTagValue { tag: String, token: String },
+    InvalidQuerySyntax(Message),
+    QueryParseError { message: Message, position: usize },
+    CommentTooLong { length: usize, limit: usize },
     RelationAlreadyExists { from: String, to: String },
-
-    #[error("关联关系不存在: {from} -> {to}")]
     RelationNotFound { from: String, to: String },
-
-    #[error("标签不存在: {tag} 在文件 {file}")]
+    RelationLimitExceeded { file: String, limit: usize },
+    InvalidRelationDescription { description: String, pattern: String },
     TagNotFound { tag: String, file: String },
-
-    #[error("存储错误: {0}")]
+    NoMetadata(String),
     StorageError(#[from] std::io::Error),
-
-    #[error("JSON 序列化错误: {0}")]
     SerializationError(#[from] serde_json::Error),
-
-    #[error("文件系统错误: {0}")]
+    LockTimeout(String),
     FileSystemError(String),
-
-    #[error("配置错误: {0}")]
-    ConfigError(String),
-
-    #[error("内部错误: {0}")]
+    InvalidDataFile { path: String, key: String, reason: Message },
+    DataFileUnavailable(String),
+    ConfigError(Message),
     InternalError(String),
 }
 
@@ -41,20 +40,112 @@ pub enum CodeNexusError {
 pub type Result<T> = std::result::Result<T, CodeNexusError>;
 
 impl CodeNexusError {
-    /// 获取错误的恢复建议
+    /// 按指定语言生成错误信息
+    pub fn message(&self, locale: Locale) -> String {
+        match locale {
+            Locale::ZhCn => match self {
+                CodeNexusError::FileNotFound(path) => format!("文件不存在: {}", path),
+                CodeNexusError::InvalidTagFormat(tag) => format!("标签格式错误: {}，应为 type:value 格式", tag),
+                CodeNexusError::TagSchemaViolation { tag, reason } => format!("标签不符合标签模式: {}，{}", tag, reason),
+                CodeNexusError::ReservedTagValue { tag, token } => format!("标签包含查询操作符 \"{}\": {}", token, tag),
+                CodeNexusError::InvalidQuerySyntax(message) => format!("查询语法错误: {}", message.text(locale)),
+                CodeNexusError::QueryParseError { message, .. } => format!("查询语法错误: {}", message.text(locale)),
+                CodeNexusError::CommentTooLong { length, limit } => format!("注释过长: {} 个字符，超过上限 {}", length, limit),
+                CodeNexusError::RelationAlreadyExists { from, to } => format!("关联关系已存在: {} -> {}", from, to),
+                CodeNexusError::RelationNotFound { from, to } => format!("关联关系不存在: {} -> {}", from, to),
+                CodeNexusError::RelationLimitExceeded { file, limit } => format!("文件 {} 的关联关系已达上限 {}", file, limit),
+                CodeNexusError::InvalidRelationDescription { description, pattern } => format!("关联描述 \"{}\" 不符合格式 {}", description, pattern),
+                CodeNexusError::TagNotFound { tag, file } => format!("标签不存在: {} 在文件 {}", tag, file),
+                CodeNexusError::NoMetadata(file) => format!("文件 {} 没有可复制的元数据", file),
+                CodeNexusError::StorageError(e) => format!("存储错误: {}", e),
+                CodeNexusError::SerializationError(e) => format!("JSON 序列化错误: {}", e),
+                CodeNexusError::LockTimeout(path) => format!("获取数据目录锁超时: {}", path),
+                CodeNexusError::FileSystemError(message) => format!("文件系统错误: {}", message),
+                CodeNexusError::InvalidDataFile { path, key, reason } => format!("数据文件 {} 中的 {} 无效: {}", path, key, reason.text(locale)),
+                CodeNexusError::DataFileUnavailable(path) => format!("数据文件 {} 加载失败，恢复前不能写入", path),
+                CodeNexusError::ConfigError(message) => format!("配置错误: {}", message.text(locale)),
+                CodeNexusError::InternalError(message) => format!("内部错误: {}", message),
+            },
+            Locale::EnUs => match self {
+                CodeNexusError::FileNotFound(path) => format!("File not found: {}", path),
+                CodeNexusError::InvalidTagFormat(tag) => format!("Invalid tag format: {}, expected type:value", tag),
+                CodeNexusError::TagSchemaViolation { tag, reason } => format!("Tag violates the tag schema: {}, {}", tag, reason),
+                CodeNexusError::ReservedTagValue { tag, token } => format!("Tag contains the query operator \"{}\": {}", token, tag),
+                CodeNexusError::InvalidQuerySyntax(message) => format!("Query syntax error: {}", message.text(locale)),
+                CodeNexusError::QueryParseError { message, .. } => format!("Query syntax error: {}", message.text(locale)),
+                CodeNexusError::CommentTooLong { length, limit } => format!("Comment too long: {} characters, limit is {}", length, limit),
+                CodeNexusError::RelationAlreadyExists { from, to } => format!("Relation already exists: {} -> {}", from, to),
+                CodeNexusError::RelationNotFound { from, to } => format!("Relation not found: {} -> {}", from, to),
+                CodeNexusError::RelationLimitExceeded { file, limit } => format!("File {} already has the maximum of {} relations", file, limit),
+                CodeNexusError::InvalidRelationDescription { description, pattern } => format!("Relation description \"{}\" does not match the pattern {}", description, pattern),
+                CodeNexusError::TagNotFound { tag, file } => format!("Tag not found: {} on file {}", tag, file),
+                CodeNexusError::NoMetadata(file) => format!("File {} has no metadata to copy", file),
+                CodeNexusError::StorageError(e) => format!("Storage error: {}", e),
+                CodeNexusError::SerializationError(e) => format!("JSON serialization error: {}", e),
+                CodeNexusError::LockTimeout(path) => format!("Timed out acquiring data directory lock: {}", path),
+                CodeNexusError::FileSystemError(message) => format!("File system error: {}", message),
+                CodeNexusError::InvalidDataFile { path, key, reason } => format!("Invalid entry {} in data file {}: {}", key, path, reason.text(locale)),
+                CodeNexusError::DataFileUnavailable(path) => format!("Data file {} failed to load and cannot be written until it is recovered", path),
+                CodeNexusError::ConfigError(message) => format!("Configuration error: {}", message.text(locale)),
+                CodeNexusError::InternalError(message) => format!("Internal error: {}", message),
+            },
+        }
+    }
+
+    /// 获取错误的恢复建议（当前语言）
     pub fn recovery_suggestion(&self) -> &'static str {
-        match self {
-            CodeNexusError::FileNotFound(_) => "请检查文件路径是否正确",
-            CodeNexusError::InvalidTagFormat(_) => "请使用 type:value 格式，如 category:api",
-            CodeNexusError::InvalidQuerySyntax(_) => "请检查查询语法，支持 AND、NOT、通配符",
-            CodeNexusError::RelationAlreadyExists { .. } => "关联关系已存在，请先移除再添加",
-            CodeNexusError::RelationNotFound { .. } => "请先添加关联关系",
-            CodeNexusError::TagNotFound { .. } => "请先为文件添加该标签",
-            CodeNexusError::StorageError(_) => "请检查文件权限和磁盘空间",
-            CodeNexusError::SerializationError(_) => "数据格式错误，请检查数据文件",
-            CodeNexusError::FileSystemError(_) => "请检查文件系统权限",
-            CodeNexusError::ConfigError(_) => "请检查配置文件格式",
-            CodeNexusError::InternalError(_) => "请重试或联系技术支持",
+        self.suggestion(current_locale())
+    }
+
+    /// 按指定语言获取错误的恢复建议
+    pub fn suggestion(&self, locale: Locale) -> &'static str {
+        match locale {
+            Locale::ZhCn => match self {
+                CodeNexusError::FileNotFound(_) => "请检查文件路径是否正确",
+                CodeNexusError::InvalidTagFormat(_) => "请使用 type:value 格式，如 category:api",
+                CodeNexusError::TagSchemaViolation { .. } => "请检查 .codenexus/tag_schema.json 中允许的标签类型和取值",
+                CodeNexusError::ReservedTagValue { .. } => "请改写标签，避免使用独立单词 AND、OR、NOT 以及括号、*、?、=[ 和 >=[，或在 .codenexus/config.json 中关闭 strict_tag_values",
+                CodeNexusError::InvalidQuerySyntax(_) => "请检查查询语法，支持 AND、NOT、通配符",
+                CodeNexusError::QueryParseError { .. } => "请检查所示位置附近的括号和 AND、OR、NOT 操作符",
+                CodeNexusError::CommentTooLong { .. } => "请精简注释内容，或在 .codenexus/config.json 中调整 max_comment_length",
+                CodeNexusError::RelationAlreadyExists { .. } => "关联关系已存在，请先移除再添加",
+                CodeNexusError::RelationNotFound { .. } => "请先添加关联关系",
+                CodeNexusError::RelationLimitExceeded { .. } => "请先移除不再需要的关联关系，或在 .codenexus/config.json 中调整 max_relations_per_file",
+                CodeNexusError::InvalidRelationDescription { .. } => "请按 .codenexus/config.json 中 relation_description_pattern 约定的格式填写描述，可用 get_relation_config 查看",
+                CodeNexusError::TagNotFound { .. } => "请先为文件添加该标签",
+                CodeNexusError::NoMetadata(_) => "源文件没有标签、注释或关联关系，请检查源文件路径，或先用 get_file_info 确认其元数据",
+                CodeNexusError::StorageError(_) => "请检查文件权限和磁盘空间",
+                CodeNexusError::SerializationError(_) => "数据格式错误，请检查数据文件",
+                CodeNexusError::LockTimeout(_) => "可能有其他 CodeNexus 进程正在写入该项目的数据，请稍后重试",
+                CodeNexusError::FileSystemError(_) => "请检查文件系统权限",
+                CodeNexusError::InvalidDataFile { .. } => "请修正或删除数据文件中的该条目，也可以从同目录下的 .json.bak 备份恢复",
+                CodeNexusError::DataFileUnavailable(_) => "请使用 recover_from_backup 从 .json.bak 备份恢复，或手动修正文件后调用 reload_project",
+                CodeNexusError::ConfigError(_) => "请检查配置文件格式",
+                CodeNexusError::InternalError(_) => "请重试或联系技术支持",
+            },
+            Locale::EnUs => match self {
+                CodeNexusError::FileNotFound(_) => "Check that the file path is correct and the file exists",
+                CodeNexusError::InvalidTagFormat(_) => "Use the type:value format, e.g. category:api",
+                CodeNexusError::TagSchemaViolation { .. } => "Check the allowed tag types and values in .codenexus/tag_schema.json",
+                CodeNexusError::ReservedTagValue { .. } => "Reword the tag to avoid the words AND, OR, NOT and parentheses, *, ?, =[ and >=[, or disable strict_tag_values in .codenexus/config.json",
+                CodeNexusError::InvalidQuerySyntax(_) => "Check the query syntax; AND, NOT and wildcards are supported",
+                CodeNexusError::QueryParseError { .. } => "Check the parentheses and AND, OR, NOT operators near the reported position",
+                CodeNexusError::CommentTooLong { .. } => "Shorten the comment, or adjust max_comment_length in .codenexus/config.json",
+                CodeNexusError::RelationAlreadyExists { .. } => "The relation already exists; remove it before adding it again",
+                CodeNexusError::RelationNotFound { .. } => "Add the relation first",
+                CodeNexusError::RelationLimitExceeded { .. } => "Remove relations that are no longer needed, or adjust max_relations_per_file in .codenexus/config.json",
+                CodeNexusError::InvalidRelationDescription { .. } => "Write the description in the format set by relation_description_pattern in .codenexus/config.json; use get_relation_config to view it",
+                CodeNexusError::TagNotFound { .. } => "Add the tag to the file first",
+                CodeNexusError::NoMetadata(_) => "The source file has no tags, comment or relations; check the source path, or confirm its metadata with get_file_info",
+                CodeNexusError::StorageError(_) => "Check file permissions and available disk space",
+                CodeNexusError::SerializationError(_) => "Invalid data format; check the data files",
+                CodeNexusError::LockTimeout(_) => "Another CodeNexus process may be writing this project's data; retry later",
+                CodeNexusError::FileSystemError(_) => "Check file system permissions",
+                CodeNexusError::InvalidDataFile { .. } => "Fix or remove the entry in the data file, or restore it from the .json.bak backup next to it",
+                CodeNexusError::DataFileUnavailable(_) => "Use recover_from_backup to restore it from the .json.bak backup, or fix the file manually and call reload_project",
+                CodeNexusError::ConfigError(_) => "Check the configuration file format",
+                CodeNexusError::InternalError(_) => "Retry or contact support",
+            },
         }
     }
 
@@ -63,28 +154,79 @@ impl CodeNexusError {
         match self {
             CodeNexusError::FileNotFound(_) => "FILE_NOT_FOUND",
             CodeNexusError::InvalidTagFormat(_) => "INVALID_TAG_FORMAT",
+            CodeNexusError::TagSchemaViolation { .. } => "TAG_SCHEMA_VIOLATION",
+            CodeNexusError::ReservedTagValue { .. } => "RESERVED_TAG_VALUE",
             CodeNexusError::InvalidQuerySyntax(_) => "INVALID_QUERY_SYNTAX",
+            CodeNexusError::QueryParseError { .. } => "INVALID_QUERY_SYNTAX",
+            CodeNexusError::CommentTooLong { .. } => "COMMENT_TOO_LONG",
             CodeNexusError::RelationAlreadyExists { .. } => "RELATION_ALREADY_EXISTS",
             CodeNexusError::RelationNotFound { .. } => "RELATION_NOT_FOUND",
+            CodeNexusError::RelationLimitExceeded { .. } => "RELATION_LIMIT_EXCEEDED",
+            CodeNexusError::InvalidRelationDescription { .. } => "INVALID_RELATION_DESCRIPTION",
             CodeNexusError::TagNotFound { .. } => "TAG_NOT_FOUND",
+            CodeNexusError::NoMetadata(_) => "NO_METADATA",
             CodeNexusError::StorageError(_) => "STORAGE_ERROR",
             CodeNexusError::SerializationError(_) => "SERIALIZATION_ERROR",
+            CodeNexusError::LockTimeout(_) => "LOCK_TIMEOUT",
             CodeNexusError::FileSystemError(_) => "FILESYSTEM_ERROR",
+            CodeNexusError::InvalidDataFile { .. } => "INVALID_DATA_FILE",
+            CodeNexusError::DataFileUnavailable(_) => "DATA_FILE_UNAVAILABLE",
             CodeNexusError::ConfigError(_) => "CONFIG_ERROR",
             CodeNexusError::InternalError(_) => "INTERNAL_ERROR",
         }
     }
 }
 
+impl fmt::Display for CodeNexusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message(current_locale()))
+    }
+}
+
 /// 格式化错误响应
 pub fn format_error_response(error: &CodeNexusError) -> String {
-    serde_json::json!({
-        "error": {
-            "code": error.error_code(),
-            "message": error.to_string(),
-            "suggestion": error.recovery_suggestion()
-        }
-    }).to_string()
+    let position = match error {
+        CodeNexusError::QueryParseError { position, .. } => Some(*position),
+        _ => None,
+    };
+    to_json(&ErrorResponse {
+        error: ErrorBody {
+            code: error.error_code().to_string(),
+            message: error.to_string(),
+            suggestion: Some(error.recovery_suggestion().to_string()),
+            position,
+        },
+    })
+}
+
+/// 将 MCP ErrorData 格式化为与 `format_error_response` 相同结构的 JSON 错误响应
+///
+/// `data` 中带有 `code`/`suggestion` 时沿用，否则使用 `INTERNAL_ERROR`
+pub fn format_error_data_response(error: &rmcp::model::ErrorData) -> String {
+    let field = |name: &str| {
+        error
+            .data
+            .as_ref()
+            .and_then(|data| data.get(name))
+            .and_then(|value| value.as_str())
+            .map(str::to_string)
+    };
+    to_json(&ErrorResponse {
+        error: ErrorBody {
+            code: field("code").unwrap_or_else(|| "INTERNAL_ERROR".to_string()),
+            message: error.message.to_string(),
+            suggestion: field("suggestion"),
+            position: None,
+        },
+    })
+}
+
+/// 序列化错误响应，失败时记录日志并返回固定的序列化错误响应
+fn to_json(response: &ErrorResponse) -> String {
+    serde_json::to_string(response).unwrap_or_else(|e| {
+        error!("序列化错误响应失败: {}", e);
+        SERIALIZATION_ERROR_RESPONSE.to_string()
+    })
 }
 
 /// 转换为 MCP ErrorData
@@ -99,3 +241,45 @@ impl From<CodeNexusError> for rmcp::model::ErrorData {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_error_data_response() {
+        let plain = rmcp::model::ErrorData::internal_error("创建项目管理器失败", None);
+        let body: serde_json::Value = serde_json::from_str(&format_error_data_response(&plain)).unwrap();
+        assert_eq!(body["error"]["code"], "INTERNAL_ERROR");
+        assert_eq!(body["error"]["message"], "创建项目管理器失败");
+
+        let converted = rmcp::model::ErrorData::from(CodeNexusError::FileNotFound("a.rs".to_string()));
+        let body: serde_json::Value = serde_json::from_str(&format_error_data_response(&converted)).unwrap();
+        assert_eq!(body["error"]["code"], "FILE_NOT_FOUND");
+        assert!(body["error"]["suggestion"].is_string());
+    }
+
+    #[test]
+    fn test_format_error_response_position() {
+        let error = CodeNexusError::QueryParseError { message: Message::UnclosedParenthesis(3), position: 3 };
+        let response: ErrorResponse = serde_json::from_str(&format_error_response(&error)).unwrap();
+        assert_eq!(response.error.code, "INVALID_QUERY_SYNTAX");
+        assert_eq!(response.error.position, Some(3));
+
+        let body: serde_json::Value = serde_json::from_str(&format_error_response(&CodeNexusError::FileNotFound("a.rs".to_string()))).unwrap();
+        assert!(body["error"].get("position").is_none());
+
+        // 序列化失败时的固定响应结构相同
+        let fallback: ErrorResponse = serde_json::from_str(SERIALIZATION_ERROR_RESPONSE).unwrap();
+        assert_eq!(fallback.error.code, "SERIALIZATION_ERROR");
+    }
+
+    #[test]
+    fn test_localized_messages() {
+        let error = CodeNexusError::CommentTooLong { length: 12, limit: 10 };
+        assert_eq!(error.message(Locale::ZhCn), "注释过长: 12 个字符，超过上限 10");
+        assert_eq!(error.message(Locale::EnUs), "Comment too long: 12 characters, limit is 10");
+        assert_eq!(error.suggestion(Locale::EnUs), "Shorten the comment, or adjust max_comment_length in .codenexus/config.json");
+        assert_eq!(Locale::default(), Locale::ZhCn);
+    }
+}
@@ -0,0 +1,373 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+
+/// 语言环境环境变量，取值如 zh-CN、en-US
+const LOCALE_ENV_VAR: &str = "CODE_NEXUS_LOCALE";
+
+/// 用户可见消息的语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    /// 简体中文（默认）
+    #[default]
+    ZhCn,
+    /// 美式英语
+    EnUs,
+}
+
+impl Locale {
+    /// 解析语言标识，忽略大小写，支持 `-`/`_` 分隔及仅语言部分（如 `en`）
+    pub fn parse(value: &str) -> Option<Self> {
+        let normalized = value.trim().to_lowercase().replace('_', "-");
+        // 忽略编码后缀，如 en_US.UTF-8
+        let normalized = normalized.split('.').next().unwrap_or_default();
+        match normalized {
+            "zh" | "zh-cn" | "zh-hans" => Some(Locale::ZhCn),
+            "en" | "en-us" => Some(Locale::EnUs),
+            _ => None,
+        }
+    }
+
+    /// 语言标识
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::ZhCn => "zh-CN",
+            Locale::EnUs => "en-US",
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Locale::EnUs,
+            _ => Locale::ZhCn,
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Locale::ZhCn => 0,
+            Locale::EnUs => 1,
+        }
+    }
+}
+
+/// 当前语言，首次访问时从环境变量读取，运行时可通过 `set_locale` 修改
+static CURRENT_LOCALE: OnceLock<AtomicU8> = OnceLock::new();
+
+fn locale_cell() -> &'static AtomicU8 {
+    CURRENT_LOCALE.get_or_init(|| {
+        let locale = std::env::var(LOCALE_ENV_VAR)
+            .ok()
+            .and_then(|value| Locale::parse(&value))
+            .unwrap_or_default();
+        AtomicU8::new(locale.to_u8())
+    })
+}
+
+/// 获取当前语言
+pub fn current_locale() -> Locale {
+    Locale::from_u8(locale_cell().load(Ordering::Relaxed))
+}
+
+/// 设置当前语言
+pub fn set_locale(locale: Locale) {
+    locale_cell().store(locale.to_u8(), Ordering::Relaxed);
+}
+
+/// 用户可见的消息：工具成功响应、警告、错误说明（如 `CodeNexusError::ConfigError`）以及资源和提示的文本
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    TagsAdded,
+    TagsRemoved,
+    TagsCleared(usize),
+    CommentAdded,
+    CommentUpdated,
+    CommentDeleted,
+    RelationAdded,
+    RelationRemoved,
+    ProjectUnloaded,
+    DebugLoggingEnabled,
+    DebugLoggingDisabled,
+    TagsExported(String),
+    LocaleChanged(Locale),
+    OperationUndone(String),
+    NothingToUndo,
+    // 警告
+    UnclosedCodeBlock(usize),
+    DuplicateRelationDescription { file: String, targets: Vec<String> },
+    // 配置错误
+    EmptyProjectPath,
+    UnexpandableProjectPath { path: String, var: String, cause: String },
+    ProjectPathNotDirectory(String),
+    EmptyFilePath,
+    PathNotFile(String),
+    FileOutsideProject(String),
+    DirectoryOutsideProject(String),
+    EmptyOutputPath,
+    OutputPathOutsideRoot(String),
+    InvalidTimeBound(String),
+    InvalidTagTypePattern { tag_type: String, error: String },
+    MergeNeedsSourceTag,
+    SameTagType,
+    InvalidDescriptionPattern { pattern: String, error: String },
+    EmptyRelationDescription,
+    InvalidRelationWeight(String),
+    EmptyRelationTarget,
+    MalformedTag(String),
+    // 查询语法错误
+    MissingQueryCondition,
+    MissingFilter,
+    InvalidPathGlob { pattern: String, error: String },
+    InvalidWildcardPattern(String),
+    // 标签查询解析错误，均带有出错位置的字符偏移
+    EmptyQuery(usize),
+    UnexpectedQueryToken { token: String, position: usize },
+    UnmatchedRightParen(usize),
+    UnclosedTagSet(usize),
+    EmptyTagInSet(usize),
+    WildcardInTagSet { tag: String, position: usize },
+    MalformedQueryTag { tag: String, position: usize },
+    MissingOperandAfter { operator: String, position: usize },
+    MissingOperandBefore { operator: String, position: usize },
+    UnexpectedQueryEnd(usize),
+    EmptyParentheses(usize),
+    UnclosedParenthesis(usize),
+    EmptyComment,
+    CommentExists(String),
+    InvalidConfigFile { path: String, error: String },
+    InvalidTagSchemaFile { path: String, error: String },
+    ConfigNotObject,
+    UnknownConfigKey(String),
+    InvalidConfig(String),
+    ContentHashesDisabled,
+    UnknownDataFile { file: String, expected: Vec<String> },
+    PathCaseFoldDisabled,
+    ProjectNotLoaded(String),
+    UnsupportedLocale(String),
+    ExactlyOneSource(String),
+    UnknownOutputTool(String),
+    // 资源
+    FileMetadataResourceDescription(String),
+    InvalidResourceUri(String),
+    NoFileMetadata(String),
+    // 提示
+    ProjectPathArgument,
+    FilePathArgument,
+    DocumentFilePromptDescription,
+    DocumentFilePrompt { project_path: String, file_path: String },
+    FindRelatedWorkPromptDescription,
+    FindRelatedWorkPrompt { project_path: String, file_path: String },
+    MissingPromptArgument { prompt: String, argument: String },
+    UnknownPrompt(String),
+}
+
+impl Message {
+    /// 按指定语言生成消息文本
+    pub fn text(&self, locale: Locale) -> String {
+        match locale {
+            Locale::ZhCn => match self {
+                Message::TagsAdded => "标签添加成功".to_string(),
+                Message::TagsRemoved => "标签移除成功".to_string(),
+                Message::TagsCleared(count) => format!("已清除 {} 个标签", count),
+                Message::CommentAdded => "注释添加成功".to_string(),
+                Message::CommentUpdated => "注释更新成功".to_string(),
+                Message::CommentDeleted => "注释删除成功".to_string(),
+                Message::RelationAdded => "关联关系添加成功".to_string(),
+                Message::RelationRemoved => "关联关系移除成功".to_string(),
+                Message::ProjectUnloaded => "项目已卸载".to_string(),
+                Message::DebugLoggingEnabled => "调试日志已启用".to_string(),
+                Message::DebugLoggingDisabled => "调试日志已关闭".to_string(),
+                Message::TagsExported(path) => format!("标签已导出到 {}", path),
+                Message::LocaleChanged(locale) => format!("语言已切换为 {}", locale.as_str()),
+                Message::OperationUndone(operation) => format!("已撤销操作 {}", operation),
+                Message::NothingToUndo => "没有可撤销的操作".to_string(),
+                Message::UnclosedCodeBlock(line) => format!("第 {} 行开始的代码块未闭合", line),
+                Message::DuplicateRelationDescription { file, targets } => format!("描述与已有关联重复: {} -> {}", file, targets.join(", ")),
+                Message::EmptyProjectPath => "项目路径不能为空".to_string(),
+                Message::UnexpandableProjectPath { path, var, cause } => format!("项目路径 {} 中的环境变量 {} 无法展开: {}", path, var, cause),
+                Message::ProjectPathNotDirectory(path) => format!("项目路径必须是目录: {}", path),
+                Message::EmptyFilePath => "文件路径不能为空".to_string(),
+                Message::PathNotFile(path) => format!("路径必须指向文件而不是目录: {}", path),
+                Message::FileOutsideProject(path) => format!("文件路径必须在项目目录内: {}", path),
+                Message::DirectoryOutsideProject(path) => format!("目录路径必须在项目目录内: {}", path),
+                Message::EmptyOutputPath => "输出文件路径不能为空".to_string(),
+                Message::OutputPathOutsideRoot(path) => format!("输出文件路径必须是根目录内的相对路径: {}", path),
+                Message::InvalidTimeBound(value) => format!("无效的时间: {}，应为 YYYY-MM-DD 或 RFC 3339 格式", value),
+                Message::InvalidTagTypePattern { tag_type, error } => format!("标签类型 {} 的正则表达式无效: {}", tag_type, error),
+                Message::MergeNeedsSourceTag => "至少需要一个不同于目标标签的源标签".to_string(),
+                Message::SameTagType => "新标签类型与原标签类型相同".to_string(),
+                Message::InvalidDescriptionPattern { pattern, error } => format!("关联描述格式 {} 的正则表达式无效: {}", pattern, error),
+                Message::EmptyRelationDescription => "关联描述不能为空".to_string(),
+                Message::InvalidRelationWeight(weight) => format!("关联权重必须是非负数: {}", weight),
+                Message::EmptyRelationTarget => "关联目标不能为空".to_string(),
+                Message::MalformedTag(tag) => format!("标签应为 type:value 格式: {}", tag),
+                Message::MissingQueryCondition => "tag_query 和 relation_keyword 至少需要提供一个".to_string(),
+                Message::MissingFilter => "至少需要提供一个过滤条件".to_string(),
+                Message::InvalidPathGlob { pattern, error } => format!("路径 glob 模式无效 {}: {}", pattern, error),
+                Message::InvalidWildcardPattern(pattern) => format!("无效的通配符模式: {}", pattern),
+                Message::EmptyQuery(position) => format!("查询不能为空（位置 {}）", position),
+                Message::UnexpectedQueryToken { token, position } => format!("意外的 \"{}\"（位置 {}）", token, position),
+                Message::UnmatchedRightParen(position) => format!("多余的右括号（位置 {}）", position),
+                Message::UnclosedTagSet(position) => format!("标签集合缺少右方括号 ]（位置 {}）", position),
+                Message::EmptyTagInSet(position) => format!("标签集合中不能有空标签（位置 {}）", position),
+                Message::WildcardInTagSet { tag, position } => format!("标签集合不支持通配符: {}（位置 {}）", tag, position),
+                Message::MalformedQueryTag { tag, position } => format!("标签格式应为 type:value: {}（位置 {}）", tag, position),
+                Message::MissingOperandAfter { operator, position } => format!("{} 操作符后缺少操作数（位置 {}）", operator, position),
+                Message::MissingOperandBefore { operator, position } => format!("{} 操作符前缺少操作数（位置 {}）", operator, position),
+                Message::UnexpectedQueryEnd(position) => format!("查询意外结束，缺少标签（位置 {}）", position),
+                Message::EmptyParentheses(position) => format!("括号内不能为空（位置 {}）", position),
+                Message::UnclosedParenthesis(position) => format!("括号未闭合（位置 {}）", position),
+                Message::EmptyComment => "注释内容不能为空".to_string(),
+                Message::CommentExists(file) => format!("文件 {} 已存在注释，请使用 update_comment 更新", file),
+                Message::InvalidConfigFile { path, error } => format!("配置文件 {} 解析失败: {}", path, error),
+                Message::InvalidTagSchemaFile { path, error } => format!("标签模式文件 {} 解析失败: {}", path, error),
+                Message::ConfigNotObject => "配置必须是 JSON 对象".to_string(),
+                Message::UnknownConfigKey(key) => format!("未知的配置项: {}", key),
+                Message::InvalidConfig(error) => format!("配置无效: {}", error),
+                Message::ContentHashesDisabled => "未启用内容哈希记录，请先通过 set_config 将 track_content_hashes 设为 true".to_string(),
+                Message::UnknownDataFile { file, expected } => format!("未知的数据文件: {}，应为 {}", file, expected.join("、")),
+                Message::PathCaseFoldDisabled => "请先通过 set_config 启用 path_case_fold 再折叠路径键".to_string(),
+                Message::ProjectNotLoaded(path) => format!("项目未加载: {}", path),
+                Message::UnsupportedLocale(locale) => format!("不支持的语言: {}，可选 zh-CN、en-US", locale),
+                Message::ExactlyOneSource(field) => format!("content 和 {} 必须且只能提供一个", field),
+                Message::UnknownOutputTool(tool) => format!("未知的工具或该工具没有声明输出结构: {}", tool),
+                Message::FileMetadataResourceDescription(project_path) => format!("{} 中文件的标签、注释和关联关系", project_path),
+                Message::InvalidResourceUri(uri) => format!("无效的资源 URI: {}", uri),
+                Message::NoFileMetadata(file) => format!("文件没有元数据: {}", file),
+                Message::ProjectPathArgument => "项目根目录路径".to_string(),
+                Message::FilePathArgument => "相对于项目根目录的文件路径".to_string(),
+                Message::DocumentFilePromptDescription => "为文件编写注释并添加标签".to_string(),
+                Message::DocumentFilePrompt { project_path, file_path } => format!(
+                    "请为项目 {project_path} 中的文件 {file_path} 整理元数据：\n\
+                     1. 调用 get_file_info 查看该文件已有的标签、注释和关联关系；\n\
+                     2. 阅读文件内容，概括其职责、主要接口和注意事项；\n\
+                     3. 调用 get_all_tags（如有标签模式，再调用 get_tag_schema）了解项目中已使用的标签，尽量复用；\n\
+                     4. 调用 add_file_comment（已有注释时用 update_file_comment）写入概括；\n\
+                     5. 调用 add_file_tags 添加 type:value 格式的标签，如 layer:api、status:done。"
+                ),
+                Message::FindRelatedWorkPromptDescription => "查找与文件相关的其他文件及其关联关系".to_string(),
+                Message::FindRelatedWorkPrompt { project_path, file_path } => format!(
+                    "请找出项目 {project_path} 中与文件 {file_path} 相关的工作：\n\
+                     1. 调用 get_related_files 获取通过共同标签或关联关系相关的文件；\n\
+                     2. 调用 query_file_relations 和 query_incoming_relations 查看具体的出向和入向关联描述；\n\
+                     3. 对感兴趣的文件调用 get_file_info 查看其注释和标签；\n\
+                     4. 总结这些文件与 {file_path} 的关系，并指出缺失的关联（可用 add_file_relation 补充）。"
+                ),
+                Message::MissingPromptArgument { prompt, argument } => format!("提示 {} 缺少参数 {}", prompt, argument),
+                Message::UnknownPrompt(prompt) => format!("提示不存在: {}", prompt),
+            },
+            Locale::EnUs => match self {
+                Message::TagsAdded => "Tags added".to_string(),
+                Message::TagsRemoved => "Tags removed".to_string(),
+                Message::TagsCleared(count) => format!("Cleared {} tags", count),
+                Message::CommentAdded => "Comment added".to_string(),
+                Message::CommentUpdated => "Comment updated".to_string(),
+                Message::CommentDeleted => "Comment deleted".to_string(),
+                Message::RelationAdded => "Relation added".to_string(),
+                Message::RelationRemoved => "Relation removed".to_string(),
+                Message::ProjectUnloaded => "Project unloaded".to_string(),
+                Message::DebugLoggingEnabled => "Debug logging enabled".to_string(),
+                Message::DebugLoggingDisabled => "Debug logging disabled".to_string(),
+                Message::TagsExported(path) => format!("Tags exported to {}", path),
+                Message::LocaleChanged(locale) => format!("Locale set to {}", locale.as_str()),
+                Message::OperationUndone(operation) => format!("Undid {}", operation),
+                Message::NothingToUndo => "Nothing to undo".to_string(),
+                Message::UnclosedCodeBlock(line) => format!("Code block starting at line {} is not closed", line),
+                Message::DuplicateRelationDescription { file, targets } => format!("Description duplicates existing relations: {} -> {}", file, targets.join(", ")),
+                Message::EmptyProjectPath => "Project path must not be empty".to_string(),
+                Message::UnexpandableProjectPath { path, var, cause } => format!("Cannot expand environment variable {} in project path {}: {}", var, path, cause),
+                Message::ProjectPathNotDirectory(path) => format!("Project path must be a directory: {}", path),
+                Message::EmptyFilePath => "File path must not be empty".to_string(),
+                Message::PathNotFile(path) => format!("Path must point to a file, not a directory: {}", path),
+                Message::FileOutsideProject(path) => format!("File path must be inside the project directory: {}", path),
+                Message::DirectoryOutsideProject(path) => format!("Directory path must be inside the project directory: {}", path),
+                Message::EmptyOutputPath => "Output file path must not be empty".to_string(),
+                Message::OutputPathOutsideRoot(path) => format!("Output file path must be a relative path inside the root directory: {}", path),
+                Message::InvalidTimeBound(value) => format!("Invalid time: {}, expected YYYY-MM-DD or RFC 3339", value),
+                Message::InvalidTagTypePattern { tag_type, error } => format!("Invalid regular expression for tag type {}: {}", tag_type, error),
+                Message::MergeNeedsSourceTag => "At least one source tag different from the target tag is required".to_string(),
+                Message::SameTagType => "The new tag type is the same as the old one".to_string(),
+                Message::InvalidDescriptionPattern { pattern, error } => format!("Invalid relation description pattern {}: {}", pattern, error),
+                Message::EmptyRelationDescription => "Relation description must not be empty".to_string(),
+                Message::InvalidRelationWeight(weight) => format!("Relation weight must be a non-negative number: {}", weight),
+                Message::EmptyRelationTarget => "Relation target must not be empty".to_string(),
+                Message::MalformedTag(tag) => format!("Tag must be in type:value format: {}", tag),
+                Message::MissingQueryCondition => "Provide at least one of tag_query and relation_keyword".to_string(),
+                Message::MissingFilter => "Provide at least one filter".to_string(),
+                Message::InvalidPathGlob { pattern, error } => format!("Invalid path glob {}: {}", pattern, error),
+                Message::InvalidWildcardPattern(pattern) => format!("Invalid wildcard pattern: {}", pattern),
+                Message::EmptyQuery(position) => format!("Query must not be empty (position {})", position),
+                Message::UnexpectedQueryToken { token, position } => format!("Unexpected \"{}\" (position {})", token, position),
+                Message::UnmatchedRightParen(position) => format!("Unmatched closing parenthesis (position {})", position),
+                Message::UnclosedTagSet(position) => format!("Tag set is missing the closing ] (position {})", position),
+                Message::EmptyTagInSet(position) => format!("Tag set contains an empty tag (position {})", position),
+                Message::WildcardInTagSet { tag, position } => format!("Wildcards are not allowed in tag sets: {} (position {})", tag, position),
+                Message::MalformedQueryTag { tag, position } => format!("Tag must be in type:value format: {} (position {})", tag, position),
+                Message::MissingOperandAfter { operator, position } => format!("Missing operand after {} (position {})", operator, position),
+                Message::MissingOperandBefore { operator, position } => format!("Missing operand before {} (position {})", operator, position),
+                Message::UnexpectedQueryEnd(position) => format!("Query ended unexpectedly, expected a tag (position {})", position),
+                Message::EmptyParentheses(position) => format!("Parentheses must not be empty (position {})", position),
+                Message::UnclosedParenthesis(position) => format!("Unclosed parenthesis (position {})", position),
+                Message::EmptyComment => "Comment must not be empty".to_string(),
+                Message::CommentExists(file) => format!("File {} already has a comment; use update_comment to change it", file),
+                Message::InvalidConfigFile { path, error } => format!("Failed to parse config file {}: {}", path, error),
+                Message::InvalidTagSchemaFile { path, error } => format!("Failed to parse tag schema file {}: {}", path, error),
+                Message::ConfigNotObject => "Configuration must be a JSON object".to_string(),
+                Message::UnknownConfigKey(key) => format!("Unknown configuration key: {}", key),
+                Message::InvalidConfig(error) => format!("Invalid configuration: {}", error),
+                Message::ContentHashesDisabled => "Content hash tracking is disabled; set track_content_hashes to true with set_config first".to_string(),
+                Message::UnknownDataFile { file, expected } => format!("Unknown data file: {}, expected one of {}", file, expected.join(", ")),
+                Message::PathCaseFoldDisabled => "Enable path_case_fold with set_config before folding path keys".to_string(),
+                Message::ProjectNotLoaded(path) => format!("Project is not loaded: {}", path),
+                Message::UnsupportedLocale(locale) => format!("Unsupported locale: {}, expected zh-CN or en-US", locale),
+                Message::ExactlyOneSource(field) => format!("Provide exactly one of content and {}", field),
+                Message::UnknownOutputTool(tool) => format!("Unknown tool, or the tool declares no output schema: {}", tool),
+                Message::FileMetadataResourceDescription(project_path) => format!("Tags, comment and relations of a file in {}", project_path),
+                Message::InvalidResourceUri(uri) => format!("Invalid resource URI: {}", uri),
+                Message::NoFileMetadata(file) => format!("File has no metadata: {}", file),
+                Message::ProjectPathArgument => "Project root directory path".to_string(),
+                Message::FilePathArgument => "File path relative to the project root".to_string(),
+                Message::DocumentFilePromptDescription => "Write a comment and add tags for a file".to_string(),
+                Message::DocumentFilePrompt { project_path, file_path } => format!(
+                    "Organize the metadata of file {file_path} in project {project_path}:\n\
+                     1. Call get_file_info to see the file's existing tags, comment and relations;\n\
+                     2. Read the file and summarize its responsibilities, main interfaces and caveats;\n\
+                     3. Call get_all_tags (and get_tag_schema if the project has a tag schema) to see the tags already in use, and reuse them where possible;\n\
+                     4. Call add_file_comment (update_file_comment if a comment exists) to store the summary;\n\
+                     5. Call add_file_tags to add tags in type:value format, such as layer:api or status:done."
+                ),
+                Message::FindRelatedWorkPromptDescription => "Find files related to a file and how they are related".to_string(),
+                Message::FindRelatedWorkPrompt { project_path, file_path } => format!(
+                    "Find the work related to file {file_path} in project {project_path}:\n\
+                     1. Call get_related_files to get files related through shared tags or relations;\n\
+                     2. Call query_file_relations and query_incoming_relations to read the outgoing and incoming relation descriptions;\n\
+                     3. Call get_file_info on the files of interest to see their comments and tags;\n\
+                     4. Summarize how these files relate to {file_path} and point out missing relations (add them with add_file_relation)."
+                ),
+                Message::MissingPromptArgument { prompt, argument } => format!("Prompt {} is missing argument {}", prompt, argument),
+                Message::UnknownPrompt(prompt) => format!("Unknown prompt: {}", prompt),
+            },
+        }
+    }
+
+    /// 按当前语言生成消息文本
+    pub fn localized(&self) -> String {
+        self.text(current_locale())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!(Locale::parse("zh-CN"), Some(Locale::ZhCn));
+        assert_eq!(Locale::parse("en_US.UTF-8"), Some(Locale::EnUs));
+        assert_eq!(Locale::parse(" EN "), Some(Locale::EnUs));
+        assert_eq!(Locale::parse("fr-FR"), None);
+        assert_eq!(Message::TagsCleared(2).text(Locale::EnUs), "Cleared 2 tags");
+        assert_eq!(Message::TagsCleared(2).text(Locale::ZhCn), "已清除 2 个标签");
+        assert_eq!(Message::TagsAdded.text(Locale::EnUs), "Tags added");
+        assert_eq!(Message::UnclosedCodeBlock(3).text(Locale::EnUs), "Code block starting at line 3 is not closed");
+    }
+}
@@ -1,4 +1,6 @@
+pub mod config;
 pub mod error;
+pub mod i18n;
 pub mod models;
 pub mod storage;
 pub mod managers;
@@ -14,12 +14,13 @@ async fn main() -> anyhow::Result<()> {
     // 创建并启动 MCP 服务器
     let server = CodeNexusServer::new().await
         .map_err(|e| anyhow::anyhow!("创建服务器失败: {:?}", e))?;
-    let service = server.serve(stdio()).await
+    let service = server.clone().serve(stdio()).await
         .map_err(|e| anyhow::anyhow!("启动服务失败: {:?}", e))?;
 
     tracing::info!("CodeNexus MCP 服务器已启动");
     service.waiting().await
         .map_err(|e| anyhow::anyhow!("服务运行失败: {:?}", e))?;
+    server.remove_all_projects().await;
 
     Ok(())
 }
@@ -1,15 +1,83 @@
+use crate::config::ProjectConfig;
 use crate::error::{CodeNexusError, Result};
+use crate::i18n::Message;
+use crate::models::{CommentList, ImportMode};
 use crate::storage::{JsonStorage, CommentsData};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
+use std::sync::Arc;
 use tracing::{debug, info};
 
+/// 索引使用的字符 n-gram 长度
+const NGRAM_LEN: usize = 3;
+
+/// 小写文本中的所有字符三元组（去重）
+fn ngrams(text_lower: &str) -> HashSet<[char; NGRAM_LEN]> {
+    let chars: Vec<char> = text_lower.chars().collect();
+    chars.windows(NGRAM_LEN).map(|w| [w[0], w[1], w[2]]).collect()
+}
+
+/// 注释全文倒排索引：小写字符三元组 -> 包含它的文件
+///
+/// 关键词的每个三元组都必然出现在匹配的注释中，因此取各三元组文件集合的交集
+/// 作为候选，再对候选注释做子串校验；完整的词、词的一部分和跨词的片段都能命中。
+/// 关键词不足三个字符时无法使用索引，回退为逐条扫描。
+#[derive(Debug, Default)]
+struct CommentIndex {
+    postings: HashMap<[char; NGRAM_LEN], HashSet<Arc<str>>>,
+}
+
+impl CommentIndex {
+    fn insert(&mut self, file_path: &str, comment: &str) {
+        let file_path: Arc<str> = Arc::from(file_path);
+        for ngram in ngrams(&comment.to_lowercase()) {
+            self.postings.entry(ngram).or_default().insert(file_path.clone());
+        }
+    }
+
+    fn remove(&mut self, file_path: &str, comment: &str) {
+        for ngram in ngrams(&comment.to_lowercase()) {
+            if let Some(files) = self.postings.get_mut(&ngram) {
+                files.remove(file_path);
+                if files.is_empty() {
+                    self.postings.remove(&ngram);
+                }
+            }
+        }
+    }
+
+    /// 可能包含关键词的候选文件；关键词不足三个字符时返回 None
+    fn candidates(&self, keyword_lower: &str) -> Option<HashSet<Arc<str>>> {
+        let mut postings = Vec::new();
+        for ngram in ngrams(keyword_lower) {
+            match self.postings.get(&ngram) {
+                Some(files) => postings.push(files),
+                None => return Some(HashSet::new()),
+            }
+        }
+        // 从最小的集合开始求交集
+        postings.sort_by_key(|files| files.len());
+        let (smallest, rest) = postings.split_first()?;
+        Some(
+            smallest
+                .iter()
+                .filter(|file| rest.iter().all(|files| files.contains(*file)))
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
 /// 注释管理器
 #[derive(Debug)]
 pub struct CommentManager {
     storage: JsonStorage,
     // 内存数据
     file_comments: HashMap<String, String>,
+    // 注释全文索引，随 file_comments 同步维护
+    index: CommentIndex,
+    // 项目配置，读取 `max_comment_length`
+    config: Arc<ProjectConfig>,
 }
 
 impl CommentManager {
@@ -18,13 +86,48 @@ impl CommentManager {
         Self {
             storage,
             file_comments: HashMap::new(),
+            index: CommentIndex::default(),
+            config: Arc::default(),
         }
     }
 
+    /// 根据 file_comments 重建全文索引
+    fn rebuild_index(&mut self) {
+        self.index = CommentIndex::default();
+        for (file_path, comment) in &self.file_comments {
+            self.index.insert(file_path, comment);
+        }
+    }
+
+    /// 写入注释并同步索引，返回旧注释
+    fn set_comment(&mut self, file_path: &str, comment: &str) -> Option<String> {
+        let old_comment = self.file_comments.insert(file_path.to_string(), comment.to_string());
+        if let Some(old_comment) = &old_comment {
+            self.index.remove(file_path, old_comment);
+        }
+        self.index.insert(file_path, comment);
+        old_comment
+    }
+
+    /// 移除注释并同步索引
+    fn take_comment(&mut self, file_path: &str) -> Option<String> {
+        let old_comment = self.file_comments.remove(file_path);
+        if let Some(old_comment) = &old_comment {
+            self.index.remove(file_path, old_comment);
+        }
+        old_comment
+    }
+
+    /// 设置项目配置（注释最大长度）
+    pub fn set_config(&mut self, config: Arc<ProjectConfig>) {
+        self.config = config;
+    }
+
     /// 初始化管理器，加载数据到内存
     pub async fn initialize(&mut self) -> Result<()> {
         let data = self.storage.load_comments().await?;
-        self.file_comments = data.file_comments;
+        self.file_comments = data.file_comments.into_iter().collect();
+        self.rebuild_index();
         info!("注释管理器初始化完成，加载了 {} 个文件的注释", self.file_comments.len());
         Ok(())
     }
@@ -40,11 +143,24 @@ impl CommentManager {
     /// 验证注释内容
     fn validate_comment(&self, comment: &str) -> Result<()> {
         if comment.trim().is_empty() {
-            return Err(CodeNexusError::ConfigError("注释内容不能为空".to_string()));
+            return Err(CodeNexusError::ConfigError(Message::EmptyComment));
+        }
+
+        let length = comment.chars().count();
+        if length > self.config.max_comment_length {
+            return Err(CodeNexusError::CommentTooLong {
+                length,
+                limit: self.config.max_comment_length,
+            });
         }
         Ok(())
     }
 
+    /// 验证待导入的注释，任一不合法则返回错误
+    pub fn validate_comments(&self, comments: &HashMap<String, String>) -> Result<()> {
+        comments.values().try_for_each(|comment| self.validate_comment(comment))
+    }
+
     /// 为文件添加注释
     pub async fn add_comment(&mut self, absolute_file_path: &Path, relative_file_path: &str, comment: &str) -> Result<()> {
         // 验证输入
@@ -53,13 +169,11 @@ impl CommentManager {
 
         // 检查是否已存在注释（使用相对路径）
         if self.file_comments.contains_key(relative_file_path) {
-            return Err(CodeNexusError::ConfigError(
-                format!("文件 {} 已存在注释，请使用 update_comment 更新", relative_file_path)
-            ));
+            return Err(CodeNexusError::ConfigError(Message::CommentExists(relative_file_path.to_string())));
         }
 
         // 添加注释（使用相对路径存储）
-        self.file_comments.insert(relative_file_path.to_string(), comment.to_string());
+        self.set_comment(relative_file_path, comment);
 
         // 保存到存储
         self.save_to_storage().await?;
@@ -68,14 +182,18 @@ impl CommentManager {
         Ok(())
     }
 
-    /// 更新文件注释
+    /// 更新文件注释，空白注释等同于 `delete_comment`
     pub async fn update_comment(&mut self, absolute_file_path: &Path, relative_file_path: &str, comment: &str) -> Result<()> {
+        if comment.trim().is_empty() {
+            return self.delete_comment(relative_file_path).await;
+        }
+
         // 验证输入
         self.validate_file_path(absolute_file_path)?;
         self.validate_comment(comment)?;
 
         // 更新注释（使用相对路径存储）
-        let old_comment = self.file_comments.insert(relative_file_path.to_string(), comment.to_string());
+        let old_comment = self.set_comment(relative_file_path, comment);
 
         // 保存到存储
         self.save_to_storage().await?;
@@ -110,10 +228,23 @@ impl CommentManager {
         &self.file_comments
     }
 
+    /// 按文件路径排序分页列出注释，可选按子串过滤（与 `search_comments` 相同，不区分大小写）
+    pub fn list_comments(&self, contains: Option<&str>, offset: usize, limit: Option<usize>) -> CommentList {
+        let matched = self.search_comments(contains.unwrap_or(""), false);
+        CommentList {
+            total: matched.len(),
+            comments: matched
+                .into_iter()
+                .skip(offset)
+                .take(limit.unwrap_or(usize::MAX))
+                .collect(),
+        }
+    }
+
     /// 删除文件注释
     pub async fn delete_comment(&mut self, file_path: &str) -> Result<()> {
         // 对于删除操作，不验证文件是否存在，因为文件可能已被删除但数据库中还有记录
-        if let Some(_) = self.file_comments.remove(file_path) {
+        if self.take_comment(file_path).is_some() {
             self.save_to_storage().await?;
             info!("删除了文件 {} 的注释", file_path);
             Ok(())
@@ -136,13 +267,56 @@ impl CommentManager {
         files
     }
 
-    /// 搜索注释内容（简单的关键词搜索）
-    pub fn search_comments(&self, keyword: &str) -> Vec<(String, String)> {
+    /// 从候选文件中筛选没有注释的文件，去重并按路径排序
+    pub fn get_undocumented_files<I: IntoIterator<Item = String>>(&self, candidates: I) -> Vec<String> {
+        let files: std::collections::BTreeSet<String> = candidates
+            .into_iter()
+            .filter(|file_path| !self.has_comment(file_path))
+            .collect();
+        files.into_iter().collect()
+    }
+
+    /// 搜索注释内容（子串匹配，`case_sensitive` 为 false 时忽略大小写），按文件路径排序
+    ///
+    /// 通过全文索引筛选候选文件，关键词不足三个字符时逐条扫描。
+    /// 索引基于小写文本建立，区分大小写时先用它缩小候选范围再逐条校验
+    pub fn search_comments(&self, keyword: &str, case_sensitive: bool) -> Vec<(String, String)> {
+        let keyword_lower = keyword.to_lowercase();
+        let Some(candidates) = self.index.candidates(&keyword_lower) else {
+            return self.scan_comments(keyword, case_sensitive);
+        };
+
+        // 忽略大小写且关键词恰好是一个三元组时，候选注释必然包含它，无需再校验
+        let exact = !case_sensitive && keyword_lower.chars().count() == NGRAM_LEN;
+        let mut results: Vec<(String, String)> = candidates
+            .into_iter()
+            .filter_map(|file_path| {
+                let comment = self.file_comments.get(&*file_path)?;
+                let matched = exact
+                    || if case_sensitive {
+                        comment.contains(keyword)
+                    } else {
+                        comment.to_lowercase().contains(&keyword_lower)
+                    };
+                matched.then(|| (file_path.to_string(), comment.clone()))
+            })
+            .collect();
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+        results
+    }
+
+    /// 逐条扫描所有注释做子串匹配
+    fn scan_comments(&self, keyword: &str, case_sensitive: bool) -> Vec<(String, String)> {
         let keyword_lower = keyword.to_lowercase();
         let mut results = Vec::new();
 
         for (file_path, comment) in &self.file_comments {
-            if comment.to_lowercase().contains(&keyword_lower) {
+            let matched = if case_sensitive {
+                comment.contains(keyword)
+            } else {
+                comment.to_lowercase().contains(&keyword_lower)
+            };
+            if matched {
                 results.push((file_path.clone(), comment.clone()));
             }
         }
@@ -170,7 +344,7 @@ impl CommentManager {
         }
 
         for file_path in files_to_remove {
-            self.file_comments.remove(&file_path);
+            self.take_comment(&file_path);
             removed_count += 1;
             debug!("清理了不存在文件的注释: {}", file_path);
         }
@@ -188,19 +362,26 @@ impl CommentManager {
         self.file_comments.clone()
     }
 
-    /// 导入注释数据
-    pub async fn import_comments(&mut self, comments: HashMap<String, String>) -> Result<usize> {
+    /// 导入注释数据，跳过空注释
+    ///
+    /// 路径键应已由调用方规范化（需要时也由调用方检查文件是否存在）
+    pub async fn import_comments(&mut self, comments: HashMap<String, String>, mode: ImportMode) -> Result<usize> {
         let mut imported_count = 0;
+        let replaced = mode == ImportMode::Replace && !self.file_comments.is_empty();
+
+        if mode == ImportMode::Replace {
+            self.file_comments.clear();
+        }
 
         for (file_path, comment) in comments {
-            // 验证文件路径和注释内容
-            if Path::new(&file_path).exists() && !comment.trim().is_empty() {
+            if !comment.trim().is_empty() {
                 self.file_comments.insert(file_path, comment);
                 imported_count += 1;
             }
         }
+        self.rebuild_index();
 
-        if imported_count > 0 {
+        if imported_count > 0 || replaced {
             self.save_to_storage().await?;
             info!("导入了 {} 个注释", imported_count);
         }
@@ -211,9 +392,161 @@ impl CommentManager {
     /// 保存数据到存储
     async fn save_to_storage(&self) -> Result<()> {
         let data = CommentsData {
-            file_comments: self.file_comments.clone(),
+            file_comments: self.file_comments.clone().into_iter().collect(),
         };
 
         self.storage.save_comments(&data).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_max_comment_length() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("a.rs");
+        fs::write(&file, "").unwrap();
+        let storage = JsonStorage::new(temp_dir.path().join(".codenexus"));
+        storage.initialize().await.unwrap();
+        let mut manager = CommentManager::new(storage);
+        manager.initialize().await.unwrap();
+        manager.set_config(Arc::new(ProjectConfig { max_comment_length: 4, ..Default::default() }));
+
+        // 按字符而非字节计算长度
+        manager.add_comment(&file, "a.rs", "登录入口").await.unwrap();
+        match manager.update_comment(&file, "a.rs", "登录入口。").await {
+            Err(CodeNexusError::CommentTooLong { length, limit }) => assert_eq!((length, limit), (5, 4)),
+            other => panic!("应返回 CommentTooLong，实际为 {:?}", other),
+        }
+        assert_eq!(manager.get_comment("a.rs").as_deref(), Some("登录入口"));
+    }
+
+    #[tokio::test]
+    async fn test_get_undocumented_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("b.rs");
+        fs::write(&file, "").unwrap();
+        let storage = JsonStorage::new(temp_dir.path().join(".codenexus"));
+        storage.initialize().await.unwrap();
+        let mut manager = CommentManager::new(storage);
+        manager.initialize().await.unwrap();
+        manager.add_comment(&file, "b.rs", "已记录").await.unwrap();
+
+        let candidates = ["c.rs", "b.rs", "a.rs", "c.rs"].map(String::from);
+        assert_eq!(manager.get_undocumented_files(candidates), vec!["a.rs", "c.rs"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_comments() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = CommentManager::new(JsonStorage::new(temp_dir.path().join(".codenexus")));
+        for (file, comment) in [("c.rs", "解析 Parser"), ("a.rs", "入口"), ("b.rs", "parser 辅助")] {
+            manager.set_comment(file, comment);
+        }
+
+        let all = manager.list_comments(None, 0, None);
+        assert_eq!(all.total, 3);
+        assert_eq!(all.comments.keys().collect::<Vec<_>>(), vec!["a.rs", "b.rs", "c.rs"]);
+
+        let page = manager.list_comments(Some("PARSER"), 1, Some(5));
+        assert_eq!(page.total, 2);
+        assert_eq!(page.comments.keys().collect::<Vec<_>>(), vec!["c.rs"]);
+        assert!(manager.list_comments(None, 10, None).comments.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_update_to_empty_deletes_comment() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("a.rs");
+        fs::write(&file, "").unwrap();
+        let storage = JsonStorage::new(temp_dir.path().join(".codenexus"));
+        storage.initialize().await.unwrap();
+        let mut manager = CommentManager::new(storage.clone());
+        manager.initialize().await.unwrap();
+
+        manager.add_comment(&file, "a.rs", "入口").await.unwrap();
+        manager.update_comment(&file, "a.rs", "").await.unwrap();
+        assert!(!manager.file_comments.contains_key("a.rs"));
+        assert!(storage.load_comments().await.unwrap().file_comments.is_empty());
+
+        // 与 delete_comment 一致：没有注释时报错，且不要求文件仍然存在
+        fs::remove_file(&file).unwrap();
+        assert!(matches!(
+            manager.update_comment(&file, "a.rs", "  ").await,
+            Err(CodeNexusError::FileNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_search_comments_uses_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("a.rs");
+        fs::write(&file, "").unwrap();
+        let storage = JsonStorage::new(temp_dir.path().join(".codenexus"));
+        storage.initialize().await.unwrap();
+        let mut manager = CommentManager::new(storage);
+        manager.initialize().await.unwrap();
+        manager.set_comment("b.rs", "Token parser, 解析器入口");
+        manager.add_comment(&file, "a.rs", "HTTP 路由").await.unwrap();
+
+        let files = |results: Vec<(String, String)>| results.into_iter().map(|(file, _)| file).collect::<Vec<_>>();
+        // 完整词、词的一部分、跨词片段，以及不足三个字符时的逐条扫描
+        assert_eq!(files(manager.search_comments("PARSER", false)), vec!["b.rs"]);
+        assert_eq!(files(manager.search_comments("pars", false)), vec!["b.rs"]);
+        assert_eq!(files(manager.search_comments("解析器", false)), vec!["b.rs"]);
+        assert_eq!(files(manager.search_comments("解析", false)), vec!["b.rs"]);
+        assert_eq!(files(manager.search_comments("en par", false)), vec!["b.rs"]);
+        assert!(manager.search_comments("token  parser", false).is_empty());
+        assert_eq!(files(manager.search_comments(",", false)), vec!["b.rs"]);
+        assert_eq!(files(manager.search_comments("", false)), vec!["a.rs", "b.rs"]);
+
+        // 区分大小写：三元组和短关键词都需要精确匹配大小写
+        assert_eq!(files(manager.search_comments("Token", true)), vec!["b.rs"]);
+        assert!(manager.search_comments("TOKEN", true).is_empty());
+        assert!(manager.search_comments("tok", true).is_empty());
+        assert_eq!(files(manager.search_comments("HT", true)), vec!["a.rs"]);
+        assert!(manager.search_comments("ht", true).is_empty());
+
+        // 更新和删除后索引同步
+        manager.update_comment(&file, "a.rs", "parser 路由").await.unwrap();
+        assert_eq!(files(manager.search_comments("parser", false)), vec!["a.rs", "b.rs"]);
+        assert!(manager.search_comments("http", false).is_empty());
+        manager.delete_comment("a.rs").await.unwrap();
+        assert_eq!(files(manager.search_comments("路由", false)), Vec::<String>::new());
+        assert!(manager.index.postings.values().all(|files| !files.contains("a.rs")));
+    }
+
+    /// 索引搜索不应慢于逐条扫描：`cargo test --release -- --ignored bench_search_comments`
+    #[test]
+    #[ignore]
+    fn bench_search_comments() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = CommentManager::new(JsonStorage::new(temp_dir.path().join(".codenexus")));
+        let words = ["parser", "router", "handler", "storage", "config", "index", "token", "session", "cache", "metrics"];
+        for i in 0..5000 {
+            let comment: Vec<String> = (0..40).map(|j| format!("{}{}", words[(i * 7 + j * 3) % words.len()], 1000 + (i * 31 + j * 17) % 9000)).collect();
+            manager.set_comment(&format!("src/file_{}.rs", i), &comment.join(" "));
+        }
+        let keywords: Vec<String> = (0..200).map(|i| format!("{}{}", words[i % words.len()], 1000 + i * 37)).collect();
+
+        let start = std::time::Instant::now();
+        let scanned: Vec<_> = keywords.iter().map(|keyword| manager.scan_comments(keyword, false)).collect();
+        let scan_elapsed = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let indexed: Vec<_> = keywords.iter().map(|keyword| manager.search_comments(keyword, false)).collect();
+        let index_elapsed = start.elapsed();
+
+        assert_eq!(scanned, indexed);
+        assert!(
+            index_elapsed <= scan_elapsed,
+            "索引搜索 {:?} 慢于逐条扫描 {:?}",
+            index_elapsed,
+            scan_elapsed
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use crate::config::ProjectConfig;
+use crate::error::Result;
+use crate::models::StaleMetadata;
+use crate::storage::{ContentHashesData, JsonStorage};
+use crate::utils::content_hash;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{debug, info};
+
+/// 文件内容哈希管理器
+///
+/// 在添加标签或注释时记录文件内容哈希，之后可找出内容已变化、元数据可能过时的文件。
+/// 需要读取文件内容，默认关闭，由配置 `track_content_hashes` 开启。
+#[derive(Debug)]
+pub struct ContentHashManager {
+    storage: JsonStorage,
+    // 文件 -> 最近一次标注时的内容哈希
+    file_hashes: HashMap<String, String>,
+    // 项目配置，读取 `track_content_hashes`
+    config: Arc<ProjectConfig>,
+}
+
+impl ContentHashManager {
+    /// 创建新的内容哈希管理器
+    pub fn new(storage: JsonStorage) -> Self {
+        Self {
+            storage,
+            file_hashes: HashMap::new(),
+            config: Arc::default(),
+        }
+    }
+
+    /// 设置项目配置（是否在标注时记录内容哈希）
+    pub fn set_config(&mut self, config: Arc<ProjectConfig>) {
+        self.config = config;
+    }
+
+    /// 是否启用了内容哈希记录
+    pub fn is_enabled(&self) -> bool {
+        self.config.track_content_hashes
+    }
+
+    /// 初始化管理器，加载数据到内存
+    pub async fn initialize(&mut self) -> Result<()> {
+        let data = self.storage.load_content_hashes().await?;
+        self.file_hashes = data.file_hashes.into_iter().collect();
+        info!("内容哈希管理器初始化完成，加载了 {} 个文件的哈希", self.file_hashes.len());
+        Ok(())
+    }
+
+    /// 记录文件当前的内容哈希，未启用时不做任何事
+    pub async fn record(&mut self, absolute_file_path: &Path, relative_file_path: &str) -> Result<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        let content = tokio::fs::read(absolute_file_path).await?;
+        let hash = content_hash(&content);
+        if self.file_hashes.get(relative_file_path) != Some(&hash) {
+            self.file_hashes.insert(relative_file_path.to_string(), hash);
+            self.save_to_storage().await?;
+            debug!("记录了文件 {} 的内容哈希", relative_file_path);
+        }
+        Ok(())
+    }
+
+    /// 只保留 `keep` 返回 true 的文件的哈希，返回移除的数量
+    pub async fn retain<F: Fn(&str) -> bool>(&mut self, keep: F) -> Result<usize> {
+        let before = self.file_hashes.len();
+        self.file_hashes.retain(|file_path, _| keep(file_path));
+        let removed = before - self.file_hashes.len();
+        if removed > 0 {
+            self.save_to_storage().await?;
+            debug!("移除了 {} 个已无元数据文件的内容哈希", removed);
+        }
+        Ok(removed)
+    }
+
+    /// 按 旧路径 -> 新路径 的映射重命名哈希的键，新路径已有哈希时保留新路径的
+    pub async fn rekey(&mut self, rekeyed: &HashMap<String, String>) -> Result<()> {
+        let mut changed = false;
+        for (old_key, new_key) in rekeyed {
+            if let Some(hash) = self.file_hashes.remove(old_key) {
+                self.file_hashes.entry(new_key.clone()).or_insert(hash);
+                changed = true;
+            }
+        }
+        if changed {
+            self.save_to_storage().await?;
+        }
+        Ok(())
+    }
+
+    /// 重新计算已记录文件的内容哈希，返回内容已变化（或已删除）的文件，按路径排序
+    pub async fn find_stale(&self, project_root: &Path) -> Vec<StaleMetadata> {
+        let mut stale = Vec::new();
+        for (file_path, recorded_hash) in &self.file_hashes {
+            let current_hash = tokio::fs::read(project_root.join(file_path))
+                .await
+                .ok()
+                .map(|content| content_hash(&content));
+            if current_hash.as_ref() != Some(recorded_hash) {
+                stale.push(StaleMetadata {
+                    file: file_path.clone(),
+                    recorded_hash: recorded_hash.clone(),
+                    current_hash,
+                });
+            }
+        }
+        stale.sort_by(|a, b| a.file.cmp(&b.file));
+        stale
+    }
+
+    /// 保存数据到存储
+    async fn save_to_storage(&self) -> Result<()> {
+        let data = ContentHashesData {
+            file_hashes: self.file_hashes.clone().into_iter().collect(),
+        };
+        self.storage.save_content_hashes(&data).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// 启用内容哈希记录的配置
+    fn tracking() -> Arc<ProjectConfig> {
+        Arc::new(ProjectConfig { track_content_hashes: true, ..Default::default() })
+    }
+
+    #[tokio::test]
+    async fn test_find_stale() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        for file in ["a.rs", "b.rs", "c.rs"] {
+            fs::write(root.join(file), "fn main() {}").unwrap();
+        }
+        let storage = JsonStorage::new(root.join(".codenexus"));
+        storage.initialize().await.unwrap();
+        let mut manager = ContentHashManager::new(storage.clone());
+        manager.initialize().await.unwrap();
+
+        // 未启用时不记录
+        manager.record(&root.join("a.rs"), "a.rs").await.unwrap();
+        assert!(manager.file_hashes.is_empty());
+
+        manager.set_config(tracking());
+        for file in ["a.rs", "b.rs", "c.rs"] {
+            manager.record(&root.join(file), file).await.unwrap();
+        }
+        assert!(manager.find_stale(root).await.is_empty());
+
+        fs::write(root.join("a.rs"), "fn main() { changed(); }").unwrap();
+        fs::remove_file(root.join("c.rs")).unwrap();
+        let mut reloaded = ContentHashManager::new(storage);
+        reloaded.initialize().await.unwrap();
+        let stale = reloaded.find_stale(root).await;
+        assert_eq!(stale.iter().map(|s| s.file.as_str()).collect::<Vec<_>>(), vec!["a.rs", "c.rs"]);
+        assert!(stale[0].current_hash.is_some());
+        assert!(stale[1].current_hash.is_none());
+
+        // 重新标注后不再过时
+        reloaded.set_config(tracking());
+        reloaded.record(&root.join("a.rs"), "a.rs").await.unwrap();
+        assert_eq!(reloaded.find_stale(root).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retain_and_rekey() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        for file in ["a.rs", "b.rs"] {
+            fs::write(root.join(file), file).unwrap();
+        }
+        let storage = JsonStorage::new(root.join(".codenexus"));
+        storage.initialize().await.unwrap();
+        let mut manager = ContentHashManager::new(storage.clone());
+        manager.set_config(tracking());
+        for file in ["a.rs", "b.rs"] {
+            manager.record(&root.join(file), file).await.unwrap();
+        }
+
+        assert_eq!(manager.retain(|file| file == "a.rs").await.unwrap(), 1);
+        manager.rekey(&HashMap::from([("a.rs".to_string(), "src/a.rs".to_string())])).await.unwrap();
+        assert_eq!(manager.file_hashes.keys().collect::<Vec<_>>(), vec!["src/a.rs"]);
+
+        // 修改已持久化
+        let mut reloaded = ContentHashManager::new(storage);
+        reloaded.initialize().await.unwrap();
+        assert_eq!(reloaded.file_hashes, manager.file_hashes);
+    }
+}
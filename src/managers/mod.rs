@@ -1,7 +1,9 @@
 pub mod tag_manager;
 pub mod comment_manager;
 pub mod relation_manager;
+pub mod content_hash_manager;
 
 pub use tag_manager::TagManager;
 pub use comment_manager::CommentManager;
 pub use relation_manager::RelationManager;
+pub use content_hash_manager::ContentHashManager;
@@ -1,8 +1,14 @@
+use crate::config::ProjectConfig;
 use crate::error::{CodeNexusError, Result};
-use crate::models::Relation;
+use crate::i18n::Message;
+use crate::models::{BulkRelationReport, GraphEdge, GraphNode, ImportMode, IncomingRelation, IntegrityIssue, IntegrityIssueKind, JsonGraph, Relation, RelationCleanupPlan, RelationConfig, RelationGraph, RelationPair, WeightedPath};
 use crate::storage::{JsonStorage, RelationsData};
-use std::collections::HashMap;
+use crate::utils::compile_path_glob;
+use regex::Regex;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 use std::path::Path;
+use std::sync::Arc;
 use tracing::{debug, info};
 
 /// 关联关系管理器
@@ -12,7 +18,11 @@ pub struct RelationManager {
     // 内存数据
     file_relations: HashMap<String, Vec<Relation>>,
     // 反向索引：目标文件 -> 指向它的关联关系
-    incoming_relations: HashMap<String, Vec<(String, String)>>, // target -> [(from_file, description)]
+    incoming_relations: HashMap<String, Vec<IncomingRelation>>,
+    // 项目配置，读取关联数量上限、重复描述检测和描述格式
+    config: Arc<ProjectConfig>,
+    // 由 `relation_description_pattern` 编译的完整匹配正则
+    description_regex: Option<Regex>,
 }
 
 impl RelationManager {
@@ -22,13 +32,48 @@ impl RelationManager {
             storage,
             file_relations: HashMap::new(),
             incoming_relations: HashMap::new(),
+            config: Arc::default(),
+            description_regex: None,
         }
     }
 
+    /// 编译关联描述必须完整匹配的正则表达式，None 表示只要求非空
+    pub fn compile_description_pattern(pattern: Option<&str>) -> Result<Option<Regex>> {
+        pattern
+            .map(|pattern| {
+                Regex::new(&format!("^(?:{})$", pattern)).map_err(|e| {
+                    CodeNexusError::ConfigError(Message::InvalidDescriptionPattern { pattern: pattern.to_string(), error: e.to_string() })
+                })
+            })
+            .transpose()
+    }
+
+    /// 设置项目配置，`description_regex` 为 [`Self::compile_description_pattern`] 对该配置的编译结果
+    pub fn set_config(&mut self, config: Arc<ProjectConfig>, description_regex: Option<Regex>) {
+        self.config = config;
+        self.description_regex = description_regex;
+    }
+
+    /// 获取当前生效的关联关系配置
+    pub fn relation_config(&self) -> RelationConfig {
+        RelationConfig {
+            description_pattern: self.config.relation_description_pattern.clone(),
+            max_relations_per_file: self.config.max_relations_per_file,
+            warn_duplicate_descriptions: self.config.warn_duplicate_relation_descriptions,
+        }
+    }
+
+    /// 源文件的关联是否已达上限
+    fn at_relation_limit(&self, from_file: &str) -> Option<usize> {
+        let limit = self.config.max_relations_per_file?;
+        let count = self.file_relations.get(from_file).map_or(0, |relations| relations.len());
+        (count >= limit).then_some(limit)
+    }
+
     /// 初始化管理器，加载数据到内存
     pub async fn initialize(&mut self) -> Result<()> {
         let data = self.storage.load_relations().await?;
-        self.file_relations = data.file_relations;
+        self.file_relations = data.file_relations.into_iter().collect();
         self.build_incoming_index();
         info!("关联关系管理器初始化完成，加载了 {} 个文件的关联关系", self.file_relations.len());
         Ok(())
@@ -43,9 +88,57 @@ impl RelationManager {
                 self.incoming_relations
                     .entry(relation.target.clone())
                     .or_default()
-                    .push((from_file.clone(), relation.description.clone()));
+                    .push(IncomingRelation::new(from_file, relation));
+            }
+        }
+    }
+
+    /// 检查反向索引与 `file_relations` 是否一致，结果按类型、文件和目标排序
+    pub fn check_integrity(&self) -> Vec<IntegrityIssue> {
+        let mut issues = Vec::new();
+
+        for (from_file, relations) in &self.file_relations {
+            for relation in relations {
+                let indexed = self.incoming_relations.get(&relation.target).is_some_and(|incoming| {
+                    incoming.iter().any(|source| {
+                        &source.source == from_file && source.description == relation.description
+                    })
+                });
+                if !indexed {
+                    issues.push(IntegrityIssue {
+                        kind: IntegrityIssueKind::MissingIncoming,
+                        file: from_file.clone(),
+                        item: relation.target.clone(),
+                    });
+                }
             }
         }
+
+        for (to_file, incoming) in &self.incoming_relations {
+            for source in incoming {
+                let exists = self.file_relations.get(&source.source).is_some_and(|relations| {
+                    relations.iter().any(|relation| {
+                        &relation.target == to_file && relation.description == source.description
+                    })
+                });
+                if !exists {
+                    issues.push(IntegrityIssue {
+                        kind: IntegrityIssueKind::StaleIncoming,
+                        file: source.source.clone(),
+                        item: to_file.clone(),
+                    });
+                }
+            }
+        }
+
+        issues.sort_by(|a, b| (a.kind as u8, &a.file, &a.item).cmp(&(b.kind as u8, &b.file, &b.item)));
+        issues
+    }
+
+    /// 根据 `file_relations` 重建反向索引
+    pub fn rebuild_incoming_index(&mut self) {
+        self.build_incoming_index();
+        info!("已重建关联关系反向索引");
     }
 
     /// 验证文件路径（使用绝对路径）
@@ -56,23 +149,42 @@ impl RelationManager {
         Ok(())
     }
 
-    /// 验证关联描述
+    /// 验证关联描述（非空，且在配置了格式时完整匹配）
     fn validate_description(&self, description: &str) -> Result<()> {
         if description.trim().is_empty() {
-            return Err(CodeNexusError::ConfigError("关联描述不能为空".to_string()));
+            return Err(CodeNexusError::ConfigError(Message::EmptyRelationDescription));
+        }
+        if let (Some(pattern), Some(regex)) = (&self.config.relation_description_pattern, &self.description_regex) {
+            if !regex.is_match(description) {
+                return Err(CodeNexusError::InvalidRelationDescription {
+                    description: description.to_string(),
+                    pattern: pattern.clone(),
+                });
+            }
         }
         Ok(())
     }
 
+    /// 验证关联权重，最短路径计算要求权重非负
+    fn validate_weight(&self, weight: Option<f64>) -> Result<()> {
+        match weight {
+            Some(weight) if !weight.is_finite() || weight < 0.0 => {
+                Err(CodeNexusError::ConfigError(Message::InvalidRelationWeight(weight.to_string())))
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// 添加文件关联关系
     pub async fn add_relation(&mut self,
                               absolute_from_file: &Path, relative_from_file: &str,
                               absolute_to_file: &Path, relative_to_file: &str,
-                              description: &str) -> Result<()> {
+                              description: &str, weight: Option<f64>) -> Result<Vec<String>> {
         // 验证输入
         self.validate_file_path(absolute_from_file)?;
         self.validate_file_path(absolute_to_file)?;
         self.validate_description(description)?;
+        self.validate_weight(weight)?;
 
         // 检查是否已存在相同的关联关系（使用相对路径）
         if let Some(relations) = self.file_relations.get(relative_from_file) {
@@ -86,28 +198,93 @@ impl RelationManager {
             }
         }
 
+        if let Some(limit) = self.at_relation_limit(relative_from_file) {
+            return Err(CodeNexusError::RelationLimitExceeded {
+                file: relative_from_file.to_string(),
+                limit,
+            });
+        }
+
+        // 描述与指向其他目标的关联相同，通常是复制粘贴遗留
+        let mut warnings = Vec::new();
+        if self.config.warn_duplicate_relation_descriptions {
+            let duplicates: Vec<&str> = self
+                .file_relations
+                .get(relative_from_file)
+                .into_iter()
+                .flatten()
+                .filter(|relation| relation.description == description)
+                .map(|relation| relation.target.as_str())
+                .collect();
+            if !duplicates.is_empty() {
+                warnings.push(
+                    Message::DuplicateRelationDescription {
+                        file: relative_from_file.to_string(),
+                        targets: duplicates.into_iter().map(str::to_string).collect(),
+                    }
+                    .localized(),
+                );
+            }
+        }
+
         // 添加关联关系（使用相对路径存储）
         let new_relation = Relation {
             target: relative_to_file.to_string(),
             description: description.to_string(),
+            weight,
         };
 
-        self.file_relations
-            .entry(relative_from_file.to_string())
-            .or_default()
-            .push(new_relation);
-
         // 更新反向索引
         self.incoming_relations
             .entry(relative_to_file.to_string())
             .or_default()
-            .push((relative_from_file.to_string(), description.to_string()));
+            .push(IncomingRelation::new(relative_from_file, &new_relation));
+
+        self.file_relations
+            .entry(relative_from_file.to_string())
+            .or_default()
+            .push(new_relation);
 
         // 保存到存储
         self.save_to_storage().await?;
         info!("添加了关联关系: {} -> {} ({})", relative_from_file, relative_to_file, description);
 
-        Ok(())
+        Ok(warnings)
+    }
+
+    /// 为 `sources` × `targets` 的每一对文件添加关联，跳过自身关联、已存在的关联和已达上限的源文件，只保存一次
+    pub async fn add_relations_bulk(&mut self, sources: &[String], targets: &[String], description: &str) -> Result<BulkRelationReport> {
+        self.validate_description(description)?;
+
+        let mut report = BulkRelationReport::default();
+        for from_file in sources {
+            for to_file in targets {
+                if from_file == to_file
+                    || self.has_relation(from_file, to_file)
+                    || self.at_relation_limit(from_file).is_some()
+                {
+                    report.skipped.push(RelationPair {
+                        from: from_file.clone(),
+                        to: to_file.clone(),
+                    });
+                    continue;
+                }
+                self.file_relations.entry(from_file.clone()).or_default().push(Relation {
+                    target: to_file.clone(),
+                    description: description.to_string(),
+                    weight: None,
+                });
+                report.created += 1;
+            }
+        }
+
+        if report.created > 0 {
+            self.build_incoming_index();
+            self.save_to_storage().await?;
+            info!("批量添加了 {} 个关联关系 ({})", report.created, description);
+        }
+
+        Ok(report)
     }
 
     /// 移除文件关联关系
@@ -141,7 +318,7 @@ impl RelationManager {
 
         // 更新反向索引
         if let Some(incoming) = self.incoming_relations.get_mut(relative_to_file) {
-            incoming.retain(|(from, _)| from != relative_from_file);
+            incoming.retain(|relation| relation.source != relative_from_file);
             if incoming.is_empty() {
                 self.incoming_relations.remove(relative_to_file);
             }
@@ -162,31 +339,104 @@ impl RelationManager {
             .unwrap_or_default()
     }
 
-    /// 获取文件的入向关联关系
-    pub fn get_incoming_relations(&self, file_path: &str) -> Vec<Relation> {
-        self.incoming_relations
+    /// 获取文件的入向关联关系，按源文件、描述排序
+    ///
+    /// 反向索引由 HashMap 重建，其中的顺序不稳定，因此返回前排序
+    pub fn get_incoming_relations(&self, file_path: &str) -> Vec<IncomingRelation> {
+        let mut incoming = self
+            .incoming_relations
             .get(file_path)
-            .map(|incoming| {
-                incoming
-                    .iter()
-                    .map(|(from_file, description)| Relation {
-                        target: from_file.clone(),
-                        description: description.clone(),
-                    })
-                    .collect()
-            })
-            .unwrap_or_default()
+            .cloned()
+            .unwrap_or_default();
+        incoming.sort_by(|a, b| a.source.cmp(&b.source).then_with(|| a.description.cmp(&b.description)));
+        incoming
+    }
+
+    /// 根据描述搜索关联关系，`case_sensitive` 为 false 时忽略大小写
+    pub fn query_relations_by_description(&self, keyword: &str, case_sensitive: bool) -> Vec<(String, Relation)> {
+        self.find_relations(keyword, None, None, case_sensitive)
+    }
+
+    /// 移除所有源文件中目标路径匹配 glob 的关联关系，返回移除的数量
+    ///
+    /// 以 `/` 结尾的模式视为目录，匹配其下所有文件（等同于 `dir/**`）
+    pub async fn remove_relations_to(&mut self, target_glob: &str) -> Result<usize> {
+        let pattern = if target_glob.ends_with('/') {
+            format!("{}**", target_glob)
+        } else {
+            target_glob.to_string()
+        };
+        let matcher = compile_path_glob(&pattern)?;
+
+        let mut removed_count = 0;
+        for relations in self.file_relations.values_mut() {
+            let before = relations.len();
+            relations.retain(|relation| !matcher.is_match(&relation.target));
+            removed_count += before - relations.len();
+        }
+
+        if removed_count > 0 {
+            self.file_relations.retain(|_, relations| !relations.is_empty());
+            self.build_incoming_index();
+            self.save_to_storage().await?;
+            info!("移除了 {} 个指向 {} 的关联关系", removed_count, target_glob);
+        }
+
+        Ok(removed_count)
+    }
+
+    /// 将描述与 `old` 完全相同的关联改为 `new`，只保存一次，返回修改的数量
+    pub async fn replace_description(&mut self, old: &str, new: &str) -> Result<usize> {
+        self.validate_description(new)?;
+
+        let mut replaced_count = 0;
+        for relation in self.file_relations.values_mut().flatten() {
+            if relation.description == old {
+                relation.description = new.to_string();
+                replaced_count += 1;
+            }
+        }
+
+        if replaced_count > 0 {
+            self.build_incoming_index();
+            self.save_to_storage().await?;
+            info!("将 {} 个关联描述从 {} 替换为 {}", replaced_count, old, new);
+        }
+
+        Ok(replaced_count)
     }
 
-    /// 根据描述搜索关联关系
-    pub fn query_relations_by_description(&self, keyword: &str) -> Vec<(String, Relation)> {
+    /// 按描述关键词搜索关联关系（忽略大小写），可按源文件和目标文件过滤，结果按源文件排序
+    pub fn search_relations(&self, keyword: &str, from_file: Option<&str>, to_file: Option<&str>) -> Vec<(String, Relation)> {
+        self.find_relations(keyword, from_file, to_file, false)
+    }
+
+    /// 按描述关键词和源/目标文件过滤关联关系，结果按源文件排序
+    fn find_relations(
+        &self,
+        keyword: &str,
+        from_file: Option<&str>,
+        to_file: Option<&str>,
+        case_sensitive: bool,
+    ) -> Vec<(String, Relation)> {
         let keyword_lower = keyword.to_lowercase();
         let mut results = Vec::new();
 
-        for (from_file, relations) in &self.file_relations {
+        for (source, relations) in &self.file_relations {
+            if from_file.is_some_and(|from_file| from_file != source) {
+                continue;
+            }
             for relation in relations {
-                if relation.description.to_lowercase().contains(&keyword_lower) {
-                    results.push((from_file.clone(), relation.clone()));
+                if to_file.is_some_and(|to_file| to_file != relation.target) {
+                    continue;
+                }
+                let matched = if case_sensitive {
+                    relation.description.contains(keyword)
+                } else {
+                    relation.description.to_lowercase().contains(&keyword_lower)
+                };
+                if matched {
+                    results.push((source.clone(), relation.clone()));
                 }
             }
         }
@@ -216,6 +466,95 @@ impl RelationManager {
         files
     }
 
+    /// 获取作为关联源或目标出现的文件列表
+    pub fn get_files_in_relations(&self) -> Vec<String> {
+        let files: std::collections::BTreeSet<&String> = self
+            .file_relations
+            .keys()
+            .chain(self.incoming_relations.keys())
+            .collect();
+        files.into_iter().cloned().collect()
+    }
+
+    /// 统计所有关联使用的描述及次数，按次数降序、描述升序排列
+    pub fn describe_vocabulary(&self) -> Vec<(String, usize)> {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for relation in self.file_relations.values().flatten() {
+            *counts.entry(relation.description.as_str()).or_default() += 1;
+        }
+
+        let mut vocabulary: Vec<(String, usize)> = counts
+            .into_iter()
+            .map(|(description, count)| (description.to_string(), count))
+            .collect();
+        vocabulary.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        vocabulary
+    }
+
+    /// 按入向关联数量降序排列文件（被依赖最多的文件在前），数量相同时按路径排序
+    pub fn rank_by_incoming(&self, limit: usize) -> Vec<(String, usize)> {
+        Self::rank_by_degree(
+            self.incoming_relations.iter().map(|(file, sources)| (file, sources.len())),
+            limit,
+        )
+    }
+
+    /// 按出向关联数量降序排列文件（依赖最多的文件在前），数量相同时按路径排序
+    pub fn rank_by_outgoing(&self, limit: usize) -> Vec<(String, usize)> {
+        Self::rank_by_degree(
+            self.file_relations.iter().map(|(file, relations)| (file, relations.len())),
+            limit,
+        )
+    }
+
+    fn rank_by_degree<'a>(degrees: impl Iterator<Item = (&'a String, usize)>, limit: usize) -> Vec<(String, usize)> {
+        let mut ranked: Vec<(String, usize)> = degrees
+            .filter(|(_, degree)| *degree > 0)
+            .map(|(file, degree)| (file.clone(), degree))
+            .collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked.truncate(limit);
+        ranked
+    }
+
+    /// 将关联视为无向图，返回连通分量（互相可达的文件组）
+    ///
+    /// 每组内文件按路径排序，各组按文件数量降序、首个路径升序排列
+    pub fn connected_components(&self) -> Vec<Vec<String>> {
+        let mut neighbors: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (source, relations) in &self.file_relations {
+            neighbors.entry(source.as_str()).or_default();
+            for relation in relations {
+                neighbors.entry(source.as_str()).or_default().push(relation.target.as_str());
+                neighbors.entry(relation.target.as_str()).or_default().push(source.as_str());
+            }
+        }
+
+        let mut visited: HashSet<&str> = HashSet::new();
+        let mut components = Vec::new();
+        for &start in neighbors.keys() {
+            if !visited.insert(start) {
+                continue;
+            }
+
+            let mut component = vec![start.to_string()];
+            let mut queue = VecDeque::from([start]);
+            while let Some(file) = queue.pop_front() {
+                for &next in &neighbors[file] {
+                    if visited.insert(next) {
+                        component.push(next.to_string());
+                        queue.push_back(next);
+                    }
+                }
+            }
+            component.sort();
+            components.push(component);
+        }
+
+        components.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a[0].cmp(&b[0])));
+        components
+    }
+
     /// 获取关联关系统计信息
     pub fn get_stats(&self) -> (usize, usize, usize) {
         let total_files_with_relations = self.file_relations.len();
@@ -224,88 +563,197 @@ impl RelationManager {
         (total_files_with_relations, total_relations, total_incoming_files)
     }
 
-    /// 获取文件的关联图谱（递归查找）
-    pub fn get_relation_graph(&self, file_path: &str, max_depth: usize) -> HashMap<String, Vec<Relation>> {
-        let mut graph = HashMap::new();
-        let mut visited = std::collections::HashSet::new();
-        self.build_relation_graph(file_path, max_depth, 0, &mut graph, &mut visited);
+    /// 获取文件的关联图谱（按广度优先沿出向关联展开）
+    ///
+    /// 图谱中的源文件数量达到 `max_nodes` 时停止展开，并将 `truncated` 置为 true
+    pub fn get_relation_graph(&self, file_path: &str, max_depth: usize, max_nodes: usize) -> RelationGraph {
+        let mut graph = RelationGraph::default();
+        let mut visited = HashSet::from([file_path.to_string()]);
+        let mut queue = VecDeque::from([(file_path.to_string(), 0)]);
+
+        while let Some((current, depth)) = queue.pop_front() {
+            if depth >= max_depth {
+                continue;
+            }
+            let Some(relations) = self.file_relations.get(&current) else {
+                continue;
+            };
+            if graph.relations.len() >= max_nodes {
+                graph.truncated = true;
+                break;
+            }
+
+            for relation in relations {
+                if visited.insert(relation.target.clone()) {
+                    queue.push_back((relation.target.clone(), depth + 1));
+                }
+            }
+            graph.relations.insert(current, relations.clone());
+        }
+
         graph
     }
 
-    /// 递归构建关联图谱
-    fn build_relation_graph(
-        &self,
-        file_path: &str,
-        max_depth: usize,
-        current_depth: usize,
-        graph: &mut HashMap<String, Vec<Relation>>,
-        visited: &mut std::collections::HashSet<String>,
-    ) {
-        if current_depth >= max_depth || visited.contains(file_path) {
-            return;
+    /// 沿出向关联查找总权重最小的路径（Dijkstra），不可达时返回 None
+    ///
+    /// 总权重相同时按路径字典序选择，保证结果稳定
+    pub fn find_weighted_path(&self, from_file: &str, to_file: &str) -> Option<WeightedPath> {
+        let mut best: HashMap<String, (f64, Vec<String>)> = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        best.insert(from_file.to_string(), (0.0, vec![from_file.to_string()]));
+        heap.push(PathState { cost: 0.0, path: vec![from_file.to_string()] });
+
+        while let Some(PathState { cost, path }) = heap.pop() {
+            let node = path.last().cloned().unwrap_or_default();
+            if node == to_file {
+                return Some(WeightedPath { path, total_weight: cost });
+            }
+            if best.get(&node).is_some_and(|(best_cost, best_path)| (*best_cost, best_path) < (cost, &path)) {
+                continue;
+            }
+
+            for relation in self.file_relations.get(&node).into_iter().flatten() {
+                let next_cost = cost + relation.weight();
+                let mut next_path = path.clone();
+                next_path.push(relation.target.clone());
+                let improved = best
+                    .get(&relation.target)
+                    .is_none_or(|(best_cost, best_path)| (next_cost, &next_path) < (*best_cost, best_path));
+                if improved {
+                    best.insert(relation.target.clone(), (next_cost, next_path.clone()));
+                    heap.push(PathState { cost: next_cost, path: next_path });
+                }
+            }
         }
 
-        visited.insert(file_path.to_string());
+        None
+    }
 
-        if let Some(relations) = self.file_relations.get(file_path) {
-            graph.insert(file_path.to_string(), relations.clone());
+    /// 导出全部关联关系为 Graphviz DOT 格式
+    pub fn to_dot(&self) -> String {
+        Self::render_dot(&self.file_relations)
+    }
 
-            // 递归查找关联文件的关联关系
-            for relation in relations {
-                self.build_relation_graph(
-                    &relation.target,
-                    max_depth,
-                    current_depth + 1,
-                    graph,
-                    visited,
-                );
-            }
+    /// 将关联关系映射渲染为 DOT 有向图，节点和边按路径排序以保证输出稳定
+    pub fn render_dot(relations: &HashMap<String, Vec<Relation>>) -> String {
+        let graph = Self::render_json_graph(relations);
+
+        let mut dot = String::from("digraph codenexus {\n");
+        for node in &graph.nodes {
+            dot.push_str(&format!("    \"{}\";\n", escape_dot(&node.id)));
+        }
+        for edge in &graph.edges {
+            dot.push_str(&format!(
+                "    \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                escape_dot(&edge.source),
+                escape_dot(&edge.target),
+                escape_dot(&edge.description)
+            ));
         }
+        dot.push_str("}\n");
+        dot
     }
 
-    /// 清理不存在文件的关联关系
-    pub async fn cleanup_invalid_relations(&mut self) -> Result<usize> {
-        let mut removed_count = 0;
-        let mut files_to_remove = Vec::new();
-        let mut relations_to_update = Vec::new();
+    /// 导出全部关联关系为 GraphML
+    pub fn to_graphml(&self) -> String {
+        Self::render_graphml(&self.file_relations)
+    }
+
+    /// 导出全部关联关系为通用 JSON 图结构
+    pub fn to_json_graph(&self) -> JsonGraph {
+        Self::render_json_graph(&self.file_relations)
+    }
 
-        // 检查源文件是否存在
-        for file_path in self.file_relations.keys() {
-            if !Path::new(file_path).exists() {
-                files_to_remove.push(file_path.clone());
+    /// 将关联关系映射转换为 JSON 图结构，节点和边按路径排序
+    pub fn render_json_graph(relations: &HashMap<String, Vec<Relation>>) -> JsonGraph {
+        let mut nodes = std::collections::BTreeSet::new();
+        let mut edges = Vec::new();
+        for (from_file, file_relations) in relations {
+            nodes.insert(from_file.clone());
+            for relation in file_relations {
+                nodes.insert(relation.target.clone());
+                edges.push(GraphEdge {
+                    source: from_file.clone(),
+                    target: relation.target.clone(),
+                    description: relation.description.clone(),
+                });
             }
         }
+        edges.sort_by(|a, b| {
+            (&a.source, &a.target, &a.description).cmp(&(&b.source, &b.target, &b.description))
+        });
+
+        JsonGraph {
+            nodes: nodes.into_iter().map(|id| GraphNode { id }).collect(),
+            edges,
+        }
+    }
+
+    /// 将关联关系映射渲染为 GraphML 文档
+    pub fn render_graphml(relations: &HashMap<String, Vec<Relation>>) -> String {
+        let graph = Self::render_json_graph(relations);
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+        xml.push_str("  <key id=\"path\" for=\"node\" attr.name=\"path\" attr.type=\"string\"/>\n");
+        xml.push_str("  <key id=\"description\" for=\"edge\" attr.name=\"description\" attr.type=\"string\"/>\n");
+        xml.push_str("  <graph id=\"codenexus\" edgedefault=\"directed\">\n");
+        for node in &graph.nodes {
+            let id = escape_xml(&node.id);
+            xml.push_str(&format!(
+                "    <node id=\"{}\"><data key=\"path\">{}</data></node>\n",
+                id, id
+            ));
+        }
+        for (index, edge) in graph.edges.iter().enumerate() {
+            xml.push_str(&format!(
+                "    <edge id=\"e{}\" source=\"{}\" target=\"{}\"><data key=\"description\">{}</data></edge>\n",
+                index,
+                escape_xml(&edge.source),
+                escape_xml(&edge.target),
+                escape_xml(&edge.description)
+            ));
+        }
+        xml.push_str("  </graph>\n</graphml>\n");
+        xml
+    }
+
+    /// 计算清理计划：源文件或目标文件在项目中已不存在的关联关系，不修改任何数据
+    pub fn plan_invalid_relations_cleanup(&self, project_root: &Path) -> RelationCleanupPlan {
+        let exists = |file_path: &str| project_root.join(file_path).exists();
+        let mut plan = RelationCleanupPlan::default();
+        let mut affected = std::collections::BTreeSet::new();
 
-        // 检查目标文件是否存在
         for (from_file, relations) in &self.file_relations {
-            let mut valid_relations = Vec::new();
+            let source_exists = exists(from_file);
             for relation in relations {
-                if Path::new(&relation.target).exists() {
-                    valid_relations.push(relation.clone());
-                } else {
-                    removed_count += 1;
-                    debug!("清理了指向不存在文件的关联: {} -> {}", from_file, relation.target);
+                if !source_exists || !exists(&relation.target) {
+                    plan.removed.push(RelationPair {
+                        from: from_file.clone(),
+                        to: relation.target.clone(),
+                    });
+                    affected.insert(from_file.clone());
                 }
             }
-            if valid_relations.len() != relations.len() {
-                relations_to_update.push((from_file.clone(), valid_relations));
-            }
         }
 
-        // 移除不存在的源文件
-        for file_path in files_to_remove {
-            if let Some(relations) = self.file_relations.remove(&file_path) {
-                removed_count += relations.len();
-                debug!("清理了不存在文件的所有关联: {}", file_path);
-            }
-        }
+        plan.removed.sort_by(|a, b| a.from.cmp(&b.from).then_with(|| a.to.cmp(&b.to)));
+        plan.affected_files = affected.into_iter().collect();
+        plan
+    }
 
-        // 更新有效的关联关系
-        for (from_file, valid_relations) in relations_to_update {
-            if valid_relations.is_empty() {
-                self.file_relations.remove(&from_file);
-            } else {
-                self.file_relations.insert(from_file, valid_relations);
+    /// 执行清理计划，返回移除的关联数量
+    pub async fn apply_relations_cleanup(&mut self, plan: &RelationCleanupPlan) -> Result<usize> {
+        let mut removed_count = 0;
+        for pair in &plan.removed {
+            if let Some(relations) = self.file_relations.get_mut(&pair.from) {
+                let before = relations.len();
+                relations.retain(|relation| relation.target != pair.to);
+                removed_count += before - relations.len();
+                if relations.is_empty() {
+                    self.file_relations.remove(&pair.from);
+                }
+                debug!("清理了无效关联: {} -> {}", pair.from, pair.to);
             }
         }
 
@@ -318,12 +766,473 @@ impl RelationManager {
         Ok(removed_count)
     }
 
+    /// 清理源文件或目标文件在项目中已不存在的关联关系，返回移除的数量
+    pub async fn cleanup_invalid_relations(&mut self, project_root: &Path) -> Result<usize> {
+        let plan = self.plan_invalid_relations_cleanup(project_root);
+        self.apply_relations_cleanup(&plan).await
+    }
+
+    /// 验证一组关联关系（目标和描述不能为空）
+    pub fn validate_relations(&self, relations: &HashMap<String, Vec<Relation>>) -> Result<()> {
+        for relation in relations.values().flatten() {
+            if relation.target.trim().is_empty() {
+                return Err(CodeNexusError::ConfigError(Message::EmptyRelationTarget));
+            }
+            self.validate_description(&relation.description)?;
+        }
+        Ok(())
+    }
+
+    /// 导入关联关系数据，返回新增的关联关系数量
+    ///
+    /// 所有关联先通过验证，任一不合法则整体拒绝；合并模式下跳过已存在的同目标关联
+    pub async fn import_relations(&mut self, relations: HashMap<String, Vec<Relation>>, mode: ImportMode) -> Result<usize> {
+        self.validate_relations(&relations)?;
+
+        if mode == ImportMode::Replace {
+            self.file_relations.clear();
+        }
+
+        let mut imported_count = 0;
+        for (from_file, file_relations) in relations {
+            let existing = self.file_relations.entry(from_file).or_default();
+            for relation in file_relations {
+                if !existing.iter().any(|r| r.target == relation.target) {
+                    existing.push(relation);
+                    imported_count += 1;
+                }
+            }
+        }
+        self.file_relations.retain(|_, relations| !relations.is_empty());
+
+        self.build_incoming_index();
+        self.save_to_storage().await?;
+        info!("导入了 {} 个关联关系", imported_count);
+
+        Ok(imported_count)
+    }
+
     /// 保存数据到存储
     async fn save_to_storage(&self) -> Result<()> {
         let data = RelationsData {
-            file_relations: self.file_relations.clone(),
+            file_relations: self.file_relations.clone().into_iter().collect(),
         };
 
         self.storage.save_relations(&data).await
     }
 }
+
+/// Dijkstra 优先队列中的状态，代价小（其次路径字典序小）的优先出队
+#[derive(Debug, PartialEq)]
+struct PathState {
+    cost: f64,
+    path: Vec<String>,
+}
+
+impl Eq for PathState {}
+
+impl Ord for PathState {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost).then_with(|| other.path.cmp(&self.path))
+    }
+}
+
+impl PartialOrd for PathState {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// 转义 XML 属性和文本中的特殊字符
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 转义 DOT 双引号字符串中的特殊字符
+fn escape_dot(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// 创建带有测试文件的临时项目和已初始化的关联关系管理器
+    async fn setup(files: &[&str]) -> (TempDir, RelationManager) {
+        let temp_dir = TempDir::new().unwrap();
+        for file in files {
+            std::fs::write(temp_dir.path().join(file), "").unwrap();
+        }
+        let storage = JsonStorage::new(temp_dir.path().join(".codenexus"));
+        storage.initialize().await.unwrap();
+        let mut manager = RelationManager::new(storage);
+        manager.initialize().await.unwrap();
+        (temp_dir, manager)
+    }
+
+    fn relation(target: &str, description: &str) -> Relation {
+        Relation {
+            target: target.to_string(),
+            description: description.to_string(),
+            weight: None,
+        }
+    }
+
+    #[test]
+    fn test_render_dot_escapes_special_characters() {
+        let mut relations = HashMap::new();
+        relations.insert(
+            "src/a \"quoted\".rs".to_string(),
+            vec![relation("src/b.rs", "调用 \\ 解析\n结果")],
+        );
+
+        let dot = RelationManager::render_dot(&relations);
+        assert_eq!(
+            dot,
+            "digraph codenexus {\n    \"src/a \\\"quoted\\\".rs\";\n    \"src/b.rs\";\n    \"src/a \\\"quoted\\\".rs\" -> \"src/b.rs\" [label=\"调用 \\\\ 解析\\n结果\"];\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_graphml_is_well_formed() {
+        let mut relations = HashMap::new();
+        relations.insert(
+            "src/a&b.rs".to_string(),
+            vec![relation("src/<c>.rs", "依赖 \"config\" & 'env'")],
+        );
+        relations.insert("src/<c>.rs".to_string(), vec![relation("src/d.rs", "调用")]);
+
+        let xml = RelationManager::render_graphml(&relations);
+        let document = roxmltree::Document::parse(&xml).expect("GraphML 应该是合法的 XML");
+
+        let nodes: Vec<&str> = document
+            .descendants()
+            .filter(|n| n.has_tag_name("node"))
+            .filter_map(|n| n.attribute("id"))
+            .collect();
+        assert_eq!(nodes, vec!["src/<c>.rs", "src/a&b.rs", "src/d.rs"]);
+
+        let edge = document
+            .descendants()
+            .find(|n| n.has_tag_name("edge") && n.attribute("source") == Some("src/a&b.rs"))
+            .unwrap();
+        assert_eq!(edge.attribute("target"), Some("src/<c>.rs"));
+        assert_eq!(edge.first_element_child().and_then(|d| d.text()), Some("依赖 \"config\" & 'env'"));
+
+        let graph = RelationManager::render_json_graph(&relations);
+        assert_eq!(graph.nodes.len(), 3);
+        assert_eq!(graph.edges.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rank_by_degree() {
+        let (_temp_dir, mut manager) = setup(&[]).await;
+
+        let mut relations = HashMap::new();
+        relations.insert("a.rs".to_string(), vec![relation("core.rs", "依赖"), relation("util.rs", "依赖")]);
+        relations.insert("b.rs".to_string(), vec![relation("core.rs", "依赖")]);
+        relations.insert("util.rs".to_string(), vec![relation("core.rs", "依赖")]);
+        manager.import_relations(relations, ImportMode::Replace).await.unwrap();
+
+        assert_eq!(
+            manager.rank_by_incoming(10),
+            vec![("core.rs".to_string(), 3), ("util.rs".to_string(), 1)]
+        );
+        assert_eq!(
+            manager.rank_by_outgoing(2),
+            vec![("a.rs".to_string(), 2), ("b.rs".to_string(), 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_relations_with_filters() {
+        let (_temp_dir, mut manager) = setup(&[]).await;
+
+        let mut relations = HashMap::new();
+        relations.insert("src/login.rs".to_string(), vec![relation("src/auth.rs", "调用 Auth 校验"), relation("src/db.rs", "读取用户")]);
+        relations.insert("src/api.rs".to_string(), vec![relation("src/auth.rs", "auth 中间件")]);
+        manager.import_relations(relations, ImportMode::Replace).await.unwrap();
+
+        let sources = |results: Vec<(String, Relation)>| results.into_iter().map(|(from, _)| from).collect::<Vec<_>>();
+        assert_eq!(sources(manager.search_relations("AUTH", None, None)), vec!["src/api.rs", "src/login.rs"]);
+        assert_eq!(sources(manager.search_relations("auth", Some("src/login.rs"), None)), vec!["src/login.rs"]);
+        assert!(manager.search_relations("auth", None, Some("src/db.rs")).is_empty());
+        assert_eq!(manager.search_relations("", None, Some("src/db.rs")).len(), 1);
+        assert_eq!(sources(manager.query_relations_by_description("Auth", true)), vec!["src/login.rs"]);
+        assert!(manager.query_relations_by_description("AUTH", true).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_remove_relations_to() {
+        let (_temp_dir, mut manager) = setup(&[]).await;
+
+        let mut relations = HashMap::new();
+        relations.insert("src/a.rs".to_string(), vec![relation("src/old/x.rs", "调用"), relation("src/new.rs", "调用")]);
+        relations.insert("src/b.rs".to_string(), vec![relation("src/old/deep/y.rs", "依赖")]);
+        manager.import_relations(relations, ImportMode::Replace).await.unwrap();
+
+        assert_eq!(manager.remove_relations_to("src/old/").await.unwrap(), 2);
+        assert!(manager.get_file_relations("src/b.rs").is_empty());
+        assert!(manager.get_incoming_relations("src/old/x.rs").is_empty());
+        assert_eq!(manager.get_file_relations("src/a.rs").len(), 1);
+        assert_eq!(manager.remove_relations_to("src/*.rs").await.unwrap(), 1);
+        assert!(manager.remove_relations_to("src/[").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_replace_description() {
+        let (_temp_dir, mut manager) = setup(&[]).await;
+
+        let mut relations = HashMap::new();
+        relations.insert("a.rs".to_string(), vec![relation("b.rs", "depends_on"), relation("c.rs", "depends_on v2")]);
+        relations.insert("b.rs".to_string(), vec![relation("c.rs", "depends_on")]);
+        manager.import_relations(relations, ImportMode::Replace).await.unwrap();
+
+        assert!(manager.replace_description("depends_on", "  ").await.is_err());
+        assert_eq!(manager.replace_description("depends_on", "depends on").await.unwrap(), 2);
+        assert_eq!(manager.get_incoming_relations("c.rs").iter().filter(|r| r.description == "depends on").count(), 1);
+        assert!(manager.check_integrity().is_empty());
+        assert_eq!(manager.replace_description("missing", "x").await.unwrap(), 0);
+
+        let saved = manager.storage.load_relations().await.unwrap();
+        assert_eq!(saved.file_relations["a.rs"][1].description, "depends_on v2");
+        assert_eq!(saved.file_relations["b.rs"][0].description, "depends on");
+    }
+
+    #[tokio::test]
+    async fn test_add_relations_bulk() {
+        let (_temp_dir, mut manager) = setup(&[]).await;
+
+        let mut relations = HashMap::new();
+        relations.insert("c1.rs".to_string(), vec![relation("s1.rs", "已有")]);
+        manager.import_relations(relations, ImportMode::Replace).await.unwrap();
+
+        let files = |list: &[&str]| list.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+        let report = manager
+            .add_relations_bulk(&files(&["c1.rs", "c2.rs", "s1.rs"]), &files(&["s1.rs", "s2.rs"]), "调用服务")
+            .await
+            .unwrap();
+        assert_eq!(report.created, 4);
+        assert_eq!(report.skipped.len(), 2);
+        assert_eq!(manager.get_incoming_relations("s2.rs").len(), 3);
+        let sources: Vec<String> = manager.get_incoming_relations("s2.rs").into_iter().map(|r| r.source).collect();
+        assert!(sources.windows(2).all(|pair| pair[0] <= pair[1]), "入向关联应按源文件排序: {:?}", sources);
+        assert_eq!(manager.get_file_relations("c1.rs")[0].description, "已有");
+        assert!(manager.add_relations_bulk(&files(&["a.rs"]), &files(&["b.rs"]), " ").await.is_err());
+    }
+
+    /// 只设置关联描述格式的配置
+    fn set_pattern(manager: &mut RelationManager, pattern: Option<&str>) {
+        let regex = RelationManager::compile_description_pattern(pattern).unwrap();
+        let config = ProjectConfig { relation_description_pattern: pattern.map(str::to_string), ..Default::default() };
+        manager.set_config(Arc::new(config), regex);
+    }
+
+    #[tokio::test]
+    async fn test_description_pattern() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs"]).await;
+        let root = temp_dir.path();
+        assert!(RelationManager::compile_description_pattern(Some("[")).is_err());
+        set_pattern(&mut manager, Some(r"\w+: .+"));
+        assert_eq!(manager.relation_config().description_pattern.as_deref(), Some(r"\w+: .+"));
+
+        // 必须完整匹配
+        let err = manager.add_relation(&root.join("a.rs"), "a.rs", &root.join("b.rs"), "b.rs", "调用解析", None).await.unwrap_err();
+        assert!(matches!(err, CodeNexusError::InvalidRelationDescription { ref pattern, .. } if pattern == r"\w+: .+"));
+        manager.add_relation(&root.join("a.rs"), "a.rs", &root.join("b.rs"), "b.rs", "calls: parser", None).await.unwrap();
+
+        // 未配置时只要求非空
+        set_pattern(&mut manager, None);
+        assert!(manager.validate_description("任意描述").is_ok());
+        assert!(manager.validate_description("  ").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_relation_limit_and_duplicate_description() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs", "c.rs", "d.rs"]).await;
+        let root = temp_dir.path();
+        manager.set_config(Arc::new(ProjectConfig { max_relations_per_file: Some(2), ..Default::default() }), None);
+
+        let warnings = manager.add_relation(&root.join("a.rs"), "a.rs", &root.join("b.rs"), "b.rs", "调用解析", None).await.unwrap();
+        assert!(warnings.is_empty());
+
+        // 指向其他目标的相同描述给出警告，但仍然添加
+        let warnings = manager.add_relation(&root.join("a.rs"), "a.rs", &root.join("c.rs"), "c.rs", "调用解析", None).await.unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("b.rs"));
+        assert!(manager.has_relation("a.rs", "c.rs"));
+
+        assert!(matches!(
+            manager.add_relation(&root.join("a.rs"), "a.rs", &root.join("d.rs"), "d.rs", "其他", None).await,
+            Err(CodeNexusError::RelationLimitExceeded { limit: 2, .. })
+        ));
+        let files = |list: &[&str]| list.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+        let report = manager.add_relations_bulk(&files(&["a.rs", "b.rs"]), &files(&["d.rs"]), "其他").await.unwrap();
+        assert_eq!(report.created, 1);
+        assert_eq!(report.skipped, vec![RelationPair { from: "a.rs".to_string(), to: "d.rs".to_string() }]);
+    }
+
+    #[test]
+    fn test_find_weighted_path() {
+        let weighted = |target: &str, weight: Option<f64>| Relation { weight, ..relation(target, "依赖") };
+        let build = |direct_weight: Option<f64>| {
+            let mut manager = RelationManager::new(JsonStorage::new("unused"));
+            manager.file_relations.insert("a.rs".to_string(), vec![weighted("b.rs", None), weighted("d.rs", direct_weight)]);
+            manager.file_relations.insert("b.rs".to_string(), vec![weighted("c.rs", None)]);
+            manager.file_relations.insert("c.rs".to_string(), vec![weighted("d.rs", None)]);
+            manager
+        };
+        let path = |files: &[&str]| files.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+
+        // 未设置权重时每条关联为 1，直接关联最短
+        let unweighted = build(None).find_weighted_path("a.rs", "d.rs").unwrap();
+        assert_eq!(unweighted.path, path(&["a.rs", "d.rs"]));
+        assert_eq!(unweighted.total_weight, 1.0);
+
+        // 直接关联代价高时绕行
+        let manager = build(Some(10.0));
+        let weighted_path = manager.find_weighted_path("a.rs", "d.rs").unwrap();
+        assert_eq!(weighted_path.path, path(&["a.rs", "b.rs", "c.rs", "d.rs"]));
+        assert_eq!(weighted_path.total_weight, 3.0);
+
+        assert_eq!(manager.find_weighted_path("a.rs", "a.rs").unwrap().path, path(&["a.rs"]));
+        assert!(manager.find_weighted_path("d.rs", "a.rs").is_none());
+    }
+
+    #[test]
+    fn test_relation_weight_defaults_when_missing() {
+        let relation: Relation = serde_json::from_str(r#"{"target":"b.rs","description":"调用"}"#).unwrap();
+        assert_eq!(relation.weight, None);
+        assert_eq!(relation.weight(), 1.0);
+        assert_eq!(serde_json::to_string(&relation).unwrap(), r#"{"target":"b.rs","description":"调用"}"#);
+    }
+
+    #[test]
+    fn test_connected_components() {
+        let mut manager = RelationManager::new(JsonStorage::new("unused"));
+        manager.file_relations.insert("a.rs".to_string(), vec![relation("b.rs", "调用")]);
+        manager.file_relations.insert("c.rs".to_string(), vec![relation("b.rs", "调用"), relation("d.rs", "依赖")]);
+        manager.file_relations.insert("x.rs".to_string(), vec![relation("y.rs", "调用")]);
+        manager.file_relations.insert("y.rs".to_string(), vec![relation("x.rs", "回调")]);
+
+        assert_eq!(
+            manager.connected_components(),
+            vec![
+                vec!["a.rs".to_string(), "b.rs".to_string(), "c.rs".to_string(), "d.rs".to_string()],
+                vec!["x.rs".to_string(), "y.rs".to_string()],
+            ]
+        );
+        assert!(RelationManager::new(JsonStorage::new("unused")).connected_components().is_empty());
+    }
+
+    #[test]
+    fn test_describe_vocabulary() {
+        let mut manager = RelationManager::new(JsonStorage::new("unused"));
+        manager.file_relations.insert("a.rs".to_string(), vec![relation("b.rs", "depends on"), relation("c.rs", "调用")]);
+        manager.file_relations.insert("b.rs".to_string(), vec![relation("c.rs", "depends on"), relation("d.rs", "depends_on")]);
+
+        assert_eq!(
+            manager.describe_vocabulary(),
+            vec![("depends on".to_string(), 2), ("depends_on".to_string(), 1), ("调用".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_check_integrity() {
+        let mut manager = RelationManager::new(JsonStorage::new("unused"));
+        manager.file_relations.insert("a.rs".to_string(), vec![relation("b.rs", "调用")]);
+        manager.build_incoming_index();
+        assert!(manager.check_integrity().is_empty());
+
+        manager.file_relations.insert("c.rs".to_string(), vec![relation("b.rs", "依赖")]);
+        manager.incoming_relations.entry("d.rs".to_string()).or_default().push(IncomingRelation::new("a.rs", &relation("d.rs", "调用")));
+        assert_eq!(
+            manager.check_integrity(),
+            vec![
+                IntegrityIssue { kind: IntegrityIssueKind::MissingIncoming, file: "c.rs".to_string(), item: "b.rs".to_string() },
+                IntegrityIssue { kind: IntegrityIssueKind::StaleIncoming, file: "a.rs".to_string(), item: "d.rs".to_string() },
+            ]
+        );
+
+        manager.rebuild_incoming_index();
+        assert!(manager.check_integrity().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_invalid_relations_dry_run() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs"]).await;
+        let root = temp_dir.path();
+
+        let mut relations = HashMap::new();
+        relations.insert("a.rs".to_string(), vec![relation("b.rs", "调用"), relation("gone.rs", "调用")]);
+        relations.insert("deleted.rs".to_string(), vec![relation("a.rs", "调用")]);
+        manager.import_relations(relations, ImportMode::Replace).await.unwrap();
+
+        let plan = manager.plan_invalid_relations_cleanup(root);
+        assert_eq!(plan.removed.len(), 2);
+        assert_eq!(plan.affected_files, vec!["a.rs".to_string(), "deleted.rs".to_string()]);
+        // 计划不修改数据；入向关联的 source 为源文件
+        assert_eq!(
+            manager.get_incoming_relations("a.rs"),
+            vec![IncomingRelation { source: "deleted.rs".to_string(), description: "调用".to_string(), weight: None }]
+        );
+
+        assert_eq!(manager.apply_relations_cleanup(&plan).await.unwrap(), 2);
+        assert!(manager.get_incoming_relations("a.rs").is_empty());
+        assert_eq!(manager.get_file_relations("a.rs").len(), 1);
+        assert!(manager.plan_invalid_relations_cleanup(root).removed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_relation_graph_limits() {
+        let (_temp_dir, mut manager) = setup(&[]).await;
+
+        let mut relations = HashMap::new();
+        relations.insert("a.rs".to_string(), vec![relation("b.rs", "调用"), relation("c.rs", "调用")]);
+        relations.insert("b.rs".to_string(), vec![relation("d.rs", "调用")]);
+        relations.insert("c.rs".to_string(), vec![relation("a.rs", "回调")]);
+        relations.insert("d.rs".to_string(), vec![relation("e.rs", "调用")]);
+        manager.import_relations(relations, ImportMode::Replace).await.unwrap();
+
+        let sources = |graph: &RelationGraph| {
+            let mut files: Vec<String> = graph.relations.keys().cloned().collect();
+            files.sort();
+            files
+        };
+
+        let graph = manager.get_relation_graph("a.rs", 10, 100);
+        assert_eq!(sources(&graph), vec!["a.rs", "b.rs", "c.rs", "d.rs"]);
+        assert!(!graph.truncated);
+
+        let shallow = manager.get_relation_graph("a.rs", 2, 100);
+        assert_eq!(sources(&shallow), vec!["a.rs", "b.rs", "c.rs"]);
+        assert!(!shallow.truncated);
+
+        // 广度优先展开，达到上限时保留离根最近的文件
+        let capped = manager.get_relation_graph("a.rs", 10, 2);
+        assert_eq!(sources(&capped), vec!["a.rs", "b.rs"]);
+        assert!(capped.truncated);
+    }
+}
@@ -1,8 +1,38 @@
+use crate::config::ProjectConfig;
 use crate::error::{CodeNexusError, Result};
-use crate::storage::{JsonStorage, TagsData};
-use std::collections::{HashMap, HashSet};
+use crate::i18n::Message;
+use crate::models::{ImportMode, IntegrityIssue, IntegrityIssueKind, JsonlImportReport, JsonlTagRecord, RejectedRecord, TagCount, TagDetailedStats, TagImportReport, TagMergePlan, TagMeta, TagSchema, TagWithMeta, UntaggedFiles};
+use crate::query::{parse_query, QueryExpr};
+use crate::storage::{JsonStorage, TagIndexSnapshot, TagsData};
+use crate::utils::{compile_path_glob, fuzzy_score, normalize_file_path, scan_project_files, scan_project_files_with, validate_file_path};
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::path::Path;
-use tracing::{debug, info};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+use tracing::{debug, error, info, warn};
+
+/// 详细统计中返回的最常用标签数量
+const TOP_TAGS_LIMIT: usize = 10;
+
+/// 缓存的已编译通配符模式数量
+const WILDCARD_CACHE_CAPACITY: usize = 64;
+
+/// 严格模式下标签中不允许作为独立单词出现的查询关键字（与查询解析器一致：按空白分词、区分大小写）
+const RESERVED_WORDS: [&str; 3] = ["AND", "OR", "NOT"];
+
+/// 严格模式下标签中不允许出现的查询符号
+///
+/// `>=[` 排在 `=[` 之前，使错误信息报告完整的操作符
+const RESERVED_SYMBOLS: [&str; 6] = ["(", ")", "*", "?", ">=[", "=["];
+
+/// 查找文本中会被查询解析器当作操作符的片段
+fn find_reserved_token(text: &str) -> Option<&'static str> {
+    text.split_whitespace()
+        .find_map(|word| RESERVED_WORDS.iter().find(|reserved| **reserved == word))
+        .or_else(|| RESERVED_SYMBOLS.iter().find(|symbol| text.contains(**symbol)))
+        .copied()
+}
 
 /// 标签管理器
 #[derive(Debug)]
@@ -12,6 +42,133 @@ pub struct TagManager {
     file_tags: HashMap<String, HashSet<String>>,
     tag_index: HashMap<String, HashSet<String>>, // tag_type -> tag_values
     tag_to_files: HashMap<String, HashSet<String>>, // tag -> files
+    // 文件 -> 标签 -> 元数据（添加时间）
+    tag_meta: HashMap<String, HashMap<String, TagMeta>>,
+    // 文件 -> 已归档的标签，不进入索引，因此不参与查询和统计
+    archived_tags: HashMap<String, BTreeSet<String>>,
+    // 可选的标签模式
+    schema: Option<CompiledTagSchema>,
+    // 最近一次加载或保存的 tags.json 内容哈希
+    tags_hash: Option<String>,
+    // 磁盘上索引快照对应的 tags.json 内容哈希
+    snapshot_hash: Option<String>,
+    // 项目配置，读取 `strict_tag_values` 和 `path_case_fold`
+    config: Arc<ProjectConfig>,
+    // 已编译通配符模式的 LRU 缓存，查询只持有 &self，因此放在锁内
+    wildcard_cache: Mutex<WildcardCache>,
+}
+
+/// 已编译通配符模式的 LRU 缓存
+#[derive(Debug)]
+struct WildcardCache {
+    entries: HashMap<String, Arc<Regex>>,
+    // 按最近使用排序，最近使用的在末尾
+    order: VecDeque<String>,
+    capacity: usize,
+}
+
+impl WildcardCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    fn get(&mut self, pattern: &str) -> Option<Arc<Regex>> {
+        let regex = self.entries.get(pattern)?.clone();
+        if let Some(pos) = self.order.iter().position(|key| key == pattern) {
+            if let Some(key) = self.order.remove(pos) {
+                self.order.push_back(key);
+            }
+        }
+        Some(regex)
+    }
+
+    fn insert(&mut self, pattern: String, regex: Arc<Regex>) {
+        if self.entries.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.order.push_back(pattern.clone());
+        self.entries.insert(pattern, regex);
+    }
+}
+
+/// 将通配符模式编译为完整匹配的正则：`*` 对应 `.*`，`?` 对应单个字符，其余字符按字面匹配
+fn compile_wildcard(pattern: &str) -> std::result::Result<Regex, regex::Error> {
+    let mut source = String::from("(?s)^");
+    for c in pattern.chars() {
+        match c {
+            '*' => source.push_str(".*"),
+            '?' => source.push('.'),
+            _ => source.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    source.push('$');
+    Regex::new(&source)
+}
+
+/// 预编译正则后的标签模式
+#[derive(Debug)]
+struct CompiledTagSchema {
+    schema: TagSchema,
+    patterns: HashMap<String, Regex>, // tag_type -> 完整匹配的正则
+}
+
+impl CompiledTagSchema {
+    /// 编译标签模式中的正则表达式
+    fn compile(schema: TagSchema) -> Result<Self> {
+        let mut patterns = HashMap::new();
+        for (tag_type, rule) in &schema.types {
+            if let Some(pattern) = &rule.pattern {
+                let regex = Regex::new(&format!("^(?:{})$", pattern)).map_err(|e| {
+                    CodeNexusError::ConfigError(Message::InvalidTagTypePattern { tag_type: tag_type.clone(), error: e.to_string() })
+                })?;
+                patterns.insert(tag_type.clone(), regex);
+            }
+        }
+        Ok(Self { schema, patterns })
+    }
+
+    /// 检查标签是否符合模式
+    fn check(&self, tag: &str, tag_type: &str, tag_value: &str) -> Result<()> {
+        let violation = |reason: String| CodeNexusError::TagSchemaViolation {
+            tag: tag.to_string(),
+            reason,
+        };
+
+        let rule = self.schema.types.get(tag_type).ok_or_else(|| {
+            let allowed: Vec<&str> = self.schema.types.keys().map(String::as_str).collect();
+            violation(format!("不允许的标签类型 {}，允许的类型: {}", tag_type, allowed.join(", ")))
+        })?;
+
+        if let Some(values) = &rule.values {
+            if !values.iter().any(|v| v == tag_value) {
+                return Err(violation(format!(
+                    "标签类型 {} 不允许取值 {}，允许的取值: {}",
+                    tag_type,
+                    tag_value,
+                    values.join(", ")
+                )));
+            }
+        }
+
+        if let Some(regex) = self.patterns.get(tag_type) {
+            if !regex.is_match(tag_value) {
+                return Err(violation(format!(
+                    "标签类型 {} 的取值 {} 不匹配正则 {}",
+                    tag_type,
+                    tag_value,
+                    rule.pattern.as_deref().unwrap_or_default()
+                )));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl TagManager {
@@ -22,17 +179,148 @@ impl TagManager {
             file_tags: HashMap::new(),
             tag_index: HashMap::new(),
             tag_to_files: HashMap::new(),
+            tag_meta: HashMap::new(),
+            archived_tags: HashMap::new(),
+            schema: None,
+            tags_hash: None,
+            snapshot_hash: None,
+            config: Arc::default(),
+            wildcard_cache: Mutex::new(WildcardCache::new(WILDCARD_CACHE_CAPACITY)),
         }
     }
 
+    /// 设置项目配置（严格标签取值校验、路径大小写折叠）
+    pub fn set_config(&mut self, config: Arc<ProjectConfig>) {
+        self.config = config;
+    }
+
     /// 初始化管理器，加载数据到内存
+    ///
+    /// 若索引快照与 tags.json 内容哈希一致则直接恢复索引，否则重建并写回快照
     pub async fn initialize(&mut self) -> Result<()> {
-        let data = self.storage.load_tags().await?;
-        self.build_indices(&data);
+        // 先完成所有可能失败的读取，失败时保留现有数据
+        let schema = match self.storage.load_tag_schema().await? {
+            Some(schema) => {
+                info!("已启用标签模式，共 {} 个标签类型", schema.types.len());
+                Some(CompiledTagSchema::compile(schema)?)
+            }
+            None => None,
+        };
+        let (data, hash) = self.storage.load_tags_with_hash().await?;
+
+        self.schema = schema;
+        self.load_side_data(&data);
+
+        self.tags_hash = Some(hash.clone());
+        match self.storage.load_tag_index().await {
+            Some(snapshot) if snapshot.source_hash == hash => {
+                self.snapshot_hash = Some(snapshot.source_hash.clone());
+                self.restore_indices(&data, snapshot);
+                debug!("从索引快照恢复了标签索引");
+            }
+            _ => {
+                self.build_indices(&data);
+                if let Err(e) = self.save_index_snapshot().await {
+                    warn!("保存标签索引快照失败: {}", e);
+                }
+            }
+        }
+
         info!("标签管理器初始化完成，加载了 {} 个文件的标签", self.file_tags.len());
         Ok(())
     }
 
+    /// 检查内存索引与 `file_tags` 是否一致，结果按类型、文件和标签排序
+    pub fn check_integrity(&self) -> Vec<IntegrityIssue> {
+        let issue = |kind, file: &str, item: &str| IntegrityIssue {
+            kind,
+            file: file.to_string(),
+            item: item.to_string(),
+        };
+        let mut issues = Vec::new();
+
+        for (file_path, tags) in &self.file_tags {
+            for tag in tags {
+                if !self.tag_to_files.get(tag).is_some_and(|files| files.contains(file_path)) {
+                    issues.push(issue(IntegrityIssueKind::MissingTagFile, file_path, tag));
+                }
+                if let Some((tag_type, tag_value)) = tag.split_once(':') {
+                    if !self.tag_index.get(tag_type).is_some_and(|values| values.contains(tag_value)) {
+                        issues.push(issue(IntegrityIssueKind::MissingTagValue, tag_type, tag_value));
+                    }
+                }
+            }
+        }
+
+        for (tag, files) in &self.tag_to_files {
+            for file_path in files {
+                if !self.file_tags.get(file_path).is_some_and(|tags| tags.contains(tag)) {
+                    issues.push(issue(IntegrityIssueKind::StaleTagFile, file_path, tag));
+                }
+            }
+        }
+
+        for (tag_type, values) in &self.tag_index {
+            for tag_value in values {
+                if !self.tag_to_files.contains_key(&format!("{}:{}", tag_type, tag_value)) {
+                    issues.push(issue(IntegrityIssueKind::StaleTagValue, tag_type, tag_value));
+                }
+            }
+        }
+
+        issues.sort_by(|a, b| (a.kind as u8, &a.file, &a.item).cmp(&(b.kind as u8, &b.file, &b.item)));
+        issues.dedup();
+        issues
+    }
+
+    /// 根据 `file_tags` 重建全部索引，并刷新索引快照
+    pub async fn rebuild_indices(&mut self) -> Result<()> {
+        let data = TagsData {
+            file_tags: self.export_tags().into_iter().collect(),
+            ..Default::default()
+        };
+        self.build_indices(&data);
+        self.save_to_storage().await?;
+        info!("已重建标签索引");
+        Ok(())
+    }
+
+    /// 从快照恢复内存索引
+    fn restore_indices(&mut self, data: &TagsData, snapshot: TagIndexSnapshot) {
+        self.file_tags = data
+            .file_tags
+            .iter()
+            .map(|(file_path, tags)| (file_path.clone(), tags.iter().cloned().collect()))
+            .collect();
+        self.tag_index = snapshot
+            .tag_index
+            .into_iter()
+            .map(|(tag_type, values)| (tag_type, values.into_iter().collect()))
+            .collect();
+        self.tag_to_files = snapshot
+            .tag_to_files
+            .into_iter()
+            .map(|(tag, files)| (tag, files.into_iter().collect()))
+            .collect();
+    }
+
+    /// 生成当前索引的快照
+    fn index_snapshot(&self, source_hash: String) -> TagIndexSnapshot {
+        TagIndexSnapshot {
+            source_hash,
+            tag_index: self
+                .tag_index
+                .iter()
+                .map(|(tag_type, values)| (tag_type.clone(), sorted(values)))
+                .collect(),
+            tag_to_files: self
+                .tag_to_files
+                .iter()
+                .map(|(tag, files)| (tag.clone(), sorted(files)))
+                .collect(),
+        }
+    }
+
     /// 构建内存索引
     fn build_indices(&mut self, data: &TagsData) {
         self.file_tags.clear();
@@ -68,6 +356,13 @@ impl TagManager {
 
     /// 移除索引中的标签
     fn remove_from_indices(&mut self, tag: &str, file_path: &str) {
+        if let Some(tags) = self.tag_meta.get_mut(file_path) {
+            tags.remove(tag);
+            if tags.is_empty() {
+                self.tag_meta.remove(file_path);
+            }
+        }
+
         // 从标签到文件映射中移除
         if let Some(files) = self.tag_to_files.get_mut(tag) {
             files.remove(file_path);
@@ -87,20 +382,31 @@ impl TagManager {
         }
     }
 
-    /// 验证标签格式
+    /// 验证标签格式，存在标签模式时同时校验类型和取值
     pub fn validate_tag(&self, tag: &str) -> Result<()> {
-        if !tag.contains(':') {
-            return Err(CodeNexusError::InvalidTagFormat(tag.to_string()));
+        let (tag_type, tag_value) = split_tag(tag)?;
+
+        if self.config.strict_tag_values {
+            if let Some(token) = find_reserved_token(tag_type).or_else(|| find_reserved_token(tag_value)) {
+                return Err(CodeNexusError::ReservedTagValue {
+                    tag: tag.to_string(),
+                    token: token.to_string(),
+                });
+            }
         }
 
-        let parts: Vec<&str> = tag.split(':').collect();
-        if parts.len() != 2 || parts[0].is_empty() || parts[1].is_empty() {
-            return Err(CodeNexusError::InvalidTagFormat(tag.to_string()));
+        if let Some(schema) = &self.schema {
+            schema.check(tag, tag_type, tag_value)?;
         }
 
         Ok(())
     }
 
+    /// 获取当前生效的标签模式
+    pub fn tag_schema(&self) -> Option<&TagSchema> {
+        self.schema.as_ref().map(|compiled| &compiled.schema)
+    }
+
     /// 验证文件路径（使用绝对路径）
     fn validate_file_path(&self, absolute_file_path: &Path) -> Result<()> {
         if !absolute_file_path.exists() {
@@ -109,8 +415,8 @@ impl TagManager {
         Ok(())
     }
 
-    /// 为文件添加标签
-    pub async fn add_tags(&mut self, absolute_file_path: &Path, relative_file_path: &str, tags: Vec<String>) -> Result<()> {
+    /// 为文件添加标签，返回实际新增的标签（已存在的标签不包含在内）
+    pub async fn add_tags(&mut self, absolute_file_path: &Path, relative_file_path: &str, tags: Vec<String>) -> Result<Vec<String>> {
         // 验证文件路径（使用绝对路径）
         self.validate_file_path(absolute_file_path)?;
 
@@ -134,6 +440,7 @@ impl TagManager {
         // 更新索引（在借用结束后）
         for tag in &added_tags {
             self.update_indices(tag, relative_file_path);
+            self.record_tag_added(relative_file_path, tag);
         }
 
         if !added_tags.is_empty() {
@@ -144,7 +451,7 @@ impl TagManager {
             debug!("文件 {} 的标签没有变化", relative_file_path);
         }
 
-        Ok(())
+        Ok(added_tags)
     }
 
     /// 移除文件标签
@@ -199,184 +506,1833 @@ impl TagManager {
         Ok(())
     }
 
-    /// 获取文件标签
-    pub fn get_file_tags(&self, file_path: &str) -> Vec<String> {
-        self.file_tags
-            .get(file_path)
-            .map(|tags| tags.iter().cloned().collect())
-            .unwrap_or_default()
-    }
+    /// 将文件的一个标签替换为另一个，只保存一次；新标签已存在时仅移除旧标签
+    pub async fn replace_file_tag(&mut self, relative_file_path: &str, old_tag: &str, new_tag: &str) -> Result<()> {
+        if !self.has_tag(relative_file_path, old_tag) {
+            return Err(CodeNexusError::TagNotFound {
+                tag: old_tag.to_string(),
+                file: relative_file_path.to_string(),
+            });
+        }
+        self.validate_tag(new_tag)?;
+        if old_tag == new_tag {
+            return Ok(());
+        }
 
-    /// 获取所有标签，按类型分组
-    pub fn get_all_tags(&self) -> HashMap<String, Vec<String>> {
-        self.tag_index
-            .iter()
-            .map(|(tag_type, tag_values)| {
-                let mut values: Vec<String> = tag_values.iter().cloned().collect();
-                values.sort();
-                (tag_type.clone(), values)
-            })
-            .collect()
+        if let Some(file_tags) = self.file_tags.get_mut(relative_file_path) {
+            file_tags.remove(old_tag);
+            let added = file_tags.insert(new_tag.to_string());
+            self.remove_from_indices(old_tag, relative_file_path);
+            if added {
+                self.update_indices(new_tag, relative_file_path);
+                self.record_tag_added(relative_file_path, new_tag);
+            }
+        }
+
+        self.save_to_storage().await?;
+        info!("将文件 {} 的标签 {} 替换为 {}", relative_file_path, old_tag, new_tag);
+        Ok(())
     }
 
-    /// 根据标签查询文件
-    pub fn query_files_by_tags(&self, query: &str) -> Result<Vec<String>> {
-        let query = query.trim();
+    /// 清除文件的全部标签，返回移除的标签数量
+    pub async fn clear_file_tags(&mut self, relative_file_path: &str) -> Result<usize> {
+        let tags = self
+            .file_tags
+            .remove(relative_file_path)
+            .ok_or_else(|| CodeNexusError::FileNotFound(relative_file_path.to_string()))?;
 
-        if query.is_empty() {
-            return Ok(Vec::new());
+        for tag in &tags {
+            self.remove_from_indices(tag, relative_file_path);
         }
 
-        // 解析并执行查询
-        let result = self.parse_and_execute_query(query)?;
-        let mut files: Vec<String> = result.into_iter().collect();
-        files.sort();
-        Ok(files)
+        self.save_to_storage().await?;
+        info!("清除了文件 {} 的 {} 个标签", relative_file_path, tags.len());
+        Ok(tags.len())
     }
 
-    /// 解析并执行查询表达式
-    fn parse_and_execute_query(&self, query: &str) -> Result<std::collections::HashSet<String>> {
-        // 处理 OR 操作（优先级最低）
-        if query.contains(" OR ") {
-            let parts: Vec<&str> = query.split(" OR ").map(|s| s.trim()).collect();
-            let mut result = std::collections::HashSet::new();
-            for part in parts {
-                let part_result = self.parse_and_execute_query(part)?;
-                result.extend(part_result);
-            }
-            return Ok(result);
-        }
-
-        // 处理 AND 操作
-        if query.contains(" AND ") {
-            let parts: Vec<&str> = query.split(" AND ").map(|s| s.trim()).collect();
-            let mut result = None;
-            for part in parts {
-                let part_result = self.parse_and_execute_query(part)?;
-                match result {
-                    None => result = Some(part_result),
-                    Some(ref mut current) => {
-                        *current = current.intersection(&part_result).cloned().collect();
-                    }
-                }
+    /// 归档文件的指定标签：从查询和统计中移除，保留在 tags.json 中以便恢复，返回归档的标签
+    pub async fn archive_tags(&mut self, relative_file_path: &str, tags: Vec<String>) -> Result<Vec<String>> {
+        let file_tags = self
+            .file_tags
+            .get(relative_file_path)
+            .ok_or_else(|| CodeNexusError::FileNotFound(relative_file_path.to_string()))?;
+        if let Some(tag) = tags.iter().find(|tag| !file_tags.contains(*tag)) {
+            return Err(CodeNexusError::TagNotFound {
+                tag: tag.clone(),
+                file: relative_file_path.to_string(),
+            });
+        }
+
+        let mut archived = Vec::new();
+        for tag in tags {
+            let Some(file_tags) = self.file_tags.get_mut(relative_file_path) else {
+                break;
+            };
+            if !file_tags.remove(&tag) {
+                continue;
+            }
+            if file_tags.is_empty() {
+                self.file_tags.remove(relative_file_path);
             }
-            return Ok(result.unwrap_or_default());
+            // 归档的标签保留添加时间，恢复时沿用
+            let meta = self.tag_meta.get(relative_file_path).and_then(|meta| meta.get(&tag)).cloned();
+            self.remove_from_indices(&tag, relative_file_path);
+            if let Some(meta) = meta {
+                self.tag_meta.entry(relative_file_path.to_string()).or_default().insert(tag.clone(), meta);
+            }
+            self.archived_tags
+                .entry(relative_file_path.to_string())
+                .or_default()
+                .insert(tag.clone());
+            archived.push(tag);
+        }
+
+        if !archived.is_empty() {
+            self.save_to_storage().await?;
+            info!("归档了文件 {} 的 {} 个标签: {:?}", relative_file_path, archived.len(), archived);
         }
+        Ok(archived)
+    }
 
-        // 处理 NOT 操作
-        if query.starts_with("NOT ") {
-            let inner_query = &query[4..].trim();
-            let inner_result = self.parse_and_execute_query(inner_query)?;
-            let all_files: std::collections::HashSet<String> = self.file_tags.keys().cloned().collect();
-            return Ok(all_files.difference(&inner_result).cloned().collect());
+    /// 恢复文件已归档的标签，恢复时按当前规则重新校验，返回恢复的标签
+    pub async fn unarchive_tags(&mut self, relative_file_path: &str, tags: Vec<String>) -> Result<Vec<String>> {
+        let archived = self.archived_tags.get(relative_file_path);
+        if let Some(tag) = tags.iter().find(|tag| !archived.is_some_and(|archived| archived.contains(*tag))) {
+            return Err(CodeNexusError::TagNotFound {
+                tag: tag.clone(),
+                file: relative_file_path.to_string(),
+            });
+        }
+        for tag in &tags {
+            self.validate_tag(tag)?;
         }
 
-        // 处理括号表达式
-        if query.starts_with('(') && query.ends_with(')') {
-            let inner_query = &query[1..query.len()-1];
-            return self.parse_and_execute_query(inner_query);
+        let mut restored = Vec::new();
+        for tag in tags {
+            let Some(archived) = self.archived_tags.get_mut(relative_file_path) else {
+                break;
+            };
+            if !archived.remove(&tag) {
+                continue;
+            }
+            if archived.is_empty() {
+                self.archived_tags.remove(relative_file_path);
+            }
+            if self.file_tags.entry(relative_file_path.to_string()).or_default().insert(tag.clone()) {
+                self.update_indices(&tag, relative_file_path);
+                self.record_tag_added(relative_file_path, &tag);
+            }
+            restored.push(tag);
         }
 
-        // 处理通配符查询
-        if query.contains('*') {
-            return self.execute_wildcard_query(query);
+        if !restored.is_empty() {
+            self.save_to_storage().await?;
+            info!("恢复了文件 {} 的 {} 个归档标签: {:?}", relative_file_path, restored.len(), restored);
         }
+        Ok(restored)
+    }
+
+    /// 获取已归档的标签，按文件和标签排序
+    pub fn get_archived_tags(&self) -> BTreeMap<String, Vec<String>> {
+        self.archived_tags
+            .iter()
+            .map(|(file_path, tags)| (file_path.clone(), tags.iter().cloned().collect()))
+            .collect()
+    }
+
+    /// 编译通配符模式，无效时返回查询语法错误
+    fn wildcard_regex(&self, pattern: &str) -> Result<Arc<Regex>> {
+        self.compiled_wildcard(pattern)
+            .ok_or_else(|| CodeNexusError::InvalidQuerySyntax(Message::InvalidWildcardPattern(pattern.to_string())))
+    }
 
-        // 单个标签查询
-        Ok(self.tag_to_files
-            .get(query)
-            .map(|files| files.iter().cloned().collect())
-            .unwrap_or_default())
+    /// 匹配通配符模式（与查询相同，`*` 匹配任意字符，`?` 匹配单个字符）的标签及带有它们的文件
+    fn tags_matching(&self, pattern: &str) -> Result<Vec<(String, Vec<String>)>> {
+        let regex = self.wildcard_regex(pattern)?;
+        let mut matched: Vec<(String, Vec<String>)> = self
+            .tag_to_files
+            .iter()
+            .filter(|(tag, _)| regex.is_match(tag))
+            .map(|(tag, files)| (tag.clone(), sorted(files)))
+            .collect();
+        matched.sort();
+        Ok(matched)
     }
 
-    /// 执行通配符查询
-    fn execute_wildcard_query(&self, pattern: &str) -> Result<std::collections::HashSet<String>> {
-        let mut result = std::collections::HashSet::new();
+    /// 带有（或已归档）匹配通配符模式的标签的文件，按路径排序
+    pub fn files_with_tags_matching(&self, pattern: &str) -> Result<Vec<String>> {
+        let regex = self.wildcard_regex(pattern)?;
+        let files: std::collections::BTreeSet<String> = self
+            .tags_matching(pattern)?
+            .into_iter()
+            .flat_map(|(_, files)| files)
+            .chain(
+                self.archived_tags
+                    .iter()
+                    .filter(|(_, archived)| archived.iter().any(|tag| regex.is_match(tag)))
+                    .map(|(file_path, _)| file_path.clone()),
+            )
+            .collect();
+        Ok(files.into_iter().collect())
+    }
 
-        // 简单的通配符实现：支持 * 匹配任意字符
-        for tag in self.tag_to_files.keys() {
-            if self.wildcard_match(pattern, tag) {
-                if let Some(files) = self.tag_to_files.get(tag) {
-                    result.extend(files.iter().cloned());
+    /// 从所有文件移除匹配通配符模式的标签（包括已归档的），只保存一次，返回移除的文件-标签数量
+    pub async fn remove_tags_matching(&mut self, pattern: &str) -> Result<usize> {
+        let mut removed_count = 0;
+        for (tag, files) in self.tags_matching(pattern)? {
+            for file_path in files {
+                if let Some(file_tags) = self.file_tags.get_mut(&file_path) {
+                    file_tags.remove(&tag);
+                    if file_tags.is_empty() {
+                        self.file_tags.remove(&file_path);
+                    }
                 }
+                self.remove_from_indices(&tag, &file_path);
+                removed_count += 1;
             }
         }
 
-        Ok(result)
-    }
+        let regex = self.wildcard_regex(pattern)?;
+        for archived in self.archived_tags.values_mut() {
+            let before = archived.len();
+            archived.retain(|tag| !regex.is_match(tag));
+            removed_count += before - archived.len();
+        }
+        self.archived_tags.retain(|_, archived| !archived.is_empty());
 
-    /// 简单的通配符匹配实现
-    fn wildcard_match(&self, pattern: &str, text: &str) -> bool {
-        // 如果模式中没有通配符，直接比较
-        if !pattern.contains('*') {
-            return pattern == text;
+        if removed_count > 0 {
+            self.prune_tag_meta();
+            self.save_to_storage().await?;
+            info!("移除了 {} 个匹配 {} 的标签", removed_count, pattern);
         }
+        Ok(removed_count)
+    }
 
-        // 将模式按 * 分割
-        let parts: Vec<&str> = pattern.split('*').collect();
+    /// 计算标签合并计划：将 `sources` 中的标签替换为 `target`，不修改任何数据
+    pub fn plan_merge_tags(&self, sources: &[String], target: &str) -> Result<TagMergePlan> {
+        self.validate_tag(target)?;
 
-        // 如果只有一个部分，说明没有 *
-        if parts.len() == 1 {
-            return pattern == text;
+        let mut source_tags: Vec<String> = sources
+            .iter()
+            .filter(|tag| tag.as_str() != target)
+            .cloned()
+            .collect();
+        source_tags.sort();
+        source_tags.dedup();
+        if source_tags.is_empty() {
+            return Err(CodeNexusError::ConfigError(Message::MergeNeedsSourceTag));
         }
 
-        let mut text_pos = 0;
+        let affected: std::collections::BTreeSet<String> = source_tags
+            .iter()
+            .filter_map(|tag| self.tag_to_files.get(tag))
+            .flatten()
+            .cloned()
+            .chain(
+                self.archived_tags
+                    .iter()
+                    .filter(|(_, archived)| source_tags.iter().any(|tag| archived.contains(tag)))
+                    .map(|(file_path, _)| file_path.clone()),
+            )
+            .collect();
 
-        // 检查第一部分（如果不为空）
-        if !parts[0].is_empty() {
-            if !text.starts_with(parts[0]) {
-                return false;
+        Ok(TagMergePlan {
+            sources: source_tags,
+            target: target.to_string(),
+            affected_files: affected.into_iter().collect(),
+        })
+    }
+
+    /// 执行标签合并计划，返回被修改的文件数量
+    ///
+    /// 已归档的源标签同样替换为目标标签（仍保持归档），并沿用源标签的添加时间
+    pub async fn apply_tag_merge(&mut self, plan: &TagMergePlan) -> Result<usize> {
+        let mut changed = 0;
+        for file_path in &plan.affected_files {
+            let mut file_changed = false;
+            if let Some(file_tags) = self.file_tags.get_mut(file_path) {
+                let removed: Vec<String> = plan
+                    .sources
+                    .iter()
+                    .filter(|tag| file_tags.remove(tag.as_str()))
+                    .cloned()
+                    .collect();
+                if !removed.is_empty() {
+                    let target_added = file_tags.insert(plan.target.clone());
+                    for tag in &removed {
+                        self.remove_from_indices(tag, file_path);
+                    }
+                    if target_added {
+                        self.update_indices(&plan.target, file_path);
+                        self.record_tag_added(file_path, &plan.target);
+                    }
+                    file_changed = true;
+                }
             }
-            text_pos += parts[0].len();
-        }
 
-        // 检查最后一部分（如果不为空）
-        if !parts[parts.len() - 1].is_empty() {
-            if !text.ends_with(parts[parts.len() - 1]) {
-                return false;
+            if let Some(archived) = self.archived_tags.get_mut(file_path) {
+                for source in &plan.sources {
+                    if archived.remove(source) {
+                        archived.insert(plan.target.clone());
+                        move_tag_meta(&mut self.tag_meta, file_path, source, &plan.target);
+                        file_changed = true;
+                    }
+                }
+            }
+
+            if file_changed {
+                changed += 1;
             }
         }
 
-        // 检查中间部分
-        for i in 1..parts.len() - 1 {
-            if !parts[i].is_empty() {
-                if let Some(pos) = text[text_pos..].find(parts[i]) {
-                    text_pos += pos + parts[i].len();
-                } else {
-                    return false;
-                }
+        if changed > 0 {
+            self.prune_tag_meta();
+            self.save_to_storage().await?;
+            info!("将标签 {:?} 合并为 {}，修改了 {} 个文件", plan.sources, plan.target, changed);
+        }
+        Ok(changed)
+    }
+
+    /// 将某个类型的所有标签改为新类型并保留取值，只保存一次，返回被修改的文件数量
+    ///
+    /// 文件已带有新类型下相同取值的标签时合并为一个，保留已有标签的添加时间；已归档的标签同样改名
+    pub async fn rename_tag_type(&mut self, old_type: &str, new_type: &str) -> Result<usize> {
+        if old_type == new_type {
+            return Err(CodeNexusError::ConfigError(Message::SameTagType));
+        }
+        let prefix = format!("{}:", old_type);
+        // 先验证所有新标签，任一无效时不做修改；归档的标签在恢复时才按当前规则校验
+        for value in self.tag_index.get(old_type).map(sorted).unwrap_or_default() {
+            self.validate_tag(&format!("{}:{}", new_type, value))?;
+        }
+        for tag in self.archived_tags.values().flatten().filter(|tag| tag.starts_with(&prefix)) {
+            split_tag(&format!("{}:{}", new_type, &tag[prefix.len()..]))?;
+        }
+
+        let rename = |tag: &str| format!("{}:{}", new_type, &tag[prefix.len()..]);
+        let mut changed_files = HashSet::new();
+        for (file_path, file_tags) in self.file_tags.iter_mut() {
+            let old_tags: Vec<String> = file_tags.iter().filter(|tag| tag.starts_with(&prefix)).cloned().collect();
+            for old_tag in old_tags {
+                let new_tag = rename(&old_tag);
+                file_tags.remove(&old_tag);
+                file_tags.insert(new_tag.clone());
+                move_tag_meta(&mut self.tag_meta, file_path, &old_tag, &new_tag);
+                changed_files.insert(file_path.clone());
+            }
+        }
+        for (file_path, archived) in self.archived_tags.iter_mut() {
+            let old_tags: Vec<String> = archived.iter().filter(|tag| tag.starts_with(&prefix)).cloned().collect();
+            for old_tag in old_tags {
+                let new_tag = rename(&old_tag);
+                archived.remove(&old_tag);
+                archived.insert(new_tag.clone());
+                move_tag_meta(&mut self.tag_meta, file_path, &old_tag, &new_tag);
+                changed_files.insert(file_path.clone());
             }
         }
+        if changed_files.is_empty() {
+            return Ok(0);
+        }
+        let changed = changed_files.len();
 
-        true
+        let data = TagsData {
+            file_tags: self.export_tags().into_iter().collect(),
+            ..Default::default()
+        };
+        self.build_indices(&data);
+        self.save_to_storage().await?;
+        info!("将标签类型 {} 重命名为 {}，修改了 {} 个文件", old_type, new_type, changed);
+        Ok(changed)
     }
 
+    /// 将多个标签合并为目标标签，返回执行的计划
+    pub async fn merge_tags(&mut self, sources: &[String], target: &str) -> Result<TagMergePlan> {
+        let plan = self.plan_merge_tags(sources, target)?;
+        self.apply_tag_merge(&plan).await?;
+        Ok(plan)
+    }
 
+    /// 重命名标签，目标标签已存在时与之合并
+    pub async fn rename_tag(&mut self, old_tag: &str, new_tag: &str) -> Result<TagMergePlan> {
+        self.merge_tags(&[old_tag.to_string()], new_tag).await
+    }
 
-    /// 获取未标记的文件
-    pub fn get_untagged_files(&self) -> Vec<String> {
-        // 这里需要扫描文件系统，暂时返回空列表
-        // 实际实现需要遍历项目文件并检查是否有标签
-        Vec::new()
+    /// 获取文件标签
+    pub fn get_file_tags(&self, file_path: &str) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .file_tags
+            .get(file_path)
+            .map(|tags| tags.iter().cloned().collect())
+            .unwrap_or_default();
+        tags.sort();
+        tags
     }
 
-    /// 保存数据到存储
-    async fn save_to_storage(&self) -> Result<()> {
-        let data = TagsData {
-            file_tags: self.file_tags
-                .iter()
-                .map(|(path, tags)| (path.clone(), tags.iter().cloned().collect()))
-                .collect(),
-        };
+    /// 批量获取多个文件的标签（已排序），没有标签的文件不出现在结果中
+    pub fn get_tags_for_files(&self, paths: &[String]) -> BTreeMap<String, Vec<String>> {
+        paths
+            .iter()
+            .filter(|path| self.file_tags.get(*path).is_some_and(|tags| !tags.is_empty()))
+            .map(|path| (path.clone(), self.get_file_tags(path)))
+            .collect()
+    }
 
-        self.storage.save_tags(&data).await
+    /// 获取文件的标签及其元数据，按标签排序；没有记录添加时间的标签 `added_at` 为空
+    pub fn get_file_tags_with_meta(&self, file_path: &str) -> Vec<TagWithMeta> {
+        let meta = self.tag_meta.get(file_path);
+        self.get_file_tags(file_path)
+            .into_iter()
+            .map(|tag| TagWithMeta {
+                meta: meta.and_then(|meta| meta.get(&tag)).cloned().unwrap_or_default(),
+                tag,
+            })
+            .collect()
     }
 
-    /// 获取统计信息
-    pub fn get_stats(&self) -> (usize, usize, usize) {
-        let total_files = self.file_tags.len();
-        let total_tags = self.tag_to_files.len();
-        let total_tag_types = self.tag_index.len();
-        (total_files, total_tags, total_tag_types)
+    /// 丢弃既不在文件标签中、也不在归档中的标签元数据
+    fn prune_tag_meta(&mut self) {
+        let (file_tags, archived_tags) = (&self.file_tags, &self.archived_tags);
+        self.tag_meta.retain(|file_path, meta| {
+            meta.retain(|tag, _| {
+                file_tags.get(file_path).is_some_and(|tags| tags.contains(tag))
+                    || archived_tags.get(file_path).is_some_and(|tags| tags.contains(tag))
+            });
+            !meta.is_empty()
+        });
+    }
+
+    /// 记录标签在文件上的添加时间，已有记录时保持不变
+    fn record_tag_added(&mut self, file_path: &str, tag: &str) {
+        self.tag_meta
+            .entry(file_path.to_string())
+            .or_default()
+            .entry(tag.to_string())
+            .or_insert_with(TagMeta::now);
+    }
+
+    /// 检查文件是否带有指定标签
+    pub fn has_tag(&self, file_path: &str, tag: &str) -> bool {
+        self.file_tags
+            .get(file_path)
+            .is_some_and(|tags| tags.contains(tag))
+    }
+
+    /// 获取所有标签，按类型分组
+    pub fn get_all_tags(&self) -> BTreeMap<String, Vec<String>> {
+        self.tag_index
+            .iter()
+            .map(|(tag_type, tag_values)| {
+                let mut values: Vec<String> = tag_values.iter().cloned().collect();
+                values.sort();
+                (tag_type.clone(), values)
+            })
+            .collect()
+    }
+
+    /// 根据标签查询文件
+    pub fn query_files_by_tags(&self, query: &str) -> Result<Vec<String>> {
+        let query = query.trim();
+
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // 解析并执行查询
+        let result = self.parse_and_execute_query(query)?;
+        let mut files: Vec<String> = result.into_keys().collect();
+        files.sort();
+        Ok(files)
+    }
+
+    /// 统计匹配查询且满足 `filter` 的文件数量，不排序也不复制路径
+    pub fn count_files_by_tags<F: Fn(&str) -> bool>(&self, query: &str, filter: F) -> Result<usize> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Ok(0);
+        }
+
+        let result = self.parse_and_execute_query(query)?;
+        Ok(result.keys().filter(|file| filter(file)).count())
+    }
+
+    /// 查询匹配的文件及使其满足查询的标签，按路径排序
+    ///
+    /// NOT 子句只排除文件，不贡献标签，因此纯 NOT 查询匹配的文件标签列表为空
+    pub fn explain_query(&self, query: &str) -> Result<Vec<(String, Vec<String>)>> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut explained: Vec<(String, Vec<String>)> = self
+            .parse_and_execute_query(query)?
+            .into_iter()
+            .map(|(file_path, matched)| (file_path, matched.into_iter().collect()))
+            .collect();
+        explained.sort();
+        Ok(explained)
+    }
+
+    /// 解析并执行查询表达式
+    fn parse_and_execute_query(&self, query: &str) -> Result<HashMap<String, BTreeSet<String>>> {
+        let expr = parse_query(query)?;
+        Ok(self.evaluate_query(&expr))
+    }
+
+    /// 对查询表达式求值，返回匹配的文件及使其满足查询的标签
+    fn evaluate_query(&self, expr: &QueryExpr) -> HashMap<String, BTreeSet<String>> {
+        match expr {
+            QueryExpr::Tag(tag) => self.files_with_tags(std::iter::once(tag.clone())),
+            QueryExpr::Wildcard(pattern) => match self.compiled_wildcard(pattern) {
+                Some(regex) => self.files_with_tags(self.tag_to_files.keys().filter(|tag| regex.is_match(tag)).cloned()),
+                None => HashMap::new(),
+            },
+            QueryExpr::HasType(tag_type) => self.files_with_tags(
+                self.tag_index
+                    .get(tag_type)
+                    .into_iter()
+                    .flatten()
+                    .map(|value| format!("{}:{}", tag_type, value)),
+            ),
+            QueryExpr::ExactSet(set) => self
+                .file_tags
+                .iter()
+                .filter(|(_, tags)| tags.len() == set.len() && set.iter().all(|tag| tags.contains(tag)))
+                .map(|(file, _)| (file.clone(), set.clone()))
+                .collect(),
+            QueryExpr::Superset(set) => self
+                .file_tags
+                .iter()
+                .filter(|(_, tags)| set.iter().all(|tag| tags.contains(tag)))
+                .map(|(file, _)| (file.clone(), set.clone()))
+                .collect(),
+            // NOT 只排除文件，不贡献标签
+            QueryExpr::Not(inner) => {
+                let inner_result = self.evaluate_query(inner);
+                self.file_tags
+                    .keys()
+                    .filter(|file| !inner_result.contains_key(*file))
+                    .map(|file| (file.clone(), BTreeSet::new()))
+                    .collect()
+            }
+            QueryExpr::And(operands) => {
+                let mut result: Option<HashMap<String, BTreeSet<String>>> = None;
+                for operand in operands {
+                    let mut operand_result = self.evaluate_query(operand);
+                    result = Some(match result {
+                        None => operand_result,
+                        Some(current) => current
+                            .into_iter()
+                            .filter_map(|(file, mut matched)| {
+                                matched.extend(operand_result.remove(&file)?);
+                                Some((file, matched))
+                            })
+                            .collect(),
+                    });
+                }
+                result.unwrap_or_default()
+            }
+            QueryExpr::Or(operands) => {
+                let mut result: HashMap<String, BTreeSet<String>> = HashMap::new();
+                for operand in operands {
+                    for (file, matched) in self.evaluate_query(operand) {
+                        result.entry(file).or_default().extend(matched);
+                    }
+                }
+                result
+            }
+        }
+    }
+
+    /// 收集带有给定标签之一的文件，并记录各文件命中的标签
+    fn files_with_tags(&self, tags: impl Iterator<Item = String>) -> HashMap<String, BTreeSet<String>> {
+        let mut result: HashMap<String, BTreeSet<String>> = HashMap::new();
+        for tag in tags {
+            for file in self.tag_to_files.get(&tag).into_iter().flatten() {
+                result.entry(file.clone()).or_default().insert(tag.clone());
+            }
+        }
+        result
+    }
+
+    /// 从缓存获取编译后的通配符模式，未命中时编译并放入缓存
+    fn compiled_wildcard(&self, pattern: &str) -> Option<Arc<Regex>> {
+        let mut cache = self.wildcard_cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(regex) = cache.get(pattern) {
+            return Some(regex);
+        }
+
+        match compile_wildcard(pattern) {
+            Ok(regex) => {
+                let regex = Arc::new(regex);
+                cache.insert(pattern.to_string(), regex.clone());
+                Some(regex)
+            }
+            Err(e) => {
+                warn!("通配符模式编译失败 {}: {}", pattern, e);
+                None
+            }
+        }
+    }
+
+
+
+    /// 搜索标签（子串匹配，`case_sensitive` 为 false 时忽略大小写），返回 (文件路径, 命中的标签)
+    pub fn search_tags(&self, keyword: &str, case_sensitive: bool) -> Vec<(String, String)> {
+        let keyword_lower = keyword.to_lowercase();
+        let mut results = Vec::new();
+
+        for (tag, files) in &self.tag_to_files {
+            let matched = if case_sensitive {
+                tag.contains(keyword)
+            } else {
+                tag.to_lowercase().contains(&keyword_lower)
+            };
+            if matched {
+                for file_path in files {
+                    results.push((file_path.clone(), tag.clone()));
+                }
+            }
+        }
+
+        results.sort();
+        results
+    }
+
+    /// 模糊搜索标签，返回 (文件路径, 命中的标签, 相似度)
+    pub fn fuzzy_search_tags(&self, keyword: &str, threshold: f64) -> Vec<(String, String, f64)> {
+        let mut results = Vec::new();
+
+        for (tag, files) in &self.tag_to_files {
+            let value = tag.split_once(':').map(|(_, value)| value).unwrap_or(tag);
+            let score = fuzzy_score(keyword, tag).max(fuzzy_score(keyword, value));
+            if score >= threshold {
+                for file_path in files {
+                    results.push((file_path.clone(), tag.clone(), score));
+                }
+            }
+        }
+
+        results.sort_by(|a, b| b.2.total_cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+        results
+    }
+
+    /// 推荐共现标签
+    ///
+    /// 在同时带有全部给定标签的文件中统计其他标签的出现次数，按次数降序、标签升序返回前 `limit` 个
+    pub fn suggest_cooccurring_tags(&self, tags: &[String], limit: usize) -> Vec<(String, usize)> {
+        let mut files: Option<HashSet<&String>> = None;
+        for tag in tags {
+            let tag_files: HashSet<&String> = self
+                .tag_to_files
+                .get(tag)
+                .map(|files| files.iter().collect())
+                .unwrap_or_default();
+            files = Some(match files {
+                None => tag_files,
+                Some(current) => current.intersection(&tag_files).copied().collect(),
+            });
+        }
+
+        let mut counts: HashMap<&String, usize> = HashMap::new();
+        for file_path in files.unwrap_or_default() {
+            if let Some(file_tags) = self.file_tags.get(file_path) {
+                for tag in file_tags {
+                    if !tags.contains(tag) {
+                        *counts.entry(tag).or_default() += 1;
+                    }
+                }
+            }
+        }
+
+        let mut suggestions: Vec<(String, usize)> = counts
+            .into_iter()
+            .map(|(tag, count)| (tag.clone(), count))
+            .collect();
+        suggestions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        suggestions.truncate(limit);
+        suggestions
+    }
+
+    /// 查找与给定文件共享标签的其他文件
+    ///
+    /// 返回 (文件路径, 共享标签数)，只保留共享数不少于 `min_shared` 的文件，按共享数降序、路径升序排列
+    pub fn files_sharing_tags(&self, file_path: &str, min_shared: usize) -> Vec<(String, usize)> {
+        let mut counts: HashMap<&String, usize> = HashMap::new();
+        for tag in self.file_tags.get(file_path).into_iter().flatten() {
+            for other in self.tag_to_files.get(tag).into_iter().flatten() {
+                if other != file_path {
+                    *counts.entry(other).or_default() += 1;
+                }
+            }
+        }
+
+        let mut files: Vec<(String, usize)> = counts
+            .into_iter()
+            .filter(|(_, count)| *count >= min_shared)
+            .map(|(path, count)| (path.clone(), count))
+            .collect();
+        files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        files
+    }
+
+    /// 统计路径匹配 glob 的文件上使用的标签：标签 -> 带有该标签的匹配文件数
+    ///
+    /// 以 `/` 结尾的模式视为目录，匹配其下所有文件（等同于 `dir/**`）
+    pub fn tags_for_path_glob(&self, glob: &str) -> Result<BTreeMap<String, usize>> {
+        let pattern = if glob.ends_with('/') {
+            format!("{}**", glob)
+        } else {
+            glob.to_string()
+        };
+        let matcher = compile_path_glob(&pattern)?;
+
+        let mut counts = BTreeMap::new();
+        for (file_path, tags) in &self.file_tags {
+            if matcher.is_match(file_path) {
+                for tag in tags {
+                    *counts.entry(tag.clone()).or_insert(0) += 1;
+                }
+            }
+        }
+        Ok(counts)
+    }
+
+    /// 按指定标签类型的取值对文件分组：value -> 带有 `tag_type:value` 的文件（已排序）
+    pub fn group_files_by_type(&self, tag_type: &str) -> BTreeMap<String, Vec<String>> {
+        let Some(values) = self.tag_index.get(tag_type) else {
+            return BTreeMap::new();
+        };
+        values
+            .iter()
+            .map(|value| {
+                let tag = format!("{}:{}", tag_type, value);
+                let mut files: Vec<String> = self
+                    .tag_to_files
+                    .get(&tag)
+                    .map(|files| files.iter().cloned().collect())
+                    .unwrap_or_default();
+                files.sort();
+                (value.clone(), files)
+            })
+            .collect()
+    }
+
+    /// 获取项目中没有指定类型标签的文件（扫描项目目录，遵循忽略规则）
+    pub fn get_files_without_type(&self, project_root: &Path, tag_type: &str) -> Result<Vec<String>> {
+        let prefix = format!("{}:", tag_type);
+        let mut files = scan_project_files(project_root, self.config.path_case_fold)?;
+        files.retain(|file_path| {
+            !self
+                .file_tags
+                .get(file_path)
+                .is_some_and(|tags| tags.iter().any(|tag| tag.starts_with(&prefix)))
+        });
+        Ok(files)
+    }
+
+    /// 获取有标签的文件列表
+    pub fn get_tagged_files(&self) -> Vec<String> {
+        let mut files: Vec<String> = self.file_tags.keys().cloned().collect();
+        files.sort();
+        files
+    }
+
+    /// 获取未标记的文件，扫描项目目录（遵循忽略规则）并过滤掉已有标签的文件
+    ///
+    /// `under` 限定扫描的子目录，`limit` 限制返回数量，达到上限后提前结束扫描
+    pub fn get_untagged_files(&self, project_root: &Path, under: Option<&str>, limit: Option<usize>) -> Result<UntaggedFiles> {
+        let (files, truncated) =
+            scan_project_files_with(project_root, self.config.path_case_fold, under, limit, |file_path| !self.file_tags.contains_key(file_path))?;
+        Ok(UntaggedFiles { files, truncated })
+    }
+
+    /// 导出全部标签数据（标签按字母排序）
+    pub fn export_tags(&self) -> HashMap<String, Vec<String>> {
+        self.file_tags
+            .iter()
+            .map(|(file_path, tags)| (file_path.clone(), sorted(tags)))
+            .collect()
+    }
+
+    /// 导入标签数据和已归档的标签，返回新增的（未归档）标签数量
+    ///
+    /// 所有标签先通过验证，任一不合法则整体拒绝，不修改现有数据；
+    /// 归档的标签在恢复时才按当前规则校验，这里只检查 type:value 格式
+    pub async fn import_tags(
+        &mut self,
+        tags: HashMap<String, Vec<String>>,
+        archived: HashMap<String, Vec<String>>,
+        mode: ImportMode,
+    ) -> Result<usize> {
+        for tag in tags.values().flatten() {
+            self.validate_tag(tag)?;
+        }
+        for tag in archived.values().flatten() {
+            split_tag(tag)?;
+        }
+
+        if mode == ImportMode::Replace {
+            self.file_tags.clear();
+            self.archived_tags.clear();
+        }
+
+        let mut imported_count = 0;
+        for (file_path, file_tags) in tags {
+            let entry = self.file_tags.entry(file_path.clone()).or_default();
+            for tag in file_tags {
+                if entry.insert(tag.clone()) {
+                    imported_count += 1;
+                    // 替换模式下重新导入的标签（如撤销恢复）保留原有的添加时间
+                    self.tag_meta.entry(file_path.clone()).or_default().entry(tag).or_insert_with(TagMeta::now);
+                }
+            }
+        }
+        self.file_tags.retain(|_, tags| !tags.is_empty());
+        for (file_path, file_archived) in archived {
+            self.archived_tags.entry(file_path).or_default().extend(file_archived);
+        }
+        self.archived_tags.retain(|_, tags| !tags.is_empty());
+        self.prune_tag_meta();
+
+        let data = TagsData {
+            file_tags: self.export_tags().into_iter().collect(),
+            ..Default::default()
+        };
+        self.build_indices(&data);
+        self.save_to_storage().await?;
+        info!("导入了 {} 个标签", imported_count);
+
+        Ok(imported_count)
+    }
+
+    /// 导出标签为 CSV，每行 `file_path,tag`，带表头，按文件路径和标签排序
+    pub fn export_csv(&self) -> Result<String> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        let to_error = |e: csv::Error| CodeNexusError::InternalError(format!("CSV 写入失败: {}", e));
+
+        writer.write_record(["file_path", "tag"]).map_err(to_error)?;
+        let mut rows: Vec<(String, String)> = self
+            .file_tags
+            .iter()
+            .flat_map(|(file_path, tags)| tags.iter().map(move |tag| (file_path.clone(), tag.clone())))
+            .collect();
+        rows.sort();
+        for (file_path, tag) in rows {
+            writer.write_record([file_path, tag]).map_err(to_error)?;
+        }
+
+        let bytes = writer
+            .into_inner()
+            .map_err(|e| CodeNexusError::InternalError(format!("CSV 写入失败: {}", e)))?;
+        String::from_utf8(bytes).map_err(|e| CodeNexusError::InternalError(format!("CSV 编码错误: {}", e)))
+    }
+
+    /// 从 CSV 导入标签，每行 `file_path,tag`，可带表头
+    ///
+    /// 文件路径相对于 `project_root` 解析并规范化；格式错误、文件不存在或标签不合法的行被拒绝并报告，
+    /// 其余行合并到现有标签中并一次性保存
+    pub async fn import_csv(&mut self, project_root: &Path, content: &str) -> Result<TagImportReport> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_reader(content.as_bytes());
+
+        let mut report = TagImportReport::default();
+        let mut accepted: HashMap<String, Vec<String>> = HashMap::new();
+
+        for (index, record) in reader.records().enumerate() {
+            let line = index + 1;
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => {
+                    let line = e.position().map(|p| p.line() as usize).unwrap_or(line);
+                    report.rejected.push(RejectedRecord { line, reason: format!("CSV 解析错误: {}", e) });
+                    continue;
+                }
+            };
+            let line = record.position().map(|p| p.line() as usize).unwrap_or(line);
+
+            if record.len() != 2 {
+                report.rejected.push(RejectedRecord {
+                    line,
+                    reason: format!("应为 2 列 (file_path,tag)，实际 {} 列", record.len()),
+                });
+                continue;
+            }
+            let (file_path, tag) = (&record[0], &record[1]);
+            if line == 1 && file_path == "file_path" && tag == "tag" {
+                continue;
+            }
+
+            if let Err(e) = self.validate_tag(tag) {
+                report.rejected.push(RejectedRecord { line, reason: e.to_string() });
+                continue;
+            }
+            let normalized = match validate_file_path(project_root, file_path)
+                .and_then(|full_path| normalize_file_path(project_root, &full_path, self.config.path_case_fold))
+            {
+                Ok(normalized) => normalized,
+                Err(e) => {
+                    report.rejected.push(RejectedRecord { line, reason: e.to_string() });
+                    continue;
+                }
+            };
+
+            accepted.entry(normalized).or_default().push(tag.to_string());
+        }
+
+        if !accepted.is_empty() {
+            report.imported = self.import_tags(accepted, HashMap::new(), ImportMode::Merge).await?;
+        }
+        info!("CSV 导入了 {} 个标签，拒绝 {} 行", report.imported, report.rejected.len());
+
+        Ok(report)
+    }
+
+    /// 逐行导入 JSON Lines 标签记录（每行 `{"file_path": ..., "tags": [...]}`），合并到现有标签
+    ///
+    /// 记录逐条校验并增量更新索引，每累计 `batch_size` 条有效记录保存一次，不在内存中保留整个输入；
+    /// 格式错误、文件不存在或含非法标签的行整行拒绝并报告行号；
+    /// 读取输入或保存失败时回滚到导入前的标签数据（包括已保存的批次）并返回错误
+    pub async fn import_jsonl<R: AsyncBufRead + Unpin>(
+        &mut self,
+        project_root: &Path,
+        reader: R,
+        batch_size: usize,
+    ) -> Result<JsonlImportReport> {
+        let previous = self.tags_data();
+        let result = self.import_jsonl_lines(project_root, reader, batch_size).await;
+        if let Err(e) = &result {
+            warn!("JSON Lines 导入中断，回滚已导入的标签: {}", e);
+            if let Err(rollback_error) = self.restore_tags_data(previous).await {
+                error!("回滚 JSON Lines 导入失败: {}", rollback_error);
+            }
+        }
+        result
+    }
+
+    /// `import_jsonl` 的逐行导入，出错时可能已保存部分批次
+    async fn import_jsonl_lines<R: AsyncBufRead + Unpin>(
+        &mut self,
+        project_root: &Path,
+        reader: R,
+        batch_size: usize,
+    ) -> Result<JsonlImportReport> {
+        let batch_size = batch_size.max(1);
+        let mut report = JsonlImportReport::default();
+        let mut pending = 0;
+        let mut lines = reader.lines();
+        let mut line = 0;
+
+        while let Some(content) = lines.next_line().await? {
+            line += 1;
+            if content.trim().is_empty() {
+                continue;
+            }
+            report.lines += 1;
+
+            let record: JsonlTagRecord = match serde_json::from_str(&content) {
+                Ok(record) => record,
+                Err(e) => {
+                    report.rejected.push(RejectedRecord { line, reason: format!("JSON 解析错误: {}", e) });
+                    continue;
+                }
+            };
+            if let Some(Err(e)) = record.tags.iter().map(|tag| self.validate_tag(tag)).find(Result::is_err) {
+                report.rejected.push(RejectedRecord { line, reason: e.to_string() });
+                continue;
+            }
+            let normalized = match validate_file_path(project_root, &record.file_path)
+                .and_then(|full_path| normalize_file_path(project_root, &full_path, self.config.path_case_fold))
+            {
+                Ok(normalized) => normalized,
+                Err(e) => {
+                    report.rejected.push(RejectedRecord { line, reason: e.to_string() });
+                    continue;
+                }
+            };
+
+            report.accepted += 1;
+            for tag in record.tags {
+                if self.file_tags.entry(normalized.clone()).or_default().insert(tag.clone()) {
+                    self.update_indices(&tag, &normalized);
+                    self.record_tag_added(&normalized, &tag);
+                    report.imported += 1;
+                }
+            }
+
+            pending += 1;
+            if pending >= batch_size {
+                self.save_to_storage().await?;
+                report.flushes += 1;
+                pending = 0;
+                info!("JSON Lines 导入进度: 已读取 {} 行，新增 {} 个标签，拒绝 {} 行", line, report.imported, report.rejected.len());
+            }
+        }
+
+        if pending > 0 {
+            self.save_to_storage().await?;
+            report.flushes += 1;
+        }
+        info!(
+            "JSON Lines 导入了 {} 条记录（{} 个标签），拒绝 {} 行",
+            report.accepted,
+            report.imported,
+            report.rejected.len()
+        );
+        Ok(report)
+    }
+
+    /// 当前的完整标签数据（与 tags.json 内容一致，包含添加时间和已归档的标签）
+    pub fn tags_data(&self) -> TagsData {
+        TagsData {
+            file_tags: self.export_tags().into_iter().collect(),
+            tag_meta: self
+                .tag_meta
+                .iter()
+                .map(|(file_path, tags)| (file_path.clone(), tags.clone().into_iter().collect()))
+                .collect(),
+            archived_tags: self
+                .archived_tags
+                .iter()
+                .map(|(file_path, tags)| (file_path.clone(), tags.iter().cloned().collect()))
+                .collect(),
+        }
+    }
+
+    /// 用 `tags_data` 取得的完整标签数据替换当前数据并保存，用于撤销恢复
+    pub async fn restore_tags_data(&mut self, data: TagsData) -> Result<()> {
+        self.load_side_data(&data);
+        self.build_indices(&data);
+        self.save_to_storage().await
+    }
+
+    /// 载入不进入索引的数据：标签添加时间和已归档的标签
+    fn load_side_data(&mut self, data: &TagsData) {
+        self.tag_meta = data
+            .tag_meta
+            .iter()
+            .map(|(file_path, tags)| (file_path.clone(), tags.clone().into_iter().collect()))
+            .collect();
+        self.archived_tags = data
+            .archived_tags
+            .iter()
+            .map(|(file_path, tags)| (file_path.clone(), tags.iter().cloned().collect()))
+            .collect();
+    }
+
+    /// 保存数据到存储
+    ///
+    /// 不写索引快照：快照只在初始化重建索引后和项目卸载时写入（见 `save_index_snapshot`），
+    /// 避免每次修改都多写一个文件
+    async fn save_to_storage(&mut self) -> Result<()> {
+        let data = self.tags_data();
+        self.tags_hash = Some(self.storage.save_tags_with_hash(&data).await?);
+        Ok(())
+    }
+
+    /// 将内存索引写入快照，供下次初始化直接恢复；快照已与 tags.json 一致时不做任何事
+    pub async fn save_index_snapshot(&mut self) -> Result<()> {
+        let Some(hash) = self.tags_hash.clone() else {
+            return Ok(());
+        };
+        if self.snapshot_hash.as_ref() == Some(&hash) {
+            return Ok(());
+        }
+        self.storage.save_tag_index(&self.index_snapshot(hash.clone())).await?;
+        self.snapshot_hash = Some(hash);
+        Ok(())
+    }
+
+    /// 获取统计信息
+    pub fn get_stats(&self) -> (usize, usize, usize) {
+        let total_files = self.file_tags.len();
+        let total_tags = self.tag_to_files.len();
+        let total_tag_types = self.tag_index.len();
+        (total_files, total_tags, total_tag_types)
+    }
+
+    /// 获取详细标签统计
+    pub fn get_detailed_stats(&self) -> TagDetailedStats {
+        let mut type_counts = std::collections::BTreeMap::new();
+        let mut top_tags: Vec<TagCount> = Vec::with_capacity(self.tag_to_files.len());
+        for (tag, files) in &self.tag_to_files {
+            if let Some((tag_type, _)) = tag.split_once(':') {
+                *type_counts.entry(tag_type.to_string()).or_insert(0) += files.len();
+            }
+            top_tags.push(TagCount {
+                tag: tag.clone(),
+                count: files.len(),
+            });
+        }
+        top_tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+        top_tags.truncate(TOP_TAGS_LIMIT);
+
+        let total_files = self.file_tags.len();
+        let assignments: usize = self.file_tags.values().map(HashSet::len).sum();
+        let average_tags_per_file = if total_files == 0 {
+            0.0
+        } else {
+            assignments as f64 / total_files as f64
+        };
+
+        TagDetailedStats {
+            total_files,
+            total_tags: self.tag_to_files.len(),
+            type_counts,
+            average_tags_per_file,
+            top_tags,
+        }
+    }
+}
+
+/// 检查 type:value 格式，返回类型和取值
+fn split_tag(tag: &str) -> Result<(&str, &str)> {
+    match tag.split_once(':') {
+        Some((tag_type, tag_value)) if !tag_type.is_empty() && !tag_value.is_empty() && !tag_value.contains(':') => {
+            Ok((tag_type, tag_value))
+        }
+        _ => Err(CodeNexusError::InvalidTagFormat(tag.to_string())),
+    }
+}
+
+/// 标签改名时将旧标签的元数据移到新标签，新标签已有元数据时保留已有的
+fn move_tag_meta(tag_meta: &mut HashMap<String, HashMap<String, TagMeta>>, file_path: &str, old_tag: &str, new_tag: &str) {
+    if let Some(meta) = tag_meta.get_mut(file_path) {
+        if let Some(old_meta) = meta.remove(old_tag) {
+            meta.entry(new_tag.to_string()).or_insert(old_meta);
+        }
+    }
+}
+
+/// 排序后的集合元素，保证写入的 JSON 稳定
+fn sorted(values: &HashSet<String>) -> Vec<String> {
+    let mut values: Vec<String> = values.iter().cloned().collect();
+    values.sort();
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// 创建带有测试文件的临时项目和已初始化的标签管理器
+    async fn setup(files: &[&str]) -> (TempDir, TagManager) {
+        let temp_dir = TempDir::new().unwrap();
+        for file in files {
+            let path = temp_dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        let storage = JsonStorage::new(temp_dir.path().join(".codenexus"));
+        storage.initialize().await.unwrap();
+        let mut manager = TagManager::new(storage);
+        manager.initialize().await.unwrap();
+        (temp_dir, manager)
+    }
+
+    /// 将字符串切片转为标签列表
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_wildcard_match() {
+        let (_temp_dir, manager) = setup(&[]).await;
+        let cases = [
+            ("v1.?", "v1.2", true),
+            ("v1.?", "v1.10", false),
+            ("v1.?", "v1.", false),
+            ("a?c*", "abc", true),
+            ("a?c*", "abcdef", true),
+            ("a?c*", "ac", false),
+            ("?bc", "abc", true),
+            ("?bc", "bc", false),
+            ("ab?", "abc", true),
+            ("ab?", "abcd", false),
+            ("s:*", "s:", true),
+            ("a*b*c", "abc", true),
+            ("a*b*c", "aXbYbZc", true),
+            ("a*b*c", "acb", false),
+            ("*?", "", false),
+            ("模块:?录", "模块:登录", true),
+        ];
+        for (pattern, text, expected) in cases {
+            assert_eq!(reference_wildcard_match(pattern, text), expected, "{} vs {}", pattern, text);
+            let regex = manager.compiled_wildcard(pattern).unwrap();
+            assert_eq!(regex.is_match(text), expected, "{} vs {}", pattern, text);
+        }
+    }
+
+    /// 逐字符回溯的通配符匹配，作为编译后正则的对照实现
+    fn reference_wildcard_match(pattern: &str, text: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        let (mut p, mut t) = (0, 0);
+        let mut backtrack: Option<(usize, usize)> = None;
+        while t < text.len() {
+            if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+                p += 1;
+                t += 1;
+            } else if p < pattern.len() && pattern[p] == '*' {
+                backtrack = Some((p, t));
+                p += 1;
+            } else if let Some((star, matched)) = backtrack {
+                p = star + 1;
+                t = matched + 1;
+                backtrack = Some((star, t));
+            } else {
+                return false;
+            }
+        }
+        pattern[p..].iter().all(|c| *c == '*')
+    }
+
+    #[tokio::test]
+    async fn test_wildcard_matches_reference_and_cache_is_bounded() {
+        let (_temp_dir, manager) = setup(&[]).await;
+        // 含正则元字符和换行的文本按字面匹配
+        let texts = ["a.b", "a+b", "a\nb", "a\\b", "(x)", "[v]", "ab", ""];
+        let patterns = ["a.b", "a?b", "a*b", "(*)", "[?]", "*", "?", "a\\*", "^a*"];
+        for pattern in patterns {
+            for text in texts {
+                let regex = manager.compiled_wildcard(pattern).unwrap();
+                assert_eq!(regex.is_match(text), reference_wildcard_match(pattern, text), "{:?} vs {:?}", pattern, text);
+            }
+        }
+
+        let mut cache = WildcardCache::new(2);
+        for pattern in ["a*", "b*", "c*"] {
+            cache.insert(pattern.to_string(), Arc::new(compile_wildcard(pattern).unwrap()));
+        }
+        assert!(cache.get("a*").is_none());
+        assert!(cache.get("b*").is_some());
+        cache.insert("d*".to_string(), Arc::new(compile_wildcard("d*").unwrap()));
+        // b* 刚被使用过，淘汰的是 c*
+        assert!(cache.get("c*").is_none());
+        assert!(cache.get("b*").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_check_integrity_and_rebuild() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs"]).await;
+        let root = temp_dir.path();
+        manager.add_tags(&root.join("a.rs"), "a.rs", vec!["layer:api".to_string()]).await.unwrap();
+        manager.add_tags(&root.join("b.rs"), "b.rs", vec!["layer:db".to_string()]).await.unwrap();
+        assert!(manager.check_integrity().is_empty());
+
+        // 模拟索引漂移
+        manager.tag_to_files.get_mut("layer:api").unwrap().remove("a.rs");
+        manager.tag_to_files.entry("status:done".to_string()).or_default().insert("b.rs".to_string());
+        manager.tag_index.entry("layer".to_string()).or_default().remove("db");
+        let kinds: Vec<IntegrityIssueKind> = manager.check_integrity().iter().map(|issue| issue.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![IntegrityIssueKind::MissingTagFile, IntegrityIssueKind::StaleTagFile, IntegrityIssueKind::MissingTagValue]
+        );
+
+        manager.rebuild_indices().await.unwrap();
+        assert!(manager.check_integrity().is_empty());
+        assert_eq!(manager.query_files_by_tags("layer:api").unwrap(), vec!["a.rs"]);
+    }
+
+    #[tokio::test]
+    async fn test_remove_shared_tag_updates_type_index() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs"]).await;
+        let root = temp_dir.path();
+        let tags = vec!["layer:api".to_string(), "status:done".to_string()];
+        manager.add_tags(&root.join("a.rs"), "a.rs", tags.clone()).await.unwrap();
+        manager.add_tags(&root.join("b.rs"), "b.rs", vec!["layer:api".to_string()]).await.unwrap();
+
+        // 仍有其他文件使用时保留在类型索引中
+        manager.remove_tags(&root.join("a.rs"), "a.rs", vec!["layer:api".to_string()]).await.unwrap();
+        assert_eq!(manager.get_all_tags().get("layer"), Some(&vec!["api".to_string()]));
+        assert_eq!(manager.query_files_by_tags("layer:api").unwrap(), vec!["b.rs"]);
+
+        // 最后一个文件移除后从类型索引中消失，索引快照同样如此
+        manager.remove_tags(&root.join("b.rs"), "b.rs", vec!["layer:api".to_string()]).await.unwrap();
+        assert!(!manager.get_all_tags().contains_key("layer"));
+        assert!(!manager.tag_index.contains_key("layer"));
+        assert!(!manager.tag_to_files.contains_key("layer:api"));
+        assert!(!manager.file_tags.contains_key("b.rs"));
+        assert_eq!(manager.get_all_tags().get("status"), Some(&vec!["done".to_string()]));
+
+        let mut reloaded = TagManager::new(JsonStorage::new(root.join(".codenexus")));
+        reloaded.initialize().await.unwrap();
+        assert_eq!(reloaded.get_all_tags(), manager.get_all_tags());
+    }
+
+    #[tokio::test]
+    async fn test_get_tags_for_files() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs"]).await;
+        let root = temp_dir.path();
+        manager.add_tags(&root.join("a.rs"), "a.rs", vec!["z:1".to_string(), "a:1".to_string()]).await.unwrap();
+
+        let paths = ["a.rs", "b.rs", "missing.rs"].map(String::from);
+        let result = manager.get_tags_for_files(&paths);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result["a.rs"], vec!["a:1", "z:1"]);
+    }
+
+    #[tokio::test]
+    async fn test_replace_file_tag() {
+        let (temp_dir, mut manager) = setup(&["a.rs"]).await;
+        manager.add_tags(&temp_dir.path().join("a.rs"), "a.rs", tags(&["status:wip", "layer:api"])).await.unwrap();
+
+        manager.replace_file_tag("a.rs", "status:wip", "status:done").await.unwrap();
+        assert_eq!(manager.get_file_tags("a.rs"), tags(&["layer:api", "status:done"]));
+        assert!(manager.query_files_by_tags("status:wip").unwrap().is_empty());
+        assert_eq!(manager.get_all_tags()["status"], vec!["done"]);
+
+        // 新标签已存在时只移除旧标签
+        manager.replace_file_tag("a.rs", "status:done", "layer:api").await.unwrap();
+        assert_eq!(manager.get_file_tags("a.rs"), tags(&["layer:api"]));
+
+        assert!(matches!(
+            manager.replace_file_tag("a.rs", "status:wip", "status:done").await,
+            Err(CodeNexusError::TagNotFound { .. })
+        ));
+        assert!(manager.replace_file_tag("a.rs", "layer:api", "invalid").await.is_err());
+        assert_eq!(manager.get_file_tags("a.rs"), tags(&["layer:api"]));
+        assert!(manager.check_integrity().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_import_jsonl() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs", "c.rs"]).await;
+        let content = [
+            r#"{"file_path": "a.rs", "tags": ["layer:api", "auth:login"]}"#,
+            "",
+            r#"{"file_path": "b.rs", "tags": ["layer:db"]}"#,
+            r#"{"file_path": "missing.rs", "tags": ["layer:db"]}"#,
+            r#"{"file_path": "c.rs", "tags": ["invalid"]}"#,
+            r#"not json"#,
+            r#"{"file_path": "./c.rs", "tags": ["layer:api"]}"#,
+            r#"{"file_path": "a.rs", "tags": ["layer:api"]}"#,
+        ]
+        .join("\n");
+
+        let report = manager.import_jsonl(temp_dir.path(), content.as_bytes(), 2).await.unwrap();
+        assert_eq!((report.lines, report.accepted, report.imported, report.flushes), (7, 4, 4, 2));
+        let lines: Vec<usize> = report.rejected.iter().map(|r| r.line).collect();
+        assert_eq!(lines, vec![4, 5, 6]);
+        assert_eq!(manager.query_files_by_tags("layer:api").unwrap(), vec!["a.rs", "c.rs"]);
+        assert!(manager.check_integrity().is_empty());
+
+        let mut reloaded = TagManager::new(manager.storage.clone());
+        reloaded.initialize().await.unwrap();
+        assert_eq!(reloaded.get_file_tags("a.rs"), vec!["auth:login", "layer:api"]);
+    }
+
+    #[tokio::test]
+    async fn test_import_jsonl_rolls_back_on_read_error() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs"]).await;
+        let root = temp_dir.path();
+        manager.add_tags(&root.join("a.rs"), "a.rs", tags(&["layer:api"])).await.unwrap();
+
+        // 第一批已保存后遇到无效的 UTF-8
+        let mut content = br#"{"file_path": "b.rs", "tags": ["layer:db"]}"#.to_vec();
+        content.extend_from_slice(b"\n\xff\xfe\n");
+        assert!(manager.import_jsonl(root, content.as_slice(), 1).await.is_err());
+        assert!(manager.get_file_tags("b.rs").is_empty());
+        assert!(manager.check_integrity().is_empty());
+
+        let mut reloaded = TagManager::new(manager.storage.clone());
+        reloaded.initialize().await.unwrap();
+        assert!(reloaded.get_file_tags("b.rs").is_empty());
+        assert_eq!(reloaded.get_file_tags("a.rs"), vec!["layer:api"]);
+    }
+
+    #[tokio::test]
+    async fn test_explain_query() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs", "c.rs"]).await;
+        let root = temp_dir.path();
+        manager.add_tags(&root.join("a.rs"), "a.rs", tags(&["layer:api", "auth:login", "status:wip"])).await.unwrap();
+        manager.add_tags(&root.join("b.rs"), "b.rs", tags(&["layer:db", "auth:token"])).await.unwrap();
+        manager.add_tags(&root.join("c.rs"), "c.rs", tags(&["layer:api", "status:done"])).await.unwrap();
+
+        let query = "(layer:api OR auth:*) AND NOT status:done";
+        let explained = manager.explain_query(query).unwrap();
+        assert_eq!(
+            explained,
+            vec![
+                ("a.rs".to_string(), tags(&["auth:login", "layer:api"])),
+                ("b.rs".to_string(), tags(&["auth:token"])),
+            ]
+        );
+        let files: Vec<String> = explained.into_iter().map(|(file, _)| file).collect();
+        assert_eq!(files, manager.query_files_by_tags(query).unwrap());
+
+        // NOT 子句不贡献标签
+        assert_eq!(manager.explain_query("NOT layer:*").unwrap(), vec![]);
+        assert_eq!(manager.explain_query("NOT status:wip").unwrap()[0], ("b.rs".to_string(), vec![]));
+        assert!(manager.explain_query("layer:api AND (").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tag_set_queries() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs", "c.rs"]).await;
+        let root = temp_dir.path();
+        manager.add_tags(&root.join("a.rs"), "a.rs", tags(&["layer:api", "auth:login"])).await.unwrap();
+        manager.add_tags(&root.join("b.rs"), "b.rs", tags(&["layer:api", "auth:login", "status:wip"])).await.unwrap();
+        manager.add_tags(&root.join("c.rs"), "c.rs", tags(&["layer:api"])).await.unwrap();
+
+        // 完全相同的集合，顺序无关
+        assert_eq!(manager.query_files_by_tags("=[auth:login, layer:api]").unwrap(), vec!["a.rs"]);
+        assert_eq!(manager.query_files_by_tags("=[layer:api]").unwrap(), vec!["c.rs"]);
+        // 超集
+        assert_eq!(manager.query_files_by_tags(">=[layer:api, auth:login]").unwrap(), vec!["a.rs", "b.rs"]);
+        assert_eq!(manager.query_files_by_tags(">=[layer:api] AND NOT =[layer:api]").unwrap(), vec!["a.rs", "b.rs"]);
+        // 不匹配
+        assert!(manager.query_files_by_tags("=[layer:api, status:wip]").unwrap().is_empty());
+        assert!(manager.query_files_by_tags(">=[layer:db]").unwrap().is_empty());
+
+        assert_eq!(
+            manager.explain_query(">=[auth:login]").unwrap(),
+            vec![("a.rs".to_string(), tags(&["auth:login"])), ("b.rs".to_string(), tags(&["auth:login"]))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_archive_and_unarchive_tags() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs"]).await;
+        let root = temp_dir.path();
+        manager.add_tags(&root.join("a.rs"), "a.rs", tags(&["layer:api", "status:wip"])).await.unwrap();
+        manager.add_tags(&root.join("b.rs"), "b.rs", tags(&["status:wip"])).await.unwrap();
+
+        assert_eq!(manager.archive_tags("a.rs", tags(&["status:wip"])).await.unwrap(), tags(&["status:wip"]));
+        assert_eq!(manager.query_files_by_tags("status:wip").unwrap(), vec!["b.rs"]);
+        assert_eq!(manager.get_file_tags("a.rs"), tags(&["layer:api"]));
+        assert!(matches!(
+            manager.archive_tags("a.rs", tags(&["status:wip"])).await,
+            Err(CodeNexusError::TagNotFound { .. })
+        ));
+
+        // 归档最后一个标签后文件不再计入已标记文件，且归档持久化
+        manager.archive_tags("b.rs", tags(&["status:wip"])).await.unwrap();
+        assert!(!manager.get_all_tags().contains_key("status"));
+        let mut reloaded = TagManager::new(manager.storage.clone());
+        reloaded.initialize().await.unwrap();
+        assert_eq!(reloaded.get_archived_tags().keys().collect::<Vec<_>>(), vec!["a.rs", "b.rs"]);
+        assert_eq!(reloaded.get_tagged_files(), vec!["a.rs"]);
+
+        assert_eq!(reloaded.unarchive_tags("a.rs", tags(&["status:wip"])).await.unwrap(), tags(&["status:wip"]));
+        assert_eq!(reloaded.query_files_by_tags("status:wip").unwrap(), vec!["a.rs"]);
+        assert!(reloaded.unarchive_tags("a.rs", tags(&["status:wip"])).await.is_err());
+        assert_eq!(reloaded.get_archived_tags().keys().collect::<Vec<_>>(), vec!["b.rs"]);
+    }
+
+    #[tokio::test]
+    async fn test_archived_tags_follow_tag_edits() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs"]).await;
+        let root = temp_dir.path();
+        manager.add_tags(&root.join("a.rs"), "a.rs", tags(&["status:wip", "owner:alice"])).await.unwrap();
+        manager.add_tags(&root.join("b.rs"), "b.rs", tags(&["status:wip"])).await.unwrap();
+        let added_at = manager.get_file_tags_with_meta("a.rs")[1].meta.added_at;
+        manager.archive_tags("a.rs", tags(&["status:wip", "owner:alice"])).await.unwrap();
+
+        // 改名与合并同样作用于归档的标签，并沿用添加时间
+        manager.rename_tag("status:wip", "status:doing").await.unwrap();
+        assert_eq!(manager.rename_tag_type("owner", "team").await.unwrap(), 1);
+        assert_eq!(manager.get_archived_tags()["a.rs"], tags(&["status:doing", "team:alice"]));
+        manager.unarchive_tags("a.rs", tags(&["status:doing"])).await.unwrap();
+        assert_eq!(manager.get_file_tags_with_meta("a.rs")[0].meta.added_at, added_at);
+
+        assert_eq!(manager.files_with_tags_matching("team:*").unwrap(), vec!["a.rs"]);
+        assert_eq!(manager.remove_tags_matching("team:*").await.unwrap(), 1);
+        assert!(manager.get_archived_tags().is_empty());
+
+        // 替换导入同时替换归档
+        manager.archive_tags("b.rs", tags(&["status:doing"])).await.unwrap();
+        let archived = HashMap::from([("a.rs".to_string(), tags(&["status:old"]))]);
+        manager.import_tags(HashMap::new(), archived, ImportMode::Replace).await.unwrap();
+        assert_eq!(manager.get_archived_tags(), BTreeMap::from([("a.rs".to_string(), tags(&["status:old"]))]));
+        assert!(manager.get_tagged_files().is_empty());
+        assert!(manager.import_tags(HashMap::new(), HashMap::from([("a.rs".to_string(), tags(&["bad"]))]), ImportMode::Merge).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tags_for_path_glob() {
+        let (temp_dir, mut manager) = setup(&["src/api/a.rs", "src/api/v2/b.rs", "src/db.rs"]).await;
+        let root = temp_dir.path();
+        manager.add_tags(&root.join("src/api/a.rs"), "src/api/a.rs", tags(&["layer:api", "status:wip"])).await.unwrap();
+        manager.add_tags(&root.join("src/api/v2/b.rs"), "src/api/v2/b.rs", tags(&["layer:api"])).await.unwrap();
+        manager.add_tags(&root.join("src/db.rs"), "src/db.rs", tags(&["layer:db"])).await.unwrap();
+
+        let counts = manager.tags_for_path_glob("src/api/").unwrap();
+        assert_eq!(counts, BTreeMap::from([("layer:api".to_string(), 2), ("status:wip".to_string(), 1)]));
+        assert_eq!(manager.tags_for_path_glob("src/*.rs").unwrap().keys().collect::<Vec<_>>(), vec!["layer:db"]);
+        assert!(manager.tags_for_path_glob("docs/**").unwrap().is_empty());
+        assert!(manager.tags_for_path_glob("src/[").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_files_sharing_tags() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs", "c.rs", "d.rs"]).await;
+        let root = temp_dir.path();
+        manager.add_tags(&root.join("a.rs"), "a.rs", tags(&["role:controller", "layer:api"])).await.unwrap();
+        manager.add_tags(&root.join("b.rs"), "b.rs", tags(&["role:controller", "layer:api"])).await.unwrap();
+        manager.add_tags(&root.join("c.rs"), "c.rs", tags(&["layer:api"])).await.unwrap();
+        manager.add_tags(&root.join("d.rs"), "d.rs", tags(&["layer:db"])).await.unwrap();
+
+        assert_eq!(
+            manager.files_sharing_tags("a.rs", 1),
+            vec![("b.rs".to_string(), 2), ("c.rs".to_string(), 1)]
+        );
+        assert_eq!(manager.files_sharing_tags("a.rs", 2), vec![("b.rs".to_string(), 2)]);
+        assert!(manager.files_sharing_tags("missing.rs", 1).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_remove_tags_matching() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs", "c.rs"]).await;
+        let root = temp_dir.path();
+        manager.add_tags(&root.join("a.rs"), "a.rs", tags(&["status:wip", "layer:api"])).await.unwrap();
+        manager.add_tags(&root.join("b.rs"), "b.rs", tags(&["status:wip-auth"])).await.unwrap();
+        manager.add_tags(&root.join("c.rs"), "c.rs", tags(&["status:done"])).await.unwrap();
+
+        assert_eq!(manager.files_with_tags_matching("status:wip*").unwrap(), vec!["a.rs", "b.rs"]);
+        assert_eq!(manager.remove_tags_matching("status:wip*").await.unwrap(), 2);
+        assert_eq!(manager.get_file_tags("a.rs"), vec!["layer:api"]);
+        assert!(manager.get_tagged_files().iter().all(|file| file != "b.rs"));
+        assert_eq!(manager.get_all_tags()["status"], vec!["done"]);
+        assert!(manager.check_integrity().is_empty());
+        assert_eq!(manager.remove_tags_matching("status:wip*").await.unwrap(), 0);
+
+        let mut reloaded = TagManager::new(JsonStorage::new(root.join(".codenexus")));
+        reloaded.initialize().await.unwrap();
+        assert_eq!(reloaded.get_tagged_files(), vec!["a.rs", "c.rs"]);
+    }
+
+    #[tokio::test]
+    async fn test_rename_tag_type() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs"]).await;
+        let root = temp_dir.path();
+        manager.add_tags(&root.join("a.rs"), "a.rs", tags(&["cat:a", "cat:b", "category:a"])).await.unwrap();
+        manager.add_tags(&root.join("b.rs"), "b.rs", tags(&["cat:c", "layer:api"])).await.unwrap();
+        let meta = manager.get_file_tags_with_meta("a.rs");
+        let (cat_b_added, category_a_added) = (meta[1].meta.added_at, meta[2].meta.added_at);
+
+        assert!(manager.rename_tag_type("cat", "cat").await.is_err());
+        assert!(manager.rename_tag_type("cat", "bad:type").await.is_err());
+        assert_eq!(manager.get_file_tags("a.rs"), vec!["cat:a", "cat:b", "category:a"]);
+
+        assert_eq!(manager.rename_tag_type("cat", "category").await.unwrap(), 2);
+        assert_eq!(manager.get_file_tags("a.rs"), vec!["category:a", "category:b"]);
+        assert_eq!(manager.get_file_tags("b.rs"), vec!["category:c", "layer:api"]);
+        // 改名的标签沿用原添加时间，合并时保留已有标签的
+        let meta = manager.get_file_tags_with_meta("a.rs");
+        assert_eq!((meta[0].meta.added_at, meta[1].meta.added_at), (category_a_added, cat_b_added));
+        assert!(!manager.get_all_tags().contains_key("cat"));
+        assert_eq!(manager.get_all_tags()["category"], vec!["a", "b", "c"]);
+        assert!(manager.check_integrity().is_empty());
+        assert_eq!(manager.rename_tag_type("cat", "category").await.unwrap(), 0);
+
+        let mut reloaded = TagManager::new(JsonStorage::new(root.join(".codenexus")));
+        reloaded.initialize().await.unwrap();
+        assert_eq!(reloaded.get_file_tags("b.rs"), vec!["category:c", "layer:api"]);
+    }
+
+    #[tokio::test]
+    async fn test_tag_added_at() {
+        let (temp_dir, mut manager) = setup(&["a.rs"]).await;
+        let root = temp_dir.path();
+        // 旧格式数据没有元数据
+        fs::write(root.join(".codenexus/tags.json"), r#"{"file_tags":{"a.rs":["layer:api"]}}"#).unwrap();
+        manager.initialize().await.unwrap();
+        assert_eq!(manager.get_file_tags_with_meta("a.rs")[0].meta, TagMeta::default());
+
+        manager.add_tags(&root.join("a.rs"), "a.rs", vec!["status:done".to_string()]).await.unwrap();
+        let tags = manager.get_file_tags_with_meta("a.rs");
+        assert_eq!(tags.iter().map(|t| t.tag.as_str()).collect::<Vec<_>>(), vec!["layer:api", "status:done"]);
+        assert!(tags[0].meta.added_at.is_none());
+        let added_at = tags[1].meta.added_at.expect("新标签应记录添加时间");
+        assert_eq!(manager.get_file_tags("a.rs"), vec!["layer:api", "status:done"]);
+
+        // 重新加载后保留，移除后清除
+        let mut reloaded = TagManager::new(JsonStorage::new(root.join(".codenexus")));
+        reloaded.initialize().await.unwrap();
+        assert_eq!(reloaded.get_file_tags_with_meta("a.rs")[1].meta.added_at, Some(added_at));
+        reloaded.remove_tags(&root.join("a.rs"), "a.rs", vec!["status:done".to_string()]).await.unwrap();
+        assert!(!reloaded.tag_meta.contains_key("a.rs"));
+        assert!(!fs::read_to_string(root.join(".codenexus/tags.json")).unwrap().contains("tag_meta"));
+    }
+
+    #[tokio::test]
+    async fn test_saved_tags_are_deterministic() {
+        let files = ["a.rs", "b.rs", "c.rs"];
+        let tags = ["z:1", "a:1", "m:1"];
+        let mut outputs = Vec::new();
+        for reversed in [false, true] {
+            let (temp_dir, mut manager) = setup(&files).await;
+            let root = temp_dir.path();
+            let mut order: Vec<&str> = files.to_vec();
+            if reversed {
+                order.reverse();
+            }
+            for file in order {
+                let mut file_tags: Vec<String> = tags.iter().map(|tag| tag.to_string()).collect();
+                if reversed {
+                    file_tags.reverse();
+                }
+                manager.add_tags(&root.join(file), file, file_tags).await.unwrap();
+            }
+            // 添加时间随运行变化，只比较标签本身
+            manager.tag_meta.clear();
+            manager.save_to_storage().await.unwrap();
+            outputs.push(fs::read_to_string(root.join(".codenexus/tags.json")).unwrap());
+        }
+        assert_eq!(outputs[0], outputs[1]);
+        assert!(outputs[0].find("a.rs").unwrap() < outputs[0].find("c.rs").unwrap());
+        assert!(outputs[0].find("a:1").unwrap() < outputs[0].find("z:1").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_strict_tag_values() {
+        let (_temp_dir, mut manager) = setup(&[]).await;
+        assert!(manager.validate_tag("note:a OR b").is_ok());
+
+        manager.set_config(Arc::new(ProjectConfig { strict_tag_values: true, ..Default::default() }));
+        let rejected = [
+            ("note:a OR b", "OR"),
+            ("note:NOT done", "NOT"),
+            ("note:f(x)", "("),
+            ("note:a*", "*"),
+            ("note:v1.?", "?"),
+            ("note:x >=[y]", ">=["),
+            ("note:=[y]", "=["),
+            ("note:x OR", "OR"),
+            ("note:a\tOR\tb", "OR"),
+            ("note:AND", "AND"),
+            ("a OR b:c", "OR"),
+            ("f(x):y", "("),
+        ];
+        for (tag, expected) in rejected {
+            match manager.validate_tag(tag) {
+                Err(CodeNexusError::ReservedTagValue { token, .. }) => assert_eq!(token, expected),
+                other => panic!("标签 {} 应被拒绝，实际为 {:?}", tag, other),
+            }
+        }
+        assert!(manager.validate_tag("note:ANDROID").is_ok());
+        assert!(manager.validate_tag("note:CANNOT fix").is_ok());
+        assert!(manager.validate_tag("note:a or b").is_ok());
+        assert!(manager.validate_tag("note:sort order").is_ok());
+        assert!(manager.validate_tag("note:a >= b").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_index_snapshot_reload() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs"]).await;
+        let root = temp_dir.path();
+        manager.add_tags(&root.join("a.rs"), "a.rs", vec!["layer:api".to_string()]).await.unwrap();
+        manager.add_tags(&root.join("b.rs"), "b.rs", vec!["layer:db".to_string()]).await.unwrap();
+
+        // 修改只写 tags.json，快照在卸载时才刷新
+        let storage = JsonStorage::new(root.join(".codenexus"));
+        let (_, hash) = storage.load_tags_with_hash().await.unwrap();
+        assert_ne!(storage.load_tag_index().await.unwrap().source_hash, hash);
+        manager.save_index_snapshot().await.unwrap();
+        assert_eq!(storage.load_tag_index().await.unwrap().source_hash, hash);
+
+        // 快照与数据一致时直接恢复索引
+        let mut reloaded = TagManager::new(storage.clone());
+        reloaded.initialize().await.unwrap();
+        assert_eq!(reloaded.get_all_tags(), manager.get_all_tags());
+        assert_eq!(reloaded.query_files_by_tags("layer:api").unwrap(), vec!["a.rs"]);
+
+        // 外部修改 tags.json 后快照失效，重新构建索引
+        let mut data = storage.load_tags().await.unwrap();
+        data.file_tags.insert("a.rs".to_string(), vec!["layer:web".to_string()]);
+        fs::write(root.join(".codenexus/tags.json"), serde_json::to_string_pretty(&data).unwrap()).unwrap();
+
+        let mut rebuilt = TagManager::new(storage);
+        rebuilt.initialize().await.unwrap();
+        assert!(rebuilt.query_files_by_tags("layer:api").unwrap().is_empty());
+        assert_eq!(rebuilt.query_files_by_tags("layer:web").unwrap(), vec!["a.rs"]);
+    }
+
+    #[tokio::test]
+    async fn test_csv_round_trip() {
+        let (temp_dir, mut manager) = setup(&["a,b.rs", "c.rs"]).await;
+        let root = temp_dir.path();
+
+        let content = "file_path,tag\n\"a,b.rs\",layer:api\nc.rs,invalid\nmissing.rs,layer:db\nc.rs,layer:db\n";
+        let report = manager.import_csv(root, content).await.unwrap();
+        assert_eq!(report.imported, 2);
+        let rejected_lines: Vec<usize> = report.rejected.iter().map(|r| r.line).collect();
+        assert_eq!(rejected_lines, vec![3, 4]);
+
+        let exported = manager.export_csv().unwrap();
+        assert_eq!(exported, "file_path,tag\n\"a,b.rs\",layer:api\nc.rs,layer:db\n");
+    }
+
+    #[tokio::test]
+    async fn test_suggest_cooccurring_tags() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs", "c.rs"]).await;
+        let root = temp_dir.path();
+        manager.add_tags(&root.join("a.rs"), "a.rs", tags(&["layer:controller", "category:api", "status:done"])).await.unwrap();
+        manager.add_tags(&root.join("b.rs"), "b.rs", tags(&["layer:controller", "category:api"])).await.unwrap();
+        manager.add_tags(&root.join("c.rs"), "c.rs", tags(&["layer:service", "status:done"])).await.unwrap();
+
+        let suggestions = manager.suggest_cooccurring_tags(&tags(&["layer:controller"]), 10);
+        assert_eq!(suggestions, vec![("category:api".to_string(), 2), ("status:done".to_string(), 1)]);
+
+        let suggestions = manager.suggest_cooccurring_tags(&tags(&["layer:controller", "status:done"]), 10);
+        assert_eq!(suggestions, vec![("category:api".to_string(), 1)]);
+
+        assert!(manager.suggest_cooccurring_tags(&tags(&["layer:unknown"]), 10).is_empty());
+        assert_eq!(manager.suggest_cooccurring_tags(&tags(&["layer:controller"]), 1).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_tag_schema_validation() {
+        let (temp_dir, manager) = setup(&[]).await;
+        assert!(manager.tag_schema().is_none());
+        assert!(manager.validate_tag("anything:goes").is_ok());
+
+        let schema = r#"{
+            "types": {
+                "layer": {},
+                "status": { "values": ["todo", "done"] },
+                "ticket": { "pattern": "[A-Z]+-[0-9]+" }
+            }
+        }"#;
+        fs::write(temp_dir.path().join(".codenexus").join("tag_schema.json"), schema).unwrap();
+        let mut manager = TagManager::new(JsonStorage::new(temp_dir.path().join(".codenexus")));
+        manager.initialize().await.unwrap();
+
+        assert_eq!(manager.tag_schema().unwrap().types.len(), 3);
+        assert!(manager.validate_tag("layer:service").is_ok());
+        assert!(manager.validate_tag("status:done").is_ok());
+        assert!(manager.validate_tag("ticket:CN-42").is_ok());
+        for tag in ["category:api", "status:wip", "ticket:cn-42x"] {
+            assert!(matches!(
+                manager.validate_tag(tag),
+                Err(CodeNexusError::TagSchemaViolation { .. })
+            ));
+        }
+        assert!(matches!(manager.validate_tag("layer"), Err(CodeNexusError::InvalidTagFormat(_))));
+    }
+
+    #[tokio::test]
+    async fn test_clear_file_tags() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs"]).await;
+        let root = temp_dir.path();
+        let tags = vec!["layer:service".to_string(), "status:done".to_string()];
+        manager.add_tags(&root.join("a.rs"), "a.rs", tags.clone()).await.unwrap();
+        manager.add_tags(&root.join("b.rs"), "b.rs", vec!["layer:service".to_string()]).await.unwrap();
+
+        assert_eq!(manager.clear_file_tags("a.rs").await.unwrap(), 2);
+        assert!(manager.get_file_tags("a.rs").is_empty());
+        assert_eq!(manager.query_files_by_tags("layer:service").unwrap(), vec!["b.rs".to_string()]);
+        assert!(manager.query_files_by_tags("status:done").unwrap().is_empty());
+        assert!(!manager.get_all_tags().contains_key("status"));
+        assert!(matches!(
+            manager.clear_file_tags("a.rs").await,
+            Err(CodeNexusError::FileNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_add_tags_returns_added() {
+        let (temp_dir, mut manager) = setup(&["a.rs"]).await;
+        let file = temp_dir.path().join("a.rs");
+
+        let added = manager.add_tags(&file, "a.rs", tags(&["layer:api", "status:done"])).await.unwrap();
+        assert_eq!(added, tags(&["layer:api", "status:done"]));
+        let added = manager.add_tags(&file, "a.rs", tags(&["status:done", "status:todo", "status:todo"])).await.unwrap();
+        assert_eq!(added, tags(&["status:todo"]));
+        assert!(manager.add_tags(&file, "a.rs", tags(&["layer:api"])).await.unwrap().is_empty());
+
+        assert_eq!(manager.get_file_tags("a.rs"), tags(&["layer:api", "status:done", "status:todo"]));
+        assert!(manager.has_tag("a.rs", "status:todo"));
+        assert!(!manager.has_tag("a.rs", "status:wip"));
+        assert!(!manager.has_tag("b.rs", "layer:api"));
+    }
+
+    #[tokio::test]
+    async fn test_wildcard_and_type_queries() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs", "c.rs", "d.rs"]).await;
+        let root = temp_dir.path();
+        manager.add_tags(&root.join("a.rs"), "a.rs", tags(&["status:done", "layer:api"])).await.unwrap();
+        manager.add_tags(&root.join("b.rs"), "b.rs", tags(&["status:wip"])).await.unwrap();
+        manager.add_tags(&root.join("c.rs"), "c.rs", tags(&["status:todo", "layer:db"])).await.unwrap();
+        manager.add_tags(&root.join("d.rs"), "d.rs", tags(&["layer:api"])).await.unwrap();
+
+        let query = |q: &str| manager.query_files_by_tags(q).unwrap();
+        // 没有 status 标签的文件不应出现在结果中
+        assert_eq!(query("status:* AND NOT status:done"), tags(&["b.rs", "c.rs"]));
+        assert_eq!(query("NOT status:done AND status:*"), tags(&["b.rs", "c.rs"]));
+        assert_eq!(query("status:*"), tags(&["a.rs", "b.rs", "c.rs"]));
+        assert_eq!(query("NOT status:*"), tags(&["d.rs"]));
+        assert_eq!(query("layer:* AND NOT status:*"), tags(&["d.rs"]));
+        assert_eq!(query("status:*o*"), tags(&["a.rs", "c.rs"]));
+        assert_eq!(query("*:api"), tags(&["a.rs", "d.rs"]));
+        // 首尾部分不能重叠匹配
+        assert!(query("status:do*done").is_empty());
+        assert!(query("unknown:*").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_group_files_by_type() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs", "c.rs"]).await;
+        let root = temp_dir.path();
+        manager.add_tags(&root.join("a.rs"), "a.rs", tags(&["layer:api", "status:done"])).await.unwrap();
+        manager.add_tags(&root.join("b.rs"), "b.rs", tags(&["layer:api"])).await.unwrap();
+        manager.add_tags(&root.join("c.rs"), "c.rs", tags(&["status:wip"])).await.unwrap();
+
+        let groups = manager.group_files_by_type("layer");
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups["api"], tags(&["a.rs", "b.rs"]));
+        assert!(manager.group_files_by_type("unknown").is_empty());
+        assert_eq!(manager.get_files_without_type(root, "layer").unwrap(), tags(&["c.rs"]));
+    }
+
+    #[tokio::test]
+    async fn test_merge_and_rename_tags() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs", "c.rs"]).await;
+        let root = temp_dir.path();
+        manager.add_tags(&root.join("a.rs"), "a.rs", tags(&["layer:api", "layer:web"])).await.unwrap();
+        manager.add_tags(&root.join("b.rs"), "b.rs", tags(&["layer:web"])).await.unwrap();
+        manager.add_tags(&root.join("c.rs"), "c.rs", tags(&["layer:db"])).await.unwrap();
+
+        let plan = manager.plan_merge_tags(&tags(&["layer:web", "layer:api"]), "layer:api").unwrap();
+        assert_eq!(plan.sources, tags(&["layer:web"]));
+        assert_eq!(plan.affected_files, tags(&["a.rs", "b.rs"]));
+        // 计划不修改数据
+        assert!(manager.has_tag("b.rs", "layer:web"));
+
+        assert_eq!(manager.apply_tag_merge(&plan).await.unwrap(), 2);
+        assert_eq!(manager.get_file_tags("a.rs"), tags(&["layer:api"]));
+        assert_eq!(manager.get_file_tags("b.rs"), tags(&["layer:api"]));
+        assert_eq!(manager.get_all_tags()["layer"], tags(&["api", "db"]));
+
+        let plan = manager.rename_tag("layer:db", "layer:storage").await.unwrap();
+        assert_eq!(plan.affected_files, tags(&["c.rs"]));
+        assert_eq!(manager.query_files_by_tags("layer:storage").unwrap(), tags(&["c.rs"]));
+        assert!(manager.rename_tag("layer:api", "invalid").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_detailed_stats() {
+        let (temp_dir, mut manager) = setup(&["a.rs", "b.rs"]).await;
+        let root = temp_dir.path();
+        assert_eq!(manager.get_detailed_stats(), TagDetailedStats::default());
+
+        manager.add_tags(&root.join("a.rs"), "a.rs", tags(&["layer:api", "status:done", "layer:web"])).await.unwrap();
+        manager.add_tags(&root.join("b.rs"), "b.rs", tags(&["layer:api"])).await.unwrap();
+
+        let stats = manager.get_detailed_stats();
+        assert_eq!((stats.total_files, stats.total_tags), (2, 3));
+        assert_eq!(stats.type_counts["layer"], 3);
+        assert_eq!(stats.type_counts["status"], 1);
+        assert_eq!(stats.average_tags_per_file, 2.0);
+        assert_eq!(stats.top_tags[0], TagCount { tag: "layer:api".to_string(), count: 2 });
+        assert_eq!(stats.top_tags[1].tag, "layer:web");
     }
 }
@@ -1,24 +1,83 @@
-use crate::error::{format_error_response, CodeNexusError};
-use crate::managers::{TagManager, CommentManager, RelationManager};
+use crate::config::ProjectConfig;
+use crate::error::{format_error_data_response, format_error_response, CodeNexusError};
+use crate::i18n::{set_locale, Locale, Message};
+use crate::managers::{TagManager, CommentManager, RelationManager, ContentHashManager};
 use crate::models::*;
-use crate::query::QueryEngine;
+use crate::mcp::diff::diff_exports;
+use crate::mcp::metrics::{MetricsCounters, Operation};
+use crate::mcp::outputs::{output_schemas, OutputSchemas};
+use crate::mcp::undo::{UndoData, UndoEntry, UndoScope, UndoStack};
+use crate::mcp::watcher::spawn_data_watcher;
+use crate::query::{QueryEngine, DEFAULT_FUZZY_THRESHOLD};
 use crate::storage::JsonStorage;
-use crate::utils::{validate_project_path, validate_file_path, get_data_dir, normalize_file_path};
+use crate::utils::{validate_project_path, validate_file_path, get_data_dir, normalize_file_path, normalize_file_path_lenient, resolve_output_path, lint_markdown, parse_time_bound};
+use crate::mcp::prompts;
+use crate::mcp::resources::{parse_resource_uri, resource_uri};
+use rmcp::model::{AnnotateAble, GetPromptRequestParam, GetPromptResult, ListPromptsResult, ListResourcesResult, PaginatedRequestParam, RawResource, ReadResourceRequestParam, ReadResourceResult, ResourceContents};
+use rmcp::service::{RequestContext, RoleServer};
 use rmcp::{ServerHandler, model::{ServerInfo, ServerCapabilities, ErrorData}, tool};
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::Mutex;
-use tracing::{error, info};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use tokio::sync::{Mutex, RwLock};
+use tracing::{error, info, warn};
 use std::fs::OpenOptions;
 use std::io::Write;
-use chrono::Local;
+use chrono::{DateTime, Local, Utc};
 
-/// 调试开关常量
-const DEBUG_ENABLED: bool = false;
+/// 关联图谱默认遍历深度
+const DEFAULT_GRAPH_DEPTH: usize = 3;
+
+/// 关联图谱默认的源文件数量上限，防止稠密图返回过多数据
+const DEFAULT_GRAPH_MAX_NODES: usize = 500;
+
+/// 推荐类工具默认返回数量
+const DEFAULT_SUGGESTION_LIMIT: usize = 10;
+
+/// 排行类工具默认返回数量
+const DEFAULT_RANK_LIMIT: usize = 10;
+
+/// JSON Lines 导入时默认每批保存的记录数
+const DEFAULT_JSONL_BATCH_SIZE: usize = 10_000;
+
+/// 导出文件所在的数据目录子目录，与数据文件分开以免被覆盖
+const EXPORTS_DIR: &str = "exports";
+
+/// 由各管理器加载的数据文件
+const DATA_FILES: [&str; 3] = ["tags.json", "comments.json", "relations.json"];
+
+/// 按标签类型分组时，没有该类型标签的文件所在的分组键
+const UNTAGGED_GROUP_KEY: &str = "(untagged)";
+
+/// 调试开关环境变量，取值为 1/true/yes/on 时启用调试日志
+const DEBUG_ENV_VAR: &str = "CODE_NEXUS_DEBUG";
+
+/// 调试开关，首次访问时从环境变量读取，运行时可通过 `set_debug_logging` 工具修改
+static DEBUG_ENABLED: OnceLock<AtomicBool> = OnceLock::new();
+
+/// 获取调试开关
+fn debug_flag() -> &'static AtomicBool {
+    DEBUG_ENABLED.get_or_init(|| {
+        let enabled = std::env::var(DEBUG_ENV_VAR)
+            .map(|value| matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+            .unwrap_or(false);
+        AtomicBool::new(enabled)
+    })
+}
+
+/// 调试日志是否启用
+pub fn debug_enabled() -> bool {
+    debug_flag().load(Ordering::Relaxed)
+}
+
+/// 设置调试日志开关
+pub fn set_debug_enabled(enabled: bool) {
+    debug_flag().store(enabled, Ordering::Relaxed);
+}
 
 /// 写入调试日志到文件
 fn write_debug_log(message: &str, project_path: Option<&str>) {
-    if !DEBUG_ENABLED {
+    if !debug_enabled() {
         return;
     }
 
@@ -65,21 +124,165 @@ fn write_debug_log(message: &str, project_path: Option<&str>) {
 /// 调试信息输出宏（带项目路径）
 macro_rules! debug_log_with_project {
     ($project_path:expr, $($arg:tt)*) => {
-        if DEBUG_ENABLED {
+        if debug_enabled() {
             let message = format!($($arg)*);
             write_debug_log(&message, Some($project_path));
         }
     };
 }
 
+/// 记录加载失败的数据文件并禁止写入，避免空数据覆盖磁盘上的原文件
+fn flag_load_error(
+    storage: &JsonStorage,
+    load_errors: &mut BTreeMap<String, String>,
+    file_name: &str,
+    error: &CodeNexusError,
+) {
+    warn!("数据文件 {} 加载失败，恢复前禁止写入: {}", file_name, error);
+    storage.mark_unavailable(file_name);
+    load_errors.insert(file_name.to_string(), error.to_string());
+}
+
+/// 按文件重新加载数据所需的共享状态，项目管理器和数据文件监听共用同一套处理
+#[derive(Debug, Clone)]
+pub struct DataFileReloader {
+    storage: JsonStorage,
+    tag_manager: Arc<RwLock<TagManager>>,
+    comment_manager: Arc<RwLock<CommentManager>>,
+    relation_manager: Arc<RwLock<RelationManager>>,
+    undo_stack: Arc<Mutex<UndoStack>>,
+    load_errors: Arc<Mutex<BTreeMap<String, String>>>,
+}
+
+impl DataFileReloader {
+    /// 从磁盘重新加载单个数据文件到对应管理器
+    ///
+    /// 成功时清空撤销栈（快照基于重新加载前的数据，撤销会覆盖外部修改）并解除写入限制；
+    /// 失败时管理器保留原有数据，文件被标记为加载失败并禁止写入
+    pub async fn reload_file(&self, file_name: &str) -> std::result::Result<(), CodeNexusError> {
+        let result = match file_name {
+            "tags.json" => self.tag_manager.write().await.initialize().await,
+            "comments.json" => self.comment_manager.write().await.initialize().await,
+            _ => self.relation_manager.write().await.initialize().await,
+        };
+
+        let mut load_errors = self.load_errors.lock().await;
+        if let Err(e) = result {
+            flag_load_error(&self.storage, &mut load_errors, file_name, &e);
+            return Err(e);
+        }
+        self.undo_stack.lock().await.clear();
+        self.storage.mark_available(file_name);
+        load_errors.remove(file_name);
+        Ok(())
+    }
+}
+
+/// 汇总各管理器当前的数据量
+fn reload_summary(
+    tag_manager: &TagManager,
+    comment_manager: &CommentManager,
+    relation_manager: &RelationManager,
+) -> ReloadSummary {
+    let (tagged_files, total_tags, _) = tag_manager.get_stats();
+    let (commented_files, _) = comment_manager.get_stats();
+    let (files_with_relations, total_relations, _) = relation_manager.get_stats();
+    ReloadSummary {
+        tagged_files,
+        total_tags,
+        commented_files,
+        files_with_relations,
+        total_relations,
+    }
+}
+
+/// 元数据包中出现的所有路径键（包括关联目标）
+fn bundle_keys(bundle: &ProjectExport) -> std::collections::BTreeSet<&String> {
+    let mut keys = std::collections::BTreeSet::new();
+    keys.extend(bundle.tags.keys());
+    keys.extend(bundle.archived_tags.keys());
+    keys.extend(bundle.comments.keys());
+    keys.extend(bundle.relations.keys());
+    keys.extend(bundle.relations.values().flatten().map(|relation| &relation.target));
+    keys
+}
+
+/// 按 `rekeyed`（原键 -> 新键）映射元数据包的路径键，合并映射后重合的条目
+///
+/// 标签取并集；注释优先保留键未变化的条目；关联关系按目标去重
+fn merge_rekeyed(bundle: ProjectExport, rekeyed: &HashMap<String, String>) -> ProjectExport {
+    let rekey = |key: &String| rekeyed.get(key).cloned().unwrap_or_else(|| key.clone());
+
+    // 按原始键排序处理，保证合并结果稳定
+    let mut merged = ProjectExport::default();
+    let mut tags: Vec<_> = bundle.tags.into_iter().collect();
+    tags.sort();
+    for (key, file_tags) in tags {
+        let entry = merged.tags.entry(rekey(&key)).or_default();
+        for tag in file_tags {
+            if !entry.contains(&tag) {
+                entry.push(tag);
+            }
+        }
+    }
+
+    let mut archived_tags: Vec<_> = bundle.archived_tags.into_iter().collect();
+    archived_tags.sort();
+    for (key, file_archived) in archived_tags {
+        let entry = merged.archived_tags.entry(rekey(&key)).or_default();
+        for tag in file_archived {
+            if !entry.contains(&tag) {
+                entry.push(tag);
+            }
+        }
+    }
+
+    let mut comments: Vec<_> = bundle.comments.into_iter().collect();
+    comments.sort_by_key(|(key, _)| (rekeyed.contains_key(key), key.clone()));
+    for (key, comment) in comments {
+        merged.comments.entry(rekey(&key)).or_insert(comment);
+    }
+
+    let mut relations: Vec<_> = bundle.relations.into_iter().collect();
+    relations.sort_by(|a, b| a.0.cmp(&b.0));
+    for (key, file_relations) in relations {
+        let entry = merged.relations.entry(rekey(&key)).or_default();
+        for relation in file_relations {
+            let target = rekey(&relation.target);
+            if !entry.iter().any(|existing| existing.target == target) {
+                entry.push(Relation { target, ..relation });
+            }
+        }
+    }
+
+    merged
+}
+
+/// 项目在缓存中的键：展开环境变量并规范化后的绝对路径
+fn project_key(project_path: &str) -> std::result::Result<String, CodeNexusError> {
+    Ok(validate_project_path(project_path)?.to_string_lossy().into_owned())
+}
+
 /// 项目管理器
 #[derive(Debug)]
 pub struct ProjectManager {
-    tag_manager: Arc<Mutex<TagManager>>,
-    comment_manager: Arc<Mutex<CommentManager>>,
-    relation_manager: Arc<Mutex<RelationManager>>,
+    tag_manager: Arc<RwLock<TagManager>>,
+    comment_manager: Arc<RwLock<CommentManager>>,
+    relation_manager: Arc<RwLock<RelationManager>>,
+    content_hash_manager: Mutex<ContentHashManager>,
     query_engine: Arc<QueryEngine>,
     project_path: String,
+    // 规范化后的项目根目录，创建时解析一次
+    root: std::path::PathBuf,
+    storage: JsonStorage,
+    // 当前生效的项目配置
+    config: Arc<ProjectConfig>,
+    // 数据文件监听器（配置启用时存在），释放时停止监听
+    data_watcher: Option<notify::RecommendedWatcher>,
+    // 修改操作前的快照，仅保存在内存中
+    undo_stack: Arc<Mutex<UndoStack>>,
+    // 加载失败的数据文件 -> 错误信息
+    load_errors: Arc<Mutex<BTreeMap<String, String>>>,
 }
 
 /// CodeNexus MCP 服务器
@@ -87,6 +290,8 @@ pub struct ProjectManager {
 pub struct CodeNexusServer {
     // 使用 HashMap 管理多个项目
     projects: Arc<Mutex<HashMap<String, Arc<Mutex<ProjectManager>>>>>,
+    // 服务器启动以来的操作计数
+    metrics: Arc<MetricsCounters>,
 }
 
 impl ProjectManager {
@@ -104,26 +309,43 @@ impl ProjectManager {
         storage.initialize().await?;
         debug_log_with_project!(project_path, "存储初始化完成");
 
+        let config = storage.load_config().await?;
+        debug_log_with_project!(project_path, "项目配置加载完成: {:?}", config);
+
         // 创建管理器
         debug_log_with_project!(project_path, "开始创建各种管理器");
         let mut tag_manager = TagManager::new(storage.clone());
         let mut comment_manager = CommentManager::new(storage.clone());
-        let mut relation_manager = RelationManager::new(storage);
+        let mut relation_manager = RelationManager::new(storage.clone());
+        let mut content_hash_manager = ContentHashManager::new(storage.clone());
 
         // 初始化管理器
         debug_log_with_project!(project_path, "开始初始化管理器");
-        tag_manager.initialize().await?;
+        // 单个数据文件损坏时以空数据继续，避免整个项目不可用
+        let mut load_errors = BTreeMap::new();
+        if let Err(e) = tag_manager.initialize().await {
+            flag_load_error(&storage, &mut load_errors, "tags.json", &e);
+            tag_manager = TagManager::new(storage.clone());
+        }
         debug_log_with_project!(project_path, "标签管理器初始化完成");
-        comment_manager.initialize().await?;
+        if let Err(e) = comment_manager.initialize().await {
+            flag_load_error(&storage, &mut load_errors, "comments.json", &e);
+            comment_manager = CommentManager::new(storage.clone());
+        }
         debug_log_with_project!(project_path, "注释管理器初始化完成");
-        relation_manager.initialize().await?;
+        if let Err(e) = relation_manager.initialize().await {
+            flag_load_error(&storage, &mut load_errors, "relations.json", &e);
+            relation_manager = RelationManager::new(storage.clone());
+        }
         debug_log_with_project!(project_path, "关联关系管理器初始化完成");
+        content_hash_manager.initialize().await?;
+        debug_log_with_project!(project_path, "内容哈希管理器初始化完成");
 
-        // 包装为 Arc<Mutex<>>
-        debug_log_with_project!(project_path, "包装管理器为 Arc<Mutex<>>");
-        let tag_manager = Arc::new(Mutex::new(tag_manager));
-        let comment_manager = Arc::new(Mutex::new(comment_manager));
-        let relation_manager = Arc::new(Mutex::new(relation_manager));
+        // 包装为 Arc<RwLock<>>
+        debug_log_with_project!(project_path, "包装管理器为 Arc<RwLock<>>");
+        let tag_manager = Arc::new(RwLock::new(tag_manager));
+        let comment_manager = Arc::new(RwLock::new(comment_manager));
+        let relation_manager = Arc::new(RwLock::new(relation_manager));
 
         // 创建查询引擎
         debug_log_with_project!(project_path, "创建查询引擎");
@@ -133,15 +355,623 @@ impl ProjectManager {
             relation_manager.clone(),
         ));
 
-        debug_log_with_project!(project_path, "项目管理器创建完成: {}", project_path);
-        Ok(Self {
+        let mut project_manager = Self {
             tag_manager,
             comment_manager,
             relation_manager,
+            content_hash_manager: Mutex::new(content_hash_manager),
             query_engine,
             project_path: project_path.to_string(),
+            root: validated_path,
+            storage,
+            config: Arc::new(ProjectConfig::default()),
+            data_watcher: None,
+            undo_stack: Arc::new(Mutex::new(UndoStack::new(config.undo_depth))),
+            load_errors: Arc::new(Mutex::new(load_errors)),
+        };
+        project_manager.apply_config(config).await?;
+
+        debug_log_with_project!(project_path, "项目管理器创建完成: {}", project_path);
+        Ok(project_manager)
+    }
+
+    /// 按文件重新加载数据的共享处理，数据文件监听也使用它
+    fn data_reloader(&self) -> DataFileReloader {
+        DataFileReloader {
+            storage: self.storage.clone(),
+            tag_manager: self.tag_manager.clone(),
+            comment_manager: self.comment_manager.clone(),
+            relation_manager: self.relation_manager.clone(),
+            undo_stack: self.undo_stack.clone(),
+            load_errors: self.load_errors.clone(),
+        }
+    }
+
+    /// 当前生效的项目配置
+    pub fn config(&self) -> Arc<ProjectConfig> {
+        self.config.clone()
+    }
+
+    /// 将文件路径规范化为相对于项目根目录的键，启用 `path_case_fold` 时转为小写
+    pub fn normalize_path(&self, file_path: &std::path::Path) -> std::result::Result<String, CodeNexusError> {
+        normalize_file_path(&self.root, file_path, self.config.path_case_fold)
+    }
+
+    /// 同 `normalize_path`，文件不存在时按词法规范化（见 `normalize_file_path_lenient`）
+    pub fn normalize_path_lenient(&self, file_path: &str) -> std::result::Result<String, CodeNexusError> {
+        normalize_file_path_lenient(&self.root, file_path, self.config.path_case_fold)
+    }
+
+    /// 将配置应用到各管理器，按需启动或停止数据文件监听
+    ///
+    /// 先执行所有可能失败的步骤（编译关联描述格式、启动监听），全部成功后才修改任何状态，
+    /// 失败时已有配置保持不变。各管理器共享同一个 `Arc<ProjectConfig>`
+    async fn apply_config(&mut self, config: ProjectConfig) -> std::result::Result<(), CodeNexusError> {
+        let description_regex = RelationManager::compile_description_pattern(config.relation_description_pattern.as_deref())?;
+        let new_watcher = if config.watch_data_files && self.data_watcher.is_none() {
+            debug_log_with_project!(&self.project_path, "启动数据文件监听");
+            Some(spawn_data_watcher(self.storage.clone(), self.data_reloader())?)
+        } else {
+            None
+        };
+
+        let config = Arc::new(config);
+        self.relation_manager.write().await.set_config(config.clone(), description_regex);
+        self.tag_manager.write().await.set_config(config.clone());
+        self.comment_manager.write().await.set_config(config.clone());
+        self.content_hash_manager.lock().await.set_config(config.clone());
+        self.undo_stack.lock().await.set_depth(config.undo_depth);
+        self.storage.set_backup_count(config.backup_count);
+
+        if !config.watch_data_files {
+            self.data_watcher = None;
+        } else if new_watcher.is_some() {
+            self.data_watcher = new_watcher;
+        }
+
+        self.config = config;
+        Ok(())
+    }
+
+    /// 更新项目配置：合并提供的字段，应用到各管理器并写入 `.codenexus/config.json`
+    ///
+    /// 未知字段会被拒绝，避免拼写错误的配置项被静默忽略
+    pub async fn set_config(&mut self, changes: serde_json::Value) -> std::result::Result<Arc<ProjectConfig>, CodeNexusError> {
+        let serde_json::Value::Object(changes) = changes else {
+            return Err(CodeNexusError::ConfigError(Message::ConfigNotObject));
+        };
+
+        let mut merged = serde_json::to_value(self.config.as_ref())?;
+        let fields = merged.as_object_mut().expect("ProjectConfig 序列化为 JSON 对象");
+        for (key, value) in changes {
+            if !fields.contains_key(&key) {
+                return Err(CodeNexusError::ConfigError(Message::UnknownConfigKey(key)));
+            }
+            fields.insert(key, value);
+        }
+        let config: ProjectConfig = serde_json::from_value(merged)
+            .map_err(|e| CodeNexusError::ConfigError(Message::InvalidConfig(e.to_string())))?;
+
+        self.apply_config(config).await?;
+        self.storage.save_config(&self.config).await?;
+        info!("项目 {} 的配置已更新", self.project_path);
+        Ok(self.config.clone())
+    }
+
+    /// 标注文件后记录其内容哈希（配置启用时），失败只记录警告，不影响标注本身
+    pub async fn record_content_hash(&self, absolute_file_path: &std::path::Path, relative_file_path: &str) {
+        if let Err(e) = self.content_hash_manager.lock().await.record(absolute_file_path, relative_file_path).await {
+            warn!("记录文件 {} 的内容哈希失败: {}", relative_file_path, e);
+        }
+    }
+
+    /// 移除已没有任何元数据（标签、归档标签、注释、出向关联）的文件的内容哈希，失败只记录警告
+    ///
+    /// 在移除元数据的操作成功后调用；调用时不能持有管理器的写锁
+    pub async fn prune_content_hashes(&self) {
+        let tag_manager = self.tag_manager.read().await;
+        let comment_manager = self.comment_manager.read().await;
+        let relation_manager = self.relation_manager.read().await;
+        let archived = tag_manager.get_archived_tags();
+        let has_metadata = |file_path: &str| {
+            !tag_manager.get_file_tags(file_path).is_empty()
+                || archived.contains_key(file_path)
+                || comment_manager.has_comment(file_path)
+                || !relation_manager.get_file_relations(file_path).is_empty()
+        };
+        if let Err(e) = self.content_hash_manager.lock().await.retain(has_metadata).await {
+            warn!("清理内容哈希失败: {}", e);
+        }
+    }
+
+    /// 将一次修改操作追加到审计日志（配置启用时），写入失败只记录警告，不影响操作本身
+    pub async fn record_audit(&self, operation: &str, files: &[String], error: Option<&CodeNexusError>) {
+        if !self.config.audit_log {
+            return;
+        }
+        let entry = AuditEntry {
+            timestamp: chrono::Utc::now(),
+            operation: operation.to_string(),
+            files: files.to_vec(),
+            success: error.is_none(),
+            error: error.map(|e| e.to_string()),
+        };
+        if let Err(e) = self.storage.append_audit_entry(&entry).await {
+            warn!("写入审计日志失败 {}: {}", operation, e);
+        }
+    }
+
+    /// 读取审计日志，按时间范围过滤后分页，条目按记录先后排列
+    pub async fn get_audit_log(
+        &self,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> std::result::Result<AuditLogPage, CodeNexusError> {
+        let entries: Vec<AuditEntry> = self
+            .storage
+            .load_audit_log()
+            .await?
+            .into_iter()
+            .filter(|entry| since.is_none_or(|since| entry.timestamp >= since))
+            .filter(|entry| until.is_none_or(|until| entry.timestamp <= until))
+            .collect();
+        let total = entries.len();
+        let entries = entries
+            .into_iter()
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect();
+        Ok(AuditLogPage { total, entries })
+    }
+
+    /// 找出标注后内容已变化的文件，未启用 `track_content_hashes` 时报错
+    pub async fn find_stale_metadata(&self) -> std::result::Result<Vec<StaleMetadata>, CodeNexusError> {
+        let root = validate_project_path(&self.project_path)?;
+        let content_hash_manager = self.content_hash_manager.lock().await;
+        if !content_hash_manager.is_enabled() {
+            return Err(CodeNexusError::ConfigError(Message::ContentHashesDisabled));
+        }
+        Ok(content_hash_manager.find_stale(&root).await)
+    }
+
+    /// 是否启用了数据文件监听
+    pub fn is_watching(&self) -> bool {
+        self.data_watcher.is_some()
+    }
+
+    /// 从磁盘重新加载全部数据并重建索引，丢弃内存中的状态
+    ///
+    /// 与创建时一样逐个加载三个数据文件：某个文件失败时仍继续加载其余文件，失败的文件保留原有数据
+    /// 并禁止写入，最后返回第一个错误。任一文件重新加载成功都会清空撤销栈
+    pub async fn reload(&self) -> std::result::Result<ReloadSummary, CodeNexusError> {
+        let reloader = self.data_reloader();
+        let mut first_error = None;
+        for file_name in DATA_FILES {
+            if let Err(e) = reloader.reload_file(file_name).await {
+                first_error.get_or_insert(e);
+            }
+        }
+        if let Some(e) = first_error {
+            return Err(e);
+        }
+
+        let tag_manager = self.tag_manager.read().await;
+        let comment_manager = self.comment_manager.read().await;
+        let relation_manager = self.relation_manager.read().await;
+        let summary = reload_summary(&tag_manager, &comment_manager, &relation_manager);
+        info!("项目 {} 已重新加载: {:?}", self.project_path, summary);
+        Ok(summary)
+    }
+
+    /// 获取项目路径
+    pub fn project_path(&self) -> &str {
+        &self.project_path
+    }
+
+    /// 导出项目全部元数据
+    pub async fn export_all(&self) -> ProjectExport {
+        let tag_manager = self.tag_manager.read().await;
+        ProjectExport {
+            tags: tag_manager.export_tags(),
+            comments: self.comment_manager.read().await.export_comments(),
+            relations: self.relation_manager.read().await.get_all_relations().clone(),
+            archived_tags: tag_manager.get_archived_tags().into_iter().collect(),
+        }
+    }
+
+    /// 将全部元数据导出到数据目录下 `exports/` 中的文件，返回写入的路径
+    pub async fn export_to_file(&self, file_name: &str) -> std::result::Result<std::path::PathBuf, CodeNexusError> {
+        let output_path = resolve_output_path(&self.storage.data_dir().join(EXPORTS_DIR), file_name)?;
+        if let Some(parent) = output_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let export = self.export_all().await;
+        tokio::fs::write(&output_path, serde_json::to_vec(&export)?).await?;
+        info!("项目元数据已导出到: {}", output_path.display());
+        Ok(output_path)
+    }
+
+    /// 将标签以 CSV 格式导出到数据目录下 `exports/` 中的文件，返回写入的路径
+    pub async fn export_tags_csv_to_file(&self, file_name: &str) -> std::result::Result<std::path::PathBuf, CodeNexusError> {
+        let output_path = resolve_output_path(&self.storage.data_dir().join(EXPORTS_DIR), file_name)?;
+        let csv_content = self.tag_manager.read().await.export_csv()?;
+        if let Some(parent) = output_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&output_path, csv_content).await?;
+        info!("CSV 标签已导出到: {}", output_path.display());
+        Ok(output_path)
+    }
+
+    /// 在修改操作执行前记录 `scope` 覆盖的数据快照，未启用撤销（`undo_depth` 为 0）时返回 None
+    ///
+    /// 调用时不能持有对应管理器的锁；操作成功后通过 `push_undo` 入栈
+    pub async fn snapshot(&self, operation: &str, scope: UndoScope) -> Option<UndoEntry> {
+        if self.config.undo_depth == 0 {
+            return None;
+        }
+        Some(UndoEntry {
+            operation: operation.to_string(),
+            scope,
+            data: self.capture(scope).await,
         })
     }
+
+    /// 读取 `scope` 覆盖的当前数据
+    async fn capture(&self, scope: UndoScope) -> UndoData {
+        let mut data = UndoData::default();
+        if scope.includes_tags() {
+            data.tags = self.tag_manager.read().await.tags_data();
+        }
+        if scope.includes_comments() {
+            data.comments = self.comment_manager.read().await.export_comments();
+        }
+        if scope.includes_relations() {
+            data.relations = self.relation_manager.read().await.get_all_relations().clone();
+        }
+        data
+    }
+
+    /// 将快照压入撤销栈；操作没有改变数据时丢弃快照，不占用撤销槽位
+    pub async fn push_undo(&self, entry: Option<UndoEntry>) {
+        let Some(entry) = entry else {
+            return;
+        };
+        if self.capture(entry.scope).await == entry.data {
+            return;
+        }
+        self.undo_stack.lock().await.push(entry);
+    }
+
+    /// 撤销最近一次修改操作，恢复快照并持久化，返回被撤销的操作名
+    ///
+    /// 撤销栈为空时返回 None；恢复失败时快照重新入栈
+    pub async fn undo_last(&self) -> std::result::Result<Option<String>, CodeNexusError> {
+        let mut undo_stack = self.undo_stack.lock().await;
+        let Some(entry) = undo_stack.pop() else {
+            return Ok(None);
+        };
+
+        if let Err(e) = self.restore_snapshot(&entry).await {
+            undo_stack.push(entry);
+            return Err(e);
+        }
+        info!("撤销了操作 {}: {}", entry.operation, self.project_path);
+        Ok(Some(entry.operation))
+    }
+
+    /// 用快照替换对应管理器的数据
+    async fn restore_snapshot(&self, entry: &UndoEntry) -> std::result::Result<(), CodeNexusError> {
+        let data = entry.data.clone();
+        if entry.scope.includes_tags() {
+            self.tag_manager.write().await.restore_tags_data(data.tags).await?;
+        }
+        if entry.scope.includes_comments() {
+            self.comment_manager.write().await.import_comments(data.comments, ImportMode::Replace).await?;
+        }
+        if entry.scope.includes_relations() {
+            self.relation_manager.write().await.import_relations(data.relations, ImportMode::Replace).await?;
+        }
+        Ok(())
+    }
+
+    /// 导入项目元数据包
+    ///
+    /// 路径键（包括关联目标）先按项目根目录规范化，项目外的路径使整个导入包被拒绝，
+    /// 规范化后重合的条目按 `rekey_paths` 的规则合并；
+    /// `verify_files` 为 true 时跳过项目中不存在的文件（包括关联目标）
+    pub async fn import_all(
+        &self,
+        bundle: ProjectExport,
+        mode: ImportMode,
+        verify_files: bool,
+    ) -> std::result::Result<ImportSummary, CodeNexusError> {
+        let root = validate_project_path(&self.project_path)?;
+        let mut rekeyed = HashMap::new();
+        for key in bundle_keys(&bundle) {
+            let normalized = self.normalize_path_lenient(key)?;
+            if &normalized != key {
+                rekeyed.insert(key.clone(), normalized);
+            }
+        }
+        let bundle = if rekeyed.is_empty() { bundle } else { merge_rekeyed(bundle, &rekeyed) };
+
+        self.import_bundle(bundle, mode, verify_files.then_some(root.as_path())).await
+    }
+
+    /// 导入路径键已规范化的元数据包
+    ///
+    /// 先整体验证导入包，验证通过后再依次写入三个管理器，任一写入失败时回滚到导入前的数据；
+    /// 给出 `verify_root` 时跳过其中不存在的文件
+    async fn import_bundle(
+        &self,
+        mut bundle: ProjectExport,
+        mode: ImportMode,
+        verify_root: Option<&std::path::Path>,
+    ) -> std::result::Result<ImportSummary, CodeNexusError> {
+        let mut tag_manager = self.tag_manager.write().await;
+        let mut comment_manager = self.comment_manager.write().await;
+        let mut relation_manager = self.relation_manager.write().await;
+
+        // 整体验证，避免部分导入
+        for tag in bundle.tags.values().flatten() {
+            tag_manager.validate_tag(tag)?;
+        }
+        comment_manager.validate_comments(&bundle.comments)?;
+        relation_manager.validate_relations(&bundle.relations)?;
+
+        let mut skipped_files = std::collections::BTreeSet::new();
+        if let Some(root) = verify_root {
+            let mut exists = |file_path: &str| {
+                let found = root.join(file_path).is_file();
+                if !found {
+                    skipped_files.insert(file_path.to_string());
+                }
+                found
+            };
+            bundle.tags.retain(|file_path, _| exists(file_path));
+            bundle.archived_tags.retain(|file_path, _| exists(file_path));
+            bundle.comments.retain(|file_path, _| exists(file_path));
+            bundle.relations.retain(|file_path, _| exists(file_path));
+            for relations in bundle.relations.values_mut() {
+                relations.retain(|relation| exists(&relation.target));
+            }
+        }
+
+        let previous = (
+            tag_manager.tags_data(),
+            comment_manager.export_comments(),
+            relation_manager.get_all_relations().clone(),
+        );
+        let result = async {
+            Ok(ImportSummary {
+                tags: tag_manager.import_tags(bundle.tags, bundle.archived_tags, mode).await?,
+                comments: comment_manager.import_comments(bundle.comments, mode).await?,
+                relations: relation_manager.import_relations(bundle.relations, mode).await?,
+                skipped_files: skipped_files.into_iter().collect(),
+            })
+        }
+        .await;
+
+        match result {
+            Ok(summary) => {
+                info!("项目 {} 导入完成: {:?}", self.project_path, summary);
+                Ok(summary)
+            }
+            Err(e) => {
+                warn!("项目 {} 导入失败，回滚到导入前的数据: {}", self.project_path, e);
+                let (tags, comments, relations) = previous;
+                let rollback = async {
+                    tag_manager.restore_tags_data(tags).await?;
+                    comment_manager.import_comments(comments, ImportMode::Replace).await?;
+                    relation_manager.import_relations(relations, ImportMode::Replace).await
+                }
+                .await;
+                if let Err(rollback_error) = rollback {
+                    error!("项目 {} 回滚导入失败: {}", self.project_path, rollback_error);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// 获取没有任何元数据（标签、注释、入向或出向关联）的项目文件
+    pub async fn get_orphan_files(&self) -> std::result::Result<Vec<String>, CodeNexusError> {
+        let root = validate_project_path(&self.project_path)?;
+        let mut files = self.tag_manager.read().await.get_untagged_files(&root, None, None)?.files;
+
+        let comment_manager = self.comment_manager.read().await;
+        let relation_manager = self.relation_manager.read().await;
+        files.retain(|file_path| {
+            !comment_manager.has_comment(file_path)
+                && relation_manager.get_file_relations(file_path).is_empty()
+                && relation_manager.get_incoming_relations(file_path).is_empty()
+        });
+        Ok(files)
+    }
+
+    /// 检查标签和关联关系索引的一致性，`repair` 为 true 且发现问题时重建索引
+    pub async fn check_integrity(&self, repair: bool) -> std::result::Result<IntegrityReport, CodeNexusError> {
+        let mut tag_manager = self.tag_manager.write().await;
+        let mut relation_manager = self.relation_manager.write().await;
+
+        let mut report = IntegrityReport {
+            issues: tag_manager.check_integrity(),
+            repaired: false,
+        };
+        report.issues.extend(relation_manager.check_integrity());
+        report.issues.extend(self.data_file_errors().await.into_iter().map(|error| IntegrityIssue {
+            kind: IntegrityIssueKind::UnreadableDataFile,
+            file: error.file,
+            item: error.error,
+        }));
+
+        if repair && !report.issues.is_empty() {
+            tag_manager.rebuild_indices().await?;
+            relation_manager.rebuild_incoming_index();
+            report.repaired = true;
+        }
+        Ok(report)
+    }
+
+    /// 加载失败的数据文件，按文件名排序
+    pub async fn data_file_errors(&self) -> Vec<DataFileError> {
+        self.load_errors
+            .lock()
+            .await
+            .iter()
+            .map(|(file, error)| DataFileError { file: file.clone(), error: error.clone() })
+            .collect()
+    }
+
+    /// 从 `.json.bak` 备份恢复数据文件并重新加载对应管理器，返回恢复的文件
+    ///
+    /// 未指定文件时恢复所有加载失败的文件；恢复后仍无法加载的文件继续禁止写入
+    pub async fn recover_from_backup(&self, file_name: Option<&str>) -> std::result::Result<Vec<String>, CodeNexusError> {
+        let files: Vec<String> = match file_name {
+            Some(file_name) if DATA_FILES.contains(&file_name) => vec![file_name.to_string()],
+            Some(file_name) => {
+                return Err(CodeNexusError::ConfigError(Message::UnknownDataFile {
+                    file: file_name.to_string(),
+                    expected: DATA_FILES.iter().map(|file| file.to_string()).collect(),
+                }))
+            }
+            None => self.load_errors.lock().await.keys().cloned().collect(),
+        };
+
+        let mut recovered = Vec::new();
+        for file_name in files {
+            self.restore_data_file(&file_name, 0).await?;
+            recovered.push(file_name);
+        }
+        Ok(recovered)
+    }
+
+    /// 用指定版本的备份覆盖数据文件并重新加载对应管理器，返回恢复后的数据量
+    pub async fn restore_backup(&self, target: BackupTarget, version: usize) -> std::result::Result<ReloadSummary, CodeNexusError> {
+        self.restore_data_file(target.file_name(), version).await?;
+        let tag_manager = self.tag_manager.read().await;
+        let comment_manager = self.comment_manager.read().await;
+        let relation_manager = self.relation_manager.read().await;
+        Ok(reload_summary(&tag_manager, &comment_manager, &relation_manager))
+    }
+
+    /// 恢复单个数据文件，加载成功后解除写入限制，失败时禁止写入
+    async fn restore_data_file(&self, file_name: &str, version: usize) -> std::result::Result<(), CodeNexusError> {
+        self.storage.restore_backup(file_name, version).await?;
+        self.data_reloader().reload_file(file_name).await?;
+        info!("项目 {} 的 {} 已从备份 {} 恢复", self.project_path, file_name, version);
+        Ok(())
+    }
+
+    /// 获取有标签或关联关系但没有注释的文件，按路径排序
+    pub async fn get_undocumented_files(&self) -> Vec<String> {
+        let mut candidates = self.tag_manager.read().await.get_tagged_files();
+        candidates.extend(self.relation_manager.read().await.get_files_in_relations());
+        self.comment_manager.read().await.get_undocumented_files(candidates)
+    }
+
+    /// 按当前项目根目录重新规范化所有已存储的路径键，返回发生变化的键数量
+    ///
+    /// 用于仓库移动或符号链接变化后的修复：规范化后重合的条目会被合并
+    /// （标签和关联取并集，注释优先保留原本就是规范形式的键）。无法规范化的键保持不变。
+    pub async fn rekey_paths(&self) -> std::result::Result<usize, CodeNexusError> {
+        let rekeyed = self
+            .rekey_with(|key| self.normalize_path_lenient(key).ok())
+            .await?;
+        info!("项目 {} 重新规范化了 {} 个路径键", self.project_path, rekeyed);
+        Ok(rekeyed)
+    }
+
+    /// 将已存储的路径键转为小写并合并冲突的条目（合并规则同 `rekey_paths`），返回变化的键数量
+    ///
+    /// 需要先启用 `path_case_fold`，否则之后按原大小写规范化的路径将找不到折叠后的键
+    pub async fn fold_path_case(&self) -> std::result::Result<usize, CodeNexusError> {
+        if !self.config.path_case_fold {
+            return Err(CodeNexusError::ConfigError(Message::PathCaseFoldDisabled));
+        }
+        let folded = self.rekey_with(|key| Some(key.to_lowercase())).await?;
+        info!("项目 {} 折叠了 {} 个路径键的大小写", self.project_path, folded);
+        Ok(folded)
+    }
+
+    /// 用 `map_key` 映射所有已存储的路径键（返回 None 表示保持不变），合并映射后重合的条目
+    async fn rekey_with<F: Fn(&str) -> Option<String>>(&self, map_key: F) -> std::result::Result<usize, CodeNexusError> {
+        let bundle = self.export_all().await;
+
+        let rekeyed: HashMap<String, String> = bundle_keys(&bundle)
+            .into_iter()
+            .filter_map(|key| match map_key(key) {
+                Some(new_key) if &new_key != key => Some((key.clone(), new_key)),
+                _ => None,
+            })
+            .collect();
+        if rekeyed.is_empty() {
+            return Ok(0);
+        }
+
+        self.import_bundle(merge_rekeyed(bundle, &rekeyed), ImportMode::Replace, None).await?;
+        if let Err(e) = self.content_hash_manager.lock().await.rekey(&rekeyed).await {
+            warn!("重命名内容哈希失败: {}", e);
+        }
+        Ok(rekeyed.len())
+    }
+
+    /// 将源文件的元数据复制到目标文件
+    ///
+    /// 标签取并集；注释仅在目标没有注释或 `overwrite` 为 true 时复制；
+    /// 只复制出向关联（跳过指向目标文件自身和目标已有的关联），
+    /// 入向关联属于其他文件，不做复制
+    pub async fn copy_metadata(
+        &self,
+        src: &str,
+        dest: &str,
+        overwrite: bool,
+    ) -> std::result::Result<CopyMetadataSummary, CodeNexusError> {
+        let root = validate_project_path(&self.project_path)?;
+        let dest_path = root.join(dest);
+        if !dest_path.is_file() {
+            return Err(CodeNexusError::FileNotFound(dest.to_string()));
+        }
+
+        let mut tag_manager = self.tag_manager.write().await;
+        let mut comment_manager = self.comment_manager.write().await;
+        let mut relation_manager = self.relation_manager.write().await;
+
+        let src_tags = tag_manager.get_file_tags(src);
+        let src_comment = comment_manager.get_comment(src);
+        let src_relations = relation_manager.get_file_relations(src);
+        if src_tags.is_empty() && src_comment.is_none() && src_relations.is_empty() {
+            return Err(CodeNexusError::NoMetadata(src.to_string()));
+        }
+
+        let mut summary = CopyMetadataSummary::default();
+
+        if !src_tags.is_empty() {
+            summary.tags = tag_manager.add_tags(&dest_path, dest, src_tags).await?.len();
+        }
+
+        if let Some(comment) = src_comment {
+            if overwrite || !comment_manager.has_comment(dest) {
+                comment_manager.update_comment(&dest_path, dest, &comment).await?;
+                summary.comment = true;
+            }
+        }
+
+        let relations: Vec<Relation> = src_relations
+            .into_iter()
+            .filter(|relation| relation.target != dest && !relation_manager.has_relation(dest, &relation.target))
+            .collect();
+        if !relations.is_empty() {
+            let relations = HashMap::from([(dest.to_string(), relations)]);
+            summary.relations = relation_manager.import_relations(relations, ImportMode::Merge).await?;
+        }
+
+        info!("已将文件 {} 的元数据复制到 {}: {:?}", src, dest, summary);
+        Ok(summary)
+    }
 }
 
 impl CodeNexusServer {
@@ -151,12 +981,16 @@ impl CodeNexusServer {
 
         Ok(Self {
             projects: Arc::new(Mutex::new(HashMap::new())),
+            metrics: Arc::new(MetricsCounters::default()),
         })
     }
 
     /// 获取或创建项目管理器
     pub async fn get_or_create_project(&self, project_path: &str) -> std::result::Result<Arc<Mutex<ProjectManager>>, ErrorData> {
         debug_log_with_project!(project_path, "获取或创建项目管理器: {}", project_path);
+        // 以展开并规范化后的路径为键，同一项目的不同写法（`~/proj`、`$HOME/proj`）共用一个管理器
+        let key = project_key(project_path).map_err(ErrorData::from)?;
+        let project_path = key.as_str();
         let mut projects = self.projects.lock().await;
 
         if let Some(project) = projects.get(project_path) {
@@ -167,7 +1001,11 @@ impl CodeNexusServer {
         // 创建新的项目管理器
         debug_log_with_project!(project_path, "项目管理器不存在，开始创建新的: {}", project_path);
         let project_manager = ProjectManager::new(project_path).await
-            .map_err(|e| ErrorData::internal_error(format!("创建项目管理器失败: {}", e), None))?;
+            .map_err(|e| {
+                error!("创建项目管理器失败: {}", e);
+                // 保留错误码和恢复建议，工具层通过 format_error_data_response 原样输出
+                ErrorData::from(e)
+            })?;
 
         let project_arc = Arc::new(Mutex::new(project_manager));
         projects.insert(project_path.to_string(), project_arc.clone());
@@ -177,31 +1015,110 @@ impl CodeNexusServer {
         Ok(project_arc)
     }
 
-    /// 执行项目操作的辅助方法
-    async fn execute_project_operation<F, R>(&self, project_path: &str, operation: F) -> String
-    where
-        F: FnOnce(Arc<Mutex<ProjectManager>>) -> R + Send,
-        R: std::future::Future<Output = String> + Send,
-    {
-        let project_manager = match self.get_or_create_project(project_path).await {
-            Ok(pm) => pm,
-            Err(e) => return format!("错误: {:?}", e),
-        };
+    /// 获取全部工具名称（按字母排序）
+    pub fn tool_names() -> Vec<String> {
+        let mut names: Vec<String> = Self::tool_box().list().into_iter().map(|tool| tool.name.to_string()).collect();
+        names.sort();
+        names
+    }
+
+    /// 获取已加载的项目路径列表
+    pub async fn loaded_projects(&self) -> Vec<String> {
+        let projects = self.projects.lock().await;
+        let mut paths: Vec<String> = projects.keys().cloned().collect();
+        paths.sort();
+        paths
+    }
+
+    /// 卸载项目管理器，返回项目此前是否已加载
+    ///
+    /// 卸载前会获取项目锁，等待正在进行的操作完成写入
+    pub async fn remove_project(&self, project_path: &str) -> bool {
+        // 项目目录已被删除时无法规范化，按原样查找
+        let key = project_key(project_path).unwrap_or_else(|_| project_path.to_string());
+        let mut projects = self.projects.lock().await;
+        match projects.remove(&key) {
+            Some(project) => {
+                Self::close_project(&project, project_path).await;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 卸载所有项目，服务退出前调用
+    pub async fn remove_all_projects(&self) {
+        let projects: Vec<_> = self.projects.lock().await.drain().collect();
+        for (key, project) in projects {
+            Self::close_project(&project, &key).await;
+        }
+    }
 
-        operation(project_manager).await
+    async fn close_project(project: &Mutex<ProjectManager>, project_path: &str) {
+        // 所有写操作在返回前均已持久化，持有项目锁即可保证没有进行中的写入
+        let pm = project.lock().await;
+        // 修改时不写索引快照，卸载时补写，下次加载即可直接恢复
+        if let Err(e) = pm.tag_manager.write().await.save_index_snapshot().await {
+            warn!("保存标签索引快照失败: {}", e);
+        }
+        info!("卸载了项目管理器: {}", project_path);
     }
 
     /// 格式化成功响应
     fn format_success_response(&self, message: &str) -> String {
-        serde_json::json!({
-            "success": true,
-            "message": message
-        }).to_string()
+        self.format_data_response(&SuccessResponse::new(message))
+    }
+
+    /// 格式化带警告的成功响应
+    fn format_success_with_warnings(&self, message: &str, warnings: &[String]) -> String {
+        self.format_data_response(&SuccessResponse::with_warnings(message, warnings.to_vec()))
+    }
+
+    /// 格式化批量修改的计划响应，`changes` 为（将）受影响的数量
+    fn format_plan_response<T: serde::Serialize>(&self, dry_run: bool, changes: usize, plan: &T) -> String {
+        self.format_data_response(&PlanResponse { dry_run, changes, plan })
+    }
+
+    /// 计算并（非 dry_run 时）执行标签合并计划
+    async fn merge_tags_with_plan(&self, operation: &str, project_path: &str, sources: &[String], target: &str, dry_run: bool) -> String {
+        let project_manager = match self.get_or_create_project(project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let snapshot = if dry_run { None } else { pm.snapshot(operation, UndoScope::Tags).await };
+        let mut tag_manager = pm.tag_manager.write().await;
+        let result = match tag_manager.plan_merge_tags(sources, target) {
+            Ok(plan) if dry_run => Ok((plan.affected_files.len(), plan)),
+            Ok(plan) => tag_manager.apply_tag_merge(&plan).await.map(|changed| (changed, plan)),
+            Err(e) => Err(e),
+        };
+        drop(tag_manager);
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+        }
+        if !dry_run {
+            let files = result.as_ref().map(|(_, plan)| plan.affected_files.as_slice()).unwrap_or(&[]);
+            pm.record_audit(operation, files, result.as_ref().err()).await;
+        }
+
+        match result {
+            Ok((changes, plan)) => {
+                debug_log_with_project!(project_path, "标签合并 {:?} -> {}: {} 个文件, dry_run: {}", plan.sources, plan.target, changes, dry_run);
+                self.format_plan_response(dry_run, changes, &plan)
+            },
+            Err(e) => {
+                debug_log_with_project!(project_path, "标签合并失败: {}", e);
+                error!("标签合并失败: {}", e);
+                format_error_response(&e)
+            }
+        }
     }
 
-    /// 格式化数据响应
+    /// 格式化数据响应，在数据的字段之外附加 `success: true`
     fn format_data_response<T: serde::Serialize>(&self, data: &T) -> String {
-        match serde_json::to_string(data) {
+        match serde_json::to_string(&DataResponse { success: true, data }) {
             Ok(json) => json,
             Err(e) => {
                 error!("序列化响应数据失败: {}", e);
@@ -219,6 +1136,7 @@ impl CodeNexusServer {
         &self,
         #[tool(aggr)] params: AddTagsParams,
     ) -> String {
+        self.metrics.record(Operation::TagAdd);
         debug_log_with_project!(&params.project_path, "添加文件标签 - 项目路径: {}, 文件路径: {}, 标签: {:?}",
                    params.project_path, params.file_path, params.tags);
 
@@ -230,7 +1148,7 @@ impl CodeNexusServer {
             },
             Err(e) => {
                 debug_log_with_project!(&params.project_path, "项目路径验证失败: {}", e);
-                return format!("项目路径验证失败: {}", e);
+                return format_error_response(&e);
             },
         };
 
@@ -241,19 +1159,7 @@ impl CodeNexusServer {
             },
             Err(e) => {
                 debug_log_with_project!(&params.project_path, "文件路径验证失败: {}", e);
-                return format!("文件路径验证失败: {}", e);
-            },
-        };
-
-        // 规范化文件路径
-        let normalized_path = match normalize_file_path(&validated_path, &full_file_path) {
-            Ok(path) => {
-                debug_log_with_project!(&params.project_path, "路径规范化成功: {}", path);
-                path
-            },
-            Err(e) => {
-                debug_log_with_project!(&params.project_path, "路径规范化失败: {}", e);
-                return format!("路径规范化失败: {}", e);
+                return format_error_response(&e);
             },
         };
 
@@ -265,17 +1171,44 @@ impl CodeNexusServer {
             },
             Err(e) => {
                 debug_log_with_project!(&params.project_path, "获取项目管理器失败: {:?}", e);
-                return format!("错误: {:?}", e);
+                return format_error_data_response(&e);
             },
         };
 
         let pm = project_manager.lock().await;
-        let result = pm.tag_manager.lock().await.add_tags(&full_file_path, &normalized_path, params.tags).await;
+        // 规范化文件路径
+        let normalized_path = match pm.normalize_path(&full_file_path) {
+            Ok(path) => {
+                debug_log_with_project!(&params.project_path, "路径规范化成功: {}", path);
+                path
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "路径规范化失败: {}", e);
+                return format_error_response(&e);
+            },
+        };
+        let snapshot = pm.snapshot("add_file_tags", UndoScope::Tags).await;
+        let result = pm.tag_manager.write().await.add_tags(&full_file_path, &normalized_path, params.tags.clone()).await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+            pm.record_content_hash(&full_file_path, &normalized_path).await;
+        }
+        pm.record_audit("add_file_tags", std::slice::from_ref(&normalized_path), result.as_ref().err()).await;
 
         match result {
-            Ok(_) => {
-                debug_log_with_project!(&params.project_path, "标签添加成功");
-                self.format_success_response("标签添加成功")
+            Ok(added) => {
+                let mut already_present: Vec<String> = Vec::new();
+                for tag in params.tags {
+                    if !added.contains(&tag) && !already_present.contains(&tag) {
+                        already_present.push(tag);
+                    }
+                }
+                debug_log_with_project!(&params.project_path, "标签添加成功，新增: {:?}，已存在: {:?}", added, already_present);
+                self.format_data_response(&AddTagsResponse {
+                    message: Message::TagsAdded.localized(),
+                    added,
+                    already_present,
+                })
             },
             Err(e) => {
                 debug_log_with_project!(&params.project_path, "添加标签失败: {}", e);
@@ -300,33 +1233,39 @@ impl CodeNexusServer {
                 debug_log_with_project!(&params.project_path, "项目路径验证成功: {}", path.display());
                 path
             },
-            Err(e) => return format!("项目路径验证失败: {}", e),
+            Err(e) => return format_error_response(&e),
         };
 
         // 对于删除操作，不验证文件是否存在，因为文件可能已被删除但数据库中还有记录
         let full_file_path = validated_path.join(&params.file_path);
         debug_log_with_project!(&params.project_path, "构建文件路径: {}", full_file_path.display());
 
-        let normalized_path = match normalize_file_path(&validated_path, &full_file_path) {
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let normalized_path = match pm.normalize_path(&full_file_path) {
             Ok(path) => {
                 debug_log_with_project!(&params.project_path, "路径规范化成功: {}", path);
                 path
             },
-            Err(e) => return format!("路径规范化失败: {}", e),
-        };
-
-        let project_manager = match self.get_or_create_project(&params.project_path).await {
-            Ok(pm) => pm,
-            Err(e) => return format!("错误: {:?}", e),
+            Err(e) => return format_error_response(&e),
         };
 
-        let pm = project_manager.lock().await;
-        let result = pm.tag_manager.lock().await.remove_tags(&full_file_path, &normalized_path, params.tags).await;
+        let snapshot = pm.snapshot("remove_file_tags", UndoScope::Tags).await;
+        let result = pm.tag_manager.write().await.remove_tags(&full_file_path, &normalized_path, params.tags).await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+            pm.prune_content_hashes().await;
+        }
+        pm.record_audit("remove_file_tags", std::slice::from_ref(&normalized_path), result.as_ref().err()).await;
 
         match result {
             Ok(_) => {
                 debug_log_with_project!(&params.project_path, "标签移除成功");
-                self.format_success_response("标签移除成功")
+                self.format_success_response(&Message::TagsRemoved.localized())
             },
             Err(e) => {
                 debug_log_with_project!(&params.project_path, "移除标签失败: {}", e);
@@ -336,407 +1275,611 @@ impl CodeNexusServer {
         }
     }
 
-    /// 根据标签查询文件
-    #[tool(description = "根据标签查询文件，支持 AND、NOT、通配符")]
-    async fn query_files_by_tags(
+    /// 替换文件标签
+    #[tool(description = "将文件的一个标签原子地替换为另一个标签，在 tags 中返回替换后的标签列表；旧标签不在该文件上时返回 TAG_NOT_FOUND")]
+    async fn replace_file_tag(
         &self,
-        #[tool(aggr)] params: TagQueryParams,
+        #[tool(aggr)] params: ReplaceFileTagParams,
     ) -> String {
-        debug_log_with_project!(&params.project_path, "标签查询 - 项目路径: {}, 查询表达式: {}", params.project_path, params.query);
+        debug_log_with_project!(&params.project_path, "替换文件标签 - 项目路径: {}, 文件路径: {}, {} -> {}",
+                   params.project_path, params.file_path, params.old_tag, params.new_tag);
 
         let project_manager = match self.get_or_create_project(&params.project_path).await {
-            Ok(pm) => {
-                debug_log_with_project!(&params.project_path, "获取项目管理器成功");
-                pm
-            },
-            Err(e) => return format!("错误: {:?}", e),
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
         };
 
         let pm = project_manager.lock().await;
-        debug_log_with_project!(&params.project_path, "开始执行标签查询");
-        let result = pm.query_engine.execute_tag_query(&params.query).await;
+        let normalized_path = match pm.normalize_path_lenient(&params.file_path) {
+            Ok(path) => path,
+            Err(e) => return format_error_response(&e),
+        };
+
+        let snapshot = pm.snapshot("replace_file_tag", UndoScope::Tags).await;
+        let result = pm
+            .tag_manager
+            .write()
+            .await
+            .replace_file_tag(&normalized_path, &params.old_tag, &params.new_tag)
+            .await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+        }
+        pm.record_audit("replace_file_tag", std::slice::from_ref(&normalized_path), result.as_ref().err()).await;
 
         match result {
-            Ok(result) => {
-                debug_log_with_project!(&params.project_path, "标签查询成功，返回{}个结果", result.files.len());
-                self.format_data_response(&result)
+            Ok(()) => {
+                debug_log_with_project!(&params.project_path, "标签替换成功");
+                self.format_data_response(&FileTagsResult { tags: pm.tag_manager.read().await.get_file_tags(&normalized_path) })
             },
             Err(e) => {
-                debug_log_with_project!(&params.project_path, "标签查询失败: {}", e);
-                error!("标签查询失败: {}", e);
+                debug_log_with_project!(&params.project_path, "替换标签失败: {}", e);
+                error!("替换标签失败: {}", e);
                 format_error_response(&e)
             }
         }
     }
 
-    /// 获取所有标签
-    #[tool(description = "获取所有标签，按类型分组")]
-    async fn get_all_tags(
+    /// 清除文件全部标签
+    #[tool(description = "清除文件的全部标签，返回移除的标签数量")]
+    async fn clear_file_tags(
         &self,
-        #[tool(aggr)] params: ProjectPathParams,
+        #[tool(aggr)] params: FilePathParams,
     ) -> String {
-        debug_log_with_project!(&params.project_path, "获取所有标签 - 项目路径: {}", params.project_path);
+        debug_log_with_project!(&params.project_path, "清除文件标签 - 项目路径: {}, 文件路径: {}",
+                   params.project_path, params.file_path);
+
+        // 验证项目路径
+        let validated_path = match validate_project_path(&params.project_path) {
+            Ok(path) => path,
+            Err(e) => return format_error_response(&e),
+        };
+
+        // 与移除标签一致，不要求文件仍然存在
+        let full_file_path = validated_path.join(&params.file_path);
 
         let project_manager = match self.get_or_create_project(&params.project_path).await {
-            Ok(pm) => {
-                debug_log_with_project!(&params.project_path, "获取项目管理器成功");
-                pm
-            },
-            Err(e) => return format!("错误: {:?}", e),
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
         };
 
         let pm = project_manager.lock().await;
-        debug_log_with_project!(&params.project_path, "开始获取所有标签");
-        let all_tags = pm.tag_manager.lock().await.get_all_tags();
-        debug_log_with_project!(&params.project_path, "获取到标签数量: {}", all_tags.len());
-        self.format_data_response(&all_tags)
+        let normalized_path = match pm.normalize_path(&full_file_path) {
+            Ok(path) => path,
+            Err(e) => return format_error_response(&e),
+        };
+
+        let snapshot = pm.snapshot("clear_file_tags", UndoScope::Tags).await;
+        let result = pm.tag_manager.write().await.clear_file_tags(&normalized_path).await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+            pm.prune_content_hashes().await;
+        }
+        pm.record_audit("clear_file_tags", std::slice::from_ref(&normalized_path), result.as_ref().err()).await;
+
+        match result {
+            Ok(removed) => {
+                debug_log_with_project!(&params.project_path, "清除了 {} 个标签", removed);
+                self.format_success_response(&Message::TagsCleared(removed).localized())
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "清除标签失败: {}", e);
+                error!("清除标签失败: {}", e);
+                format_error_response(&e)
+            }
+        }
     }
 
-    /// 为文件添加注释
-    #[tool(description = "为文件添加注释")]
-    async fn add_file_comment(
+    /// 归档文件标签
+    #[tool(description = "归档文件的指定标签：归档的标签不参与查询和统计，但保留在 tags.json 中，可用 unarchive_file_tags 恢复")]
+    async fn archive_file_tags(
         &self,
-        #[tool(aggr)] params: AddCommentParams,
+        #[tool(aggr)] params: ArchiveTagsParams,
     ) -> String {
-        debug_log_with_project!(&params.project_path, "添加文件注释 - 项目路径: {}, 文件路径: {}, 注释长度: {}",
-                   params.project_path, params.file_path, params.comment.len());
+        debug_log_with_project!(&params.project_path, "归档文件标签 - 项目路径: {}, 文件路径: {}, 标签: {:?}",
+                   params.project_path, params.file_path, params.tags);
 
-        // 验证路径
-        let validated_path = match validate_project_path(&params.project_path) {
-            Ok(path) => {
-                debug_log_with_project!(&params.project_path, "项目路径验证成功: {}", path.display());
-                path
-            },
-            Err(e) => return format!("项目路径验证失败: {}", e),
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
         };
 
-        let full_file_path = match validate_file_path(&validated_path, &params.file_path) {
-            Ok(path) => {
-                debug_log_with_project!(&params.project_path, "文件路径验证成功: {}", path.display());
-                path
-            },
-            Err(e) => return format!("文件路径验证失败: {}", e),
+        let pm = project_manager.lock().await;
+        // 已归档的文件可能已被删除，不要求文件存在
+        let normalized_path = match pm.normalize_path_lenient(&params.file_path) {
+            Ok(path) => path,
+            Err(e) => return format_error_response(&e),
         };
 
-        let normalized_path = match normalize_file_path(&validated_path, &full_file_path) {
-            Ok(path) => {
-                debug_log_with_project!(&params.project_path, "路径规范化成功: {}", path);
-                path
+        let snapshot = pm.snapshot("archive_file_tags", UndoScope::Tags).await;
+        let result = pm.tag_manager.write().await.archive_tags(&normalized_path, params.tags).await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+        }
+        pm.record_audit("archive_file_tags", std::slice::from_ref(&normalized_path), result.as_ref().err()).await;
+
+        match result {
+            Ok(tags) => {
+                debug_log_with_project!(&params.project_path, "标签归档成功: {:?}", tags);
+                self.format_data_response(&ArchivedTagsResult { archived: tags })
             },
-            Err(e) => return format!("路径规范化失败: {}", e),
-        };
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "归档标签失败: {}", e);
+                error!("归档标签失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 恢复归档标签
+    #[tool(description = "恢复文件已归档的标签，使其重新参与查询；恢复时按当前标签规则校验")]
+    async fn unarchive_file_tags(
+        &self,
+        #[tool(aggr)] params: ArchiveTagsParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "恢复归档标签 - 项目路径: {}, 文件路径: {}, 标签: {:?}",
+                   params.project_path, params.file_path, params.tags);
 
         let project_manager = match self.get_or_create_project(&params.project_path).await {
             Ok(pm) => pm,
-            Err(e) => return format!("错误: {:?}", e),
+            Err(e) => return format_error_data_response(&e),
         };
 
         let pm = project_manager.lock().await;
-        let result = pm.comment_manager.lock().await.add_comment(&full_file_path, &normalized_path, &params.comment).await;
+        // 已归档的文件可能已被删除，不要求文件存在
+        let normalized_path = match pm.normalize_path_lenient(&params.file_path) {
+            Ok(path) => path,
+            Err(e) => return format_error_response(&e),
+        };
+
+        let snapshot = pm.snapshot("unarchive_file_tags", UndoScope::Tags).await;
+        let result = pm.tag_manager.write().await.unarchive_tags(&normalized_path, params.tags).await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+        }
+        pm.record_audit("unarchive_file_tags", std::slice::from_ref(&normalized_path), result.as_ref().err()).await;
 
         match result {
-            Ok(_) => {
-                debug_log_with_project!(&params.project_path, "注释添加成功");
-                self.format_success_response("注释添加成功")
+            Ok(tags) => {
+                debug_log_with_project!(&params.project_path, "归档标签恢复成功: {:?}", tags);
+                self.format_data_response(&RestoredTagsResult { restored: tags })
             },
             Err(e) => {
-                debug_log_with_project!(&params.project_path, "添加注释失败: {}", e);
-                error!("添加注释失败: {}", e);
+                debug_log_with_project!(&params.project_path, "恢复归档标签失败: {}", e);
+                error!("恢复归档标签失败: {}", e);
                 format_error_response(&e)
             }
         }
     }
 
-    /// 更新文件注释
-    #[tool(description = "更新文件注释")]
-    async fn update_file_comment(
+    /// 获取已归档的标签
+    #[tool(description = "获取项目中所有已归档的标签，在 files 中按文件分组")]
+    async fn get_archived_tags(
         &self,
-        #[tool(aggr)] params: AddCommentParams,
+        #[tool(aggr)] params: ProjectPathParams,
     ) -> String {
-        debug_log_with_project!(&params.project_path, "更新文件注释 - 项目路径: {}, 文件路径: {}, 注释长度: {}",
-                   params.project_path, params.file_path, params.comment.len());
+        debug_log_with_project!(&params.project_path, "获取归档标签 - 项目路径: {}", params.project_path);
 
-        // 验证路径
-        let validated_path = match validate_project_path(&params.project_path) {
-            Ok(path) => {
-                debug_log_with_project!(&params.project_path, "项目路径验证成功: {}", path.display());
-                path
-            },
-            Err(e) => return format!("项目路径验证失败: {}", e),
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
         };
 
-        let full_file_path = match validate_file_path(&validated_path, &params.file_path) {
-            Ok(path) => {
-                debug_log_with_project!(&params.project_path, "文件路径验证成功: {}", path.display());
-                path
-            },
-            Err(e) => return format!("文件路径验证失败: {}", e),
-        };
+        let pm = project_manager.lock().await;
+        let archived = pm.tag_manager.read().await.get_archived_tags();
+        self.format_data_response(&TagsByFile { files: archived })
+    }
 
-        let normalized_path = match normalize_file_path(&validated_path, &full_file_path) {
-            Ok(path) => {
-                debug_log_with_project!(&params.project_path, "路径规范化成功: {}", path);
-                path
-            },
-            Err(e) => return format!("路径规范化失败: {}", e),
-        };
+    /// 获取单个文件的标签
+    #[tool(description = "获取单个文件的标签列表（已排序，在 tags 中返回），比 get_file_info 更轻量")]
+    async fn get_file_tags(
+        &self,
+        #[tool(aggr)] params: FilePathParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "获取文件标签 - 项目路径: {}, 文件路径: {}",
+                   params.project_path, params.file_path);
 
         let project_manager = match self.get_or_create_project(&params.project_path).await {
             Ok(pm) => pm,
-            Err(e) => return format!("错误: {:?}", e),
+            Err(e) => return format_error_data_response(&e),
         };
 
         let pm = project_manager.lock().await;
-        let result = pm.comment_manager.lock().await.update_comment(&full_file_path, &normalized_path, &params.comment).await;
+        // 只读取元数据，不要求文件仍然存在于磁盘
+        let normalized_path = match pm.normalize_path_lenient(&params.file_path) {
+            Ok(path) => path,
+            Err(e) => return format_error_response(&e),
+        };
 
-        match result {
-            Ok(_) => {
-                debug_log_with_project!(&params.project_path, "注释更新成功");
-                self.format_success_response("注释更新成功")
-            },
-            Err(e) => {
-                debug_log_with_project!(&params.project_path, "更新注释失败: {}", e);
-                error!("更新注释失败: {}", e);
-                format_error_response(&e)
-            }
-        }
+        let tags = pm.tag_manager.read().await.get_file_tags(&normalized_path);
+        debug_log_with_project!(&params.project_path, "文件 {} 有 {} 个标签", normalized_path, tags.len());
+        self.format_data_response(&FileTagsResult { tags })
     }
 
-    /// 添加文件关联关系
-    #[tool(description = "添加文件间的关联关系")]
-    async fn add_file_relation(
+    /// 批量获取文件标签
+    #[tool(description = "批量获取多个文件的标签，在 files 中返回 文件路径 -> 标签列表（已排序）的映射；没有标签的文件不包含在结果中。文件不要求仍存在于磁盘，适合渲染文件树时一次性获取所有可见文件的标签")]
+    async fn get_tags_for_files(
         &self,
-        #[tool(aggr)] params: AddRelationParams,
+        #[tool(aggr)] params: FilePathsParams,
     ) -> String {
-        debug_log_with_project!(&params.project_path, "添加文件关联关系 - 项目路径: {}, 源文件: {}, 目标文件: {}, 描述: {}",
-                   params.project_path, params.from_file, params.to_file, params.description);
+        debug_log_with_project!(&params.project_path, "批量获取文件标签 - 项目路径: {}, 文件数: {}",
+                   params.project_path, params.file_paths.len());
 
-        // 验证路径
-        let validated_path = match validate_project_path(&params.project_path) {
-            Ok(path) => {
-                debug_log_with_project!(&params.project_path, "项目路径验证成功: {}", path.display());
-                path
-            },
-            Err(e) => return format!("项目路径验证失败: {}", e),
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
         };
 
-        let from_file_path = match validate_file_path(&validated_path, &params.from_file) {
-            Ok(path) => {
-                debug_log_with_project!(&params.project_path, "源文件路径验证成功: {}", path.display());
-                path
-            },
-            Err(e) => return format!("源文件路径验证失败: {}", e),
+        let pm = project_manager.lock().await;
+        let normalized_paths = match params
+            .file_paths
+            .iter()
+            .map(|file_path| pm.normalize_path_lenient(file_path))
+            .collect::<std::result::Result<Vec<_>, _>>()
+        {
+            Ok(paths) => paths,
+            Err(e) => return format_error_response(&e),
         };
 
-        let to_file_path = match validate_file_path(&validated_path, &params.to_file) {
-            Ok(path) => {
-                debug_log_with_project!(&params.project_path, "目标文件路径验证成功: {}", path.display());
-                path
-            },
-            Err(e) => return format!("目标文件路径验证失败: {}", e),
-        };
+        let tags = pm.tag_manager.read().await.get_tags_for_files(&normalized_paths);
+        debug_log_with_project!(&params.project_path, "{} 个文件有标签", tags.len());
+        self.format_data_response(&TagsByFile { files: tags })
+    }
 
-        let normalized_from = match normalize_file_path(&validated_path, &from_file_path) {
-            Ok(path) => {
-                debug_log_with_project!(&params.project_path, "源文件路径规范化成功: {}", path);
-                path
-            },
-            Err(e) => return format!("源文件路径规范化失败: {}", e),
+    /// 检查文件是否带有指定标签
+    #[tool(description = "检查文件是否带有指定标签，结果在 has_tag 中（true 或 false）")]
+    async fn file_has_tag(
+        &self,
+        #[tool(aggr)] params: FileTagParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "检查文件标签 - 项目路径: {}, 文件路径: {}, 标签: {}",
+                   params.project_path, params.file_path, params.tag);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
         };
 
-        let normalized_to = match normalize_file_path(&validated_path, &to_file_path) {
-            Ok(path) => {
-                debug_log_with_project!(&params.project_path, "目标文件路径规范化成功: {}", path);
-                path
-            },
-            Err(e) => return format!("目标文件路径规范化失败: {}", e),
+        let pm = project_manager.lock().await;
+        // 只读取元数据，不要求文件仍然存在于磁盘
+        let normalized_path = match pm.normalize_path_lenient(&params.file_path) {
+            Ok(path) => path,
+            Err(e) => return format_error_response(&e),
         };
 
+        let has_tag = pm.tag_manager.read().await.has_tag(&normalized_path, &params.tag);
+        debug_log_with_project!(&params.project_path, "文件 {} 是否带有标签 {}: {}", normalized_path, params.tag, has_tag);
+        self.format_data_response(&HasTagResult { has_tag })
+    }
+
+    /// 重命名标签
+    #[tool(description = "将所有文件上的标签重命名为新标签（新标签已存在时合并）；dry_run 为 true 时只预览将被修改的文件")]
+    async fn rename_tag(
+        &self,
+        #[tool(aggr)] params: RenameTagParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "重命名标签 - 项目路径: {}, {} -> {}, dry_run: {:?}",
+                   params.project_path, params.old_tag, params.new_tag, params.dry_run);
+        self.merge_tags_with_plan("rename_tag", &params.project_path, std::slice::from_ref(&params.old_tag), &params.new_tag, params.dry_run.unwrap_or(false)).await
+    }
+
+    /// 按通配符移除标签
+    #[tool(description = "从所有文件移除匹配通配符模式的标签（与 query_files_by_tags 的通配符语义相同），用于整体淘汰一类标签，如 status:wip*。返回移除的文件-标签数量 removed 和受影响的文件 affected_files")]
+    async fn remove_tags_matching(
+        &self,
+        #[tool(aggr)] params: RemoveTagsMatchingParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "按通配符移除标签 - 项目路径: {}, 模式: {}",
+                   params.project_path, params.pattern);
+
         let project_manager = match self.get_or_create_project(&params.project_path).await {
             Ok(pm) => pm,
-            Err(e) => return format!("错误: {:?}", e),
+            Err(e) => return format_error_data_response(&e),
         };
 
         let pm = project_manager.lock().await;
-        let result = pm.relation_manager.lock().await.add_relation(
-            &from_file_path, &normalized_from,
-            &to_file_path, &normalized_to,
-            &params.description
-        ).await;
+        let snapshot = pm.snapshot("remove_tags_matching", UndoScope::Tags).await;
+        let mut tag_manager = pm.tag_manager.write().await;
+        let result = match tag_manager.files_with_tags_matching(&params.pattern) {
+            Ok(affected_files) => tag_manager
+                .remove_tags_matching(&params.pattern)
+                .await
+                .map(|removed| (removed, affected_files)),
+            Err(e) => Err(e),
+        };
+        drop(tag_manager);
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+            pm.prune_content_hashes().await;
+        }
+        pm.record_audit("remove_tags_matching", result.as_ref().map(|(_, files)| files.as_slice()).unwrap_or(&[]), result.as_ref().err()).await;
 
         match result {
-            Ok(_) => {
-                debug_log_with_project!(&params.project_path, "关联关系添加成功");
-                self.format_success_response("关联关系添加成功")
+            Ok((removed, affected_files)) => {
+                debug_log_with_project!(&params.project_path, "移除了 {} 个标签，涉及 {} 个文件", removed, affected_files.len());
+                self.format_data_response(&RemovedTagsResult { removed, affected_files })
             },
             Err(e) => {
-                debug_log_with_project!(&params.project_path, "添加关联关系失败: {}", e);
-                error!("添加关联关系失败: {}", e);
+                debug_log_with_project!(&params.project_path, "按通配符移除标签失败: {}", e);
+                error!("按通配符移除标签失败: {}", e);
                 format_error_response(&e)
             }
         }
     }
 
-    /// 移除文件关联关系
-    #[tool(description = "移除文件间的关联关系")]
-    async fn remove_file_relation(
+    /// 重命名标签类型
+    #[tool(description = "将某个类型的所有标签改为新类型并保留取值，如把所有 cat:* 改为 category:*；文件已带有新类型下相同取值的标签时合并。返回被修改的文件数量")]
+    async fn rename_tag_type(
         &self,
-        #[tool(aggr)] params: RemoveRelationParams,
+        #[tool(aggr)] params: RenameTagTypeParams,
     ) -> String {
-        debug_log_with_project!(&params.project_path, "移除文件关联关系 - 项目路径: {}, 源文件: {}, 目标文件: {}",
-                   params.project_path, params.from_file, params.to_file);
-
-        // 验证项目路径
-        let validated_path = match validate_project_path(&params.project_path) {
-            Ok(path) => {
-                debug_log_with_project!(&params.project_path, "项目路径验证成功: {}", path.display());
-                path
-            },
-            Err(e) => return format!("项目路径验证失败: {}", e),
-        };
-
-        // 对于删除操作，不验证文件是否存在，因为文件可能已被删除但数据库中还有记录
-        let from_file_path = validated_path.join(&params.from_file);
-        let to_file_path = validated_path.join(&params.to_file);
-        debug_log_with_project!(&params.project_path, "构建源文件路径: {}", from_file_path.display());
-        debug_log_with_project!(&params.project_path, "构建目标文件路径: {}", to_file_path.display());
-
-        let normalized_from = match normalize_file_path(&validated_path, &from_file_path) {
-            Ok(path) => {
-                debug_log_with_project!(&params.project_path, "源文件路径规范化成功: {}", path);
-                path
-            },
-            Err(e) => return format!("源文件路径规范化失败: {}", e),
-        };
-
-        let normalized_to = match normalize_file_path(&validated_path, &to_file_path) {
-            Ok(path) => {
-                debug_log_with_project!(&params.project_path, "目标文件路径规范化成功: {}", path);
-                path
-            },
-            Err(e) => return format!("目标文件路径规范化失败: {}", e),
-        };
+        debug_log_with_project!(&params.project_path, "重命名标签类型 - 项目路径: {}, {} -> {}",
+                   params.project_path, params.old_type, params.new_type);
 
         let project_manager = match self.get_or_create_project(&params.project_path).await {
             Ok(pm) => pm,
-            Err(e) => return format!("错误: {:?}", e),
+            Err(e) => return format_error_data_response(&e),
         };
 
         let pm = project_manager.lock().await;
-        let result = pm.relation_manager.lock().await.remove_relation(
-            &from_file_path, &normalized_from,
-            &to_file_path, &normalized_to
-        ).await;
+        let snapshot = pm.snapshot("rename_tag_type", UndoScope::Tags).await;
+        let result = pm.tag_manager.write().await.rename_tag_type(&params.old_type, &params.new_type).await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+        }
+        pm.record_audit("rename_tag_type", &[], result.as_ref().err()).await;
 
         match result {
-            Ok(_) => {
-                debug_log_with_project!(&params.project_path, "关联关系移除成功");
-                self.format_success_response("关联关系移除成功")
+            Ok(changed) => {
+                debug_log_with_project!(&params.project_path, "修改了 {} 个文件", changed);
+                self.format_data_response(&ChangedCount { changed })
             },
             Err(e) => {
-                debug_log_with_project!(&params.project_path, "移除关联关系失败: {}", e);
-                error!("移除关联关系失败: {}", e);
+                debug_log_with_project!(&params.project_path, "重命名标签类型失败: {}", e);
+                error!("重命名标签类型失败: {}", e);
                 format_error_response(&e)
             }
         }
     }
 
-    /// 查询文件关联关系
-    #[tool(description = "查询文件的出向关联关系")]
-    async fn query_file_relations(
+    /// 合并标签
+    #[tool(description = "将多个标签合并为一个目标标签，带有任一源标签的文件改为带有目标标签；dry_run 为 true 时只预览将被修改的文件")]
+    async fn merge_tags(
         &self,
-        #[tool(aggr)] params: FilePathParams,
+        #[tool(aggr)] params: MergeTagsParams,
     ) -> String {
-        debug_log_with_project!(&params.project_path, "查询文件关联关系 - 项目路径: {}, 文件路径: {}",
-                   params.project_path, params.file_path);
+        debug_log_with_project!(&params.project_path, "合并标签 - 项目路径: {}, {:?} -> {}, dry_run: {:?}",
+                   params.project_path, params.source_tags, params.target_tag, params.dry_run);
+        self.merge_tags_with_plan("merge_tags", &params.project_path, &params.source_tags, &params.target_tag, params.dry_run.unwrap_or(false)).await
+    }
+
+    /// 复制文件元数据
+    #[tool(description = "将源文件的标签、注释和出向关联复制到目标文件（不复制入向关联）")]
+    async fn copy_file_metadata(
+        &self,
+        #[tool(aggr)] params: CopyMetadataParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "复制文件元数据 - 项目路径: {}, 源文件: {}, 目标文件: {}",
+                   params.project_path, params.source_file, params.dest_file);
 
-        // 验证路径
         let validated_path = match validate_project_path(&params.project_path) {
-            Ok(path) => {
-                debug_log_with_project!(&params.project_path, "项目路径验证成功: {}", path.display());
-                path
-            },
-            Err(e) => return format!("项目路径验证失败: {}", e),
+            Ok(path) => path,
+            Err(e) => return format_error_response(&e),
         };
 
-        let full_file_path = match validate_file_path(&validated_path, &params.file_path) {
-            Ok(path) => {
-                debug_log_with_project!(&params.project_path, "文件路径验证成功: {}", path.display());
-                path
-            },
-            Err(e) => return format!("文件路径验证失败: {}", e),
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
         };
 
-        let normalized_path = match normalize_file_path(&validated_path, &full_file_path) {
-            Ok(path) => {
-                debug_log_with_project!(&params.project_path, "路径规范化成功: {}", path);
-                path
+        let pm = project_manager.lock().await;
+        // 源文件只读取元数据，不要求仍然存在
+        let normalized_source = match pm.normalize_path(&validated_path.join(&params.source_file)) {
+            Ok(path) => path,
+            Err(e) => return format_error_response(&e),
+        };
+
+        let dest_file_path = match validate_file_path(&validated_path, &params.dest_file) {
+            Ok(path) => path,
+            Err(e) => return format_error_response(&e),
+        };
+
+        let normalized_dest = match pm.normalize_path(&dest_file_path) {
+            Ok(path) => path,
+            Err(e) => return format_error_response(&e),
+        };
+
+        let snapshot = pm.snapshot("copy_file_metadata", UndoScope::All).await;
+        let result = pm.copy_metadata(&normalized_source, &normalized_dest, params.overwrite.unwrap_or(false)).await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+        }
+        pm.record_audit("copy_file_metadata", &[normalized_source.clone(), normalized_dest.clone()], result.as_ref().err()).await;
+        match result {
+            Ok(summary) => {
+                debug_log_with_project!(&params.project_path, "元数据复制成功: {:?}", summary);
+                self.format_data_response(&summary)
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "复制元数据失败: {}", e);
+                error!("复制元数据失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 根据标签查询文件
+    #[tool(description = "根据标签查询文件，支持 AND、OR、NOT、括号和通配符（* 匹配任意数量的字符，? 匹配单个字符，如 version:v1.?），type:* 表示带有该类型任意取值的标签；=[a:1, b:2] 匹配标签集合完全相同的文件，>=[a:1, b:2] 匹配包含全部给定标签的文件")]
+    async fn query_files_by_tags(
+        &self,
+        #[tool(aggr)] params: TagQueryParams,
+    ) -> String {
+        self.metrics.record(Operation::Query);
+        debug_log_with_project!(&params.project_path, "标签查询 - 项目路径: {}, 查询表达式: {}, 路径过滤: {:?}",
+                   params.project_path, params.query, params.path_glob);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => {
+                debug_log_with_project!(&params.project_path, "获取项目管理器成功");
+                pm
             },
-            Err(e) => return format!("路径规范化失败: {}", e),
+            Err(e) => return format_error_data_response(&e),
         };
 
+        let pm = project_manager.lock().await;
+        if params.explain.unwrap_or(false) {
+            return match pm.query_engine.explain_tag_query(&params.query, params.path_glob.as_deref()).await {
+                Ok(explained) => {
+                    debug_log_with_project!(&params.project_path, "标签查询说明成功，共{}个结果", explained.len());
+                    self.format_data_response(&QueryExplanation { matches: explained })
+                },
+                Err(e) => {
+                    debug_log_with_project!(&params.project_path, "标签查询失败: {}", e);
+                    error!("标签查询失败: {}", e);
+                    format_error_response(&e)
+                }
+            };
+        }
+
+        debug_log_with_project!(&params.project_path, "开始执行标签查询");
+        let count_only = params.count_only.unwrap_or(false);
+        let result = pm.query_engine.execute_tag_query(&params.query, params.path_glob.as_deref(), count_only).await;
+
+        match result {
+            Ok(result) => {
+                debug_log_with_project!(&params.project_path, "标签查询成功，共{}个结果", result.total);
+                self.format_data_response(&result)
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "标签查询失败: {}", e);
+                error!("标签查询失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 校验标签查询语法
+    #[tool(description = "校验标签查询表达式的语法，返回 {valid, error, position}，position 为出错位置的字符偏移")]
+    async fn validate_tag_query(
+        &self,
+        #[tool(aggr)] params: ValidateQueryParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "校验查询语法 - 项目路径: {}, 查询表达式: {}",
+                   params.project_path, params.query);
+
         let project_manager = match self.get_or_create_project(&params.project_path).await {
             Ok(pm) => pm,
-            Err(e) => return format!("错误: {:?}", e),
+            Err(e) => return format_error_data_response(&e),
         };
 
         let pm = project_manager.lock().await;
-        let relations = pm.relation_manager.lock().await.get_file_relations(&normalized_path);
-        self.format_data_response(&relations)
+        let validation = pm.query_engine.check_query_syntax(&params.query);
+        debug_log_with_project!(&params.project_path, "查询语法校验结果: {:?}", validation);
+        self.format_data_response(&validation)
     }
 
-    /// 查询入向关联关系
-    #[tool(description = "查询指向该文件的关联关系")]
-    async fn query_incoming_relations(
+    /// 复合查询
+    #[tool(description = "结合标签查询和关联描述关键词查询文件：两者都提供时取交集，只提供一个时返回该条件的结果")]
+    async fn complex_query(
         &self,
-        #[tool(aggr)] params: FilePathParams,
+        #[tool(aggr)] params: ComplexQueryParams,
     ) -> String {
-        debug_log_with_project!(&params.project_path, "查询入向关联关系 - 项目路径: {}, 文件路径: {}",
-                   params.project_path, params.file_path);
+        self.metrics.record(Operation::Query);
+        debug_log_with_project!(&params.project_path, "复合查询 - 项目路径: {}, 标签查询: {:?}, 关联关键词: {:?}",
+                   params.project_path, params.tag_query, params.relation_keyword);
 
-        // 验证路径
-        let validated_path = match validate_project_path(&params.project_path) {
-            Ok(path) => {
-                debug_log_with_project!(&params.project_path, "项目路径验证成功: {}", path.display());
-                path
-            },
-            Err(e) => return format!("项目路径验证失败: {}", e),
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
         };
 
-        let full_file_path = match validate_file_path(&validated_path, &params.file_path) {
-            Ok(path) => {
-                debug_log_with_project!(&params.project_path, "文件路径验证成功: {}", path.display());
-                path
+        let pm = project_manager.lock().await;
+        let result = pm.query_engine.execute_complex_query(
+            params.tag_query.as_deref(),
+            params.relation_keyword.as_deref(),
+        ).await;
+
+        match result {
+            Ok(result) => {
+                debug_log_with_project!(&params.project_path, "复合查询成功，返回{}个结果", result.files.len());
+                self.format_data_response(&result)
             },
-            Err(e) => return format!("文件路径验证失败: {}", e),
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "复合查询失败: {}", e);
+                error!("复合查询失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 多条件过滤
+    #[tool(description = "按多个条件一次性过滤文件，返回同时满足全部条件的文件：tag_query 标签查询、has_comment 是否有注释、min_incoming/min_outgoing 入向/出向关联数量下限、path_glob 路径匹配。至少提供一个条件；未提供 tag_query 时在所有带有元数据的文件中过滤")]
+    async fn multi_filter(
+        &self,
+        #[tool(aggr)] params: MultiFilterParams,
+    ) -> String {
+        self.metrics.record(Operation::Query);
+        debug_log_with_project!(&params.project_path, "多条件过滤 - 项目路径: {}, 标签查询: {:?}, 有注释: {:?}, 入向下限: {:?}, 出向下限: {:?}, 路径: {:?}",
+                   params.project_path, params.tag_query, params.has_comment, params.min_incoming, params.min_outgoing, params.path_glob);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
         };
 
-        let normalized_path = match normalize_file_path(&validated_path, &full_file_path) {
-            Ok(path) => {
-                debug_log_with_project!(&params.project_path, "路径规范化成功: {}", path);
-                path
-            },
-            Err(e) => return format!("路径规范化失败: {}", e),
+        let filter = MultiFilter {
+            tag_query: params.tag_query,
+            has_comment: params.has_comment,
+            min_incoming: params.min_incoming,
+            min_outgoing: params.min_outgoing,
+            path_glob: params.path_glob,
         };
+        let pm = project_manager.lock().await;
+        match pm.query_engine.multi_filter(&filter).await {
+            Ok(result) => {
+                debug_log_with_project!(&params.project_path, "多条件过滤成功，返回{}个结果", result.files.len());
+                self.format_data_response(&result)
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "多条件过滤失败: {}", e);
+                error!("多条件过滤失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 获取所有标签
+    #[tool(description = "获取所有标签，在 tag_types 中按类型分组")]
+    async fn get_all_tags(
+        &self,
+        #[tool(aggr)] params: ProjectPathParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "获取所有标签 - 项目路径: {}", params.project_path);
 
         let project_manager = match self.get_or_create_project(&params.project_path).await {
-            Ok(pm) => pm,
-            Err(e) => return format!("错误: {:?}", e),
+            Ok(pm) => {
+                debug_log_with_project!(&params.project_path, "获取项目管理器成功");
+                pm
+            },
+            Err(e) => return format_error_data_response(&e),
         };
 
         let pm = project_manager.lock().await;
-        let relations = pm.relation_manager.lock().await.get_incoming_relations(&normalized_path);
-        self.format_data_response(&relations)
+        debug_log_with_project!(&params.project_path, "开始获取所有标签");
+        let all_tags = pm.tag_manager.read().await.get_all_tags();
+        debug_log_with_project!(&params.project_path, "获取到标签数量: {}", all_tags.len());
+        self.format_data_response(&TagsByType { tag_types: all_tags })
     }
 
-    /// 获取文件完整信息
-    #[tool(description = "获取文件的完整信息，包括标签、注释、关联关系")]
-    async fn get_file_info(
+    /// 为文件添加注释
+    #[tool(description = "为文件添加注释")]
+    async fn add_file_comment(
         &self,
-        #[tool(aggr)] params: FilePathParams,
+        #[tool(aggr)] params: AddCommentParams,
     ) -> String {
-        debug_log_with_project!(&params.project_path, "获取文件信息 - 项目路径: {}, 文件路径: {}",
-                   params.project_path, params.file_path);
+        self.metrics.record(Operation::CommentEdit);
+        debug_log_with_project!(&params.project_path, "添加文件注释 - 项目路径: {}, 文件路径: {}, 注释长度: {}",
+                   params.project_path, params.file_path, params.comment.len());
 
         // 验证路径
         let validated_path = match validate_project_path(&params.project_path) {
@@ -744,7 +1887,7 @@ impl CodeNexusServer {
                 debug_log_with_project!(&params.project_path, "项目路径验证成功: {}", path.display());
                 path
             },
-            Err(e) => return format!("项目路径验证失败: {}", e),
+            Err(e) => return format_error_response(&e),
         };
 
         let full_file_path = match validate_file_path(&validated_path, &params.file_path) {
@@ -752,117 +1895,2473 @@ impl CodeNexusServer {
                 debug_log_with_project!(&params.project_path, "文件路径验证成功: {}", path.display());
                 path
             },
-            Err(e) => return format!("文件路径验证失败: {}", e),
+            Err(e) => return format_error_response(&e),
+        };
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
         };
 
-        let normalized_path = match normalize_file_path(&validated_path, &full_file_path) {
+        let pm = project_manager.lock().await;
+        let normalized_path = match pm.normalize_path(&full_file_path) {
             Ok(path) => {
                 debug_log_with_project!(&params.project_path, "路径规范化成功: {}", path);
                 path
             },
-            Err(e) => return format!("路径规范化失败: {}", e),
-        };
-
-        let project_manager = match self.get_or_create_project(&params.project_path).await {
-            Ok(pm) => pm,
-            Err(e) => return format!("错误: {:?}", e),
+            Err(e) => return format_error_response(&e),
         };
 
-        let pm = project_manager.lock().await;
-        let result = pm.query_engine.get_file_info(&normalized_path).await;
+        let snapshot = pm.snapshot("add_file_comment", UndoScope::Comments).await;
+        let result = pm.comment_manager.write().await.add_comment(&full_file_path, &normalized_path, &params.comment).await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+            pm.record_content_hash(&full_file_path, &normalized_path).await;
+        }
+        pm.record_audit("add_file_comment", std::slice::from_ref(&normalized_path), result.as_ref().err()).await;
 
         match result {
-            Ok(file_info) => {
-                debug_log_with_project!(&params.project_path, "获取文件信息成功");
-                self.format_data_response(&file_info)
+            Ok(_) if params.validate_markdown.unwrap_or(false) => {
+                let warnings = lint_markdown(&params.comment);
+                debug_log_with_project!(&params.project_path, "注释添加成功，Markdown 警告: {:?}", warnings);
+                self.format_success_with_warnings(&Message::CommentAdded.localized(), &warnings)
+            },
+            Ok(_) => {
+                debug_log_with_project!(&params.project_path, "注释添加成功");
+                self.format_success_response(&Message::CommentAdded.localized())
             },
             Err(e) => {
-                debug_log_with_project!(&params.project_path, "获取文件信息失败: {}", e);
-                error!("获取文件信息失败: {}", e);
+                debug_log_with_project!(&params.project_path, "添加注释失败: {}", e);
+                error!("添加注释失败: {}", e);
                 format_error_response(&e)
             }
         }
     }
 
-    /// 获取系统状态
-    #[tool(description = "获取系统状态和统计信息")]
-    async fn get_system_status(
+    /// 更新文件注释
+    #[tool(description = "更新文件注释。传入空白注释时删除该文件的注释（与 delete_file_comment 相同，文件可以已从磁盘删除）")]
+    async fn update_file_comment(
         &self,
-        #[tool(aggr)] params: ProjectPathParams,
+        #[tool(aggr)] params: AddCommentParams,
     ) -> String {
-        debug_log_with_project!(&params.project_path, "获取系统状态 - 项目路径: {}", params.project_path);
+        self.metrics.record(Operation::CommentEdit);
+        debug_log_with_project!(&params.project_path, "更新文件注释 - 项目路径: {}, 文件路径: {}, 注释长度: {}",
+                   params.project_path, params.file_path, params.comment.len());
 
-        let project_manager = match self.get_or_create_project(&params.project_path).await {
-            Ok(pm) => {
-                debug_log_with_project!(&params.project_path, "获取项目管理器成功");
-                pm
+        // 验证路径
+        let validated_path = match validate_project_path(&params.project_path) {
+            Ok(path) => {
+                debug_log_with_project!(&params.project_path, "项目路径验证成功: {}", path.display());
+                path
             },
-            Err(e) => return format!("错误: {:?}", e),
+            Err(e) => return format_error_response(&e),
+        };
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
         };
 
         let pm = project_manager.lock().await;
-        debug_log_with_project!(&params.project_path, "开始获取系统状态");
-        let result = pm.query_engine.get_system_status().await;
+        // 空白注释表示删除，文件可能已被删除，使用宽松规范化
+        let deleting = params.comment.trim().is_empty();
+        let (full_file_path, normalized_path) = if deleting {
+            match pm.normalize_path_lenient(&params.file_path) {
+                Ok(path) => (validated_path.join(&path), path),
+                Err(e) => return format_error_response(&e),
+            }
+        } else {
+            let full_file_path = match validate_file_path(&validated_path, &params.file_path) {
+                Ok(path) => {
+                    debug_log_with_project!(&params.project_path, "文件路径验证成功: {}", path.display());
+                    path
+                },
+                Err(e) => return format_error_response(&e),
+            };
+
+            match pm.normalize_path(&full_file_path) {
+                Ok(path) => {
+                    debug_log_with_project!(&params.project_path, "路径规范化成功: {}", path);
+                    (full_file_path, path)
+                },
+                Err(e) => return format_error_response(&e),
+            }
+        };
+
+        let snapshot = pm.snapshot("update_file_comment", UndoScope::Comments).await;
+        let result = pm.comment_manager.write().await.update_comment(&full_file_path, &normalized_path, &params.comment).await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+            if !deleting {
+                pm.record_content_hash(&full_file_path, &normalized_path).await;
+            }
+        }
+        pm.record_audit("update_file_comment", std::slice::from_ref(&normalized_path), result.as_ref().err()).await;
 
         match result {
-            Ok(status) => {
-                debug_log_with_project!(&params.project_path, "获取系统状态成功");
-                self.format_data_response(&status)
+            Ok(_) if deleting => {
+                debug_log_with_project!(&params.project_path, "注释已删除");
+                self.format_success_response(&Message::CommentDeleted.localized())
+            },
+            Ok(_) if params.validate_markdown.unwrap_or(false) => {
+                let warnings = lint_markdown(&params.comment);
+                debug_log_with_project!(&params.project_path, "注释更新成功，Markdown 警告: {:?}", warnings);
+                self.format_success_with_warnings(&Message::CommentUpdated.localized(), &warnings)
+            },
+            Ok(_) => {
+                debug_log_with_project!(&params.project_path, "注释更新成功");
+                self.format_success_response(&Message::CommentUpdated.localized())
             },
             Err(e) => {
-                debug_log_with_project!(&params.project_path, "获取系统状态失败: {}", e);
-                error!("获取系统状态失败: {}", e);
+                debug_log_with_project!(&params.project_path, "更新注释失败: {}", e);
+                error!("更新注释失败: {}", e);
                 format_error_response(&e)
             }
         }
     }
 
-    /// 搜索文件
-    #[tool(description = "综合搜索文件，包括注释和关联关系描述")]
-    async fn search_files(
+    /// 删除文件注释
+    #[tool(description = "删除文件注释。文件可以已从磁盘删除，用于清理已删除文件的注释")]
+    async fn delete_file_comment(
         &self,
-        #[tool(param)]
-        #[schemars(description = "项目根目录路径")]
-        project_path: String,
-        #[tool(param)]
-        #[schemars(description = "搜索关键词")]
-        keyword: String,
+        #[tool(aggr)] params: FilePathParams,
     ) -> String {
-        debug_log_with_project!(&project_path, "搜索文件 - 项目路径: {}, 关键词: {}", project_path, keyword);
+        self.metrics.record(Operation::CommentEdit);
+        debug_log_with_project!(&params.project_path, "删除文件注释 - 项目路径: {}, 文件路径: {}",
+                   params.project_path, params.file_path);
 
-        let project_manager = match self.get_or_create_project(&project_path).await {
-            Ok(pm) => {
-                debug_log_with_project!(&project_path, "获取项目管理器成功");
-                pm
-            },
-            Err(e) => return format!("错误: {:?}", e),
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
         };
 
         let pm = project_manager.lock().await;
-        debug_log_with_project!(&project_path, "开始执行搜索查询");
-        let result = pm.query_engine.search_files(&keyword).await;
+        // 文件可能已被删除但仍有注释记录，使用宽松规范化
+        let normalized_path = match pm.normalize_path_lenient(&params.file_path) {
+            Ok(path) => path,
+            Err(e) => return format_error_response(&e),
+        };
+
+        let snapshot = pm.snapshot("delete_file_comment", UndoScope::Comments).await;
+        let result = pm.comment_manager.write().await.delete_comment(&normalized_path).await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+            pm.prune_content_hashes().await;
+        }
+        pm.record_audit("delete_file_comment", std::slice::from_ref(&normalized_path), result.as_ref().err()).await;
 
         match result {
-            Ok(results) => {
-                debug_log_with_project!(&project_path, "搜索文件成功，返回{}个结果", results.len());
-                self.format_data_response(&results)
+            Ok(_) => {
+                debug_log_with_project!(&params.project_path, "注释删除成功");
+                self.format_success_response(&Message::CommentDeleted.localized())
             },
             Err(e) => {
-                debug_log_with_project!(&project_path, "搜索文件失败: {}", e);
-                error!("搜索文件失败: {}", e);
+                debug_log_with_project!(&params.project_path, "删除注释失败: {}", e);
+                error!("删除注释失败: {}", e);
                 format_error_response(&e)
             }
         }
     }
-}
 
-#[tool(tool_box)]
-impl ServerHandler for CodeNexusServer {
-    fn get_info(&self) -> ServerInfo {
-        ServerInfo {
-            instructions: Some("CodeNexus 代码库关系管理工具 - 通过标签、注释和关联关系管理代码文件".into()),
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
-            ..Default::default()
-        }
+    /// 添加文件关联关系
+    #[tool(description = "添加文件间的关联关系")]
+    async fn add_file_relation(
+        &self,
+        #[tool(aggr)] params: AddRelationParams,
+    ) -> String {
+        self.metrics.record(Operation::RelationEdit);
+        debug_log_with_project!(&params.project_path, "添加文件关联关系 - 项目路径: {}, 源文件: {}, 目标文件: {}, 描述: {}",
+                   params.project_path, params.from_file, params.to_file, params.description);
+
+        // 验证路径
+        let validated_path = match validate_project_path(&params.project_path) {
+            Ok(path) => {
+                debug_log_with_project!(&params.project_path, "项目路径验证成功: {}", path.display());
+                path
+            },
+            Err(e) => return format_error_response(&e),
+        };
+
+        let from_file_path = match validate_file_path(&validated_path, &params.from_file) {
+            Ok(path) => {
+                debug_log_with_project!(&params.project_path, "源文件路径验证成功: {}", path.display());
+                path
+            },
+            Err(e) => return format_error_response(&e),
+        };
+
+        let to_file_path = match validate_file_path(&validated_path, &params.to_file) {
+            Ok(path) => {
+                debug_log_with_project!(&params.project_path, "目标文件路径验证成功: {}", path.display());
+                path
+            },
+            Err(e) => return format_error_response(&e),
+        };
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let normalized_from = match pm.normalize_path(&from_file_path) {
+            Ok(path) => {
+                debug_log_with_project!(&params.project_path, "源文件路径规范化成功: {}", path);
+                path
+            },
+            Err(e) => return format_error_response(&e),
+        };
+
+        let normalized_to = match pm.normalize_path(&to_file_path) {
+            Ok(path) => {
+                debug_log_with_project!(&params.project_path, "目标文件路径规范化成功: {}", path);
+                path
+            },
+            Err(e) => return format_error_response(&e),
+        };
+
+        let snapshot = pm.snapshot("add_file_relation", UndoScope::Relations).await;
+        let result = pm.relation_manager.write().await.add_relation(
+            &from_file_path, &normalized_from,
+            &to_file_path, &normalized_to,
+            &params.description, params.weight
+        ).await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+        }
+        pm.record_audit("add_file_relation", &[normalized_from.clone(), normalized_to.clone()], result.as_ref().err()).await;
+
+        match result {
+            Ok(warnings) => {
+                debug_log_with_project!(&params.project_path, "关联关系添加成功，警告: {:?}", warnings);
+                self.format_success_with_warnings(&Message::RelationAdded.localized(), &warnings)
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "添加关联关系失败: {}", e);
+                error!("添加关联关系失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 移除文件关联关系
+    #[tool(description = "移除文件间的关联关系")]
+    async fn remove_file_relation(
+        &self,
+        #[tool(aggr)] params: RemoveRelationParams,
+    ) -> String {
+        self.metrics.record(Operation::RelationEdit);
+        debug_log_with_project!(&params.project_path, "移除文件关联关系 - 项目路径: {}, 源文件: {}, 目标文件: {}",
+                   params.project_path, params.from_file, params.to_file);
+
+        // 验证项目路径
+        let validated_path = match validate_project_path(&params.project_path) {
+            Ok(path) => {
+                debug_log_with_project!(&params.project_path, "项目路径验证成功: {}", path.display());
+                path
+            },
+            Err(e) => return format_error_response(&e),
+        };
+
+        // 对于删除操作，不验证文件是否存在，因为文件可能已被删除但数据库中还有记录
+        let from_file_path = validated_path.join(&params.from_file);
+        let to_file_path = validated_path.join(&params.to_file);
+        debug_log_with_project!(&params.project_path, "构建源文件路径: {}", from_file_path.display());
+        debug_log_with_project!(&params.project_path, "构建目标文件路径: {}", to_file_path.display());
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let normalized_from = match pm.normalize_path(&from_file_path) {
+            Ok(path) => {
+                debug_log_with_project!(&params.project_path, "源文件路径规范化成功: {}", path);
+                path
+            },
+            Err(e) => return format_error_response(&e),
+        };
+
+        let normalized_to = match pm.normalize_path(&to_file_path) {
+            Ok(path) => {
+                debug_log_with_project!(&params.project_path, "目标文件路径规范化成功: {}", path);
+                path
+            },
+            Err(e) => return format_error_response(&e),
+        };
+
+        let snapshot = pm.snapshot("remove_file_relation", UndoScope::Relations).await;
+        let result = pm.relation_manager.write().await.remove_relation(
+            &from_file_path, &normalized_from,
+            &to_file_path, &normalized_to
+        ).await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+            pm.prune_content_hashes().await;
+        }
+        pm.record_audit("remove_file_relation", &[normalized_from.clone(), normalized_to.clone()], result.as_ref().err()).await;
+
+        match result {
+            Ok(_) => {
+                debug_log_with_project!(&params.project_path, "关联关系移除成功");
+                self.format_success_response(&Message::RelationRemoved.localized())
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "移除关联关系失败: {}", e);
+                error!("移除关联关系失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 查询文件关联关系
+    #[tool(description = "查询文件的出向关联关系，在 relations 中返回")]
+    async fn query_file_relations(
+        &self,
+        #[tool(aggr)] params: FilePathParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "查询文件关联关系 - 项目路径: {}, 文件路径: {}",
+                   params.project_path, params.file_path);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        // 只读取元数据，不要求文件仍然存在于磁盘
+        let normalized_path = match pm.normalize_path_lenient(&params.file_path) {
+            Ok(path) => {
+                debug_log_with_project!(&params.project_path, "路径规范化成功: {}", path);
+                path
+            },
+            Err(e) => return format_error_response(&e),
+        };
+
+        let relations = pm.relation_manager.read().await.get_file_relations(&normalized_path);
+        self.format_data_response(&RelationList { relations })
+    }
+
+    /// 按标签批量添加关联关系
+    #[tool(description = "为 from_query 匹配的每个文件添加指向 to_query 匹配的每个文件的关联（跳过自身关联和已存在的关联），返回创建数量和跳过的关联")]
+    async fn add_relations_by_tags(
+        &self,
+        #[tool(aggr)] params: AddRelationsByTagsParams,
+    ) -> String {
+        self.metrics.record(Operation::RelationEdit);
+        debug_log_with_project!(&params.project_path, "按标签批量添加关联 - 项目路径: {}, {} -> {}",
+                   params.project_path, params.from_query, params.to_query);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let files = {
+            let tag_manager = pm.tag_manager.read().await;
+            tag_manager
+                .query_files_by_tags(&params.from_query)
+                .and_then(|sources| Ok((sources, tag_manager.query_files_by_tags(&params.to_query)?)))
+        };
+        let (sources, targets) = match files {
+            Ok(files) => files,
+            Err(e) => return format_error_response(&e),
+        };
+
+        let snapshot = pm.snapshot("add_relations_by_tags", UndoScope::Relations).await;
+        let result = pm.relation_manager.write().await.add_relations_bulk(&sources, &targets, &params.description).await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+        }
+        pm.record_audit("add_relations_by_tags", &sources, result.as_ref().err()).await;
+
+        match result {
+            Ok(report) => {
+                debug_log_with_project!(&params.project_path, "创建了 {} 个关联，跳过 {} 个", report.created, report.skipped.len());
+                self.format_data_response(&report)
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "按标签批量添加关联失败: {}", e);
+                error!("按标签批量添加关联失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 按目标批量移除关联关系
+    #[tool(description = "移除所有指向匹配 glob 的目标文件的关联关系（如 src/old/**），返回移除数量")]
+    async fn remove_relations_to(
+        &self,
+        #[tool(aggr)] params: RemoveRelationsToParams,
+    ) -> String {
+        self.metrics.record(Operation::RelationEdit);
+        debug_log_with_project!(&params.project_path, "按目标移除关联关系 - 项目路径: {}, 模式: {}",
+                   params.project_path, params.target_glob);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let snapshot = pm.snapshot("remove_relations_to", UndoScope::Relations).await;
+        let result = pm.relation_manager.write().await.remove_relations_to(&params.target_glob).await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+            pm.prune_content_hashes().await;
+        }
+        pm.record_audit("remove_relations_to", &[], result.as_ref().err()).await;
+
+        match result {
+            Ok(removed) => {
+                debug_log_with_project!(&params.project_path, "移除了 {} 个关联关系", removed);
+                self.format_data_response(&RemovedCount { removed })
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "按目标移除关联关系失败: {}", e);
+                error!("按目标移除关联关系失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 批量替换关联描述
+    #[tool(description = "将描述与 old_description 完全相同的所有关联改为 new_description，返回修改数量；配合 get_relation_vocabulary 统一项目中的关联用词")]
+    async fn replace_relation_description(
+        &self,
+        #[tool(aggr)] params: ReplaceRelationDescriptionParams,
+    ) -> String {
+        self.metrics.record(Operation::RelationEdit);
+        debug_log_with_project!(&params.project_path, "替换关联描述 - 项目路径: {}, {} -> {}",
+                   params.project_path, params.old_description, params.new_description);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let snapshot = pm.snapshot("replace_relation_description", UndoScope::Relations).await;
+        let result = pm
+            .relation_manager
+            .write()
+            .await
+            .replace_description(&params.old_description, &params.new_description)
+            .await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+        }
+        pm.record_audit("replace_relation_description", &[], result.as_ref().err()).await;
+
+        match result {
+            Ok(replaced) => {
+                debug_log_with_project!(&params.project_path, "替换了 {} 个关联描述", replaced);
+                self.format_data_response(&ReplacedCount { replaced })
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "替换关联描述失败: {}", e);
+                error!("替换关联描述失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 获取全部关联关系
+    #[tool(description = "获取项目的全部关联关系，默认在 relations 中返回 源文件 -> 关联列表 的映射（按源文件排序）；flatten 为 true 时在 edges 中返回按源文件和目标文件排序的边列表，便于客户端自行构建关联图")]
+    async fn get_all_relations(
+        &self,
+        #[tool(aggr)] params: AllRelationsParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "获取全部关联关系 - 项目路径: {}, flatten: {:?}", params.project_path, params.flatten);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let relation_manager = pm.relation_manager.read().await;
+        let relations = relation_manager.get_all_relations();
+        if params.flatten.unwrap_or(false) {
+            let edges = RelationManager::render_json_graph(relations).edges;
+            debug_log_with_project!(&params.project_path, "共 {} 个关联关系", edges.len());
+            return self.format_data_response(&EdgeList { edges });
+        }
+
+        let sorted: BTreeMap<String, Vec<Relation>> = relations.iter().map(|(file, relations)| (file.clone(), relations.clone())).collect();
+        debug_log_with_project!(&params.project_path, "共 {} 个文件有关联关系", sorted.len());
+        self.format_data_response(&RelationMap { relations: sorted })
+    }
+
+    /// 搜索关联关系
+    #[tool(description = "按描述关键词搜索关联关系（忽略大小写），可用 from_file/to_file 限定源文件或目标文件，结果按源文件排序")]
+    async fn search_relations(
+        &self,
+        #[tool(aggr)] params: SearchRelationsParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "搜索关联关系 - 项目路径: {}, 关键词: {}, from: {:?}, to: {:?}",
+                   params.project_path, params.keyword, params.from_file, params.to_file);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        // 文件可能已被删除但仍有关联记录，使用宽松规范化
+        let normalize = |file_path: &Option<String>| {
+            file_path
+                .as_deref()
+                .map(|file_path| pm.normalize_path_lenient(file_path))
+                .transpose()
+        };
+        let (from_file, to_file) = match (normalize(&params.from_file), normalize(&params.to_file)) {
+            (Ok(from_file), Ok(to_file)) => (from_file, to_file),
+            (Err(e), _) | (_, Err(e)) => return format_error_response(&e),
+        };
+
+        let edges: Vec<GraphEdge> = pm
+            .relation_manager
+            .read()
+            .await
+            .search_relations(&params.keyword, from_file.as_deref(), to_file.as_deref())
+            .into_iter()
+            .map(|(source, relation)| GraphEdge {
+                source,
+                target: relation.target,
+                description: relation.description,
+            })
+            .collect();
+
+        debug_log_with_project!(&params.project_path, "找到 {} 个匹配的关联关系", edges.len());
+        self.format_data_response(&EdgeList { edges })
+    }
+
+    /// 获取相关文件
+    #[tool(description = "推荐与文件相关的其他文件：带有相同标签的文件，以及出向关联的目标和入向关联的来源。相关度为共同标签数加关联权重之和，结果按相关度降序排列")]
+    async fn get_related_files(
+        &self,
+        #[tool(aggr)] params: RelatedFilesParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "获取相关文件 - 项目路径: {}, 文件路径: {}",
+                   params.project_path, params.file_path);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let normalized_path = match pm.normalize_path_lenient(&params.file_path) {
+            Ok(path) => path,
+            Err(e) => return format_error_response(&e),
+        };
+
+        let limit = params.limit.unwrap_or(DEFAULT_SUGGESTION_LIMIT);
+        match pm.query_engine.get_related_files(&normalized_path, limit).await {
+            Ok(files) => {
+                debug_log_with_project!(&params.project_path, "找到 {} 个相关文件", files.len());
+                let files: Vec<RelatedFile> = files
+                    .into_iter()
+                    .map(|(path, score)| RelatedFile { path, score })
+                    .collect();
+                self.format_data_response(&RelatedFiles { files })
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "获取相关文件失败: {}", e);
+                error!("获取相关文件失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 获取有效标签
+    #[tool(description = "获取文件的有效标签：自身标签（own）加上沿出向关联在 max_depth 层内可达文件的标签（inherited，附来源文件），tags 为两者并集")]
+    async fn get_effective_tags(
+        &self,
+        #[tool(aggr)] params: EffectiveTagsParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "获取有效标签 - 项目路径: {}, 文件路径: {}, 深度: {:?}",
+                   params.project_path, params.file_path, params.max_depth);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let normalized_path = match pm.normalize_path_lenient(&params.file_path) {
+            Ok(path) => path,
+            Err(e) => return format_error_response(&e),
+        };
+
+        let max_depth = params.max_depth.unwrap_or(DEFAULT_GRAPH_DEPTH);
+        match pm.query_engine.get_effective_tags(&normalized_path, max_depth).await {
+            Ok(effective) => {
+                debug_log_with_project!(&params.project_path, "有效标签: {:?}", effective.tags);
+                self.format_data_response(&effective)
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "获取有效标签失败: {}", e);
+                error!("获取有效标签失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 查找加权最短关联路径
+    #[tool(description = "沿出向关联查找两个文件之间总权重最小的路径，未设置权重的关联按 1.0 计算。路径在 path 中返回，不可达时为 null")]
+    async fn find_weighted_relation_path(
+        &self,
+        #[tool(aggr)] params: FindWeightedPathParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "查找加权最短路径 - 项目路径: {}, 起点: {}, 终点: {}",
+                   params.project_path, params.from_file, params.to_file);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let normalized_from = match pm.normalize_path_lenient(&params.from_file) {
+            Ok(path) => path,
+            Err(e) => return format_error_response(&e),
+        };
+
+        let normalized_to = match pm.normalize_path_lenient(&params.to_file) {
+            Ok(path) => path,
+            Err(e) => return format_error_response(&e),
+        };
+
+        let path = pm.relation_manager.read().await.find_weighted_path(&normalized_from, &normalized_to);
+        debug_log_with_project!(&params.project_path, "加权最短路径: {:?}", path);
+        self.format_data_response(&WeightedPathResult { path })
+    }
+
+    /// 查询入向关联关系
+    #[tool(description = "查询指向该文件的关联关系，返回 source（源文件）、description 和可选的 weight")]
+    async fn query_incoming_relations(
+        &self,
+        #[tool(aggr)] params: FilePathParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "查询入向关联关系 - 项目路径: {}, 文件路径: {}",
+                   params.project_path, params.file_path);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        // 只读取元数据，不要求文件仍然存在于磁盘
+        let normalized_path = match pm.normalize_path_lenient(&params.file_path) {
+            Ok(path) => {
+                debug_log_with_project!(&params.project_path, "路径规范化成功: {}", path);
+                path
+            },
+            Err(e) => return format_error_response(&e),
+        };
+
+        let relations = pm.relation_manager.read().await.get_incoming_relations(&normalized_path);
+        self.format_data_response(&IncomingRelations { relations })
+    }
+
+    /// 获取文件完整信息
+    #[tool(description = "获取文件的完整信息，包括标签、注释、关联关系；relations（出向）和 incoming_relations（入向）中的 other_file 为关联另一端的文件，direction 为 outgoing 或 incoming；tracked 表示文件是否有任何元数据，文件已从磁盘删除时仍可查询")]
+    async fn get_file_info(
+        &self,
+        #[tool(aggr)] params: FilePathParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "获取文件信息 - 项目路径: {}, 文件路径: {}",
+                   params.project_path, params.file_path);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        // 只读取元数据，不要求文件仍然存在于磁盘
+        let normalized_path = match pm.normalize_path_lenient(&params.file_path) {
+            Ok(path) => {
+                debug_log_with_project!(&params.project_path, "路径规范化成功: {}", path);
+                path
+            },
+            Err(e) => return format_error_response(&e),
+        };
+
+        let result = pm.query_engine.get_file_info(&normalized_path).await;
+
+        match result {
+            Ok(file_info) => {
+                debug_log_with_project!(&params.project_path, "获取文件信息成功");
+                self.format_data_response(&file_info)
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "获取文件信息失败: {}", e);
+                error!("获取文件信息失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 获取系统状态
+    #[tool(description = "获取系统状态和统计信息；runtime_metrics 为服务器启动以来查询、添加标签、注释修改和关联修改的调用次数（所有项目合计）")]
+    async fn get_system_status(
+        &self,
+        #[tool(aggr)] params: ProjectPathParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "获取系统状态 - 项目路径: {}", params.project_path);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => {
+                debug_log_with_project!(&params.project_path, "获取项目管理器成功");
+                pm
+            },
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        debug_log_with_project!(&params.project_path, "开始获取系统状态");
+        let result = pm.query_engine.get_system_status().await;
+
+        match result {
+            Ok(mut status) => {
+                debug_log_with_project!(&params.project_path, "获取系统状态成功");
+                status.runtime_metrics = self.metrics.snapshot();
+                status.data_file_errors = pm.data_file_errors().await;
+                self.format_data_response(&status)
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "获取系统状态失败: {}", e);
+                error!("获取系统状态失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 搜索文件
+    #[tool(description = "综合搜索文件，包括标签、注释和关联关系描述")]
+    async fn search_files(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "项目根目录路径")]
+        project_path: String,
+        #[tool(param)]
+        #[schemars(description = "搜索关键词")]
+        keyword: String,
+        #[tool(param)]
+        #[schemars(description = "限定搜索字段（tags、comments、relations、path 的任意子集），默认 tags、comments、relations")]
+        fields: Option<Vec<SearchField>>,
+        #[tool(param)]
+        #[schemars(description = "命中过少时是否对文件路径和标签启用模糊匹配，默认 false")]
+        fuzzy: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = "模糊匹配相似度阈值（0.0 ~ 1.0），默认 0.85")]
+        fuzzy_threshold: Option<f64>,
+        #[tool(param)]
+        #[schemars(description = "子串匹配是否区分大小写（作用于标签、注释、关联描述和路径），默认 false")]
+        case_sensitive: Option<bool>,
+    ) -> String {
+        self.metrics.record(Operation::Query);
+        debug_log_with_project!(&project_path, "搜索文件 - 项目路径: {}, 关键词: {}, 字段: {:?}, 模糊: {:?}",
+                   project_path, keyword, fields, fuzzy);
+
+        let options = SearchOptions {
+            fields: fields.unwrap_or_default(),
+            fuzzy_threshold: fuzzy
+                .unwrap_or(false)
+                .then(|| fuzzy_threshold.unwrap_or(DEFAULT_FUZZY_THRESHOLD)),
+            case_sensitive: case_sensitive.unwrap_or(false),
+        };
+
+        let project_manager = match self.get_or_create_project(&project_path).await {
+            Ok(pm) => {
+                debug_log_with_project!(&project_path, "获取项目管理器成功");
+                pm
+            },
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        debug_log_with_project!(&project_path, "开始执行搜索查询");
+        let result = pm.query_engine.search_files(&keyword, &options).await;
+
+        match result {
+            Ok(response) => {
+                debug_log_with_project!(&project_path, "搜索文件成功，返回{}个结果", response.total);
+                self.format_data_response(&response)
+            },
+            Err(e) => {
+                debug_log_with_project!(&project_path, "搜索文件失败: {}", e);
+                error!("搜索文件失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 列出已加载的项目
+    #[tool(description = "在 projects 中列出服务器当前缓存的项目路径（展开并规范化后的绝对路径）")]
+    async fn list_loaded_projects(&self) -> String {
+        let projects = self.loaded_projects().await;
+        self.format_data_response(&LoadedProjects { projects })
+    }
+
+    /// 重新规范化路径键
+    #[tool(description = "按当前项目根目录重新规范化所有已存储的文件路径并合并重复条目（仓库移动或符号链接变化后使用），返回变化的键数量")]
+    async fn rekey_paths(
+        &self,
+        #[tool(aggr)] params: ProjectPathParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "重新规范化路径键 - 项目路径: {}", params.project_path);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let snapshot = pm.snapshot("rekey_paths", UndoScope::All).await;
+        let result = pm.rekey_paths().await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+        }
+        pm.record_audit("rekey_paths", &[], result.as_ref().err()).await;
+        match result {
+            Ok(changed) => {
+                debug_log_with_project!(&params.project_path, "重新规范化了 {} 个路径键", changed);
+                self.format_data_response(&ChangedCount { changed })
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "重新规范化路径键失败: {}", e);
+                error!("重新规范化路径键失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 折叠路径键大小写
+    #[tool(description = "启用 path_case_fold 后，将已存储的文件路径转为小写并合并仅大小写不同的条目（标签和关联取并集）。在大小写敏感的文件系统上会把真实存在的不同文件合并，执行前请确认，可用 undo_last_operation 撤销")]
+    async fn fold_path_case(
+        &self,
+        #[tool(aggr)] params: ProjectPathParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "折叠路径键大小写 - 项目路径: {}", params.project_path);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let snapshot = pm.snapshot("fold_path_case", UndoScope::All).await;
+        let result = pm.fold_path_case().await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+        }
+        pm.record_audit("fold_path_case", &[], result.as_ref().err()).await;
+        match result {
+            Ok(changed) => {
+                debug_log_with_project!(&params.project_path, "折叠了 {} 个路径键", changed);
+                self.format_data_response(&ChangedCount { changed })
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "折叠路径键失败: {}", e);
+                error!("折叠路径键失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 重新加载项目
+    #[tool(description = "从磁盘重新加载项目的标签、注释和关联关系（如手动编辑或 git 同步了 .codenexus 下的 JSON 文件后），返回重新加载后的数据统计")]
+    async fn reload_project(
+        &self,
+        #[tool(aggr)] params: ProjectPathParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "重新加载项目 - 项目路径: {}", params.project_path);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        match pm.reload().await {
+            Ok(summary) => {
+                debug_log_with_project!(&params.project_path, "项目重新加载成功: {:?}", summary);
+                self.format_data_response(&summary)
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "重新加载项目失败: {}", e);
+                error!("重新加载项目失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 从备份恢复数据文件
+    #[tool(description = "用 .json.bak 备份恢复加载失败的数据文件并重新加载；原文件保留为 .json.corrupt。file_name 为空时恢复所有加载失败的文件")]
+    async fn recover_from_backup(
+        &self,
+        #[tool(aggr)] params: RecoverFromBackupParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "从备份恢复 - 项目路径: {}, 文件: {:?}", params.project_path, params.file_name);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let result = pm.recover_from_backup(params.file_name.as_deref()).await;
+        pm.record_audit("recover_from_backup", &[], result.as_ref().err()).await;
+
+        match result {
+            Ok(recovered) => {
+                debug_log_with_project!(&params.project_path, "已从备份恢复: {:?}", recovered);
+                self.format_data_response(&RecoveredFiles { recovered })
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "从备份恢复失败: {}", e);
+                error!("从备份恢复失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 恢复数据文件备份
+    #[tool(description = "用备份覆盖标签、注释或关联关系数据文件并重新加载，用于撤销错误的修改；version 为备份版本（0 为最近一次），返回恢复后的数据量")]
+    async fn restore_backup(
+        &self,
+        #[tool(aggr)] params: RestoreBackupParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "恢复备份 - 项目路径: {}, 目标: {:?}, 版本: {:?}", params.project_path, params.target, params.version);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let scope = match params.target {
+            BackupTarget::Tags => UndoScope::Tags,
+            BackupTarget::Comments => UndoScope::Comments,
+            BackupTarget::Relations => UndoScope::Relations,
+        };
+        let snapshot = pm.snapshot("restore_backup", scope).await;
+        let result = pm.restore_backup(params.target, params.version.unwrap_or(0)).await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+        }
+        pm.record_audit("restore_backup", &[], result.as_ref().err()).await;
+
+        match result {
+            Ok(summary) => {
+                debug_log_with_project!(&params.project_path, "备份恢复成功: {:?}", summary);
+                self.format_data_response(&summary)
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "恢复备份失败: {}", e);
+                error!("恢复备份失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 卸载项目
+    #[tool(description = "卸载项目，等待进行中的写入完成后从缓存中移除以释放内存")]
+    async fn unload_project(
+        &self,
+        #[tool(aggr)] params: ProjectPathParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "卸载项目 - 项目路径: {}", params.project_path);
+
+        if self.remove_project(&params.project_path).await {
+            self.format_success_response(&Message::ProjectUnloaded.localized())
+        } else {
+            format_error_response(&CodeNexusError::ConfigError(Message::ProjectNotLoaded(params.project_path)))
+        }
+    }
+
+    /// 撤销最近一次修改操作
+    #[tool(description = "撤销该项目最近一次修改操作（标签、注释、关联、导入、合并等），恢复修改前的数据并保存。撤销记录按项目保存在内存中，服务器重启或卸载项目后丢失，保留数量由 .codenexus/config.json 的 undo_depth 配置（默认 20）")]
+    async fn undo_last_operation(
+        &self,
+        #[tool(aggr)] params: ProjectPathParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "撤销最近一次操作 - 项目路径: {}", params.project_path);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let result = pm.undo_last().await;
+        if matches!(result, Ok(Some(_))) {
+            pm.prune_content_hashes().await;
+        }
+        pm.record_audit("undo_last_operation", &[], result.as_ref().err()).await;
+
+        match result {
+            Ok(operation) => {
+                debug_log_with_project!(&params.project_path, "撤销结果: {:?}", operation);
+                let message = match &operation {
+                    Some(operation) => Message::OperationUndone(operation.clone()),
+                    None => Message::NothingToUndo,
+                };
+                self.format_data_response(&UndoResponse {
+                    message: message.localized(),
+                    operation,
+                })
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "撤销失败: {}", e);
+                error!("撤销失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 设置调试日志开关
+    #[tool(description = "运行时开启或关闭调试日志（写入项目数据目录下的 debug.log）")]
+    async fn set_debug_logging(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "是否启用调试日志")]
+        enabled: bool,
+    ) -> String {
+        set_debug_enabled(enabled);
+        info!("调试日志已{}", if enabled { "启用" } else { "关闭" });
+        let message = if enabled { Message::DebugLoggingEnabled } else { Message::DebugLoggingDisabled };
+        self.format_success_response(&message.localized())
+    }
+
+    /// 设置响应语言
+    #[tool(description = "运行时切换错误信息、恢复建议和成功消息的语言，支持 zh-CN（默认）和 en-US")]
+    async fn set_locale(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "语言标识，如 zh-CN、en-US")]
+        locale: String,
+    ) -> String {
+        match Locale::parse(&locale) {
+            Some(locale) => {
+                set_locale(locale);
+                info!("响应语言已切换为 {}", locale.as_str());
+                self.format_success_response(&Message::LocaleChanged(locale).localized())
+            }
+            None => format_error_response(&CodeNexusError::ConfigError(Message::UnsupportedLocale(locale))),
+        }
+    }
+
+    /// 导出项目元数据
+    #[tool(description = "导出项目全部元数据（标签、注释、关联关系以及已归档的标签）为一个 JSON 文档")]
+    async fn export_project(
+        &self,
+        #[tool(aggr)] params: ProjectPathParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "导出项目 - 项目路径: {}", params.project_path);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let export = pm.export_all().await;
+        self.format_data_response(&export)
+    }
+
+    /// 导出项目元数据到文件
+    #[tool(description = "将项目全部元数据（格式同 export_project）写入数据目录下 exports/ 中的文件，只返回文件路径 path，避免大型项目的导出内容超出传输大小限制")]
+    async fn export_project_to_file(
+        &self,
+        #[tool(aggr)] params: ExportToFileParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "导出项目到文件 - 项目路径: {}, 文件名: {}", params.project_path, params.file_name);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        match pm.export_to_file(&params.file_name).await {
+            Ok(path) => self.format_data_response(&ExportedFile { path: path.display().to_string() }),
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "导出项目到文件失败: {}", e);
+                error!("导出项目到文件失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 比较两个元数据包
+    #[tool(description = "比较两个 export_project 导出的元数据包，按文件返回从 base 到 head 新增/移除的标签、注释变化以及新增/移除/变更的关联关系，用于审查元数据变更")]
+    async fn diff_projects(
+        &self,
+        #[tool(aggr)] params: DiffProjectsParams,
+    ) -> String {
+        let diff = diff_exports(&params.base, &params.head);
+        info!("元数据包比较完成，{} 个文件有变化", diff.files.len());
+        self.format_data_response(&diff)
+    }
+
+    /// 导入项目元数据
+    #[tool(description = "导入 export_project 导出的元数据包，支持 merge（合并）和 replace（替换）模式")]
+    async fn import_project(
+        &self,
+        #[tool(aggr)] params: ImportProjectParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "导入项目 - 项目路径: {}, 模式: {:?}", params.project_path, params.mode);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let snapshot = pm.snapshot("import_project", UndoScope::All).await;
+        let result = pm.import_all(
+            params.bundle,
+            params.mode.unwrap_or_default(),
+            params.verify_files.unwrap_or(false),
+        ).await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+            pm.prune_content_hashes().await;
+        }
+        pm.record_audit("import_project", &[], result.as_ref().err()).await;
+
+        match result {
+            Ok(summary) => {
+                debug_log_with_project!(&params.project_path, "导入项目成功: {:?}", summary);
+                self.format_data_response(&summary)
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "导入项目失败: {}", e);
+                error!("导入项目失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 导出标签为 CSV
+    #[tool(description = "导出项目标签为 CSV（每行 file_path,tag），可选写入数据目录下 exports/ 中的文件")]
+    async fn export_tags_csv(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "项目根目录路径")]
+        project_path: String,
+        #[tool(param)]
+        #[schemars(description = "输出文件名（相对于数据目录下的 exports/，不能包含 .. 或绝对路径），为空时直接返回 CSV 内容")]
+        output_file: Option<String>,
+    ) -> String {
+        debug_log_with_project!(&project_path, "导出 CSV 标签 - 项目路径: {}, 输出文件: {:?}", project_path, output_file);
+
+        let project_manager = match self.get_or_create_project(&project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let Some(output_file) = output_file else {
+            return match pm.tag_manager.read().await.export_csv() {
+                Ok(content) => self.format_data_response(&CsvContent { content }),
+                Err(e) => {
+                    error!("导出 CSV 标签失败: {}", e);
+                    format_error_response(&e)
+                }
+            };
+        };
+
+        match pm.export_tags_csv_to_file(&output_file).await {
+            Ok(path) => {
+                debug_log_with_project!(&project_path, "CSV 标签已写入: {}", path.display());
+                self.format_success_response(&Message::TagsExported(path.display().to_string()).localized())
+            },
+            Err(e) => {
+                error!("写入 CSV 文件失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 从 CSV 导入标签
+    #[tool(description = "从 CSV 导入标签（每行 file_path,tag，支持带引号的路径），报告被拒绝的行")]
+    async fn import_tags_csv(
+        &self,
+        #[tool(aggr)] params: ImportTagsCsvParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "导入 CSV 标签 - 项目路径: {}, CSV 文件: {:?}", params.project_path, params.csv_file);
+
+        let validated_path = match validate_project_path(&params.project_path) {
+            Ok(path) => path,
+            Err(e) => return format_error_response(&e),
+        };
+
+        let content = match (params.content, params.csv_file) {
+            (Some(content), None) => content,
+            (None, Some(csv_file)) => {
+                let csv_path = match validate_file_path(&validated_path, &csv_file) {
+                    Ok(path) => path,
+                    Err(e) => return format_error_response(&e),
+                };
+                match tokio::fs::read_to_string(&csv_path).await {
+                    Ok(content) => content,
+                    Err(e) => return format_error_response(&CodeNexusError::StorageError(e)),
+                }
+            }
+            _ => {
+                return format_error_response(&CodeNexusError::ConfigError(Message::ExactlyOneSource("csv_file".to_string())))
+            }
+        };
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let snapshot = pm.snapshot("import_tags_csv", UndoScope::Tags).await;
+        let result = pm.tag_manager.write().await.import_csv(&validated_path, &content).await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+        }
+        pm.record_audit("import_tags_csv", &[], result.as_ref().err()).await;
+
+        match result {
+            Ok(report) => {
+                debug_log_with_project!(&params.project_path, "CSV 导入完成: 新增 {}，拒绝 {}", report.imported, report.rejected.len());
+                self.format_data_response(&report)
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "CSV 导入失败: {}", e);
+                error!("CSV 导入失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 从 JSON Lines 导入标签
+    #[tool(description = "从 JSON Lines 导入标签，每行 {\"file_path\": ..., \"tags\": [...]}，逐行校验并分批保存，适合大规模迁移。返回读取行数、接受记录数、新增标签数、保存次数和被拒绝的行号")]
+    async fn import_tags_jsonl(
+        &self,
+        #[tool(aggr)] params: ImportTagsJsonlParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "导入 JSON Lines 标签 - 项目路径: {}, 文件: {:?}", params.project_path, params.jsonl_file);
+
+        let validated_path = match validate_project_path(&params.project_path) {
+            Ok(path) => path,
+            Err(e) => return format_error_response(&e),
+        };
+
+        let reader: Box<dyn tokio::io::AsyncBufRead + Unpin + Send> = match (params.content, params.jsonl_file) {
+            (Some(content), None) => Box::new(std::io::Cursor::new(content)),
+            (None, Some(jsonl_file)) => {
+                let jsonl_path = match validate_file_path(&validated_path, &jsonl_file) {
+                    Ok(path) => path,
+                    Err(e) => return format_error_response(&e),
+                };
+                match tokio::fs::File::open(&jsonl_path).await {
+                    Ok(file) => Box::new(tokio::io::BufReader::new(file)),
+                    Err(e) => return format_error_response(&CodeNexusError::StorageError(e)),
+                }
+            }
+            _ => {
+                return format_error_response(&CodeNexusError::ConfigError(Message::ExactlyOneSource("jsonl_file".to_string())))
+            }
+        };
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let batch_size = params.batch_size.unwrap_or(DEFAULT_JSONL_BATCH_SIZE);
+        let snapshot = pm.snapshot("import_tags_jsonl", UndoScope::Tags).await;
+        let result = pm.tag_manager.write().await.import_jsonl(&validated_path, reader, batch_size).await;
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+        }
+        pm.record_audit("import_tags_jsonl", &[], result.as_ref().err()).await;
+
+        match result {
+            Ok(report) => {
+                debug_log_with_project!(&params.project_path, "JSON Lines 导入完成: 新增 {}，拒绝 {}", report.imported, report.rejected.len());
+                self.format_data_response(&report)
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "JSON Lines 导入失败: {}", e);
+                error!("JSON Lines 导入失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 获取关联图谱
+    #[tool(description = "以某个文件为根，沿出向关联按广度优先展开关联图谱。受 max_depth 和 max_nodes 限制，达到节点上限时返回 truncated: true")]
+    async fn get_relation_graph(
+        &self,
+        #[tool(aggr)] params: RelationGraphParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "获取关联图谱 - 项目路径: {}, 根文件: {}, 深度: {:?}, 节点上限: {:?}",
+                   params.project_path, params.file_path, params.max_depth, params.max_nodes);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let normalized_path = match pm.normalize_path_lenient(&params.file_path) {
+            Ok(path) => path,
+            Err(e) => return format_error_response(&e),
+        };
+
+        let graph = pm.relation_manager.read().await.get_relation_graph(
+            &normalized_path,
+            params.max_depth.unwrap_or(DEFAULT_GRAPH_DEPTH),
+            params.max_nodes.unwrap_or(DEFAULT_GRAPH_MAX_NODES),
+        );
+        if graph.truncated {
+            warn!("关联图谱达到节点上限，已截断: {}", normalized_path);
+        }
+        self.format_data_response(&graph)
+    }
+
+    /// 导出关联关系为 DOT
+    #[tool(description = "导出文件关联关系为 Graphviz DOT 有向图，可选以某个文件为根限定范围（受 max_depth 和 max_nodes 限制）")]
+    async fn export_relations_dot(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "项目根目录路径")]
+        project_path: String,
+        #[tool(param)]
+        #[schemars(description = "根文件路径（相对于项目根目录），为空时导出全部关联关系")]
+        root_file: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "从根文件出发的最大深度，默认 3")]
+        max_depth: Option<usize>,
+        #[tool(param)]
+        #[schemars(description = "从根文件展开时的源文件数量上限，默认 500")]
+        max_nodes: Option<usize>,
+    ) -> String {
+        debug_log_with_project!(&project_path, "导出 DOT - 项目路径: {}, 根文件: {:?}, 深度: {:?}, 节点上限: {:?}", project_path, root_file, max_depth, max_nodes);
+
+        let project_manager = match self.get_or_create_project(&project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let root = match root_file.map(|root_file| pm.normalize_path_lenient(&root_file)).transpose() {
+            Ok(root) => root,
+            Err(e) => return format_error_response(&e),
+        };
+        let relation_manager = pm.relation_manager.read().await;
+        let (dot, truncated) = match root {
+            Some(root) => {
+                let graph = relation_manager.get_relation_graph(
+                    &root,
+                    max_depth.unwrap_or(DEFAULT_GRAPH_DEPTH),
+                    max_nodes.unwrap_or(DEFAULT_GRAPH_MAX_NODES),
+                );
+                (RelationManager::render_dot(&graph.relations), graph.truncated)
+            }
+            None => (relation_manager.to_dot(), false),
+        };
+        self.format_data_response(&DotExport { dot, truncated })
+    }
+
+    /// 导出关联关系为 GraphML
+    #[tool(description = "导出文件关联关系为 GraphML，可直接导入 Gephi、Cytoscape 等工具")]
+    async fn export_relations_graphml(
+        &self,
+        #[tool(aggr)] params: ProjectPathParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "导出 GraphML - 项目路径: {}", params.project_path);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let graphml = pm.relation_manager.read().await.to_graphml();
+        self.format_data_response(&GraphmlExport { graphml })
+    }
+
+    /// 导出关联关系为 JSON 图结构
+    #[tool(description = "导出文件关联关系为通用 JSON 图结构 {nodes, edges}")]
+    async fn export_relations_json_graph(
+        &self,
+        #[tool(aggr)] params: ProjectPathParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "导出 JSON 图 - 项目路径: {}", params.project_path);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let graph = pm.relation_manager.read().await.to_json_graph();
+        self.format_data_response(&graph)
+    }
+
+    /// 按标签类型分组文件
+    #[tool(description = "按指定标签类型的取值对文件分组，在 groups 中返回 value -> 文件列表；include_untagged 为 true 时在 \"(untagged)\" 键下列出没有该类型标签的项目文件")]
+    async fn group_files_by_tag_type(
+        &self,
+        #[tool(aggr)] params: GroupByTagTypeParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "按标签类型分组 - 项目路径: {}, 类型: {}", params.project_path, params.tag_type);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let tag_manager = pm.tag_manager.read().await;
+        let mut groups = tag_manager.group_files_by_type(&params.tag_type);
+
+        if params.include_untagged.unwrap_or(false) {
+            let untagged = validate_project_path(&params.project_path)
+                .and_then(|root| tag_manager.get_files_without_type(&root, &params.tag_type));
+            match untagged {
+                Ok(files) => {
+                    groups.insert(UNTAGGED_GROUP_KEY.to_string(), files);
+                }
+                Err(e) => {
+                    debug_log_with_project!(&params.project_path, "按标签类型分组失败: {}", e);
+                    error!("按标签类型分组失败: {}", e);
+                    return format_error_response(&e);
+                }
+            }
+        }
+
+        debug_log_with_project!(&params.project_path, "标签类型 {} 共 {} 个分组", params.tag_type, groups.len());
+        self.format_data_response(&FileGroups { groups })
+    }
+
+    /// 获取未标记的文件
+    #[tool(description = "扫描项目目录（遵循 .gitignore，跳过数据目录），列出没有任何标签的文件；可用 under 限定子目录、limit 限制数量，返回结果是否被截断")]
+    async fn get_untagged_files(
+        &self,
+        #[tool(aggr)] params: UntaggedFilesParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "获取未标记的文件 - 项目路径: {}", params.project_path);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let result = match validate_project_path(&params.project_path) {
+            Ok(root) => pm.tag_manager.read().await.get_untagged_files(&root, params.under.as_deref(), params.limit),
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(untagged) => {
+                debug_log_with_project!(
+                    &params.project_path,
+                    "找到 {} 个未标记的文件（截断: {}）",
+                    untagged.files.len(),
+                    untagged.truncated
+                );
+                self.format_data_response(&untagged)
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "获取未标记的文件失败: {}", e);
+                error!("获取未标记的文件失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 清理无效关联关系
+    #[tool(description = "清理源文件或目标文件已不存在的关联关系；dry_run 为 true 时只预览将被移除的关联")]
+    async fn cleanup_invalid_relations(
+        &self,
+        #[tool(aggr)] params: CleanupRelationsParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "清理无效关联 - 项目路径: {}, dry_run: {:?}", params.project_path, params.dry_run);
+        let dry_run = params.dry_run.unwrap_or(false);
+
+        let validated_path = match validate_project_path(&params.project_path) {
+            Ok(path) => path,
+            Err(e) => return format_error_response(&e),
+        };
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        if dry_run {
+            let plan = pm.relation_manager.read().await.plan_invalid_relations_cleanup(&validated_path);
+            return self.format_plan_response(true, plan.removed.len(), &plan);
+        }
+
+        let snapshot = pm.snapshot("cleanup_invalid_relations", UndoScope::Relations).await;
+        let mut relation_manager = pm.relation_manager.write().await;
+        let plan = relation_manager.plan_invalid_relations_cleanup(&validated_path);
+        let result = relation_manager.apply_relations_cleanup(&plan).await;
+        drop(relation_manager);
+        if result.is_ok() {
+            pm.push_undo(snapshot).await;
+            pm.prune_content_hashes().await;
+        }
+        pm.record_audit("cleanup_invalid_relations", &[], result.as_ref().err()).await;
+
+        match result {
+            Ok(removed) => {
+                debug_log_with_project!(&params.project_path, "清理了 {} 个无效关联", removed);
+                self.format_plan_response(false, removed, &plan)
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "清理无效关联失败: {}", e);
+                error!("清理无效关联失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 列出注释
+    #[tool(description = "列出项目中的注释，返回 { total, comments: { 文件路径: 注释 } }，按文件路径排序。contains 可选，只保留包含该子串的注释（不区分大小写）；offset/limit 用于分页，total 为分页前的数量")]
+    async fn list_comments(
+        &self,
+        #[tool(aggr)] params: ListCommentsParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "列出注释 - 项目路径: {}, 过滤: {:?}, offset: {:?}, limit: {:?}",
+                   params.project_path, params.contains, params.offset, params.limit);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let comments = pm.comment_manager.read().await.list_comments(
+            params.contains.as_deref(),
+            params.offset.unwrap_or(0),
+            params.limit,
+        );
+        debug_log_with_project!(&params.project_path, "共 {} 条注释，返回 {} 条", comments.total, comments.comments.len());
+        self.format_data_response(&comments)
+    }
+
+    /// 获取缺少注释的文件
+    #[tool(description = "列出已有标签或关联关系（作为源或目标）但没有注释的文件，按路径排序，用于查找仍需补充文档的文件")]
+    async fn get_undocumented_files(
+        &self,
+        #[tool(aggr)] params: ProjectPathParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "获取缺少注释的文件 - 项目路径: {}", params.project_path);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let files = pm.get_undocumented_files().await;
+        debug_log_with_project!(&params.project_path, "找到 {} 个缺少注释的文件", files.len());
+        self.format_data_response(&FileList { files })
+    }
+
+    /// 检查数据完整性
+    #[tool(description = "检查内存索引与标签、关联数据是否一致（标签 -> 文件索引、标签类型索引、关联反向索引），列出每一处不一致；repair 为 true 时根据数据重建索引。用于排查保存中断或手工编辑 JSON 导致的问题")]
+    async fn check_integrity(
+        &self,
+        #[tool(aggr)] params: CheckIntegrityParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "检查数据完整性 - 项目路径: {}, repair: {:?}", params.project_path, params.repair);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        match pm.check_integrity(params.repair.unwrap_or(false)).await {
+            Ok(report) => {
+                debug_log_with_project!(&params.project_path, "发现 {} 处不一致，已修复: {}", report.issues.len(), report.repaired);
+                if !report.issues.is_empty() {
+                    warn!("项目 {} 发现 {} 处索引不一致", params.project_path, report.issues.len());
+                }
+                self.format_data_response(&report)
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "检查数据完整性失败: {}", e);
+                error!("检查数据完整性失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 查找可能过时的元数据
+    #[tool(description = "重新计算文件内容哈希，列出自最近一次添加标签或注释以来内容已变化（或已删除）的文件，这些文件的注释和标签可能需要复查。需先在配置中开启 track_content_hashes，只有开启后标注过的文件会被检查；重新添加标签或更新注释即视为已复查")]
+    async fn find_stale_metadata(
+        &self,
+        #[tool(aggr)] params: ProjectPathParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "查找过时元数据 - 项目路径: {}", params.project_path);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        match pm.find_stale_metadata().await {
+            Ok(stale) => {
+                debug_log_with_project!(&params.project_path, "找到 {} 个内容已变化的文件", stale.len());
+                self.format_data_response(&StaleFiles { files: stale })
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "查找过时元数据失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 获取孤立文件
+    #[tool(description = "扫描项目目录（遵循 .gitignore，跳过数据目录），列出没有标签、注释和关联关系的文件")]
+    async fn get_orphan_files(
+        &self,
+        #[tool(aggr)] params: ProjectPathParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "获取孤立文件 - 项目路径: {}", params.project_path);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let result = pm.get_orphan_files().await;
+
+        match result {
+            Ok(files) => {
+                debug_log_with_project!(&params.project_path, "找到 {} 个孤立文件", files.len());
+                self.format_data_response(&FileList { files })
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "获取孤立文件失败: {}", e);
+                error!("获取孤立文件失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 获取关联描述词表
+    #[tool(description = "列出项目中所有不同的关联描述及其使用次数，按次数降序排列，用于发现 \"depends on\" 与 \"depends_on\" 这类近似重复的描述并统一用词")]
+    async fn get_relation_vocabulary(
+        &self,
+        #[tool(aggr)] params: ProjectPathParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "获取关联描述词表 - 项目路径: {}", params.project_path);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let vocabulary: Vec<DescriptionCount> = pm
+            .relation_manager
+            .read()
+            .await
+            .describe_vocabulary()
+            .into_iter()
+            .map(|(description, count)| DescriptionCount { description, count })
+            .collect();
+        debug_log_with_project!(&params.project_path, "共 {} 种关联描述", vocabulary.len());
+        self.format_data_response(&RelationVocabulary { descriptions: vocabulary })
+    }
+
+    /// 获取被依赖最多的文件
+    #[tool(description = "按入向关联数量降序列出文件，找出被依赖最多的核心文件")]
+    async fn get_relation_hubs(
+        &self,
+        #[tool(aggr)] params: RelationRankParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "获取被依赖最多的文件 - 项目路径: {}, 数量: {:?}", params.project_path, params.limit);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let ranked: Vec<FileDegree> = pm
+            .relation_manager
+            .read()
+            .await
+            .rank_by_incoming(params.limit.unwrap_or(DEFAULT_RANK_LIMIT))
+            .into_iter()
+            .map(|(path, degree)| FileDegree { path, degree })
+            .collect();
+        debug_log_with_project!(&params.project_path, "返回 {} 个文件", ranked.len());
+        self.format_data_response(&RankedFiles { files: ranked })
+    }
+
+    /// 获取依赖最多的文件
+    #[tool(description = "按出向关联数量降序列出文件，找出依赖其他文件最多的末端文件（如入口、编排文件）")]
+    async fn get_relation_leaves(
+        &self,
+        #[tool(aggr)] params: RelationRankParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "获取依赖最多的文件 - 项目路径: {}, 数量: {:?}", params.project_path, params.limit);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let ranked: Vec<FileDegree> = pm
+            .relation_manager
+            .read()
+            .await
+            .rank_by_outgoing(params.limit.unwrap_or(DEFAULT_RANK_LIMIT))
+            .into_iter()
+            .map(|(path, degree)| FileDegree { path, degree })
+            .collect();
+        debug_log_with_project!(&params.project_path, "返回 {} 个文件", ranked.len());
+        self.format_data_response(&RankedFiles { files: ranked })
+    }
+
+    /// 获取关联图的连通分量
+    #[tool(description = "将关联视为无向图，列出互相连通的文件组（按文件数量降序），用于发现孤立的文件簇和代码库的核心部分")]
+    async fn get_relation_components(
+        &self,
+        #[tool(aggr)] params: ProjectPathParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "获取关联连通分量 - 项目路径: {}", params.project_path);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let components = pm.relation_manager.read().await.connected_components();
+        debug_log_with_project!(&params.project_path, "共 {} 个连通分量", components.len());
+        self.format_data_response(&RelationComponents { components })
+    }
+
+    /// 推荐共现标签
+    #[tool(description = "根据已有标签推荐经常一起出现的其他标签及其次数")]
+    async fn suggest_tags(
+        &self,
+        #[tool(aggr)] params: SuggestTagsParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "推荐共现标签 - 项目路径: {}, 标签: {:?}", params.project_path, params.tags);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let suggestions: Vec<TagCount> = pm
+            .tag_manager
+            .read()
+            .await
+            .suggest_cooccurring_tags(&params.tags, params.limit.unwrap_or(DEFAULT_SUGGESTION_LIMIT))
+            .into_iter()
+            .map(|(tag, count)| TagCount { tag, count })
+            .collect();
+        debug_log_with_project!(&params.project_path, "推荐了 {} 个共现标签", suggestions.len());
+        self.format_data_response(&CooccurringTags { tags: suggestions })
+    }
+
+    /// 按路径统计标签
+    #[tool(description = "统计路径匹配 glob 的文件上使用的标签，在 tags 中返回 { 标签: 带有该标签的匹配文件数 }，按标签排序。以 / 结尾的模式匹配该目录下所有文件，用于概括某个子目录")]
+    async fn tags_for_path(
+        &self,
+        #[tool(aggr)] params: TagsForPathParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "按路径统计标签 - 项目路径: {}, 路径: {}", params.project_path, params.path_glob);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let result = pm.tag_manager.read().await.tags_for_path_glob(&params.path_glob);
+        match result {
+            Ok(counts) => {
+                debug_log_with_project!(&params.project_path, "匹配文件上共有 {} 个不同标签", counts.len());
+                self.format_data_response(&TagUsage { tags: counts })
+            },
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "按路径统计标签失败: {}", e);
+                error!("按路径统计标签失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 查找共享标签的文件
+    #[tool(description = "查找与给定文件共享标签的其他文件及共享的标签数，只返回共享数不少于 min_shared 的文件，按共享数降序排列")]
+    async fn files_sharing_tags(
+        &self,
+        #[tool(aggr)] params: FilesSharingTagsParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "查找共享标签的文件 - 项目路径: {}, 文件路径: {}, 最少共享: {:?}",
+                   params.project_path, params.file_path, params.min_shared);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let normalized_path = match pm.normalize_path_lenient(&params.file_path) {
+            Ok(path) => path,
+            Err(e) => return format_error_response(&e),
+        };
+
+        let files: Vec<SharedTagsFile> = pm
+            .tag_manager
+            .read()
+            .await
+            .files_sharing_tags(&normalized_path, params.min_shared.unwrap_or(1))
+            .into_iter()
+            .map(|(path, shared_tags)| SharedTagsFile { path, shared_tags })
+            .collect();
+        debug_log_with_project!(&params.project_path, "找到 {} 个共享标签的文件", files.len());
+        self.format_data_response(&SharedTagsFiles { files })
+    }
+
+    /// 获取标签模式
+    #[tool(description = "获取当前生效的标签模式（.codenexus/tag_schema.json），在 schema 中返回，未配置时为 null")]
+    async fn get_tag_schema(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "项目根目录路径")]
+        project_path: String,
+    ) -> String {
+        debug_log_with_project!(&project_path, "获取标签模式 - 项目路径: {}", project_path);
+
+        let project_manager = match self.get_or_create_project(&project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let tag_manager = pm.tag_manager.read().await;
+        self.format_data_response(&TagSchemaResult { schema: tag_manager.tag_schema().cloned() })
+    }
+
+    /// 获取项目配置
+    #[tool(description = "获取当前生效的项目配置（.codenexus/config.json，文件不存在或缺少字段时使用默认值）")]
+    async fn get_config(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "项目根目录路径")]
+        project_path: String,
+    ) -> String {
+        debug_log_with_project!(&project_path, "获取项目配置 - 项目路径: {}", project_path);
+
+        let project_manager = match self.get_or_create_project(&project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        self.format_data_response(pm.config().as_ref())
+    }
+
+    /// 更新项目配置
+    #[tool(description = "更新项目配置：只修改提供的配置项，立即生效并写入 .codenexus/config.json，返回更新后的完整配置。未知配置项或取值无效时报错且配置保持不变。可用配置项见 get_config")]
+    async fn set_config(
+        &self,
+        #[tool(aggr)] params: SetConfigParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "更新项目配置 - 项目路径: {}, 修改: {}", params.project_path, params.config);
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let mut pm = project_manager.lock().await;
+        let result = pm.set_config(params.config).await;
+        pm.record_audit("set_config", &[], result.as_ref().err()).await;
+
+        match result {
+            Ok(config) => self.format_data_response(config.as_ref()),
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "更新项目配置失败: {}", e);
+                error!("更新项目配置失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 获取审计日志
+    #[tool(description = "读取数据目录下 audit.jsonl 中的修改操作审计日志（需在配置中启用 audit_log），返回 { total, entries }，条目按时间先后排列。since/until 可按时间过滤，offset/limit 用于分页")]
+    async fn get_audit_log(
+        &self,
+        #[tool(aggr)] params: AuditLogParams,
+    ) -> String {
+        debug_log_with_project!(&params.project_path, "获取审计日志 - 项目路径: {}, since: {:?}, until: {:?}, offset: {:?}, limit: {:?}",
+                   params.project_path, params.since, params.until, params.offset, params.limit);
+
+        let since = match params.since.as_deref().map(|since| parse_time_bound(since, false)).transpose() {
+            Ok(since) => since,
+            Err(e) => return format_error_response(&e),
+        };
+        let until = match params.until.as_deref().map(|until| parse_time_bound(until, true)).transpose() {
+            Ok(until) => until,
+            Err(e) => return format_error_response(&e),
+        };
+
+        let project_manager = match self.get_or_create_project(&params.project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        match pm.get_audit_log(since, until, params.offset.unwrap_or(0), params.limit).await {
+            Ok(page) => self.format_data_response(&page),
+            Err(e) => {
+                debug_log_with_project!(&params.project_path, "获取审计日志失败: {}", e);
+                error!("获取审计日志失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 获取关联关系配置
+    #[tool(description = "获取当前生效的关联关系配置（来自 .codenexus/config.json）：description_pattern 为关联描述必须完整匹配的正则表达式（未配置时为 null，只要求描述非空），以及每个文件的关联数量上限和是否检测重复描述")]
+    async fn get_relation_config(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "项目根目录路径")]
+        project_path: String,
+    ) -> String {
+        debug_log_with_project!(&project_path, "获取关联关系配置 - 项目路径: {}", project_path);
+
+        let project_manager = match self.get_or_create_project(&project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let pm = project_manager.lock().await;
+        let relation_manager = pm.relation_manager.read().await;
+        self.format_data_response(&relation_manager.relation_config())
+    }
+
+    /// 获取查询建议
+    #[tool(description = "根据输入的部分内容获取标签查询建议，可选模糊匹配并返回相似度")]
+    async fn get_query_suggestions(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "项目根目录路径")]
+        project_path: String,
+        #[tool(param)]
+        #[schemars(description = "部分查询内容")]
+        partial_query: String,
+        #[tool(param)]
+        #[schemars(description = "子串命中过少时是否启用模糊匹配，默认 false")]
+        fuzzy: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = "模糊匹配相似度阈值（0.0 ~ 1.0），默认 0.85")]
+        fuzzy_threshold: Option<f64>,
+    ) -> String {
+        debug_log_with_project!(&project_path, "获取查询建议 - 项目路径: {}, 部分查询: {}, 模糊: {:?}",
+                   project_path, partial_query, fuzzy);
+
+        let project_manager = match self.get_or_create_project(&project_path).await {
+            Ok(pm) => pm,
+            Err(e) => return format_error_data_response(&e),
+        };
+
+        let threshold = fuzzy
+            .unwrap_or(false)
+            .then(|| fuzzy_threshold.unwrap_or(DEFAULT_FUZZY_THRESHOLD));
+
+        let pm = project_manager.lock().await;
+        let result = pm.query_engine.get_scored_query_suggestions(&partial_query, threshold).await;
+
+        match result {
+            Ok(suggestions) => {
+                debug_log_with_project!(&project_path, "获取查询建议成功，返回{}个结果", suggestions.len());
+                self.format_data_response(&QuerySuggestions { suggestions })
+            },
+            Err(e) => {
+                debug_log_with_project!(&project_path, "获取查询建议失败: {}", e);
+                error!("获取查询建议失败: {}", e);
+                format_error_response(&e)
+            }
+        }
+    }
+
+    /// 获取工具的输出结构
+    #[tool(description = "在 schemas 中返回各工具成功响应的 JSON Schema（键为工具名），可用 tool 只查看一个工具；成功响应都是带有 success: true 的对象，出错时所有工具都返回 {\"error\": {code, message, suggestion}}")]
+    async fn describe_outputs(
+        &self,
+        #[tool(aggr)] params: DescribeOutputsParams,
+    ) -> String {
+        let mut schemas = output_schemas();
+        let Some(tool) = params.tool else {
+            return self.format_data_response(&OutputSchemas { schemas });
+        };
+
+        match schemas.remove_entry(tool.as_str()) {
+            Some((name, schema)) => self.format_data_response(&OutputSchemas { schemas: BTreeMap::from([(name, schema)]) }),
+            None => format_error_response(&CodeNexusError::ConfigError(Message::UnknownOutputTool(tool))),
+        }
+    }
+}
+
+#[tool(tool_box)]
+impl ServerHandler for CodeNexusServer {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            instructions: Some("CodeNexus 代码库关系管理工具 - 通过标签、注释和关联关系管理代码文件".into()),
+            capabilities: ServerCapabilities::builder().enable_tools().enable_resources().enable_prompts().build(),
+            ..Default::default()
+        }
+    }
+
+    /// 列出常用工作流的提示
+    async fn list_prompts(
+        &self,
+        _request: PaginatedRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> std::result::Result<ListPromptsResult, ErrorData> {
+        Ok(ListPromptsResult {
+            prompts: prompts::list_prompts(),
+            next_cursor: None,
+        })
+    }
+
+    /// 生成指定提示的消息
+    async fn get_prompt(
+        &self,
+        request: GetPromptRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> std::result::Result<GetPromptResult, ErrorData> {
+        prompts::get_prompt(&request.name, request.arguments.as_ref())
+    }
+
+    /// 列出已加载项目中所有被跟踪文件的元数据资源
+    async fn list_resources(
+        &self,
+        _request: PaginatedRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> std::result::Result<ListResourcesResult, ErrorData> {
+        let projects: Vec<(String, Arc<Mutex<ProjectManager>>)> = self
+            .projects
+            .lock()
+            .await
+            .iter()
+            .map(|(path, project)| (path.clone(), project.clone()))
+            .collect();
+
+        let mut resources = Vec::new();
+        for (project_path, project) in projects {
+            let pm = project.lock().await;
+            for file_path in pm.query_engine.get_tracked_files().await {
+                let mut resource = RawResource::new(resource_uri(&project_path, &file_path), file_path);
+                resource.description = Some(Message::FileMetadataResourceDescription(project_path.clone()).localized());
+                resource.mime_type = Some("application/json".to_string());
+                resources.push(resource.no_annotation());
+            }
+        }
+        resources.sort_by(|a, b| a.uri.cmp(&b.uri));
+
+        Ok(ListResourcesResult {
+            resources,
+            next_cursor: None,
+        })
+    }
+
+    /// 读取文件元数据资源，内容为 FileInfo 的 JSON
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> std::result::Result<ReadResourceResult, ErrorData> {
+        let (project_path, file_path) = parse_resource_uri(&request.uri).ok_or_else(|| {
+            ErrorData::invalid_params(Message::InvalidResourceUri(request.uri.clone()).localized(), None)
+        })?;
+
+        let project = self.get_or_create_project(&project_path).await?;
+        let pm = project.lock().await;
+        let file_info = pm.query_engine.get_file_info(&file_path).await?;
+        if !file_info.tracked {
+            return Err(ErrorData::resource_not_found(Message::NoFileMetadata(file_path).localized(), None));
+        }
+
+        let text = serde_json::to_string(&file_info).map_err(CodeNexusError::SerializationError)?;
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: request.uri,
+                mime_type: Some("application/json".to_string()),
+                text,
+            }],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_query_metadata_of_deleted_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = temp_dir.path().to_str().unwrap().to_string();
+        fs::create_dir_all(temp_dir.path().join("src")).unwrap();
+        fs::write(temp_dir.path().join("src/old.rs"), "").unwrap();
+        fs::write(temp_dir.path().join("src/main.rs"), "").unwrap();
+
+        let server = CodeNexusServer::new().await.unwrap();
+        server.add_file_tags(AddTagsParams {
+            project_path: project_path.clone(),
+            file_path: "src/old.rs".to_string(),
+            tags: vec!["status:legacy".to_string()],
+        }).await;
+        server.add_file_relation(AddRelationParams {
+            project_path: project_path.clone(),
+            from_file: "src/old.rs".to_string(),
+            to_file: "src/main.rs".to_string(),
+            description: "调用入口".to_string(),
+            weight: None,
+        }).await;
+
+        // 文件已从磁盘删除，非规范写法按词法规范化后仍能查到元数据
+        fs::remove_file(temp_dir.path().join("src/old.rs")).unwrap();
+        let file_path = "./src/tmp/../old.rs".to_string();
+
+        let tags: serde_json::Value = serde_json::from_str(&server.get_file_tags(FilePathParams {
+            project_path: project_path.clone(),
+            file_path: file_path.clone(),
+        }).await).unwrap();
+        assert_eq!(tags, serde_json::json!({"success": true, "tags": ["status:legacy"]}));
+
+        let has_tag: serde_json::Value = serde_json::from_str(&server.file_has_tag(FileTagParams {
+            project_path: project_path.clone(),
+            file_path: file_path.clone(),
+            tag: "status:legacy".to_string(),
+        }).await).unwrap();
+        assert_eq!(has_tag, serde_json::json!({"success": true, "has_tag": true}));
+
+        let relations: serde_json::Value = serde_json::from_str(&server.query_file_relations(FilePathParams {
+            project_path: project_path.clone(),
+            file_path,
+        }).await).unwrap();
+        assert_eq!(relations["relations"][0]["target"], "src/main.rs");
+
+        // 超出项目目录的路径仍然被拒绝
+        let outside: serde_json::Value = serde_json::from_str(&server.get_file_tags(FilePathParams {
+            project_path,
+            file_path: "../old.rs".to_string(),
+        }).await).unwrap();
+        assert!(outside["error"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_undo_snapshot_rules() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = temp_dir.path().to_str().unwrap();
+        fs::write(temp_dir.path().join("a.rs"), "").unwrap();
+        let full_path = temp_dir.path().join("a.rs");
+
+        let server = CodeNexusServer::new().await.unwrap();
+        let project = server.get_or_create_project(project_path).await.unwrap();
+        let mut pm = project.lock().await;
+        let tags = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+
+        let snapshot = pm.snapshot("add_file_tags", UndoScope::Tags).await;
+        pm.tag_manager.write().await.add_tags(&full_path, "a.rs", tags(&["layer:api"])).await.unwrap();
+        pm.push_undo(snapshot).await;
+        let added_at = pm.tag_manager.read().await.get_file_tags_with_meta("a.rs")[0].meta.added_at;
+        assert_eq!(pm.undo_stack.lock().await.len(), 1);
+
+        // 没有改变数据的操作不占用撤销槽位
+        let snapshot = pm.snapshot("add_file_tags", UndoScope::Tags).await;
+        pm.tag_manager.write().await.add_tags(&full_path, "a.rs", tags(&["layer:api"])).await.unwrap();
+        pm.push_undo(snapshot).await;
+        assert_eq!(pm.undo_stack.lock().await.len(), 1);
+
+        // 撤销恢复原有的添加时间
+        let snapshot = pm.snapshot("remove_file_tags", UndoScope::Tags).await;
+        pm.tag_manager.write().await.remove_tags(&full_path, "a.rs", tags(&["layer:api"])).await.unwrap();
+        pm.push_undo(snapshot).await;
+        assert_eq!(pm.undo_last().await.unwrap().as_deref(), Some("remove_file_tags"));
+        assert_eq!(pm.tag_manager.read().await.get_file_tags_with_meta("a.rs")[0].meta.added_at, added_at);
+
+        // 重新加载后快照失效
+        pm.reload().await.unwrap();
+        assert!(pm.undo_stack.lock().await.is_empty());
+
+        pm.set_config(serde_json::json!({ "undo_depth": 0 })).await.unwrap();
+        assert!(pm.snapshot("add_file_tags", UndoScope::Tags).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_archived_tags_in_export_and_undo() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = temp_dir.path().to_str().unwrap().to_string();
+        fs::write(temp_dir.path().join("a.rs"), "").unwrap();
+
+        let server = CodeNexusServer::new().await.unwrap();
+        server.add_file_tags(AddTagsParams {
+            project_path: project_path.clone(),
+            file_path: "a.rs".to_string(),
+            tags: vec!["layer:api".to_string(), "status:wip".to_string()],
+        }).await;
+        server.archive_file_tags(ArchiveTagsParams {
+            project_path: project_path.clone(),
+            file_path: "a.rs".to_string(),
+            tags: vec!["status:wip".to_string()],
+        }).await;
+
+        let project = server.get_or_create_project(&project_path).await.unwrap();
+        let pm = project.lock().await;
+        let exported = pm.export_all().await;
+        assert_eq!(exported.archived_tags["a.rs"], vec!["status:wip".to_string()]);
+
+        // 替换导入不含归档的包会清空归档，导入导出包则恢复
+        pm.import_all(ProjectExport::default(), ImportMode::Replace, false).await.unwrap();
+        assert!(pm.export_all().await.archived_tags.is_empty());
+        pm.import_all(exported, ImportMode::Replace, false).await.unwrap();
+        assert_eq!(pm.export_all().await.archived_tags.len(), 1);
+
+        // 归档记录撤销快照
+        drop(pm);
+        server.archive_file_tags(ArchiveTagsParams {
+            project_path: project_path.clone(),
+            file_path: "a.rs".to_string(),
+            tags: vec!["layer:api".to_string()],
+        }).await;
+        let pm = project.lock().await;
+        assert_eq!(pm.undo_last().await.unwrap().as_deref(), Some("archive_file_tags"));
+        assert_eq!(pm.export_all().await.tags["a.rs"], vec!["layer:api".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_content_hashes_follow_metadata() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = temp_dir.path().to_str().unwrap().to_string();
+        for file in ["A.rs", "b.rs"] {
+            fs::write(temp_dir.path().join(file), "").unwrap();
+        }
+
+        let server = CodeNexusServer::new().await.unwrap();
+        let project = server.get_or_create_project(&project_path).await.unwrap();
+        project.lock().await.set_config(serde_json::json!({"track_content_hashes": true, "path_case_fold": true})).await.unwrap();
+        let file_path = |file: &str| FilePathParams { project_path: project_path.clone(), file_path: file.to_string() };
+        for file in ["A.rs", "b.rs"] {
+            server.add_file_tags(AddTagsParams {
+                project_path: project_path.clone(),
+                file_path: file.to_string(),
+                tags: vec!["layer:api".to_string()],
+            }).await;
+        }
+        server.add_file_comment(AddCommentParams {
+            project_path: project_path.clone(),
+            file_path: "b.rs".to_string(),
+            comment: "入口".to_string(),
+            validate_markdown: None,
+        }).await;
+        for file in ["A.rs", "b.rs"] {
+            fs::write(temp_dir.path().join(file), "changed").unwrap();
+        }
+        let stale_files = || async {
+            project.lock().await.find_stale_metadata().await.unwrap().into_iter().map(|s| s.file).collect::<Vec<_>>()
+        };
+
+        // 折叠大小写时哈希随键一起重命名
+        project.lock().await.fold_path_case().await.unwrap();
+        assert_eq!(stale_files().await, vec!["a.rs", "b.rs"]);
+
+        // 仍有注释时保留哈希，最后一项元数据移除后清理
+        server.clear_file_tags(file_path("b.rs")).await;
+        assert_eq!(stale_files().await, vec!["a.rs", "b.rs"]);
+        server.delete_file_comment(file_path("b.rs")).await;
+        server.clear_file_tags(file_path("A.rs")).await;
+        assert!(stale_files().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_export_tags_csv_stays_in_exports_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = temp_dir.path().to_str().unwrap().to_string();
+        fs::write(temp_dir.path().join("a.rs"), "").unwrap();
+
+        let server = CodeNexusServer::new().await.unwrap();
+        server.add_file_tags(AddTagsParams {
+            project_path: project_path.clone(),
+            file_path: "a.rs".to_string(),
+            tags: vec!["layer:api".to_string()],
+        }).await;
+
+        let response: serde_json::Value =
+            serde_json::from_str(&server.export_tags_csv(project_path.clone(), Some("tags.csv".to_string())).await).unwrap();
+        assert_eq!(response["success"], true);
+        let written = fs::read_to_string(temp_dir.path().join(".codenexus/exports/tags.csv")).unwrap();
+        assert!(written.contains("a.rs,layer:api"));
+
+        for escaping in ["../tags.csv", "../../outside.csv", temp_dir.path().join("abs.csv").to_str().unwrap()] {
+            let response: serde_json::Value =
+                serde_json::from_str(&server.export_tags_csv(project_path.clone(), Some(escaping.to_string())).await).unwrap();
+            assert!(response["error"]["code"].is_string(), "{} 应被拒绝", escaping);
+        }
+        assert!(!temp_dir.path().join(".codenexus/tags.csv").exists());
+        assert!(!temp_dir.path().join("abs.csv").exists());
+    }
+
+    #[tokio::test]
+    async fn test_import_normalizes_keys_and_rolls_back() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = temp_dir.path().to_str().unwrap();
+        fs::write(temp_dir.path().join("a.rs"), "").unwrap();
+
+        let server = CodeNexusServer::new().await.unwrap();
+        let project = server.get_or_create_project(project_path).await.unwrap();
+        let pm = project.lock().await;
+
+        let mut bundle = ProjectExport::default();
+        bundle.tags.insert("./a.rs".to_string(), vec!["layer:api".to_string()]);
+        bundle.comments.insert(temp_dir.path().join("a.rs").to_str().unwrap().to_string(), "入口".to_string());
+        pm.import_all(bundle, ImportMode::Merge, true).await.unwrap();
+        let exported = pm.export_all().await;
+        assert_eq!(exported.tags.keys().collect::<Vec<_>>(), vec!["a.rs"]);
+        assert_eq!(exported.comments.keys().collect::<Vec<_>>(), vec!["a.rs"]);
+
+        // 项目外的路径使整个导入包被拒绝
+        let mut bundle = ProjectExport::default();
+        bundle.tags.insert("a.rs".to_string(), vec!["status:done".to_string()]);
+        bundle.tags.insert("../outside.rs".to_string(), vec!["status:done".to_string()]);
+        assert!(pm.import_all(bundle, ImportMode::Merge, false).await.is_err());
+        assert_eq!(pm.export_all().await.tags["a.rs"], vec!["layer:api".to_string()]);
+
+        // 后续数据文件写入失败时，已写入的标签回滚
+        pm.storage.mark_unavailable("relations.json");
+        let mut bundle = ProjectExport::default();
+        bundle.tags.insert("a.rs".to_string(), vec!["status:done".to_string()]);
+        bundle.relations.insert("a.rs".to_string(), vec![Relation {
+            target: "a.rs".to_string(),
+            description: "自身".to_string(),
+            weight: None,
+        }]);
+        assert!(pm.import_all(bundle, ImportMode::Replace, false).await.is_err());
+        pm.storage.mark_available("relations.json");
+        assert_eq!(pm.export_all().await.tags["a.rs"], vec!["layer:api".to_string()]);
+        assert_eq!(pm.export_all().await.comments["a.rs"], "入口");
+    }
+
+    #[tokio::test]
+    async fn test_failed_config_leaves_managers_unchanged() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = temp_dir.path().to_str().unwrap();
+        fs::write(temp_dir.path().join("a.rs"), "").unwrap();
+        let server = CodeNexusServer::new().await.unwrap();
+        let project = server.get_or_create_project(project_path).await.unwrap();
+        let mut pm = project.lock().await;
+
+        let config = pm.set_config(serde_json::json!({ "strict_tag_values": true, "max_comment_length": 8 })).await.unwrap();
+        assert!(pm.set_config(serde_json::json!({ "strict_tag_values": false, "relation_description_pattern": "[" })).await.is_err());
+
+        // 之前生效的配置在各管理器中保持不变
+        assert!(Arc::ptr_eq(&pm.config(), &config));
+        assert!(pm.tag_manager.read().await.validate_tag("note:a OR b").is_err());
+        assert_eq!(pm.relation_manager.read().await.relation_config().description_pattern, None);
+        let full_path = temp_dir.path().join("a.rs");
+        assert!(matches!(
+            pm.comment_manager.write().await.add_comment(&full_path, "a.rs", "超过八个字符的注释内容").await,
+            Err(CodeNexusError::CommentTooLong { limit: 8, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reload_data_file_clears_undo_and_flags_errors() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = temp_dir.path().to_str().unwrap();
+        fs::write(temp_dir.path().join("a.rs"), "").unwrap();
+        let full_path = temp_dir.path().join("a.rs");
+        let data_dir = temp_dir.path().join(".codenexus");
+
+        let server = CodeNexusServer::new().await.unwrap();
+        let project = server.get_or_create_project(project_path).await.unwrap();
+        let pm = project.lock().await;
+        let snapshot = pm.snapshot("add_file_tags", UndoScope::Tags).await;
+        pm.tag_manager.write().await.add_tags(&full_path, "a.rs", vec!["layer:api".to_string()]).await.unwrap();
+        pm.push_undo(snapshot).await;
+
+        // 外部修改被重新加载后，撤销不能再覆盖它
+        fs::write(data_dir.join("tags.json"), r#"{"file_tags": {"a.rs": ["layer:db"]}}"#).unwrap();
+        pm.data_reloader().reload_file("tags.json").await.unwrap();
+        assert!(pm.undo_stack.lock().await.is_empty());
+
+        // 损坏的文件保留内存数据并禁止写入，避免覆盖可恢复的原文件
+        fs::write(data_dir.join("tags.json"), "{ broken").unwrap();
+        assert!(pm.data_reloader().reload_file("tags.json").await.is_err());
+        assert_eq!(pm.data_file_errors().await[0].file, "tags.json");
+        assert_eq!(pm.export_all().await.tags["a.rs"], vec!["layer:db".to_string()]);
+        assert!(matches!(
+            pm.tag_manager.write().await.add_tags(&full_path, "a.rs", vec!["status:done".to_string()]).await,
+            Err(CodeNexusError::DataFileUnavailable(_))
+        ));
+        assert_eq!(fs::read_to_string(data_dir.join("tags.json")).unwrap(), "{ broken");
+    }
+
+    #[tokio::test]
+    async fn test_reload_continues_past_broken_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = temp_dir.path().to_str().unwrap();
+        fs::write(temp_dir.path().join("a.rs"), "").unwrap();
+        let full_path = temp_dir.path().join("a.rs");
+        let data_dir = temp_dir.path().join(".codenexus");
+
+        let server = CodeNexusServer::new().await.unwrap();
+        let project = server.get_or_create_project(project_path).await.unwrap();
+        let pm = project.lock().await;
+        pm.comment_manager.write().await.add_comment(&full_path, "a.rs", "原注释").await.unwrap();
+        let snapshot = pm.snapshot("add_file_tags", UndoScope::Tags).await;
+        pm.tag_manager.write().await.add_tags(&full_path, "a.rs", vec!["layer:api".to_string()]).await.unwrap();
+        pm.push_undo(snapshot).await;
+
+        fs::write(data_dir.join("tags.json"), r#"{"file_tags": {"a.rs": ["layer:db"]}}"#).unwrap();
+        fs::write(data_dir.join("comments.json"), "{ broken").unwrap();
+        fs::write(data_dir.join("relations.json"), r#"{"file_relations": {"a.rs": [{"target": "b.rs", "description": "调用"}]}}"#).unwrap();
+        assert!(pm.reload().await.is_err());
+
+        // 其余文件照常加载，损坏的文件保留原有数据并禁止写入，撤销栈已清空
+        let export = pm.export_all().await;
+        assert_eq!(export.tags["a.rs"], vec!["layer:db".to_string()]);
+        assert_eq!(export.comments["a.rs"], "原注释");
+        assert_eq!(export.relations["a.rs"].len(), 1);
+        assert_eq!(pm.data_file_errors().await[0].file, "comments.json");
+        assert!(pm.undo_stack.lock().await.is_empty());
+
+        // 修正后重新加载成功并解除写入限制
+        fs::write(data_dir.join("comments.json"), r#"{"file_comments": {"a.rs": "新注释"}}"#).unwrap();
+        assert_eq!(pm.reload().await.unwrap().commented_files, 1);
+        assert!(pm.data_file_errors().await.is_empty());
+        pm.comment_manager.write().await.update_comment(&full_path, "a.rs", "再次修改").await.unwrap();
     }
 }
@@ -0,0 +1,105 @@
+use crate::models::{CommentChange, FileDiff, ProjectDiff, ProjectExport, RelationChange};
+use std::collections::{BTreeSet, HashSet};
+
+/// 比较两个元数据包，返回从 `base` 到 `head` 的逐文件差异
+///
+/// 关联按目标文件匹配：目标只在一侧出现为新增或移除，两侧都有但描述或权重不同为变更
+pub fn diff_exports(base: &ProjectExport, head: &ProjectExport) -> ProjectDiff {
+    let files: BTreeSet<&String> = [base, head]
+        .iter()
+        .flat_map(|bundle| bundle.tags.keys().chain(bundle.comments.keys()).chain(bundle.relations.keys()))
+        .collect();
+
+    let mut diff = ProjectDiff::default();
+    for file in files {
+        let mut file_diff = FileDiff::default();
+
+        let base_tags: HashSet<&String> = base.tags.get(file).into_iter().flatten().collect();
+        let head_tags: HashSet<&String> = head.tags.get(file).into_iter().flatten().collect();
+        file_diff.tags_added = sorted(head_tags.difference(&base_tags).map(|tag| tag.to_string()).collect());
+        file_diff.tags_removed = sorted(base_tags.difference(&head_tags).map(|tag| tag.to_string()).collect());
+
+        let base_comment = base.comments.get(file);
+        let head_comment = head.comments.get(file);
+        if base_comment != head_comment {
+            file_diff.comment = Some(CommentChange {
+                before: base_comment.cloned(),
+                after: head_comment.cloned(),
+            });
+        }
+
+        let base_relations = base.relations.get(file).map(Vec::as_slice).unwrap_or_default();
+        let head_relations = head.relations.get(file).map(Vec::as_slice).unwrap_or_default();
+        for relation in head_relations {
+            match base_relations.iter().find(|existing| existing.target == relation.target) {
+                None => file_diff.relations_added.push(relation.clone()),
+                Some(existing) if existing != relation => file_diff.relations_changed.push(RelationChange {
+                    before: existing.clone(),
+                    after: relation.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+        file_diff.relations_removed = base_relations
+            .iter()
+            .filter(|relation| !head_relations.iter().any(|other| other.target == relation.target))
+            .cloned()
+            .collect();
+        file_diff.relations_added.sort_by(|a, b| a.target.cmp(&b.target));
+        file_diff.relations_removed.sort_by(|a, b| a.target.cmp(&b.target));
+        file_diff.relations_changed.sort_by(|a, b| a.after.target.cmp(&b.after.target));
+
+        if !file_diff.is_empty() {
+            diff.files.insert(file.clone(), file_diff);
+        }
+    }
+    diff
+}
+
+fn sorted(mut values: Vec<String>) -> Vec<String> {
+    values.sort();
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Relation;
+
+    fn relation(target: &str, description: &str) -> Relation {
+        Relation {
+            target: target.to_string(),
+            description: description.to_string(),
+            weight: None,
+        }
+    }
+
+    #[test]
+    fn test_diff_exports() {
+        let mut base = ProjectExport::default();
+        base.tags.insert("a.rs".to_string(), vec!["layer:api".to_string(), "status:todo".to_string()]);
+        base.comments.insert("a.rs".to_string(), "入口".to_string());
+        base.comments.insert("same.rs".to_string(), "不变".to_string());
+        base.relations.insert("a.rs".to_string(), vec![relation("b.rs", "调用"), relation("c.rs", "依赖")]);
+
+        let mut head = base.clone();
+        head.tags.insert("a.rs".to_string(), vec!["status:done".to_string(), "layer:api".to_string()]);
+        head.comments.remove("a.rs");
+        head.relations.insert("a.rs".to_string(), vec![relation("c.rs", "强依赖"), relation("d.rs", "调用")]);
+        head.tags.insert("new.rs".to_string(), vec!["layer:db".to_string()]);
+
+        let diff = diff_exports(&base, &head);
+        assert_eq!(diff.files.keys().collect::<Vec<_>>(), vec!["a.rs", "new.rs"]);
+
+        let a = &diff.files["a.rs"];
+        assert_eq!(a.tags_added, vec!["status:done"]);
+        assert_eq!(a.tags_removed, vec!["status:todo"]);
+        assert_eq!(a.comment, Some(CommentChange { before: Some("入口".to_string()), after: None }));
+        assert_eq!(a.relations_added, vec![relation("d.rs", "调用")]);
+        assert_eq!(a.relations_removed, vec![relation("b.rs", "调用")]);
+        assert_eq!(a.relations_changed[0].after, relation("c.rs", "强依赖"));
+        assert_eq!(diff.files["new.rs"].tags_added, vec!["layer:db"]);
+
+        assert!(diff_exports(&head, &head).files.is_empty());
+    }
+}
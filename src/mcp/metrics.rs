@@ -0,0 +1,66 @@
+use crate::models::RuntimeMetrics;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// 计入运行时指标的操作类别
+#[derive(Debug, Clone, Copy)]
+pub enum Operation {
+    /// 标签查询、复杂查询和综合搜索
+    Query,
+    /// 添加标签
+    TagAdd,
+    /// 添加、更新或删除注释
+    CommentEdit,
+    /// 添加、删除或修改关联关系
+    RelationEdit,
+}
+
+/// 服务器启动以来各类操作的调用次数（包含失败的调用），所有项目共享
+#[derive(Debug, Default)]
+pub struct MetricsCounters {
+    queries: AtomicU64,
+    tag_adds: AtomicU64,
+    comment_edits: AtomicU64,
+    relation_edits: AtomicU64,
+}
+
+impl MetricsCounters {
+    /// 记录一次操作
+    pub fn record(&self, operation: Operation) {
+        let counter = match operation {
+            Operation::Query => &self.queries,
+            Operation::TagAdd => &self.tag_adds,
+            Operation::CommentEdit => &self.comment_edits,
+            Operation::RelationEdit => &self.relation_edits,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 读取当前计数
+    pub fn snapshot(&self) -> RuntimeMetrics {
+        RuntimeMetrics {
+            queries: self.queries.load(Ordering::Relaxed),
+            tag_adds: self.tag_adds.load(Ordering::Relaxed),
+            comment_edits: self.comment_edits.load(Ordering::Relaxed),
+            relation_edits: self.relation_edits.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_snapshot() {
+        let counters = MetricsCounters::default();
+        counters.record(Operation::Query);
+        counters.record(Operation::Query);
+        counters.record(Operation::RelationEdit);
+
+        let metrics = counters.snapshot();
+        assert_eq!(metrics.queries, 2);
+        assert_eq!(metrics.tag_adds, 0);
+        assert_eq!(metrics.comment_edits, 0);
+        assert_eq!(metrics.relation_edits, 1);
+    }
+}
@@ -1,3 +1,10 @@
 pub mod adapter;
+pub mod diff;
+pub mod metrics;
+pub mod outputs;
+pub mod prompts;
+pub mod resources;
+pub mod undo;
+pub mod watcher;
 
 pub use adapter::CodeNexusServer;
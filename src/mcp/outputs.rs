@@ -0,0 +1,142 @@
+use crate::config::ProjectConfig;
+use crate::models::*;
+use schemars::schema::RootSchema;
+use schemars::{schema_for, JsonSchema};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// 根据参数返回两种结构之一的响应，仅用于生成 schema（`anyOf`）
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+#[serde(untagged)]
+enum OneOf<A, B> {
+    First(A),
+    Second(B),
+}
+
+macro_rules! output_schemas {
+    ($($tool:ident => $ty:ty),* $(,)?) => {
+        BTreeMap::from([$((stringify!($tool), schema_for!(DataResponse<$ty>))),*])
+    };
+}
+
+/// describe_outputs 的响应
+#[derive(Serialize)]
+pub struct OutputSchemas {
+    pub schemas: BTreeMap<&'static str, RootSchema>,
+}
+
+/// 各工具成功响应的 JSON Schema，键为工具名（不含 describe_outputs 自身）
+///
+/// 成功响应都是带有 `success: true` 的 JSON 对象（[`DataResponse`]），出错时所有工具都返回 [`ErrorResponse`]
+pub fn output_schemas() -> BTreeMap<&'static str, RootSchema> {
+    output_schemas! {
+        add_file_tags => AddTagsResponse,
+        remove_file_tags => SuccessResponse,
+        clear_file_tags => SuccessResponse,
+        replace_file_tag => FileTagsResult,
+        archive_file_tags => ArchivedTagsResult,
+        unarchive_file_tags => RestoredTagsResult,
+        get_archived_tags => TagsByFile,
+        get_file_tags => FileTagsResult,
+        get_tags_for_files => TagsByFile,
+        file_has_tag => HasTagResult,
+        remove_tags_matching => RemovedTagsResult,
+        rename_tag => PlanResponse<TagMergePlan>,
+        rename_tag_type => ChangedCount,
+        merge_tags => PlanResponse<TagMergePlan>,
+        copy_file_metadata => CopyMetadataSummary,
+        query_files_by_tags => OneOf<QueryResult, QueryExplanation>,
+        validate_tag_query => QueryValidation,
+        complex_query => QueryResult,
+        multi_filter => QueryResult,
+        get_all_tags => TagsByType,
+        add_file_comment => SuccessResponse,
+        update_file_comment => SuccessResponse,
+        delete_file_comment => SuccessResponse,
+        add_file_relation => SuccessResponse,
+        remove_file_relation => SuccessResponse,
+        query_file_relations => RelationList,
+        add_relations_by_tags => BulkRelationReport,
+        remove_relations_to => RemovedCount,
+        replace_relation_description => ReplacedCount,
+        get_all_relations => OneOf<RelationMap, EdgeList>,
+        search_relations => EdgeList,
+        get_related_files => RelatedFiles,
+        get_effective_tags => EffectiveTags,
+        find_weighted_relation_path => WeightedPathResult,
+        query_incoming_relations => IncomingRelations,
+        get_file_info => FileInfo,
+        get_system_status => SystemStatus,
+        search_files => SearchResponse,
+        list_loaded_projects => LoadedProjects,
+        rekey_paths => ChangedCount,
+        fold_path_case => ChangedCount,
+        reload_project => ReloadSummary,
+        recover_from_backup => RecoveredFiles,
+        restore_backup => ReloadSummary,
+        unload_project => SuccessResponse,
+        undo_last_operation => UndoResponse,
+        set_debug_logging => SuccessResponse,
+        set_locale => SuccessResponse,
+        export_project => ProjectExport,
+        export_project_to_file => ExportedFile,
+        diff_projects => ProjectDiff,
+        import_project => ImportSummary,
+        export_tags_csv => OneOf<CsvContent, SuccessResponse>,
+        import_tags_csv => TagImportReport,
+        import_tags_jsonl => JsonlImportReport,
+        get_relation_graph => RelationGraph,
+        export_relations_dot => DotExport,
+        export_relations_graphml => GraphmlExport,
+        export_relations_json_graph => JsonGraph,
+        group_files_by_tag_type => FileGroups,
+        get_untagged_files => UntaggedFiles,
+        cleanup_invalid_relations => PlanResponse<RelationCleanupPlan>,
+        list_comments => CommentList,
+        get_undocumented_files => FileList,
+        check_integrity => IntegrityReport,
+        find_stale_metadata => StaleFiles,
+        get_orphan_files => FileList,
+        get_relation_vocabulary => RelationVocabulary,
+        get_relation_hubs => RankedFiles,
+        get_relation_leaves => RankedFiles,
+        get_relation_components => RelationComponents,
+        suggest_tags => CooccurringTags,
+        tags_for_path => TagUsage,
+        files_sharing_tags => SharedTagsFiles,
+        get_tag_schema => TagSchemaResult,
+        get_config => ProjectConfig,
+        set_config => ProjectConfig,
+        get_audit_log => AuditLogPage,
+        get_relation_config => RelationConfig,
+        get_query_suggestions => QuerySuggestions,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_schemas_describe_response_fields() {
+        let schemas = output_schemas();
+
+        let untagged = serde_json::to_value(&schemas["get_untagged_files"]).unwrap();
+        let properties = untagged["properties"].as_object().unwrap();
+        assert!(properties.contains_key("files") && properties.contains_key("truncated"));
+
+        // 取决于参数的响应列出两种结构
+        let query = serde_json::to_value(&schemas["query_files_by_tags"]).unwrap();
+        assert_eq!(query["anyOf"].as_array().unwrap().len(), 2);
+
+        // 所有成功响应都是带有 success 字段的对象
+        for (tool, schema) in &schemas {
+            let schema = serde_json::to_value(schema).unwrap();
+            assert_eq!(schema["type"], "object", "{}", tool);
+            assert!(schema["properties"]["success"].is_object(), "{}", tool);
+        }
+        let has_tag = serde_json::to_value(&schemas["file_has_tag"]).unwrap();
+        assert_eq!(has_tag["properties"]["has_tag"]["type"], "boolean");
+    }
+}
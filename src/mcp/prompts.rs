@@ -0,0 +1,104 @@
+use crate::i18n::Message;
+use rmcp::model::{ErrorData, GetPromptResult, JsonObject, Prompt, PromptArgument, PromptMessage, PromptMessageRole};
+
+/// 引导为文件编写注释和标签的提示
+pub const DOCUMENT_FILE_PROMPT: &str = "document_file";
+
+/// 引导查找相关文件的提示
+pub const FIND_RELATED_WORK_PROMPT: &str = "find_related_work";
+
+/// 所有提示共用的参数
+fn file_arguments() -> Vec<PromptArgument> {
+    vec![
+        PromptArgument {
+            name: "project_path".to_string(),
+            description: Some(Message::ProjectPathArgument.localized()),
+            required: Some(true),
+        },
+        PromptArgument {
+            name: "file_path".to_string(),
+            description: Some(Message::FilePathArgument.localized()),
+            required: Some(true),
+        },
+    ]
+}
+
+/// 列出服务器提供的提示
+pub fn list_prompts() -> Vec<Prompt> {
+    vec![
+        Prompt::new(
+            DOCUMENT_FILE_PROMPT,
+            Some(Message::DocumentFilePromptDescription.localized()),
+            Some(file_arguments()),
+        ),
+        Prompt::new(
+            FIND_RELATED_WORK_PROMPT,
+            Some(Message::FindRelatedWorkPromptDescription.localized()),
+            Some(file_arguments()),
+        ),
+    ]
+}
+
+/// 按名称生成提示内容，缺少必需参数或提示不存在时返回 invalid_params
+pub fn get_prompt(name: &str, arguments: Option<&JsonObject>) -> Result<GetPromptResult, ErrorData> {
+    let argument = |key: &str| {
+        arguments
+            .and_then(|arguments| arguments.get(key))
+            .and_then(|value| value.as_str())
+            .filter(|value| !value.trim().is_empty())
+            .ok_or_else(|| {
+                let message = Message::MissingPromptArgument { prompt: name.to_string(), argument: key.to_string() };
+                ErrorData::invalid_params(message.localized(), None)
+            })
+    };
+
+    let (description, text) = match name {
+        DOCUMENT_FILE_PROMPT => {
+            let (project_path, file_path) = (argument("project_path")?, argument("file_path")?);
+            (
+                Message::DocumentFilePromptDescription,
+                Message::DocumentFilePrompt { project_path: project_path.to_string(), file_path: file_path.to_string() },
+            )
+        }
+        FIND_RELATED_WORK_PROMPT => {
+            let (project_path, file_path) = (argument("project_path")?, argument("file_path")?);
+            (
+                Message::FindRelatedWorkPromptDescription,
+                Message::FindRelatedWorkPrompt { project_path: project_path.to_string(), file_path: file_path.to_string() },
+            )
+        }
+        _ => return Err(ErrorData::invalid_params(Message::UnknownPrompt(name.to_string()).localized(), None)),
+    };
+
+    Ok(GetPromptResult {
+        description: Some(description.localized()),
+        messages: vec![PromptMessage::new_text(PromptMessageRole::User, text.localized())],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_prompt() {
+        let mut arguments = JsonObject::new();
+        arguments.insert("project_path".to_string(), "/repo".into());
+        arguments.insert("file_path".to_string(), "src/login.rs".into());
+
+        let result = get_prompt(DOCUMENT_FILE_PROMPT, Some(&arguments)).unwrap();
+        assert_eq!(result.messages.len(), 1);
+        let text = serde_json::to_string(&result.messages[0]).unwrap();
+        assert!(text.contains("src/login.rs") && text.contains("add_file_tags"));
+
+        arguments.remove("file_path");
+        assert!(get_prompt(FIND_RELATED_WORK_PROMPT, Some(&arguments)).is_err());
+        assert!(get_prompt("unknown", None).is_err());
+        assert_eq!(list_prompts().len(), 2);
+
+        // 提示正文同样来自多语言目录
+        let english = Message::FindRelatedWorkPrompt { project_path: "/repo".to_string(), file_path: "src/login.rs".to_string() }
+            .text(crate::i18n::Locale::EnUs);
+        assert!(english.starts_with("Find the work related to file src/login.rs in project /repo"));
+    }
+}
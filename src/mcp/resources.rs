@@ -0,0 +1,71 @@
+/// 资源 URI 前缀
+pub const RESOURCE_URI_SCHEME: &str = "codenexus://";
+
+/// 构造文件元数据资源的 URI：`codenexus://{project}/{path}`
+///
+/// 项目路径整体百分号编码为一段（包括其中的 `/`），文件路径按段编码并保留 `/`
+pub fn resource_uri(project_path: &str, file_path: &str) -> String {
+    let path = file_path.split('/').map(percent_encode).collect::<Vec<_>>().join("/");
+    format!("{}{}/{}", RESOURCE_URI_SCHEME, percent_encode(project_path), path)
+}
+
+/// 解析资源 URI，返回 (项目路径, 文件相对路径)
+pub fn parse_resource_uri(uri: &str) -> Option<(String, String)> {
+    let rest = uri.strip_prefix(RESOURCE_URI_SCHEME)?;
+    let (project, path) = rest.split_once('/')?;
+    let project = percent_decode(project)?;
+    let path = percent_decode(path)?;
+    if project.is_empty() || path.is_empty() {
+        return None;
+    }
+    Some((project, path))
+}
+
+/// 对 RFC 3986 非保留字符以外的字节进行百分号编码
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// 百分号解码，编码不合法或结果不是 UTF-8 时返回 None
+fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_uri_round_trip() {
+        let uri = resource_uri("/home/dev/my project", "src/登录 page.rs");
+        assert_eq!(uri, "codenexus://%2Fhome%2Fdev%2Fmy%20project/src/%E7%99%BB%E5%BD%95%20page.rs");
+        assert_eq!(
+            parse_resource_uri(&uri),
+            Some(("/home/dev/my project".to_string(), "src/登录 page.rs".to_string()))
+        );
+        assert_eq!(parse_resource_uri("file:///tmp/a.rs"), None);
+        assert_eq!(parse_resource_uri("codenexus://%2Ftmp"), None);
+        assert_eq!(parse_resource_uri("codenexus://%2Ftmp/%ZZ"), None);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::models::Relation;
+use crate::storage::TagsData;
+use std::collections::{HashMap, VecDeque};
+
+/// 撤销快照覆盖的数据范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UndoScope {
+    Tags,
+    Comments,
+    Relations,
+    /// 标签、注释和关联关系
+    All,
+}
+
+impl UndoScope {
+    pub fn includes_tags(self) -> bool {
+        matches!(self, UndoScope::Tags | UndoScope::All)
+    }
+
+    pub fn includes_comments(self) -> bool {
+        matches!(self, UndoScope::Comments | UndoScope::All)
+    }
+
+    pub fn includes_relations(self) -> bool {
+        matches!(self, UndoScope::Relations | UndoScope::All)
+    }
+}
+
+/// 快照数据，只有 `scope` 覆盖的部分有效
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UndoData {
+    /// 完整标签数据，包含添加时间和已归档的标签
+    pub tags: TagsData,
+    pub comments: HashMap<String, String>,
+    pub relations: HashMap<String, Vec<Relation>>,
+}
+
+/// 一次修改操作执行前的数据快照
+#[derive(Debug, Clone)]
+pub struct UndoEntry {
+    /// 触发快照的工具名
+    pub operation: String,
+    pub scope: UndoScope,
+    pub data: UndoData,
+}
+
+/// 有界撤销栈，超出深度时丢弃最早的快照
+///
+/// 仅保存在内存中，服务器重启或项目卸载后丢失
+#[derive(Debug)]
+pub struct UndoStack {
+    entries: VecDeque<UndoEntry>,
+    depth: usize,
+}
+
+impl UndoStack {
+    /// 创建撤销栈，`depth` 为 0 时禁用撤销
+    pub fn new(depth: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            depth,
+        }
+    }
+
+    pub fn push(&mut self, entry: UndoEntry) {
+        if self.depth == 0 {
+            return;
+        }
+        if self.entries.len() == self.depth {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// 调整保留数量，超出新深度的最早快照被丢弃
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        while self.entries.len() > depth {
+            self.entries.pop_front();
+        }
+    }
+
+    /// 丢弃全部快照，用于数据被外部替换（重新加载、从备份恢复）之后
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn pop(&mut self) -> Option<UndoEntry> {
+        self.entries.pop_back()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(operation: &str) -> UndoEntry {
+        UndoEntry {
+            operation: operation.to_string(),
+            scope: UndoScope::Tags,
+            data: UndoData::default(),
+        }
+    }
+
+    #[test]
+    fn test_undo_stack_is_bounded() {
+        let mut stack = UndoStack::new(2);
+        stack.push(entry("a"));
+        stack.push(entry("b"));
+        stack.push(entry("c"));
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack.pop().unwrap().operation, "c");
+        assert_eq!(stack.pop().unwrap().operation, "b");
+        assert!(stack.pop().is_none());
+
+        stack.push(entry("a"));
+        stack.push(entry("b"));
+        stack.set_depth(1);
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack.pop().unwrap().operation, "b");
+
+        stack.push(entry("c"));
+        stack.clear();
+        assert!(stack.is_empty());
+
+        let mut disabled = UndoStack::new(0);
+        disabled.push(entry("a"));
+        assert!(disabled.is_empty());
+    }
+}
@@ -0,0 +1,68 @@
+use crate::error::{CodeNexusError, Result};
+use crate::mcp::adapter::DataFileReloader;
+use crate::storage::JsonStorage;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+/// 收到变更事件后等待的时间，用于合并一次写入触发的多个事件
+const DEBOUNCE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 需要监听的数据文件
+const WATCHED_FILES: [&str; 3] = ["tags.json", "comments.json", "relations.json"];
+
+/// 监听数据目录中的 JSON 文件，外部修改时通过 [`DataFileReloader`] 重新加载对应的管理器
+///
+/// 服务器自身写入的内容通过 `JsonStorage::is_own_write` 比对哈希后忽略。
+/// 重新加载成功会清空撤销栈，失败时该文件被禁止写入，与 reload_project 的处理一致。
+/// 返回的 watcher 被释放时监听停止，后台任务随之退出。
+pub fn spawn_data_watcher(storage: JsonStorage, reloader: DataFileReloader) -> Result<RecommendedWatcher> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<PathBuf>();
+
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        match event {
+            Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                for path in event.paths {
+                    let _ = tx.send(path);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => warn!("数据文件监听出错: {}", e),
+        }
+    })
+    .map_err(|e| CodeNexusError::FileSystemError(format!("创建数据文件监听失败: {}", e)))?;
+
+    watcher
+        .watch(storage.data_dir(), RecursiveMode::NonRecursive)
+        .map_err(|e| CodeNexusError::FileSystemError(format!("监听数据目录失败 {:?}: {}", storage.data_dir(), e)))?;
+
+    tokio::spawn(async move {
+        while let Some(path) = rx.recv().await {
+            tokio::time::sleep(DEBOUNCE_INTERVAL).await;
+            let mut changed: HashSet<PathBuf> = HashSet::from([path]);
+            while let Ok(path) = rx.try_recv() {
+                changed.insert(path);
+            }
+
+            for path in changed {
+                let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+                    continue;
+                };
+                if !WATCHED_FILES.contains(&file_name) || storage.is_own_write(&path).await {
+                    continue;
+                }
+
+                match reloader.reload_file(file_name).await {
+                    Ok(()) => info!("检测到 {:?} 被外部修改，已重新加载", path),
+                    Err(e) => warn!("重新加载 {:?} 失败: {}", path, e),
+                }
+            }
+        }
+        debug!("数据文件监听已停止: {:?}", storage.data_dir());
+    });
+
+    Ok(watcher)
+}
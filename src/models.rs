@@ -1,22 +1,133 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use schemars::JsonSchema;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+/// 按键排序后序列化 HashMap，使响应和导出内容的顺序稳定
+fn serialize_sorted<S, V>(map: &HashMap<String, V>, serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+    V: Serialize,
+{
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
 
 /// 文件完整信息
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct FileInfo {
     pub path: String,
     pub tags: Vec<String>,
     pub comment: Option<String>,
-    pub relations: Vec<Relation>,
-    pub incoming_relations: Vec<Relation>,
+    /// 出向关联：other_file 为目标文件
+    pub relations: Vec<DirectedRelation>,
+    /// 入向关联：other_file 为源文件
+    pub incoming_relations: Vec<DirectedRelation>,
+    /// 文件是否出现在任一管理器中（有标签、注释或入向/出向关联）
+    pub tracked: bool,
 }
 
 /// 文件关联关系
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Relation {
     pub target: String,
     pub description: String,
+    /// 依赖强度，未设置时按 `DEFAULT_RELATION_WEIGHT` 计算
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f64>,
+}
+
+/// 关联方向，相对于当前查询的文件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RelationDirection {
+    /// 当前文件指向 other_file
+    Outgoing,
+    /// other_file 指向当前文件
+    Incoming,
+}
+
+/// 带方向的关联关系，`other_file` 为关联另一端的文件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct DirectedRelation {
+    pub other_file: String,
+    pub description: String,
+    pub direction: RelationDirection,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f64>,
+}
+
+impl DirectedRelation {
+    /// 由出向关联构造，`relation.target` 为目标文件
+    pub fn outgoing(relation: Relation) -> Self {
+        Self {
+            other_file: relation.target,
+            description: relation.description,
+            direction: RelationDirection::Outgoing,
+            weight: relation.weight,
+        }
+    }
+
+    /// 由入向关联构造，`relation.source` 为源文件
+    pub fn incoming(relation: IncomingRelation) -> Self {
+        Self {
+            other_file: relation.source,
+            description: relation.description,
+            direction: RelationDirection::Incoming,
+            weight: relation.weight,
+        }
+    }
+}
+
+/// 指向某个文件的入向关联关系
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct IncomingRelation {
+    /// 发出该关联的源文件
+    pub source: String,
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f64>,
+}
+
+impl IncomingRelation {
+    /// 由源文件及其出向关联构造
+    pub fn new(source: &str, relation: &Relation) -> Self {
+        Self {
+            source: source.to_string(),
+            description: relation.description.clone(),
+            weight: relation.weight,
+        }
+    }
+}
+
+/// 关联关系的默认权重
+pub const DEFAULT_RELATION_WEIGHT: f64 = 1.0;
+
+impl Relation {
+    /// 生效的权重
+    pub fn weight(&self) -> f64 {
+        self.weight.unwrap_or(DEFAULT_RELATION_WEIGHT)
+    }
+}
+
+/// 关联图节点
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct GraphNode {
+    pub id: String,
+}
+
+/// 关联图边
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    pub description: String,
+}
+
+/// 通用 JSON 图结构，便于外部工具导入
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct JsonGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
 }
 
 /// 标签查询参数
@@ -26,6 +137,87 @@ pub struct TagQueryParams {
     pub project_path: String,
     #[schemars(description = "标签查询表达式，支持 AND、NOT、通配符")]
     pub query: String,
+    #[schemars(description = "可选的路径 glob 过滤（路径使用正斜杠，支持 ** 递归匹配），如 src/api/**")]
+    pub path_glob: Option<String>,
+    #[schemars(description = "为 true 时只返回匹配数量 total，files 为空，适合只需要统计的场景，默认 false")]
+    pub count_only: Option<bool>,
+    #[schemars(description = "为 true 时在 matches 中返回每个匹配文件及使其满足查询的标签 [{path, matched_tags}]（NOT 子句不贡献标签），优先于 count_only，默认 false")]
+    pub explain: Option<bool>,
+}
+
+/// 标签查询匹配说明
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct QueryMatchExplanation {
+    pub path: String,
+    /// 使文件满足查询表达式的标签
+    pub matched_tags: Vec<String>,
+}
+
+/// 复合查询参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ComplexQueryParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "标签查询表达式，支持 AND、OR、NOT、括号、通配符以及标签集合 =[...]（完全相同）和 >=[...]（超集）")]
+    pub tag_query: Option<String>,
+    #[schemars(description = "关联描述关键词，匹配关联关系的源文件")]
+    pub relation_keyword: Option<String>,
+}
+
+/// 多条件过滤参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct MultiFilterParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "可选：标签查询表达式，支持 AND、OR、NOT、括号和通配符")]
+    pub tag_query: Option<String>,
+    #[schemars(description = "可选：true 只保留有注释的文件，false 只保留没有注释的文件")]
+    pub has_comment: Option<bool>,
+    #[schemars(description = "可选：入向关联数量下限（含）")]
+    pub min_incoming: Option<usize>,
+    #[schemars(description = "可选：出向关联数量下限（含）")]
+    pub min_outgoing: Option<usize>,
+    #[schemars(description = "可选：路径 glob，匹配相对于项目根目录的路径，如 src/**/*.rs")]
+    pub path_glob: Option<String>,
+}
+
+/// 多条件过滤条件，未设置的条件不参与过滤
+#[derive(Debug, Clone, Default)]
+pub struct MultiFilter {
+    pub tag_query: Option<String>,
+    pub has_comment: Option<bool>,
+    pub min_incoming: Option<usize>,
+    pub min_outgoing: Option<usize>,
+    pub path_glob: Option<String>,
+}
+
+impl MultiFilter {
+    /// 是否没有设置任何条件
+    pub fn is_empty(&self) -> bool {
+        self.tag_query.is_none()
+            && self.has_comment.is_none()
+            && self.min_incoming.is_none()
+            && self.min_outgoing.is_none()
+            && self.path_glob.is_none()
+    }
+}
+
+/// 查询语法校验结果
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct QueryValidation {
+    pub valid: bool,
+    pub error: Option<String>,
+    /// 出错位置的字符偏移
+    pub position: Option<usize>,
+}
+
+/// 校验查询语法参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ValidateQueryParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "待校验的标签查询表达式")]
+    pub query: String,
 }
 
 /// 添加标签参数
@@ -50,6 +242,30 @@ pub struct RemoveTagsParams {
     pub tags: Vec<String>,
 }
 
+/// 替换文件标签参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ReplaceFileTagParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "文件路径（相对于项目根目录）")]
+    pub file_path: String,
+    #[schemars(description = "要替换的现有标签")]
+    pub old_tag: String,
+    #[schemars(description = "新标签，格式为 type:value")]
+    pub new_tag: String,
+}
+
+/// 归档或恢复标签参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ArchiveTagsParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "文件路径（相对于项目根目录）")]
+    pub file_path: String,
+    #[schemars(description = "要归档或恢复的标签列表")]
+    pub tags: Vec<String>,
+}
+
 /// 添加注释参数
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct AddCommentParams {
@@ -59,6 +275,8 @@ pub struct AddCommentParams {
     pub file_path: String,
     #[schemars(description = "注释内容")]
     pub comment: String,
+    #[schemars(description = "是否按 Markdown 检查注释（如未闭合的代码块），问题以 warnings 返回而不会导致失败，默认 false")]
+    pub validate_markdown: Option<bool>,
 }
 
 /// 添加关联关系参数
@@ -72,6 +290,8 @@ pub struct AddRelationParams {
     pub to_file: String,
     #[schemars(description = "关联关系描述")]
     pub description: String,
+    #[schemars(description = "关联权重（非负数，表示依赖强度或代价），默认 1.0")]
+    pub weight: Option<f64>,
 }
 
 /// 移除关联关系参数
@@ -94,6 +314,986 @@ pub struct FilePathParams {
     pub file_path: String,
 }
 
+/// 批量文件路径参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FilePathsParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "文件路径列表（相对于项目根目录）")]
+    pub file_paths: Vec<String>,
+}
+
+/// 单个文件标签参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FileTagParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "文件路径（相对于项目根目录）")]
+    pub file_path: String,
+    #[schemars(description = "标签，格式为 type:value")]
+    pub tag: String,
+}
+
+/// 标签模式，定义允许的标签类型及其取值约束
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TagSchema {
+    /// tag_type -> 取值规则
+    #[serde(default)]
+    pub types: BTreeMap<String, TagTypeRule>,
+}
+
+/// 单个标签类型的取值规则，未设置的约束不做限制
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TagTypeRule {
+    /// 允许的取值列表
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub values: Option<Vec<String>>,
+    /// 取值需完整匹配的正则表达式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+}
+
+/// 导入模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    /// 与现有数据合并：标签取并集，注释覆盖，已存在的关联关系跳过
+    #[default]
+    Merge,
+    /// 用导入数据整体替换现有数据
+    Replace,
+}
+
+/// 项目元数据导出包
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ProjectExport {
+    #[serde(default, serialize_with = "serialize_sorted")]
+    pub tags: HashMap<String, Vec<String>>,
+    #[serde(default, serialize_with = "serialize_sorted")]
+    pub comments: HashMap<String, String>,
+    #[serde(default, serialize_with = "serialize_sorted")]
+    pub relations: HashMap<String, Vec<Relation>>,
+    /// 文件 -> 已归档的标签，没有归档时省略
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "serialize_sorted")]
+    pub archived_tags: HashMap<String, Vec<String>>,
+}
+
+/// 比较元数据包参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DiffProjectsParams {
+    #[schemars(description = "作为比较基准的元数据包（由 export_project 导出）")]
+    pub base: ProjectExport,
+    #[schemars(description = "与基准比较的元数据包（由 export_project 导出）")]
+    pub head: ProjectExport,
+}
+
+/// 注释变化，`before`/`after` 为 None 表示注释不存在
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CommentChange {
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// 指向同一目标的关联在描述或权重上的变化
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RelationChange {
+    pub before: Relation,
+    pub after: Relation,
+}
+
+/// 单个文件的元数据差异，只输出有变化的部分
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct FileDiff {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags_added: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags_removed: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<CommentChange>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub relations_added: Vec<Relation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub relations_removed: Vec<Relation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub relations_changed: Vec<RelationChange>,
+}
+
+impl FileDiff {
+    pub fn is_empty(&self) -> bool {
+        *self == FileDiff::default()
+    }
+}
+
+/// 两个元数据包的差异，按文件路径排序，无变化的文件不出现
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ProjectDiff {
+    pub files: BTreeMap<String, FileDiff>,
+}
+
+/// 导入项目参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ImportProjectParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "由 export_project 导出的元数据包")]
+    pub bundle: ProjectExport,
+    #[schemars(description = "导入模式：merge（合并，默认）或 replace（替换）")]
+    pub mode: Option<ImportMode>,
+    #[schemars(description = "是否跳过项目中不存在的文件，默认 false")]
+    pub verify_files: Option<bool>,
+}
+
+/// 导入结果统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ImportSummary {
+    pub tags: usize,
+    pub comments: usize,
+    pub relations: usize,
+    pub skipped_files: Vec<String>,
+}
+
+/// 复制文件元数据参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CopyMetadataParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "源文件路径（相对于项目根目录）")]
+    pub source_file: String,
+    #[schemars(description = "目标文件路径（相对于项目根目录），必须存在")]
+    pub dest_file: String,
+    #[schemars(description = "目标文件已有注释时是否覆盖，默认 false")]
+    pub overwrite: Option<bool>,
+}
+
+/// 元数据复制结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct CopyMetadataSummary {
+    /// 新增的标签数量
+    pub tags: usize,
+    /// 是否复制了注释
+    pub comment: bool,
+    /// 新增的出向关联数量
+    pub relations: usize,
+}
+
+/// 未标记文件的扫描结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct UntaggedFiles {
+    pub files: Vec<String>,
+    /// 是否因达到 `limit` 而提前结束扫描
+    pub truncated: bool,
+}
+
+/// 重新加载后的数据统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ReloadSummary {
+    pub tagged_files: usize,
+    pub total_tags: usize,
+    pub commented_files: usize,
+    pub files_with_relations: usize,
+    pub total_relations: usize,
+}
+
+/// 导入时被拒绝的记录
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RejectedRecord {
+    /// 记录所在行号（从 1 开始）
+    pub line: usize,
+    pub reason: String,
+}
+
+/// 标签批量导入报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TagImportReport {
+    /// 新增的标签数量
+    pub imported: usize,
+    pub rejected: Vec<RejectedRecord>,
+}
+
+/// 相关文件推荐参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RelatedFilesParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "文件路径（相对于项目根目录）")]
+    pub file_path: String,
+    #[schemars(description = "返回的最大文件数量，默认 10")]
+    pub limit: Option<usize>,
+}
+
+/// 获取全部关联关系参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AllRelationsParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "为 true 时在 edges 中返回扁平的边列表 [{source, target, description}]，默认 false 在 relations 中返回按源文件分组的映射")]
+    pub flatten: Option<bool>,
+}
+
+/// 加权最短路径参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FindWeightedPathParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "起点文件路径（相对于项目根目录）")]
+    pub from_file: String,
+    #[schemars(description = "终点文件路径（相对于项目根目录）")]
+    pub to_file: String,
+}
+
+/// 加权最短路径
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WeightedPath {
+    /// 从起点到终点经过的文件，包含两端
+    pub path: Vec<String>,
+    pub total_weight: f64,
+}
+
+/// 以某个文件为根的关联图谱
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct RelationGraph {
+    /// 源文件 -> 出向关联关系
+    #[serde(serialize_with = "serialize_sorted")]
+    pub relations: HashMap<String, Vec<Relation>>,
+    /// 是否因达到节点上限而提前停止展开
+    pub truncated: bool,
+}
+
+/// 获取关联图谱参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RelationGraphParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "根文件路径（相对于项目根目录）")]
+    pub file_path: String,
+    #[schemars(description = "从根文件出发的最大深度，默认 3")]
+    pub max_depth: Option<usize>,
+    #[schemars(description = "图谱中源文件数量上限，达到后停止展开并返回 truncated: true，默认 500")]
+    pub max_nodes: Option<usize>,
+}
+
+/// 搜索关联关系参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SearchRelationsParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "关联描述中的关键词（忽略大小写）")]
+    pub keyword: String,
+    #[schemars(description = "只返回从该文件出发的关联（可选）")]
+    pub from_file: Option<String>,
+    #[schemars(description = "只返回指向该文件的关联（可选）")]
+    pub to_file: Option<String>,
+}
+
+/// 按标签批量添加关联参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AddRelationsByTagsParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "源文件的标签查询表达式，如 layer:controller")]
+    pub from_query: String,
+    #[schemars(description = "目标文件的标签查询表达式，如 layer:service")]
+    pub to_query: String,
+    #[schemars(description = "关联关系描述")]
+    pub description: String,
+}
+
+/// 按目标批量移除关联参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RemoveRelationsToParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "目标路径 glob 模式，如 src/old/**；以 / 结尾时匹配该目录下所有文件")]
+    pub target_glob: String,
+}
+
+/// 批量替换关联描述参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ReplaceRelationDescriptionParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "原描述，只替换与之完全相同的描述")]
+    pub old_description: String,
+    #[schemars(description = "新描述，不能为空")]
+    pub new_description: String,
+}
+
+/// 清理无效关联参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CleanupRelationsParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "为 true 时只返回将被清理的关联，不做修改，默认 false")]
+    pub dry_run: Option<bool>,
+}
+
+/// 完整性检查参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CheckIntegrityParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "为 true 时在发现不一致后根据 tags.json 和 relations.json 的数据重建索引，默认 false")]
+    pub repair: Option<bool>,
+}
+
+/// 索引不一致的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegrityIssueKind {
+    /// 文件带有标签，但标签 -> 文件索引中缺少该文件
+    MissingTagFile,
+    /// 标签 -> 文件索引中的文件实际没有该标签
+    StaleTagFile,
+    /// 标签类型索引中缺少在用的取值
+    MissingTagValue,
+    /// 标签类型索引中的取值已没有文件使用
+    StaleTagValue,
+    /// 关联关系缺少对应的入向索引
+    MissingIncoming,
+    /// 入向索引中的关联关系已不存在
+    StaleIncoming,
+    /// 数据文件加载失败，对应数据以空数据代替且暂停写入
+    UnreadableDataFile,
+}
+
+/// 一处索引不一致
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct IntegrityIssue {
+    pub kind: IntegrityIssueKind,
+    /// 相关文件；标签类型索引的问题为标签类型
+    pub file: String,
+    /// 相关的标签、取值或关联目标
+    pub item: String,
+}
+
+/// 完整性检查结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct IntegrityReport {
+    /// 检查时发现的不一致（修复前）
+    pub issues: Vec<IntegrityIssue>,
+    /// 是否已重建索引
+    pub repaired: bool,
+}
+
+/// 重命名标签参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RenameTagParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "原标签，格式为 type:value")]
+    pub old_tag: String,
+    #[schemars(description = "新标签，格式为 type:value；已存在时与之合并")]
+    pub new_tag: String,
+    #[schemars(description = "为 true 时只返回将被修改的文件，不做修改，默认 false")]
+    pub dry_run: Option<bool>,
+}
+
+/// 按通配符移除标签参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RemoveTagsMatchingParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "标签通配符模式，* 匹配任意字符，? 匹配单个字符，如 status:wip*")]
+    pub pattern: String,
+}
+
+/// 按路径统计标签参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TagsForPathParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "路径 glob，匹配相对于项目根目录的路径，如 src/api/**；以 / 结尾时匹配该目录下所有文件")]
+    pub path_glob: String,
+}
+
+/// 重命名标签类型参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RenameTagTypeParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "原标签类型，如 cat")]
+    pub old_type: String,
+    #[schemars(description = "新标签类型，如 category")]
+    pub new_type: String,
+}
+
+/// 合并标签参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct MergeTagsParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "要合并的标签列表")]
+    pub source_tags: Vec<String>,
+    #[schemars(description = "合并后的目标标签，格式为 type:value")]
+    pub target_tag: String,
+    #[schemars(description = "为 true 时只返回将被修改的文件，不做修改，默认 false")]
+    pub dry_run: Option<bool>,
+}
+
+/// 一条关联关系的端点
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct RelationPair {
+    pub from: String,
+    pub to: String,
+}
+
+/// 当前生效的关联关系配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RelationConfig {
+    /// 关联描述必须完整匹配的正则表达式
+    pub description_pattern: Option<String>,
+    /// 每个源文件的关联数量上限
+    pub max_relations_per_file: Option<usize>,
+    /// 是否检测重复的关联描述
+    pub warn_duplicate_descriptions: bool,
+}
+
+/// 批量添加关联的结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct BulkRelationReport {
+    pub created: usize,
+    /// 因指向自身或已存在而跳过的关联
+    pub skipped: Vec<RelationPair>,
+}
+
+/// 无效关联清理计划
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct RelationCleanupPlan {
+    /// 将被移除的关联关系
+    pub removed: Vec<RelationPair>,
+    /// 关联被修改的源文件
+    pub affected_files: Vec<String>,
+}
+
+/// 标签合并（重命名）计划
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TagMergePlan {
+    /// 被合并的标签（已排除目标标签本身）
+    pub sources: Vec<String>,
+    pub target: String,
+    /// 带有任一源标签、将被修改的文件
+    pub affected_files: Vec<String>,
+}
+
+/// 导出到文件参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExportToFileParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "导出文件名（相对于数据目录下的 exports/），如 snapshot.json")]
+    pub file_name: String,
+}
+
+/// 导入 CSV 标签参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ImportTagsCsvParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "内联 CSV 内容，每行 file_path,tag，可带表头")]
+    pub content: Option<String>,
+    #[schemars(description = "CSV 文件路径（相对于项目根目录），与 content 二选一")]
+    pub csv_file: Option<String>,
+}
+
+/// 从 JSON Lines 导入标签参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ImportTagsJsonlParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "内联 JSON Lines 内容，每行一个 {\"file_path\": ..., \"tags\": [...]} 记录")]
+    pub content: Option<String>,
+    #[schemars(description = "JSON Lines 文件路径（相对于项目根目录），与 content 二选一；逐行读取，适合大文件")]
+    pub jsonl_file: Option<String>,
+    #[schemars(description = "每累计多少条有效记录写入一次 tags.json，默认 10000")]
+    pub batch_size: Option<usize>,
+}
+
+/// JSON Lines 中的一条标签记录
+#[derive(Debug, Clone, Deserialize)]
+pub struct JsonlTagRecord {
+    pub file_path: String,
+    pub tags: Vec<String>,
+}
+
+/// JSON Lines 标签导入报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct JsonlImportReport {
+    /// 读取的非空行数
+    pub lines: usize,
+    /// 通过校验的记录数
+    pub accepted: usize,
+    /// 新增的标签数量
+    pub imported: usize,
+    /// 写入 tags.json 的次数
+    pub flushes: usize,
+    pub rejected: Vec<RejectedRecord>,
+}
+
+/// 文件关联度数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct FileDegree {
+    pub path: String,
+    pub degree: usize,
+}
+
+/// 内容在标注后发生变化的文件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct StaleMetadata {
+    pub file: String,
+    /// 最近一次添加标签或注释时的内容哈希
+    pub recorded_hash: String,
+    /// 当前内容哈希，文件已删除时为空
+    pub current_hash: Option<String>,
+}
+
+/// 关联描述及其使用次数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct DescriptionCount {
+    pub description: String,
+    pub count: usize,
+}
+
+/// 审计日志条目，对应 `audit.jsonl` 中的一行
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    /// 执行的工具名
+    pub operation: String,
+    /// 操作涉及的文件，按模式或全项目执行的批量操作为空
+    #[serde(default)]
+    pub files: Vec<String>,
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 分页后的审计日志
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct AuditLogPage {
+    /// 时间过滤后、分页前的条目总数
+    pub total: usize,
+    pub entries: Vec<AuditEntry>,
+}
+
+/// 获取审计日志参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AuditLogParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "可选：起始时间（含），格式 YYYY-MM-DD 或 RFC 3339")]
+    pub since: Option<String>,
+    #[schemars(description = "可选：结束时间（含），格式 YYYY-MM-DD（包含当天全部记录）或 RFC 3339")]
+    pub until: Option<String>,
+    #[schemars(description = "按时间先后排序后跳过的条目数，默认 0")]
+    pub offset: Option<usize>,
+    #[schemars(description = "返回的最大条目数，默认不限制")]
+    pub limit: Option<usize>,
+}
+
+/// 与给定文件共享标签的文件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SharedTagsFile {
+    pub path: String,
+    pub shared_tags: usize,
+}
+
+/// 查找共享标签文件参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FilesSharingTagsParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "文件路径（相对于项目根目录）")]
+    pub file_path: String,
+    #[schemars(description = "最少共享标签数，默认 1")]
+    pub min_shared: Option<usize>,
+}
+
+/// 工具的成功响应：数据的字段之外附加 `success: true`
+///
+/// 数据必须序列化为 JSON 对象，数组、布尔值等需先包装为结果结构
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct DataResponse<T> {
+    pub success: bool,
+    #[serde(flatten)]
+    pub data: T,
+}
+
+/// 修改类工具的成功响应
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SuccessResponse {
+    pub message: String,
+    /// 操作成功但需要提醒的问题（如 Markdown 格式、重复的关联描述）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warnings: Option<Vec<String>>,
+}
+
+impl SuccessResponse {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self { message: message.into(), warnings: None }
+    }
+
+    pub fn with_warnings(message: impl Into<String>, warnings: Vec<String>) -> Self {
+        Self { message: message.into(), warnings: Some(warnings) }
+    }
+}
+
+/// 添加标签的响应
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AddTagsResponse {
+    pub message: String,
+    /// 新增的标签
+    pub added: Vec<String>,
+    /// 文件已有、未重复添加的标签
+    pub already_present: Vec<String>,
+}
+
+/// 撤销操作的响应
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UndoResponse {
+    pub message: String,
+    /// 被撤销的操作名称，没有可撤销的操作时为 null
+    pub operation: Option<String>,
+}
+
+/// 批量修改的计划响应
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PlanResponse<T> {
+    pub dry_run: bool,
+    /// （将）受影响的数量
+    pub changes: usize,
+    pub plan: T,
+}
+
+/// 错误响应，所有工具出错时都返回该结构
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ErrorResponse {
+    pub error: ErrorBody,
+}
+
+/// 错误详情
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ErrorBody {
+    pub code: String,
+    pub message: String,
+    pub suggestion: Option<String>,
+    /// 查询解析错误的出错位置（字符偏移），便于客户端高亮
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<usize>,
+}
+
+/// 归档标签的结果
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ArchivedTagsResult {
+    pub archived: Vec<String>,
+}
+
+/// 取消归档的结果
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RestoredTagsResult {
+    pub restored: Vec<String>,
+}
+
+/// 按通配符移除标签的结果
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RemovedTagsResult {
+    pub removed: usize,
+    pub affected_files: Vec<String>,
+}
+
+/// 单个文件的标签（已排序）
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FileTagsResult {
+    pub tags: Vec<String>,
+}
+
+/// 文件是否带有指定标签
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HasTagResult {
+    pub has_tag: bool,
+}
+
+/// 文件路径 -> 标签列表
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TagsByFile {
+    pub files: BTreeMap<String, Vec<String>>,
+}
+
+/// 标签类型 -> 取值列表
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TagsByType {
+    pub tag_types: BTreeMap<String, Vec<String>>,
+}
+
+/// 标签 -> 带有该标签的文件数
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TagUsage {
+    pub tags: BTreeMap<String, usize>,
+}
+
+/// 经常一起出现的标签
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CooccurringTags {
+    pub tags: Vec<TagCount>,
+}
+
+/// 标签查询建议
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct QuerySuggestions {
+    pub suggestions: Vec<TagSuggestion>,
+}
+
+/// 查询结果中每个文件匹配的标签
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct QueryExplanation {
+    pub matches: Vec<QueryMatchExplanation>,
+}
+
+/// 当前生效的标签模式，未配置时为 null
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TagSchemaResult {
+    pub schema: Option<TagSchema>,
+}
+
+/// 标签取值 -> 文件列表
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FileGroups {
+    pub groups: BTreeMap<String, Vec<String>>,
+}
+
+/// 文件路径列表（已排序）
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FileList {
+    pub files: Vec<String>,
+}
+
+/// 服务器缓存的项目路径
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LoadedProjects {
+    pub projects: Vec<String>,
+}
+
+/// 内容已变化的文件
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StaleFiles {
+    pub files: Vec<StaleMetadata>,
+}
+
+/// 与给定文件共享标签的文件
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SharedTagsFiles {
+    pub files: Vec<SharedTagsFile>,
+}
+
+/// 推荐的相关文件
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RelatedFiles {
+    pub files: Vec<RelatedFile>,
+}
+
+/// 按关联数量排序的文件
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RankedFiles {
+    pub files: Vec<FileDegree>,
+}
+
+/// 文件的出向关联
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RelationList {
+    pub relations: Vec<Relation>,
+}
+
+/// 源文件 -> 关联列表
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RelationMap {
+    pub relations: BTreeMap<String, Vec<Relation>>,
+}
+
+/// 指向文件的入向关联
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct IncomingRelations {
+    pub relations: Vec<IncomingRelation>,
+}
+
+/// 关联边列表
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EdgeList {
+    pub edges: Vec<GraphEdge>,
+}
+
+/// 加权最短路径，不可达时为 null
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WeightedPathResult {
+    pub path: Option<WeightedPath>,
+}
+
+/// 关联描述及其使用次数
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RelationVocabulary {
+    pub descriptions: Vec<DescriptionCount>,
+}
+
+/// 互相连通的文件组
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RelationComponents {
+    pub components: Vec<Vec<String>>,
+}
+
+/// 批量修改的条目数
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ChangedCount {
+    pub changed: usize,
+}
+
+/// 批量移除的条目数
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RemovedCount {
+    pub removed: usize,
+}
+
+/// 批量替换的条目数
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReplacedCount {
+    pub replaced: usize,
+}
+
+/// 从备份恢复的数据文件
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RecoveredFiles {
+    pub recovered: Vec<String>,
+}
+
+/// 导出文件的路径
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ExportedFile {
+    pub path: String,
+}
+
+/// 导出的 CSV 内容
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CsvContent {
+    pub content: String,
+}
+
+/// 导出的 DOT 图
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DotExport {
+    pub dot: String,
+    /// 以某个文件为根导出时，是否因节点数量上限被截断
+    pub truncated: bool,
+}
+
+/// 导出的 GraphML 文档
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GraphmlExport {
+    pub graphml: String,
+}
+
+/// 获取工具输出结构参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DescribeOutputsParams {
+    #[schemars(description = "可选：只返回该工具的输出结构，默认返回全部")]
+    pub tool: Option<String>,
+}
+
+/// 相关文件推荐及其相关度
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RelatedFile {
+    pub path: String,
+    pub score: f64,
+}
+
+/// 文件自身的标签与沿出向关联继承的标签
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct EffectiveTags {
+    /// 自身标签与继承标签的并集，已排序
+    pub tags: Vec<String>,
+    /// 文件自身的标签
+    pub own: Vec<String>,
+    /// 仅通过关联继承的标签（不含自身已有的标签）
+    pub inherited: Vec<InheritedTag>,
+}
+
+/// 继承的标签及其来源文件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct InheritedTag {
+    pub tag: String,
+    /// 可达且带有该标签的文件，按路径排序
+    pub sources: Vec<String>,
+}
+
+/// 获取有效标签参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct EffectiveTagsParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "文件路径（相对于项目根目录）")]
+    pub file_path: String,
+    #[schemars(description = "沿出向关联继承标签的最大深度，默认 3；0 表示只返回自身标签")]
+    pub max_depth: Option<usize>,
+}
+
+/// 更新项目配置参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SetConfigParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "要修改的配置项，如 {\"max_relations_per_file\": 20}；未提供的配置项保持不变")]
+    pub config: serde_json::Value,
+}
+
+/// 列出注释参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ListCommentsParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "可选：只返回包含该子串的注释（不区分大小写）")]
+    pub contains: Option<String>,
+    #[schemars(description = "按文件路径排序后跳过的条目数，默认 0")]
+    pub offset: Option<usize>,
+    #[schemars(description = "返回的最大条目数，默认不限制")]
+    pub limit: Option<usize>,
+}
+
+/// 分页后的注释列表
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct CommentList {
+    /// 过滤后（分页前）的注释总数
+    pub total: usize,
+    /// 文件路径 -> 注释
+    pub comments: BTreeMap<String, String>,
+}
+
+/// 关联排行参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RelationRankParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "返回的最大文件数量，默认 10")]
+    pub limit: Option<usize>,
+}
+
+/// 共现标签推荐参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SuggestTagsParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "已有标签列表，统计同时带有这些标签的文件中的其他标签")]
+    pub tags: Vec<String>,
+    #[schemars(description = "返回的最大推荐数量，默认 10")]
+    pub limit: Option<usize>,
+}
+
+/// 按标签类型分组参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GroupByTagTypeParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "标签类型，如 layer")]
+    pub tag_type: String,
+    #[schemars(description = "是否在 \"(untagged)\" 键下列出没有该类型标签的项目文件，默认 false")]
+    pub include_untagged: Option<bool>,
+}
+
 /// 项目路径参数
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct ProjectPathParams {
@@ -101,27 +1301,212 @@ pub struct ProjectPathParams {
     pub project_path: String,
 }
 
+/// 获取未标记文件的参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct UntaggedFilesParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "只扫描该子目录（相对于项目根目录），默认扫描整个项目")]
+    pub under: Option<String>,
+    #[schemars(description = "返回的最大文件数量，达到后停止扫描，默认不限制")]
+    pub limit: Option<usize>,
+}
+
+/// 搜索字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchField {
+    Tags,
+    Comments,
+    Relations,
+    /// 文件路径，仅在显式指定或模糊匹配时参与搜索
+    Path,
+}
+
+impl SearchField {
+    /// 默认搜索字段
+    pub const ALL: [SearchField; 3] = [SearchField::Tags, SearchField::Comments, SearchField::Relations];
+}
+
+/// 搜索选项
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    /// 限定参与匹配的字段，为空时使用默认字段
+    pub fields: Vec<SearchField>,
+    /// 模糊匹配阈值，为 None 时不启用模糊匹配
+    pub fuzzy_threshold: Option<f64>,
+    /// 子串匹配是否区分大小写，默认不区分
+    pub case_sensitive: bool,
+}
+
+/// 搜索命中信息
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SearchMatch {
+    pub field: SearchField,
+    pub snippet: String,
+    /// 模糊匹配的相似度，精确/子串匹配时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+}
+
+/// 搜索结果
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SearchResult {
+    pub file_info: FileInfo,
+    pub matches: Vec<SearchMatch>,
+    /// 相关度，精确/子串匹配为 1.0，模糊匹配取最高相似度
+    pub score: f64,
+}
+
+/// 搜索响应
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SearchResponse {
+    pub total: usize,
+    pub files: Vec<SearchResult>,
+    /// 命中文件中各标签出现的文件数
+    pub tag_breakdown: BTreeMap<String, usize>,
+}
+
+/// 单个文件上某个标签的元数据
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct TagMeta {
+    /// 标签被添加的时间，早期数据中的标签没有记录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added_at: Option<DateTime<Utc>>,
+}
+
+impl TagMeta {
+    /// 以当前时间作为添加时间
+    pub fn now() -> Self {
+        Self { added_at: Some(Utc::now()) }
+    }
+}
+
+/// 带元数据的标签
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct TagWithMeta {
+    pub tag: String,
+    #[serde(flatten)]
+    pub meta: TagMeta,
+}
+
+/// 标签计数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+/// 带相似度的查询建议
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TagSuggestion {
+    pub tag: String,
+    pub score: f64,
+}
+
 /// 查询结果
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct QueryResult {
     pub files: Vec<String>,
     pub total: usize,
 }
 
 /// 标签统计信息
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TagStats {
-    pub tag_types: HashMap<String, Vec<String>>,
+    pub tag_types: BTreeMap<String, Vec<String>>,
     pub total_files: usize,
     pub total_tags: usize,
 }
 
+/// 详细标签统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TagDetailedStats {
+    /// 有标签的文件数量
+    pub total_files: usize,
+    /// 不同标签的数量
+    pub total_tags: usize,
+    /// 各标签类型的使用次数（文件-标签对的数量）
+    pub type_counts: BTreeMap<String, usize>,
+    /// 有标签的文件平均标签数
+    pub average_tags_per_file: f64,
+    /// 使用最多的标签，按次数降序、标签升序排列
+    pub top_tags: Vec<TagCount>,
+}
+
 /// 系统状态信息
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SystemStatus {
     pub total_files: usize,
     pub tagged_files: usize,
     pub commented_files: usize,
     pub total_relations: usize,
     pub tag_stats: TagStats,
+    pub tag_details: TagDetailedStats,
+    /// 服务器启动以来的操作计数
+    #[serde(default)]
+    pub runtime_metrics: RuntimeMetrics,
+    /// 加载失败的数据文件
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub data_file_errors: Vec<DataFileError>,
+}
+
+/// 加载失败的数据文件及错误信息
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct DataFileError {
+    pub file: String,
+    pub error: String,
+}
+
+/// 可从备份恢复的数据
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupTarget {
+    Tags,
+    Comments,
+    Relations,
+}
+
+impl BackupTarget {
+    /// 对应的数据文件名
+    pub fn file_name(self) -> &'static str {
+        match self {
+            BackupTarget::Tags => "tags.json",
+            BackupTarget::Comments => "comments.json",
+            BackupTarget::Relations => "relations.json",
+        }
+    }
+}
+
+/// 恢复备份参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RestoreBackupParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "要恢复的数据：tags、comments 或 relations")]
+    pub target: BackupTarget,
+    #[schemars(description = "备份版本，0（默认）为最近一次保存前的备份，1 为更早一次，依此类推；可用版本数由配置 backup_count 决定")]
+    pub version: Option<usize>,
+}
+
+/// 从备份恢复数据文件参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RecoverFromBackupParams {
+    #[schemars(description = "项目根目录路径")]
+    pub project_path: String,
+    #[schemars(description = "要恢复的数据文件：tags.json、comments.json 或 relations.json；为空时恢复所有加载失败的文件")]
+    pub file_name: Option<String>,
+}
+
+/// 服务器启动以来各类操作的调用次数（包含失败的调用）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RuntimeMetrics {
+    /// 标签查询、复杂查询和综合搜索
+    pub queries: u64,
+    /// 添加标签
+    pub tag_adds: u64,
+    /// 添加、更新或删除注释
+    pub comment_edits: u64,
+    /// 添加、删除或修改关联关系（含批量操作）
+    pub relation_edits: u64,
 }
@@ -1,24 +1,104 @@
 use crate::error::{CodeNexusError, Result};
+use crate::i18n::Message;
 use crate::managers::{TagManager, CommentManager, RelationManager};
-use crate::models::{FileInfo, QueryResult, SystemStatus, TagStats};
+use crate::models::{DirectedRelation, EffectiveTags, FileInfo, InheritedTag, QueryMatchExplanation, DEFAULT_RELATION_WEIGHT, MultiFilter, QueryResult, QueryValidation, SearchField, SearchMatch, SearchOptions, SearchResponse, SearchResult, SystemStatus, TagStats, TagSuggestion};
+use crate::query::parser::parse_query;
+use crate::utils::{compile_path_glob, fuzzy_score};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::RwLock;
 use tracing::debug;
 
+/// 搜索片段中关键词前后保留的字符数
+const SNIPPET_RADIUS: usize = 40;
+
+/// 子串命中少于该数量时才启用模糊匹配
+const FUZZY_FALLBACK_MIN_HITS: usize = 5;
+
+/// 默认模糊匹配阈值
+pub const DEFAULT_FUZZY_THRESHOLD: f64 = 0.85;
+
+/// 查询建议的最大数量
+const MAX_SUGGESTIONS: usize = 10;
+
+/// 截取关键词附近的文本片段，未找到关键词时返回开头部分
+fn make_snippet(text: &str, keyword: &str, radius: usize, case_sensitive: bool) -> String {
+    let fold = |c: char| if case_sensitive { c } else { c.to_lowercase().next().unwrap_or(c) };
+    let chars: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = chars.iter().map(|c| fold(*c)).collect();
+    let needle: Vec<char> = keyword.chars().map(fold).collect();
+
+    let position = if needle.is_empty() || needle.len() > lower.len() {
+        None
+    } else {
+        lower.windows(needle.len()).position(|window| window == needle.as_slice())
+    };
+
+    let (start, end) = match position {
+        Some(pos) => (pos.saturating_sub(radius), (pos + needle.len() + radius).min(chars.len())),
+        None => (0, (radius * 2).min(chars.len())),
+    };
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push_str("...");
+    }
+    snippet.extend(&chars[start..end]);
+    if end < chars.len() {
+        snippet.push_str("...");
+    }
+    snippet
+}
+
+/// 从已持有读锁的各管理器中汇总单个文件的完整信息
+fn build_file_info(
+    tag_manager: &TagManager,
+    comment_manager: &CommentManager,
+    relation_manager: &RelationManager,
+    file_path: &str,
+) -> FileInfo {
+    let tags = tag_manager.get_file_tags(file_path);
+    let comment = comment_manager.get_comment(file_path);
+    let relations: Vec<DirectedRelation> = relation_manager
+        .get_file_relations(file_path)
+        .into_iter()
+        .map(DirectedRelation::outgoing)
+        .collect();
+    let incoming_relations: Vec<DirectedRelation> = relation_manager
+        .get_incoming_relations(file_path)
+        .into_iter()
+        .map(DirectedRelation::incoming)
+        .collect();
+
+    let tracked = !tags.is_empty()
+        || comment.is_some()
+        || !relations.is_empty()
+        || !incoming_relations.is_empty();
+
+    FileInfo {
+        path: file_path.to_string(),
+        tags,
+        comment,
+        relations,
+        incoming_relations,
+        tracked,
+    }
+}
+
 /// 查询引擎
 #[derive(Debug)]
 pub struct QueryEngine {
-    tag_manager: Arc<Mutex<TagManager>>,
-    comment_manager: Arc<Mutex<CommentManager>>,
-    relation_manager: Arc<Mutex<RelationManager>>,
+    tag_manager: Arc<RwLock<TagManager>>,
+    comment_manager: Arc<RwLock<CommentManager>>,
+    relation_manager: Arc<RwLock<RelationManager>>,
 }
 
 impl QueryEngine {
     /// 创建新的查询引擎
     pub fn new(
-        tag_manager: Arc<Mutex<TagManager>>,
-        comment_manager: Arc<Mutex<CommentManager>>,
-        relation_manager: Arc<Mutex<RelationManager>>,
+        tag_manager: Arc<RwLock<TagManager>>,
+        comment_manager: Arc<RwLock<CommentManager>>,
+        relation_manager: Arc<RwLock<RelationManager>>,
     ) -> Self {
         Self {
             tag_manager,
@@ -28,80 +108,91 @@ impl QueryEngine {
     }
 
     /// 执行标签查询
-    pub async fn execute_tag_query(&self, query: &str) -> Result<QueryResult> {
-        let tag_manager = self.tag_manager.lock().await;
-        let files = tag_manager.query_files_by_tags(query)?;
-        
+    ///
+    /// `path_glob` 用于对结果做路径后置过滤，匹配规范化后的相对路径
+    pub async fn execute_tag_query(&self, query: &str, path_glob: Option<&str>, count_only: bool) -> Result<QueryResult> {
+        let matcher = path_glob.map(compile_path_glob).transpose()?;
+
+        let tag_manager = self.tag_manager.read().await;
+        if count_only {
+            let total = tag_manager.count_files_by_tags(query, |file| {
+                matcher.as_ref().is_none_or(|matcher| matcher.is_match(file))
+            })?;
+            return Ok(QueryResult { files: Vec::new(), total });
+        }
+
+        let mut files = tag_manager.query_files_by_tags(query)?;
+        if let Some(matcher) = matcher {
+            files.retain(|file| matcher.is_match(file));
+        }
+
         Ok(QueryResult {
             total: files.len(),
             files,
         })
     }
 
+    /// 执行标签查询并说明每个文件匹配的标签，`path_glob` 含义与 `execute_tag_query` 相同
+    pub async fn explain_tag_query(&self, query: &str, path_glob: Option<&str>) -> Result<Vec<QueryMatchExplanation>> {
+        let matcher = path_glob.map(compile_path_glob).transpose()?;
+        let explained = self.tag_manager.read().await.explain_query(query)?;
+        Ok(explained
+            .into_iter()
+            .filter(|(path, _)| matcher.as_ref().is_none_or(|matcher| matcher.is_match(path)))
+            .map(|(path, matched_tags)| QueryMatchExplanation { path, matched_tags })
+            .collect())
+    }
+
     /// 获取文件完整信息
     pub async fn get_file_info(&self, file_path: &str) -> Result<FileInfo> {
-        // 并行获取各种信息
-        let (tags, comment, relations, incoming_relations) = tokio::join!(
-            async {
-                let tag_manager = self.tag_manager.lock().await;
-                tag_manager.get_file_tags(file_path)
-            },
-            async {
-                let comment_manager = self.comment_manager.lock().await;
-                comment_manager.get_comment(file_path)
-            },
-            async {
-                let relation_manager = self.relation_manager.lock().await;
-                relation_manager.get_file_relations(file_path)
-            },
-            async {
-                let relation_manager = self.relation_manager.lock().await;
-                relation_manager.get_incoming_relations(file_path)
-            }
-        );
-
-        Ok(FileInfo {
-            path: file_path.to_string(),
-            tags,
-            comment,
-            relations,
-            incoming_relations,
-        })
+        let tag_manager = self.tag_manager.read().await;
+        let comment_manager = self.comment_manager.read().await;
+        let relation_manager = self.relation_manager.read().await;
+        Ok(build_file_info(&tag_manager, &comment_manager, &relation_manager, file_path))
     }
 
     /// 复合查询：结合标签和关联关系
+    ///
+    /// 两个条件都提供时返回同时满足两者的文件（交集）；只提供一个时返回该条件的结果。
+    /// 关联关键词匹配关联描述，命中的是关联的源文件。
     pub async fn execute_complex_query(
         &self,
         tag_query: Option<&str>,
         relation_keyword: Option<&str>,
     ) -> Result<QueryResult> {
-        let mut result_files = Vec::new();
+        if tag_query.is_none() && relation_keyword.is_none() {
+            return Err(CodeNexusError::InvalidQuerySyntax(Message::MissingQueryCondition));
+        }
+
+        let mut result_files: Option<Vec<String>> = None;
 
         // 如果有标签查询
         if let Some(query) = tag_query {
-            let tag_manager = self.tag_manager.lock().await;
-            result_files = tag_manager.query_files_by_tags(query)?;
+            let tag_manager = self.tag_manager.read().await;
+            result_files = Some(tag_manager.query_files_by_tags(query)?);
         }
 
         // 如果有关联关系关键词搜索
         if let Some(keyword) = relation_keyword {
-            let relation_manager = self.relation_manager.lock().await;
-            let relation_results = relation_manager.query_relations_by_description(keyword);
-            
-            let relation_files: Vec<String> = relation_results
+            let relation_manager = self.relation_manager.read().await;
+            let relation_files: Vec<String> = relation_manager
+                .query_relations_by_description(keyword, false)
                 .into_iter()
                 .map(|(from_file, _)| from_file)
                 .collect();
 
-            if result_files.is_empty() {
-                result_files = relation_files;
-            } else {
+            result_files = Some(match result_files {
                 // 求交集
-                result_files.retain(|file| relation_files.contains(file));
-            }
+                Some(mut files) => {
+                    files.retain(|file| relation_files.contains(file));
+                    files
+                }
+                None => relation_files,
+            });
         }
 
         // 去重并排序
+        let mut result_files = result_files.unwrap_or_default();
         result_files.sort();
         result_files.dedup();
 
@@ -111,25 +202,70 @@ impl QueryEngine {
         })
     }
 
+    /// 多条件过滤：返回同时满足全部已设置条件的文件（已排序）
+    ///
+    /// 提供 `tag_query` 时以其结果为候选，否则以所有带有元数据的文件为候选
+    /// （包括只作为关联目标出现的文件）
+    pub async fn multi_filter(&self, filter: &MultiFilter) -> Result<QueryResult> {
+        if filter.is_empty() {
+            return Err(CodeNexusError::InvalidQuerySyntax(Message::MissingFilter));
+        }
+        let matcher = filter.path_glob.as_deref().map(compile_path_glob).transpose()?;
+
+        let mut files: Vec<String> = match filter.tag_query.as_deref() {
+            Some(query) => self.tag_manager.read().await.query_files_by_tags(query)?,
+            None => {
+                let mut files: std::collections::BTreeSet<String> = self.get_tracked_files().await.into_iter().collect();
+                files.extend(self.relation_manager.read().await.get_files_in_relations());
+                files.into_iter().collect()
+            }
+        };
+
+        if let Some(matcher) = matcher {
+            files.retain(|file| matcher.is_match(file));
+        }
+
+        if let Some(has_comment) = filter.has_comment {
+            let comment_manager = self.comment_manager.read().await;
+            files.retain(|file| comment_manager.has_comment(file) == has_comment);
+        }
+
+        if filter.min_incoming.is_some() || filter.min_outgoing.is_some() {
+            let relation_manager = self.relation_manager.read().await;
+            files.retain(|file| {
+                filter.min_incoming.is_none_or(|min| relation_manager.get_incoming_relations(file).len() >= min)
+                    && filter.min_outgoing.is_none_or(|min| relation_manager.get_file_relations(file).len() >= min)
+            });
+        }
+
+        files.sort();
+        files.dedup();
+        Ok(QueryResult {
+            total: files.len(),
+            files,
+        })
+    }
+
     /// 获取系统状态
     pub async fn get_system_status(&self) -> Result<SystemStatus> {
         let (tag_stats, comment_stats, relation_stats) = tokio::join!(
             async {
-                let tag_manager = self.tag_manager.lock().await;
+                let tag_manager = self.tag_manager.read().await;
                 tag_manager.get_stats()
             },
             async {
-                let comment_manager = self.comment_manager.lock().await;
+                let comment_manager = self.comment_manager.read().await;
                 comment_manager.get_stats()
             },
             async {
-                let relation_manager = self.relation_manager.lock().await;
+                let relation_manager = self.relation_manager.read().await;
                 relation_manager.get_stats()
             }
         );
 
-        let tag_manager = self.tag_manager.lock().await;
+        let tag_manager = self.tag_manager.read().await;
         let all_tags = tag_manager.get_all_tags();
+        let tag_details = tag_manager.get_detailed_stats();
 
         let tag_stats_info = TagStats {
             tag_types: all_tags,
@@ -143,85 +279,248 @@ impl QueryEngine {
             commented_files: comment_stats.0,
             total_relations: relation_stats.1,
             tag_stats: tag_stats_info,
+            tag_details,
+            runtime_metrics: Default::default(),
+            data_file_errors: Vec::new(),
         })
     }
 
     /// 搜索文件（综合搜索）
-    pub async fn search_files(&self, keyword: &str) -> Result<Vec<FileInfo>> {
-        let mut results = Vec::new();
-        let mut file_set = std::collections::HashSet::new();
+    ///
+    /// 默认在标签、注释和关联关系描述中做大小写不敏感的子串匹配；启用模糊匹配后，
+    /// 若子串命中的文件少于 `FUZZY_FALLBACK_MIN_HITS` 个，再对文件路径和标签做相似度匹配。
+    /// 结果按相关度降序、文件路径升序排列。
+    pub async fn search_files(&self, keyword: &str, options: &SearchOptions) -> Result<SearchResponse> {
+        let fields = if options.fields.is_empty() { &SearchField::ALL[..] } else { &options.fields[..] };
+        let mut file_matches: HashMap<String, Vec<SearchMatch>> = HashMap::new();
+
+        // 搜索标签
+        if fields.contains(&SearchField::Tags) {
+            let tag_manager = self.tag_manager.read().await;
+            for (file_path, tag) in tag_manager.search_tags(keyword, options.case_sensitive) {
+                file_matches.entry(file_path).or_default().push(SearchMatch {
+                    field: SearchField::Tags,
+                    snippet: tag,
+                    score: None,
+                });
+            }
+        }
 
         // 搜索注释
-        let comment_manager = self.comment_manager.lock().await;
-        let comment_results = comment_manager.search_comments(keyword);
-        
-        for (file_path, _) in comment_results {
-            file_set.insert(file_path);
+        if fields.contains(&SearchField::Comments) {
+            let comment_manager = self.comment_manager.read().await;
+            for (file_path, comment) in comment_manager.search_comments(keyword, options.case_sensitive) {
+                file_matches.entry(file_path).or_default().push(SearchMatch {
+                    field: SearchField::Comments,
+                    snippet: make_snippet(&comment, keyword, SNIPPET_RADIUS, options.case_sensitive),
+                    score: None,
+                });
+            }
         }
-        drop(comment_manager);
 
         // 搜索关联关系描述
-        let relation_manager = self.relation_manager.lock().await;
-        let relation_results = relation_manager.query_relations_by_description(keyword);
-        
-        for (file_path, _) in relation_results {
-            file_set.insert(file_path);
+        if fields.contains(&SearchField::Relations) {
+            let relation_manager = self.relation_manager.read().await;
+            for (file_path, relation) in relation_manager.query_relations_by_description(keyword, options.case_sensitive) {
+                file_matches.entry(file_path).or_default().push(SearchMatch {
+                    field: SearchField::Relations,
+                    snippet: relation.description,
+                    score: None,
+                });
+            }
         }
-        drop(relation_manager);
 
-        // 获取每个文件的完整信息
-        for file_path in file_set {
-            if let Ok(file_info) = self.get_file_info(&file_path).await {
-                results.push(file_info);
+        // 搜索文件路径（仅显式指定时）
+        if fields.contains(&SearchField::Path) {
+            let keyword_lower = keyword.to_lowercase();
+            for file_path in self.get_tracked_files().await {
+                let matched = if options.case_sensitive {
+                    file_path.contains(keyword)
+                } else {
+                    file_path.to_lowercase().contains(&keyword_lower)
+                };
+                if matched {
+                    file_matches.entry(file_path.clone()).or_default().push(SearchMatch {
+                        field: SearchField::Path,
+                        snippet: file_path,
+                        score: None,
+                    });
+                }
             }
         }
 
-        // 按文件路径排序
-        results.sort_by(|a, b| a.path.cmp(&b.path));
+        // 命中过少时回退到模糊匹配
+        if let Some(threshold) = options.fuzzy_threshold {
+            if file_matches.len() < FUZZY_FALLBACK_MIN_HITS {
+                self.collect_fuzzy_matches(keyword, threshold, &options.fields, &mut file_matches).await;
+            }
+        }
 
-        Ok(results)
+        // 一次性持有各管理器的读锁，逐个汇总文件信息
+        let mut results: Vec<SearchResult> = {
+            let tag_manager = self.tag_manager.read().await;
+            let comment_manager = self.comment_manager.read().await;
+            let relation_manager = self.relation_manager.read().await;
+            file_matches
+                .into_iter()
+                .map(|(file_path, matches)| {
+                    let file_info = build_file_info(&tag_manager, &comment_manager, &relation_manager, &file_path);
+                    let score = matches
+                        .iter()
+                        .map(|m| m.score.unwrap_or(1.0))
+                        .fold(0.0, f64::max);
+                    SearchResult { file_info, matches, score }
+                })
+                .collect()
+        };
+
+        // 按相关度降序、文件路径升序排序
+        results.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.file_info.path.cmp(&b.file_info.path))
+        });
+
+        let mut tag_breakdown = std::collections::BTreeMap::new();
+        for tag in results.iter().flat_map(|result| &result.file_info.tags) {
+            *tag_breakdown.entry(tag.clone()).or_insert(0) += 1;
+        }
+
+        Ok(SearchResponse {
+            total: results.len(),
+            files: results,
+            tag_breakdown,
+        })
+    }
+
+    /// 对文件路径和标签做模糊匹配，结果并入 `file_matches`
+    async fn collect_fuzzy_matches(
+        &self,
+        keyword: &str,
+        threshold: f64,
+        fields: &[SearchField],
+        file_matches: &mut HashMap<String, Vec<SearchMatch>>,
+    ) {
+        if fields.is_empty() || fields.contains(&SearchField::Path) {
+            for file_path in self.get_tracked_files().await {
+                if file_matches.contains_key(&file_path) {
+                    continue;
+                }
+                let file_name = file_path.rsplit('/').next().unwrap_or(&file_path);
+                let score = fuzzy_score(keyword, &file_path).max(fuzzy_score(keyword, file_name));
+                if score >= threshold {
+                    file_matches.entry(file_path.clone()).or_default().push(SearchMatch {
+                        field: SearchField::Path,
+                        snippet: file_path,
+                        score: Some(score),
+                    });
+                }
+            }
+        }
+
+        if fields.is_empty() || fields.contains(&SearchField::Tags) {
+            let tag_manager = self.tag_manager.read().await;
+            for (file_path, tag, score) in tag_manager.fuzzy_search_tags(keyword, threshold) {
+                let matches = file_matches.entry(file_path).or_default();
+                if !matches.iter().any(|m| m.field == SearchField::Tags && m.snippet == tag) {
+                    matches.push(SearchMatch {
+                        field: SearchField::Tags,
+                        snippet: tag,
+                        score: Some(score),
+                    });
+                }
+            }
+        }
     }
 
-    /// 获取相关文件推荐
-    pub async fn get_related_files(&self, file_path: &str, max_results: usize) -> Result<Vec<String>> {
-        let mut related_files = std::collections::HashSet::new();
+    /// 获取所有带有元数据的文件（有标签、注释或出向关联关系）
+    pub async fn get_tracked_files(&self) -> Vec<String> {
+        let mut files = std::collections::BTreeSet::new();
+        files.extend(self.tag_manager.read().await.get_tagged_files());
+        files.extend(self.comment_manager.read().await.get_commented_files());
+        files.extend(self.relation_manager.read().await.get_related_files());
+        files.into_iter().collect()
+    }
 
-        // 基于标签的相关性
-        let tag_manager = self.tag_manager.lock().await;
+    /// 获取相关文件推荐，按相关度降序、路径升序排列
+    ///
+    /// 相关度为共同标签数量加上与该文件之间关联（出向和入向）的权重之和
+    pub async fn get_related_files(&self, file_path: &str, max_results: usize) -> Result<Vec<(String, f64)>> {
+        let mut scores: HashMap<String, f64> = HashMap::new();
+
+        // 基于标签的相关性：每个共同标签计 1 分
+        let tag_manager = self.tag_manager.read().await;
         let file_tags = tag_manager.get_file_tags(file_path);
-        
+
         for tag in &file_tags {
             let tag_files = tag_manager.query_files_by_tags(tag)?;
             for tag_file in tag_files {
                 if tag_file != file_path {
-                    related_files.insert(tag_file);
+                    *scores.entry(tag_file).or_default() += 1.0;
                 }
             }
         }
         drop(tag_manager);
 
-        // 基于关联关系的相关性
-        let relation_manager = self.relation_manager.lock().await;
-        let outgoing_relations = relation_manager.get_file_relations(file_path);
-        let incoming_relations = relation_manager.get_incoming_relations(file_path);
-
-        for relation in outgoing_relations {
-            related_files.insert(relation.target);
+        // 基于关联关系的相关性：累加关联权重
+        let relation_manager = self.relation_manager.read().await;
+        for relation in relation_manager.get_file_relations(file_path) {
+            *scores.entry(relation.target.clone()).or_default() += relation.weight();
         }
-
-        for relation in incoming_relations {
-            related_files.insert(relation.target);
+        for relation in relation_manager.get_incoming_relations(file_path) {
+            *scores.entry(relation.source).or_default() += relation.weight.unwrap_or(DEFAULT_RELATION_WEIGHT);
         }
         drop(relation_manager);
+        scores.remove(file_path);
 
-        // 转换为向量并限制结果数量
-        let mut result: Vec<String> = related_files.into_iter().collect();
-        result.sort();
+        // 按相关度排序后限制结果数量
+        let mut result: Vec<(String, f64)> = scores.into_iter().collect();
+        result.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
         result.truncate(max_results);
 
         Ok(result)
     }
 
+    /// 获取文件的有效标签：自身标签加上沿出向关联 `max_depth` 层内可达文件的标签
+    ///
+    /// 复用关联图谱的广度优先遍历，环路中的文件只访问一次
+    pub async fn get_effective_tags(&self, file_path: &str, max_depth: usize) -> Result<EffectiveTags> {
+        let graph = self
+            .relation_manager
+            .read()
+            .await
+            .get_relation_graph(file_path, max_depth, usize::MAX);
+        let reachable: BTreeSet<&str> = graph
+            .relations
+            .values()
+            .flatten()
+            .map(|relation| relation.target.as_str())
+            .filter(|target| *target != file_path)
+            .collect();
+
+        let tag_manager = self.tag_manager.read().await;
+        let own = tag_manager.get_file_tags(file_path);
+        let mut inherited: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for source in reachable {
+            for tag in tag_manager.get_file_tags(source) {
+                if !own.contains(&tag) {
+                    inherited.entry(tag).or_default().push(source.to_string());
+                }
+            }
+        }
+
+        let mut tags: Vec<String> = own.iter().chain(inherited.keys()).cloned().collect();
+        tags.sort();
+        Ok(EffectiveTags {
+            tags,
+            own,
+            inherited: inherited
+                .into_iter()
+                .map(|(tag, sources)| InheritedTag { tag, sources })
+                .collect(),
+        })
+    }
+
     /// 批量获取文件信息
     pub async fn get_batch_file_info(&self, file_paths: &[String]) -> Result<Vec<FileInfo>> {
         let mut results = Vec::new();
@@ -241,36 +540,20 @@ impl QueryEngine {
 
     /// 验证查询语法
     pub fn validate_query_syntax(&self, query: &str) -> Result<()> {
-        if query.trim().is_empty() {
-            return Err(CodeNexusError::InvalidQuerySyntax("查询不能为空".to_string()));
-        }
-
-        // 简单的语法验证
-        let query = query.trim();
-        
-        // 检查 AND 操作符
-        if query.contains(" AND ") {
-            let parts: Vec<&str> = query.split(" AND ").collect();
-            for part in parts {
-                if part.trim().is_empty() {
-                    return Err(CodeNexusError::InvalidQuerySyntax(
-                        "AND 操作符前后不能为空".to_string()
-                    ));
-                }
-            }
-        }
+        parse_query(query).map(|_| ())
+    }
 
-        // 检查标签格式（如果包含冒号）
-        if query.contains(':') && !query.contains(' ') {
-            // 单个标签格式检查
-            if query.split(':').count() != 2 {
-                return Err(CodeNexusError::InvalidQuerySyntax(
-                    "标签格式应为 type:value".to_string()
-                ));
-            }
+    /// 校验查询语法并返回结构化结果
+    pub fn check_query_syntax(&self, query: &str) -> QueryValidation {
+        match self.validate_query_syntax(query) {
+            Ok(()) => QueryValidation { valid: true, error: None, position: None },
+            Err(CodeNexusError::QueryParseError { message, position }) => QueryValidation {
+                valid: false,
+                error: Some(message.localized()),
+                position: Some(position),
+            },
+            Err(e) => QueryValidation { valid: false, error: Some(e.to_string()), position: None },
         }
-
-        Ok(())
     }
 
     /// 获取查询建议
@@ -281,7 +564,7 @@ impl QueryEngine {
             return Ok(suggestions);
         }
 
-        let tag_manager = self.tag_manager.lock().await;
+        let tag_manager = self.tag_manager.read().await;
         let all_tags = tag_manager.get_all_tags();
 
         // 基于标签类型的建议
@@ -302,8 +585,306 @@ impl QueryEngine {
         }
 
         suggestions.sort();
-        suggestions.truncate(10); // 限制建议数量
+        suggestions.truncate(MAX_SUGGESTIONS); // 限制建议数量
+
+        Ok(suggestions)
+    }
+
+    /// 获取带相似度的查询建议
+    ///
+    /// 子串命中的建议相似度为 1.0；启用模糊匹配且子串命中过少时，
+    /// 追加相似度不低于阈值的标签，按相似度降序排列。
+    pub async fn get_scored_query_suggestions(
+        &self,
+        partial_query: &str,
+        fuzzy_threshold: Option<f64>,
+    ) -> Result<Vec<TagSuggestion>> {
+        let mut suggestions: Vec<TagSuggestion> = self
+            .get_query_suggestions(partial_query)
+            .await?
+            .into_iter()
+            .map(|tag| TagSuggestion { tag, score: 1.0 })
+            .collect();
+
+        let threshold = match fuzzy_threshold {
+            Some(threshold) if suggestions.len() < FUZZY_FALLBACK_MIN_HITS && !partial_query.is_empty() => threshold,
+            _ => return Ok(suggestions),
+        };
+
+        let tag_manager = self.tag_manager.read().await;
+        for (tag_type, tag_values) in tag_manager.get_all_tags() {
+            for value in tag_values {
+                let full_tag = format!("{}:{}", tag_type, value);
+                if suggestions.iter().any(|s| s.tag == full_tag) {
+                    continue;
+                }
+                let score = fuzzy_score(partial_query, &full_tag).max(fuzzy_score(partial_query, &value));
+                if score >= threshold {
+                    suggestions.push(TagSuggestion { tag: full_tag, score });
+                }
+            }
+        }
+
+        suggestions.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.tag.cmp(&b.tag)));
+        suggestions.truncate(MAX_SUGGESTIONS);
 
         Ok(suggestions)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ImportMode, Relation, RelationDirection};
+    use crate::storage::JsonStorage;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    /// 创建带有初始标签和关联关系的查询引擎
+    async fn setup(tags: &[(&str, &str)], relations: &[(&str, &str, &str)]) -> (TempDir, QueryEngine) {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = JsonStorage::new(temp_dir.path().join(".codenexus"));
+        storage.initialize().await.unwrap();
+
+        let mut tag_manager = TagManager::new(storage.clone());
+        tag_manager.initialize().await.unwrap();
+        let mut file_tags: HashMap<String, Vec<String>> = HashMap::new();
+        for (file, tag) in tags {
+            file_tags.entry(file.to_string()).or_default().push(tag.to_string());
+        }
+        tag_manager.import_tags(file_tags, HashMap::new(), ImportMode::Replace).await.unwrap();
+
+        let mut relation_manager = RelationManager::new(storage.clone());
+        relation_manager.initialize().await.unwrap();
+        let mut file_relations: HashMap<String, Vec<Relation>> = HashMap::new();
+        for (from, to, description) in relations {
+            file_relations.entry(from.to_string()).or_default().push(Relation {
+                target: to.to_string(),
+                description: description.to_string(),
+                weight: None,
+            });
+        }
+        relation_manager.import_relations(file_relations, ImportMode::Replace).await.unwrap();
+
+        let engine = QueryEngine::new(
+            Arc::new(RwLock::new(tag_manager)),
+            Arc::new(RwLock::new(CommentManager::new(storage))),
+            Arc::new(RwLock::new(relation_manager)),
+        );
+        (temp_dir, engine)
+    }
+
+    #[tokio::test]
+    async fn test_execute_complex_query() {
+        let (_temp_dir, engine) = setup(
+            &[("a.rs", "layer:api"), ("b.rs", "layer:api"), ("c.rs", "layer:db")],
+            &[("a.rs", "c.rs", "调用数据库"), ("c.rs", "d.rs", "读取数据库配置")],
+        )
+        .await;
+
+        let tag_only = engine.execute_complex_query(Some("layer:api"), None).await.unwrap();
+        assert_eq!(tag_only.files, vec!["a.rs".to_string(), "b.rs".to_string()]);
+
+        let relation_only = engine.execute_complex_query(None, Some("数据库")).await.unwrap();
+        assert_eq!(relation_only.files, vec!["a.rs".to_string(), "c.rs".to_string()]);
+
+        let combined = engine.execute_complex_query(Some("layer:api"), Some("数据库")).await.unwrap();
+        assert_eq!(combined.files, vec!["a.rs".to_string()]);
+        assert_eq!(combined.total, 1);
+
+        // 标签查询无结果时交集为空，而不是退化为关联结果
+        let empty = engine.execute_complex_query(Some("layer:ui"), Some("数据库")).await.unwrap();
+        assert!(empty.files.is_empty());
+
+        assert!(matches!(
+            engine.execute_complex_query(None, None).await,
+            Err(CodeNexusError::InvalidQuerySyntax(Message::MissingQueryCondition))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_execute_tag_query_count_only() {
+        let (_temp_dir, engine) = setup(
+            &[("src/a.rs", "layer:api"), ("src/b.rs", "layer:api"), ("tests/c.rs", "layer:api"), ("d.rs", "layer:db")],
+            &[],
+        )
+        .await;
+
+        let full = engine.execute_tag_query("layer:api", None, false).await.unwrap();
+        let count = engine.execute_tag_query("layer:api", None, true).await.unwrap();
+        assert_eq!(count.total, full.total);
+        assert_eq!(count.total, 3);
+        assert!(count.files.is_empty());
+
+        let filtered = engine.execute_tag_query("layer:api", Some("src/**"), true).await.unwrap();
+        assert_eq!(filtered.total, 2);
+        assert!(engine.execute_tag_query("layer:api AND", None, true).await.is_err());
+    }
+
+    #[test]
+    fn test_make_snippet() {
+        // 短文本完整返回
+        assert_eq!(make_snippet("用户认证 API", "api", 40, false), "用户认证 API");
+
+        // 长文本截取关键词附近内容
+        let text = format!("{}keyword{}", "a".repeat(100), "b".repeat(100));
+        let snippet = make_snippet(&text, "KEYWORD", 5, false);
+        assert_eq!(snippet, "...aaaaakeywordbbbbb...");
+
+        // 未命中时返回开头部分
+        let snippet = make_snippet(&"x".repeat(100), "missing", 5, false);
+        assert_eq!(snippet, format!("{}...", "x".repeat(10)));
+    }
+
+    #[tokio::test]
+    async fn test_get_file_info_tracked() {
+        let (_temp_dir, engine) = setup(&[("a.rs", "layer:api")], &[("b.rs", "c.rs", "调用")]).await;
+
+        for file in ["a.rs", "b.rs", "c.rs"] {
+            assert!(engine.get_file_info(file).await.unwrap().tracked, "{} 应被视为已跟踪", file);
+        }
+        let info = engine.get_file_info("untracked.rs").await.unwrap();
+        assert!(!info.tracked);
+        assert!(info.tags.is_empty() && info.comment.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_file_info_relation_direction() {
+        let (_temp_dir, engine) = setup(&[], &[("a.rs", "b.rs", "调用"), ("b.rs", "c.rs", "读取")]).await;
+
+        let info = engine.get_file_info("b.rs").await.unwrap();
+        assert_eq!(info.relations.len(), 1);
+        assert_eq!(info.relations[0].other_file, "c.rs");
+        assert_eq!(info.relations[0].direction, RelationDirection::Outgoing);
+        assert_eq!(info.incoming_relations.len(), 1);
+        assert_eq!(info.incoming_relations[0].other_file, "a.rs");
+        assert_eq!(info.incoming_relations[0].description, "调用");
+        assert_eq!(info.incoming_relations[0].direction, RelationDirection::Incoming);
+    }
+
+    #[tokio::test]
+    async fn test_search_files_tag_breakdown() {
+        let (_temp_dir, engine) = setup(
+            &[("a.rs", "auth:login"), ("a.rs", "layer:api"), ("b.rs", "auth:token"), ("b.rs", "layer:api"), ("c.rs", "layer:db")],
+            &[],
+        )
+        .await;
+
+        let response = engine.search_files("auth", &SearchOptions::default()).await.unwrap();
+        assert_eq!(response.total, 2);
+        let paths: Vec<&str> = response.files.iter().map(|r| r.file_info.path.as_str()).collect();
+        assert_eq!(paths, vec!["a.rs", "b.rs"]);
+        assert_eq!(response.tag_breakdown["layer:api"], 2);
+        assert_eq!(response.tag_breakdown["auth:login"], 1);
+        assert!(!response.tag_breakdown.contains_key("layer:db"));
+    }
+
+    #[tokio::test]
+    async fn test_search_files_many_results_sorted() {
+        let files: Vec<String> = (0..50).rev().map(|i| format!("src/f{:02}.rs", i)).collect();
+        let tags: Vec<(&str, &str)> = files.iter().map(|file| (file.as_str(), "layer:api")).collect();
+        let (_temp_dir, engine) = setup(&tags, &[("src/f07.rs", "src/f08.rs", "调用")]).await;
+
+        // 大量结果仍按路径排序且信息完整
+        let response = engine.search_files("layer", &SearchOptions::default()).await.unwrap();
+        let paths: Vec<&str> = response.files.iter().map(|r| r.file_info.path.as_str()).collect();
+        let mut expected: Vec<&str> = files.iter().map(String::as_str).collect();
+        expected.sort();
+        assert_eq!(paths, expected);
+        assert_eq!(response.files[7].file_info.relations.len(), 1);
+        assert_eq!(response.files[8].file_info.incoming_relations.len(), 1);
+        assert_eq!(response.tag_breakdown["layer:api"], 50);
+    }
+
+    #[tokio::test]
+    async fn test_get_related_files_scored() {
+        let (_temp_dir, engine) = setup(
+            &[("a.rs", "layer:api"), ("a.rs", "auth:login"), ("b.rs", "layer:api"), ("b.rs", "auth:login"), ("c.rs", "layer:api")],
+            &[("a.rs", "c.rs", "调用"), ("d.rs", "a.rs", "依赖")],
+        )
+        .await;
+
+        // b.rs 共享两个标签；c.rs 共享一个标签且有出向关联；d.rs 仅有入向关联
+        let related = engine.get_related_files("a.rs", 10).await.unwrap();
+        assert_eq!(
+            related,
+            vec![("b.rs".to_string(), 2.0), ("c.rs".to_string(), 2.0), ("d.rs".to_string(), 1.0)]
+        );
+
+        // 先排序再截断
+        let top = engine.get_related_files("a.rs", 1).await.unwrap();
+        assert_eq!(top, vec![("b.rs".to_string(), 2.0)]);
+    }
+
+    #[tokio::test]
+    async fn test_get_effective_tags() {
+        let (_temp_dir, engine) = setup(
+            &[("a.rs", "layer:api"), ("b.rs", "layer:api"), ("b.rs", "domain:auth"), ("c.rs", "domain:auth"), ("c.rs", "storage:db")],
+            &[("a.rs", "b.rs", "调用"), ("b.rs", "c.rs", "读取"), ("c.rs", "a.rs", "回调")],
+        )
+        .await;
+
+        let tags = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+        let effective = engine.get_effective_tags("a.rs", 1).await.unwrap();
+        assert_eq!(effective.own, tags(&["layer:api"]));
+        assert_eq!(effective.inherited, vec![InheritedTag { tag: "domain:auth".to_string(), sources: tags(&["b.rs"]) }]);
+
+        // 环路回到自身时不重复访问，自身已有的标签不算继承
+        let effective = engine.get_effective_tags("a.rs", 5).await.unwrap();
+        assert_eq!(effective.tags, tags(&["domain:auth", "layer:api", "storage:db"]));
+        assert_eq!(effective.inherited[0].sources, tags(&["b.rs", "c.rs"]));
+        assert_eq!(effective.inherited[1].tag, "storage:db");
+
+        assert!(engine.get_effective_tags("a.rs", 0).await.unwrap().inherited.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_files_case_sensitive() {
+        let (_temp_dir, engine) = setup(
+            &[("a.rs", "proto:HTTP"), ("b.rs", "proto:http")],
+            &[("c.rs", "d.rs", "调用 API 网关"), ("d.rs", "e.rs", "api 辅助函数")],
+        )
+        .await;
+
+        let paths = |response: SearchResponse| {
+            response.files.into_iter().map(|r| r.file_info.path).collect::<Vec<_>>()
+        };
+        let insensitive = engine.search_files("HTTP", &SearchOptions::default()).await.unwrap();
+        assert_eq!(paths(insensitive), vec!["a.rs", "b.rs"]);
+
+        let options = SearchOptions { case_sensitive: true, ..Default::default() };
+        assert_eq!(paths(engine.search_files("HTTP", &options).await.unwrap()), vec!["a.rs"]);
+        assert_eq!(paths(engine.search_files("API", &options).await.unwrap()), vec!["c.rs"]);
+    }
+
+    #[tokio::test]
+    async fn test_multi_filter() {
+        let (_temp_dir, engine) = setup(
+            &[("src/a.rs", "layer:api"), ("src/b.rs", "layer:api"), ("lib/c.rs", "layer:api")],
+            &[("src/b.rs", "src/a.rs", "调用"), ("lib/c.rs", "src/a.rs", "调用"), ("src/a.rs", "src/d.rs", "读取")],
+        )
+        .await;
+
+        let filter = |tag_query: Option<&str>, min_incoming: Option<usize>, path_glob: Option<&str>| MultiFilter {
+            tag_query: tag_query.map(String::from),
+            has_comment: Some(false),
+            min_incoming,
+            min_outgoing: None,
+            path_glob: path_glob.map(String::from),
+        };
+
+        let result = engine.multi_filter(&filter(Some("layer:api"), Some(2), None)).await.unwrap();
+        assert_eq!(result.files, vec!["src/a.rs"]);
+        let result = engine.multi_filter(&filter(Some("layer:api"), None, Some("src/**"))).await.unwrap();
+        assert_eq!(result.files, vec!["src/a.rs", "src/b.rs"]);
+
+        // 未提供标签查询时，只作为关联目标出现的文件也参与过滤
+        let result = engine.multi_filter(&filter(None, Some(1), None)).await.unwrap();
+        assert_eq!(result.files, vec!["src/a.rs", "src/d.rs"]);
+        let outgoing = MultiFilter { min_outgoing: Some(1), ..Default::default() };
+        assert_eq!(engine.multi_filter(&outgoing).await.unwrap().total, 3);
+
+        let err = engine.multi_filter(&MultiFilter::default()).await.unwrap_err();
+        assert_eq!(err.message(crate::i18n::Locale::EnUs), "Query syntax error: Provide at least one filter");
+    }
+}
@@ -1,3 +1,5 @@
 pub mod engine;
+pub mod parser;
 
-pub use engine::QueryEngine;
+pub use engine::{QueryEngine, DEFAULT_FUZZY_THRESHOLD};
+pub use parser::{parse_query, QueryExpr};
@@ -0,0 +1,377 @@
+use crate::error::{CodeNexusError, Result};
+use crate::i18n::Message;
+use std::collections::BTreeSet;
+
+/// 标签查询表达式
+///
+/// 优先级从低到高：OR、AND、NOT，括号可改变优先级
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryExpr {
+    /// 精确匹配的标签
+    Tag(String),
+    /// 含 `*` 或 `?` 的通配符标签
+    Wildcard(String),
+    /// `type:*` 简写：带有该类型任意取值的标签
+    HasType(String),
+    /// `=[a, b]`：标签集合与给定集合完全相同
+    ExactSet(BTreeSet<String>),
+    /// `>=[a, b]`：标签集合包含给定集合的全部标签
+    Superset(BTreeSet<String>),
+    Not(Box<QueryExpr>),
+    And(Vec<QueryExpr>),
+    Or(Vec<QueryExpr>),
+}
+
+/// 词法单元
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Term(String),
+    /// 标签集合字面量，`exact` 为 true 表示 `=[...]`，否则为 `>=[...]`
+    TagSet { tags: BTreeSet<String>, exact: bool },
+}
+
+/// 带字符位置的词法单元
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    /// 在查询字符串中的字符偏移（从 0 开始）
+    position: usize,
+}
+
+/// 解析标签查询表达式
+///
+/// 出错时返回 `QueryParseError`，其中 `position` 为出错位置的字符偏移
+pub fn parse_query(query: &str) -> Result<QueryExpr> {
+    let tokens = tokenize(query)?;
+    if tokens.is_empty() {
+        return Err(parse_error(0, Message::EmptyQuery));
+    }
+
+    let mut parser = Parser { tokens, pos: 0, end: query.chars().count() };
+    let expr = parser.parse_or()?;
+    if let Some(token) = parser.peek() {
+        return Err(match token.kind {
+            TokenKind::RParen => parse_error(token.position, Message::UnmatchedRightParen),
+            _ => parse_error(token.position, |position| Message::UnexpectedQueryToken { token: describe(&token.kind), position }),
+        });
+    }
+    Ok(expr)
+}
+
+/// 构造解析错误，`message` 由出错位置生成本地化消息
+fn parse_error(position: usize, message: impl FnOnce(usize) -> Message) -> CodeNexusError {
+    CodeNexusError::QueryParseError {
+        message: message(position),
+        position,
+    }
+}
+
+/// 词法单元在查询中的写法，用于错误信息（与语言无关）
+fn describe(kind: &TokenKind) -> String {
+    match kind {
+        TokenKind::LParen => "(".to_string(),
+        TokenKind::RParen => ")".to_string(),
+        TokenKind::And => "AND".to_string(),
+        TokenKind::Or => "OR".to_string(),
+        TokenKind::Not => "NOT".to_string(),
+        TokenKind::Term(term) => term.clone(),
+        TokenKind::TagSet { exact: true, .. } => "=[...]".to_string(),
+        TokenKind::TagSet { exact: false, .. } => ">=[...]".to_string(),
+    }
+}
+
+/// 将查询切分为词法单元
+///
+/// 连续的非关键字单词合并为一个标签（保留原始空白），以兼容值中带空格的标签；
+/// 以 `=[` 或 `>=[` 开头的单词读取到 `]` 为止，作为逗号分隔的标签集合
+fn tokenize(query: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens: Vec<Token> = Vec::new();
+    // 正在合并的标签：(起始位置, 结束位置)
+    let mut term: Option<(usize, usize)> = None;
+    let mut i = 0;
+
+    let flush = |term: &mut Option<(usize, usize)>, tokens: &mut Vec<Token>| {
+        if let Some((start, end)) = term.take() {
+            tokens.push(Token {
+                kind: TokenKind::Term(chars[start..end].iter().collect()),
+                position: start,
+            });
+        }
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '(' || c == ')' {
+            flush(&mut term, &mut tokens);
+            let kind = if c == '(' { TokenKind::LParen } else { TokenKind::RParen };
+            tokens.push(Token { kind, position: i });
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        let set_prefix = ["=[", ">=["].into_iter().find(|prefix| chars[i..].iter().take(prefix.len()).copied().eq(prefix.chars()));
+        if let Some(prefix) = set_prefix {
+            flush(&mut term, &mut tokens);
+            let body_start = start + prefix.len();
+            let body_end = (body_start..chars.len())
+                .find(|&j| chars[j] == ']')
+                .ok_or_else(|| parse_error(start, Message::UnclosedTagSet))?;
+            let body: String = chars[body_start..body_end].iter().collect();
+            tokens.push(Token {
+                kind: TokenKind::TagSet { tags: parse_tag_set(&body, start)?, exact: prefix == "=[" },
+                position: start,
+            });
+            i = body_end + 1;
+            continue;
+        }
+
+        while i < chars.len() && !chars[i].is_whitespace() && chars[i] != '(' && chars[i] != ')' {
+            i += 1;
+        }
+        let word: String = chars[start..i].iter().collect();
+        let keyword = match word.as_str() {
+            "AND" => Some(TokenKind::And),
+            "OR" => Some(TokenKind::Or),
+            "NOT" => Some(TokenKind::Not),
+            _ => None,
+        };
+
+        match keyword {
+            Some(kind) => {
+                flush(&mut term, &mut tokens);
+                tokens.push(Token { kind, position: start });
+            }
+            None => {
+                term = Some(match term {
+                    Some((term_start, _)) => (term_start, i),
+                    None => (start, i),
+                });
+            }
+        }
+    }
+    flush(&mut term, &mut tokens);
+
+    Ok(tokens)
+}
+
+/// 解析标签集合字面量中逗号分隔的标签，`position` 为集合在查询中的起始位置
+fn parse_tag_set(body: &str, position: usize) -> Result<BTreeSet<String>> {
+    let mut tags = BTreeSet::new();
+    for tag in body.split(',').map(str::trim) {
+        if tag.is_empty() {
+            return Err(parse_error(position, Message::EmptyTagInSet));
+        }
+        if tag.contains(['*', '?']) {
+            return Err(parse_error(position, |position| Message::WildcardInTagSet { tag: tag.to_string(), position }));
+        }
+        if tag.contains(':') && tag.split(':').count() != 2 {
+            return Err(parse_error(position, |position| Message::MalformedQueryTag { tag: tag.to_string(), position }));
+        }
+        tags.insert(tag.to_string());
+    }
+    Ok(tags)
+}
+
+/// 递归下降解析器
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// 查询的字符长度，用于报告末尾缺少操作数的位置
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next_if(&mut self, kind: &TokenKind) -> Option<Token> {
+        match self.peek() {
+            Some(token) if &token.kind == kind => {
+                self.pos += 1;
+                Some(self.tokens[self.pos - 1].clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// or_expr := and_expr (OR and_expr)*
+    fn parse_or(&mut self) -> Result<QueryExpr> {
+        let mut operands = vec![self.parse_and()?];
+        while let Some(op) = self.next_if(&TokenKind::Or) {
+            operands.push(self.parse_operand(&op)?);
+        }
+        Ok(if operands.len() == 1 { operands.remove(0) } else { QueryExpr::Or(operands) })
+    }
+
+    /// and_expr := unary (AND unary)*
+    fn parse_and(&mut self) -> Result<QueryExpr> {
+        let mut operands = vec![self.parse_unary()?];
+        while let Some(op) = self.next_if(&TokenKind::And) {
+            operands.push(self.parse_operand(&op)?);
+        }
+        Ok(if operands.len() == 1 { operands.remove(0) } else { QueryExpr::And(operands) })
+    }
+
+    /// 解析二元操作符右侧的操作数，缺失时报告操作符位置
+    fn parse_operand(&mut self, op: &Token) -> Result<QueryExpr> {
+        if self.starts_operand() {
+            match op.kind {
+                TokenKind::Or => self.parse_and(),
+                _ => self.parse_unary(),
+            }
+        } else {
+            Err(parse_error(op.position, |position| Message::MissingOperandAfter { operator: describe(&op.kind), position }))
+        }
+    }
+
+    /// unary := NOT unary | primary
+    fn parse_unary(&mut self) -> Result<QueryExpr> {
+        if let Some(op) = self.next_if(&TokenKind::Not) {
+            if !self.starts_operand() {
+                return Err(parse_error(op.position, |position| Message::MissingOperandAfter { operator: "NOT".to_string(), position }));
+            }
+            return Ok(QueryExpr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    /// primary := '(' or_expr ')' | TERM
+    fn parse_primary(&mut self) -> Result<QueryExpr> {
+        let token = match self.peek() {
+            Some(token) => token.clone(),
+            None => return Err(parse_error(self.end, Message::UnexpectedQueryEnd)),
+        };
+        self.pos += 1;
+
+        match token.kind {
+            TokenKind::LParen => {
+                if self.next_if(&TokenKind::RParen).is_some() {
+                    return Err(parse_error(token.position, Message::EmptyParentheses));
+                }
+                let expr = self.parse_or()?;
+                if self.next_if(&TokenKind::RParen).is_none() {
+                    return Err(parse_error(token.position, Message::UnclosedParenthesis));
+                }
+                Ok(expr)
+            }
+            TokenKind::Term(term) => {
+                if term.contains(':') && term.split(':').count() != 2 {
+                    return Err(parse_error(token.position, |position| Message::MalformedQueryTag { tag: term, position }));
+                }
+                if let Some(tag_type) = term.strip_suffix(":*").filter(|t| !t.is_empty() && !t.contains('*')) {
+                    Ok(QueryExpr::HasType(tag_type.to_string()))
+                } else if term.contains(['*', '?']) {
+                    Ok(QueryExpr::Wildcard(term))
+                } else {
+                    Ok(QueryExpr::Tag(term))
+                }
+            }
+            TokenKind::TagSet { tags, exact: true } => Ok(QueryExpr::ExactSet(tags)),
+            TokenKind::TagSet { tags, exact: false } => Ok(QueryExpr::Superset(tags)),
+            TokenKind::RParen => Err(parse_error(token.position, Message::UnmatchedRightParen)),
+            _ => Err(parse_error(token.position, |position| Message::MissingOperandBefore { operator: describe(&token.kind), position })),
+        }
+    }
+
+    /// 下一个词法单元能否作为操作数的开头
+    fn starts_operand(&self) -> bool {
+        matches!(
+            self.peek().map(|token| &token.kind),
+            Some(TokenKind::LParen | TokenKind::Not | TokenKind::Term(_) | TokenKind::TagSet { .. })
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(value: &str) -> QueryExpr {
+        QueryExpr::Tag(value.to_string())
+    }
+
+    /// 返回解析错误的位置
+    fn error_position(query: &str) -> usize {
+        match parse_query(query) {
+            Err(CodeNexusError::QueryParseError { position, .. }) => position,
+            other => panic!("查询 {:?} 应解析失败，实际为 {:?}", query, other),
+        }
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        assert_eq!(
+            parse_query("a:1 OR b:2 AND NOT c:*").unwrap(),
+            QueryExpr::Or(vec![
+                tag("a:1"),
+                QueryExpr::And(vec![tag("b:2"), QueryExpr::Not(Box::new(QueryExpr::HasType("c".to_string())))]),
+            ])
+        );
+        assert_eq!(
+            parse_query("(a:1 OR b:2) AND c:3").unwrap(),
+            QueryExpr::And(vec![QueryExpr::Or(vec![tag("a:1"), tag("b:2")]), tag("c:3")])
+        );
+        assert_eq!(parse_query("c*:*").unwrap(), QueryExpr::Wildcard("c*:*".to_string()));
+        assert_eq!(parse_query("status:d*").unwrap(), QueryExpr::Wildcard("status:d*".to_string()));
+        assert_eq!(parse_query("version:v1.?").unwrap(), QueryExpr::Wildcard("version:v1.?".to_string()));
+        // 值中带空格的标签作为一个整体
+        assert_eq!(parse_query("note:hello  world").unwrap(), tag("note:hello  world"));
+    }
+
+    #[test]
+    fn test_parse_tag_sets() {
+        let set = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect::<BTreeSet<_>>();
+        assert_eq!(parse_query("=[layer:api, auth:login]").unwrap(), QueryExpr::ExactSet(set(&["auth:login", "layer:api"])));
+        assert_eq!(
+            parse_query(">=[layer:api] AND NOT status:wip").unwrap(),
+            QueryExpr::And(vec![
+                QueryExpr::Superset(set(&["layer:api"])),
+                QueryExpr::Not(Box::new(tag("status:wip"))),
+            ])
+        );
+        assert_eq!(
+            parse_query("(=[a:1,b:2])").unwrap(),
+            QueryExpr::ExactSet(set(&["a:1", "b:2"]))
+        );
+
+        assert_eq!(error_position("a:1 OR >=[a:1"), 7);
+        assert_eq!(error_position("=[a:1,,b:2]"), 0);
+        assert_eq!(error_position("=[a:*]"), 0);
+        assert_eq!(error_position("=[a:1] b:2"), 7);
+    }
+
+    #[test]
+    fn test_parse_error_positions() {
+        assert_eq!(error_position(""), 0);
+        assert_eq!(error_position("(a:1 AND b:2"), 0);
+        assert_eq!(error_position("a:1 AND b:2)"), 11);
+        assert_eq!(error_position("a:1 AND"), 4);
+        assert_eq!(error_position("OR a:1"), 0);
+        assert_eq!(error_position("a:1 AND NOT"), 8);
+        assert_eq!(error_position("a:1 AND ()"), 8);
+        assert_eq!(error_position("(a:1) b:2"), 6);
+        // 位置按字符计算
+        assert_eq!(error_position("模块:登录 OR"), 6);
+
+        // 消息来自多语言目录并带有位置
+        match parse_query("a:1 AND") {
+            Err(CodeNexusError::QueryParseError { message, .. }) => {
+                assert_eq!(message, Message::MissingOperandAfter { operator: "AND".to_string(), position: 4 });
+                assert_eq!(message.text(crate::i18n::Locale::EnUs), "Missing operand after AND (position 4)");
+            }
+            other => panic!("应解析失败，实际为 {:?}", other),
+        }
+    }
+}
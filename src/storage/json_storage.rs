@@ -1,33 +1,169 @@
+use crate::config::{ProjectConfig, DEFAULT_BACKUP_COUNT};
 use crate::error::{CodeNexusError, Result};
-use crate::models::Relation;
+use crate::i18n::Message;
+use crate::models::{AuditEntry, Relation, TagMeta, TagSchema};
+use crate::utils::content_hash;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use fs2::FileExt;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::fs;
-use tracing::{debug, error, info};
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, error, info, warn};
+
+/// 默认的数据目录锁等待时间
+const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 获取数据目录锁的重试间隔
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
 
 /// JSON 存储管理器
 #[derive(Debug, Clone)]
 pub struct JsonStorage {
     data_dir: PathBuf,
+    lock_timeout: Duration,
+    // 本进程最近写入的各文件内容哈希，用于区分外部修改
+    written_hashes: Arc<Mutex<HashMap<PathBuf, String>>>,
+    // 加载失败的数据文件，恢复前拒绝写入，避免覆盖损坏的文件和它的备份
+    unavailable_files: Arc<Mutex<HashSet<PathBuf>>>,
+    // 每个数据文件保留的备份数量，所有克隆共享
+    backup_count: Arc<AtomicUsize>,
+}
+
+/// 数据文件第 `version` 个备份的路径：0 为 `.json.bak`，其余为 `.json.bak.N`
+fn backup_path(file_path: &Path, version: usize) -> PathBuf {
+    match version {
+        0 => file_path.with_extension("json.bak"),
+        version => file_path.with_extension(format!("json.bak.{}", version)),
+    }
+}
+
+/// 先写入同目录下的临时文件再重命名覆盖目标，读取方不会看到写入到一半的内容
+async fn write_atomic(file_path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut temp_name = file_path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = file_path.with_file_name(temp_name);
+    fs::write(&temp_path, contents).await?;
+    fs::rename(&temp_path, file_path).await
 }
 
 /// 标签数据结构
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct TagsData {
-    pub file_tags: HashMap<String, Vec<String>>,
+    pub file_tags: BTreeMap<String, Vec<String>>,
+    /// 文件 -> 标签 -> 元数据，旧数据中没有该字段时为空
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tag_meta: BTreeMap<String, BTreeMap<String, TagMeta>>,
+    /// 文件 -> 已归档的标签，不参与查询，可恢复
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub archived_tags: BTreeMap<String, Vec<String>>,
 }
 
 /// 注释数据结构
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct CommentsData {
-    pub file_comments: HashMap<String, String>,
+    pub file_comments: BTreeMap<String, String>,
 }
 
 /// 关联关系数据结构
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct RelationsData {
-    pub file_relations: HashMap<String, Vec<Relation>>,
+    pub file_relations: BTreeMap<String, Vec<Relation>>,
+}
+
+/// 文件内容哈希数据结构
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ContentHashesData {
+    pub file_hashes: BTreeMap<String, String>,
+}
+
+/// 标签索引快照
+///
+/// 与 tags.json 内容哈希绑定，哈希一致时可直接恢复内存索引而无需重建
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct TagIndexSnapshot {
+    /// 生成快照时 tags.json 的内容哈希
+    pub source_hash: String,
+    /// tag_type -> tag_values
+    pub tag_index: BTreeMap<String, Vec<String>>,
+    /// tag -> files
+    pub tag_to_files: BTreeMap<String, Vec<String>>,
+}
+
+/// 反序列化后的数据校验，手工编辑的数据文件出错时指明文件和具体条目
+trait ValidateData {
+    fn validate(&self, file_path: &Path) -> Result<()>;
+}
+
+fn invalid_data(file_path: &Path, key: impl Into<String>, reason: Message) -> CodeNexusError {
+    CodeNexusError::InvalidDataFile {
+        path: file_path.display().to_string(),
+        key: key.into(),
+        reason,
+    }
+}
+
+impl ValidateData for TagsData {
+    fn validate(&self, file_path: &Path) -> Result<()> {
+        for (file, tags) in &self.file_tags {
+            if file.trim().is_empty() {
+                return Err(invalid_data(file_path, "file_tags[\"\"]", Message::EmptyFilePath));
+            }
+            for (index, tag) in tags.iter().enumerate() {
+                let valid = tag
+                    .split_once(':')
+                    .is_some_and(|(tag_type, value)| !tag_type.is_empty() && !value.is_empty() && !value.contains(':'));
+                if !valid {
+                    return Err(invalid_data(file_path, format!("file_tags[{:?}][{}]", file, index), Message::MalformedTag(tag.clone())));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ValidateData for CommentsData {
+    fn validate(&self, file_path: &Path) -> Result<()> {
+        if self.file_comments.keys().any(|file| file.trim().is_empty()) {
+            return Err(invalid_data(file_path, "file_comments[\"\"]", Message::EmptyFilePath));
+        }
+        Ok(())
+    }
+}
+
+impl ValidateData for ContentHashesData {
+    fn validate(&self, file_path: &Path) -> Result<()> {
+        if self.file_hashes.keys().any(|file| file.trim().is_empty()) {
+            return Err(invalid_data(file_path, "file_hashes[\"\"]", Message::EmptyFilePath));
+        }
+        Ok(())
+    }
+}
+
+impl ValidateData for RelationsData {
+    fn validate(&self, file_path: &Path) -> Result<()> {
+        for (file, relations) in &self.file_relations {
+            if file.trim().is_empty() {
+                return Err(invalid_data(file_path, "file_relations[\"\"]", Message::EmptyFilePath));
+            }
+            for (index, relation) in relations.iter().enumerate() {
+                let key = || format!("file_relations[{:?}][{}]", file, index);
+                if relation.target.trim().is_empty() {
+                    return Err(invalid_data(file_path, key(), Message::EmptyRelationTarget));
+                }
+                if relation.description.trim().is_empty() {
+                    return Err(invalid_data(file_path, key(), Message::EmptyRelationDescription));
+                }
+                if let Some(weight) = relation.weight.filter(|weight| !weight.is_finite() || *weight < 0.0) {
+                    return Err(invalid_data(file_path, key(), Message::InvalidRelationWeight(weight.to_string())));
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 impl JsonStorage {
@@ -35,6 +171,53 @@ impl JsonStorage {
     pub fn new<P: AsRef<Path>>(data_dir: P) -> Self {
         Self {
             data_dir: data_dir.as_ref().to_path_buf(),
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            written_hashes: Arc::new(Mutex::new(HashMap::new())),
+            unavailable_files: Arc::new(Mutex::new(HashSet::new())),
+            backup_count: Arc::new(AtomicUsize::new(DEFAULT_BACKUP_COUNT)),
+        }
+    }
+
+    /// 设置每个数据文件保留的备份数量
+    pub fn set_backup_count(&self, backup_count: usize) {
+        self.backup_count.store(backup_count, Ordering::Relaxed);
+    }
+
+    /// 设置获取数据目录锁的等待时间
+    pub fn with_lock_timeout(mut self, lock_timeout: Duration) -> Self {
+        self.lock_timeout = lock_timeout;
+        self
+    }
+
+    /// 获取数据目录的跨进程锁（`.codenexus/.lock` 上的 advisory 锁）
+    ///
+    /// 进程内的写入已由每个项目的 `Mutex<ProjectManager>` 串行化。多个服务器进程指向同一项目时，
+    /// 该锁只保证单次读写不会交错（不会读到或写出半个文件），并不合并数据：每个进程保存的都是
+    /// 自己内存中的完整数据，最后写入的进程仍会覆盖其他进程的修改。返回的文件句柄被释放时自动解锁。
+    async fn acquire_lock(&self) -> Result<std::fs::File> {
+        let lock_path = self.data_dir.join(".lock");
+        let lock_file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)?;
+
+        let started = Instant::now();
+        loop {
+            match lock_file.try_lock_exclusive() {
+                Ok(()) => return Ok(lock_file),
+                Err(e) if e.kind() == fs2::lock_contended_error().kind() => {
+                    if started.elapsed() >= self.lock_timeout {
+                        return Err(CodeNexusError::LockTimeout(format!(
+                            "{:?} 在 {}ms 内未能获取",
+                            lock_path,
+                            self.lock_timeout.as_millis()
+                        )));
+                    }
+                    tokio::time::sleep(LOCK_RETRY_INTERVAL).await;
+                }
+                Err(e) => return Err(CodeNexusError::StorageError(e)),
+            }
         }
     }
 
@@ -70,12 +253,86 @@ impl JsonStorage {
         self.load_json_file(&file_path).await
     }
 
+    /// 加载标签数据，同时返回文件内容哈希
+    pub async fn load_tags_with_hash(&self) -> Result<(TagsData, String)> {
+        let file_path = self.data_dir.join("tags.json");
+        self.load_json_file_with_hash(&file_path).await
+    }
+
     /// 保存标签数据
     pub async fn save_tags(&self, data: &TagsData) -> Result<()> {
+        self.save_tags_with_hash(data).await.map(|_| ())
+    }
+
+    /// 保存标签数据，返回写入内容的哈希
+    pub async fn save_tags_with_hash(&self, data: &TagsData) -> Result<String> {
         let file_path = self.data_dir.join("tags.json");
         self.save_json_file(&file_path, data).await
     }
 
+    /// 加载标签索引快照，文件不存在或无法解析时返回 None
+    pub async fn load_tag_index(&self) -> Option<TagIndexSnapshot> {
+        let file_path = self.data_dir.join("tags.index.json");
+        let content = fs::read_to_string(&file_path).await.ok()?;
+        match serde_json::from_str(&content) {
+            Ok(snapshot) => Some(snapshot),
+            Err(e) => {
+                debug!("标签索引快照无法解析，将重建索引 {:?}: {}", file_path, e);
+                None
+            }
+        }
+    }
+
+    /// 保存标签索引快照（不创建备份）
+    pub async fn save_tag_index(&self, snapshot: &TagIndexSnapshot) -> Result<()> {
+        let file_path = self.data_dir.join("tags.index.json");
+        let json_data = serde_json::to_string(snapshot)?;
+        let _lock = self.acquire_lock().await?;
+        write_atomic(&file_path, json_data.as_bytes()).await?;
+        debug!("标签索引快照已保存到: {:?}", file_path);
+        Ok(())
+    }
+
+    /// 加载项目配置，文件不存在时返回默认配置
+    pub async fn load_config(&self) -> Result<ProjectConfig> {
+        let file_path = self.data_dir.join("config.json");
+        if !file_path.exists() {
+            return Ok(ProjectConfig::default());
+        }
+
+        let content = fs::read_to_string(&file_path).await?;
+        let config = serde_json::from_str(&content)
+            .map_err(|e| CodeNexusError::ConfigError(Message::InvalidConfigFile {
+                path: file_path.display().to_string(),
+                error: e.to_string(),
+            }))?;
+        debug!("加载项目配置: {:?}", file_path);
+        Ok(config)
+    }
+
+    /// 保存项目配置
+    pub async fn save_config(&self, config: &ProjectConfig) -> Result<()> {
+        let file_path = self.data_dir.join("config.json");
+        self.save_json_file(&file_path, config).await.map(|_| ())
+    }
+
+    /// 加载标签模式，文件不存在时返回 None
+    pub async fn load_tag_schema(&self) -> Result<Option<TagSchema>> {
+        let file_path = self.data_dir.join("tag_schema.json");
+        if !file_path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&file_path).await?;
+        let schema = serde_json::from_str(&content)
+            .map_err(|e| CodeNexusError::ConfigError(Message::InvalidTagSchemaFile {
+                path: file_path.display().to_string(),
+                error: e.to_string(),
+            }))?;
+        debug!("加载标签模式: {:?}", file_path);
+        Ok(Some(schema))
+    }
+
     /// 加载注释数据
     pub async fn load_comments(&self) -> Result<CommentsData> {
         let file_path = self.data_dir.join("comments.json");
@@ -85,7 +342,7 @@ impl JsonStorage {
     /// 保存注释数据
     pub async fn save_comments(&self, data: &CommentsData) -> Result<()> {
         let file_path = self.data_dir.join("comments.json");
-        self.save_json_file(&file_path, data).await
+        self.save_json_file(&file_path, data).await.map(|_| ())
     }
 
     /// 加载关联关系数据
@@ -97,20 +354,83 @@ impl JsonStorage {
     /// 保存关联关系数据
     pub async fn save_relations(&self, data: &RelationsData) -> Result<()> {
         let file_path = self.data_dir.join("relations.json");
-        self.save_json_file(&file_path, data).await
+        self.save_json_file(&file_path, data).await.map(|_| ())
+    }
+
+    /// 加载文件内容哈希，文件不存在时返回空数据
+    pub async fn load_content_hashes(&self) -> Result<ContentHashesData> {
+        let file_path = self.data_dir.join("content_hashes.json");
+        if !file_path.exists() {
+            return Ok(ContentHashesData::default());
+        }
+        self.load_json_file(&file_path).await
+    }
+
+    /// 保存文件内容哈希
+    pub async fn save_content_hashes(&self, data: &ContentHashesData) -> Result<()> {
+        let file_path = self.data_dir.join("content_hashes.json");
+        self.save_json_file(&file_path, data).await.map(|_| ())
+    }
+
+    /// 追加一条审计日志（`audit.jsonl`，每行一个 JSON 对象）
+    pub async fn append_audit_entry(&self, entry: &AuditEntry) -> Result<()> {
+        let file_path = self.data_dir.join("audit.jsonl");
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        let _lock = self.acquire_lock().await?;
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(&file_path).await?;
+        file.write_all(line.as_bytes()).await?;
+        file.flush().await?;
+        Ok(())
+    }
+
+    /// 加载全部审计日志，文件不存在时返回空列表；无法解析的行被跳过
+    pub async fn load_audit_log(&self) -> Result<Vec<AuditEntry>> {
+        let file_path = self.data_dir.join("audit.jsonl");
+        if !file_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&file_path).await?;
+        let mut entries = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(line) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => warn!("审计日志第 {} 行无法解析，已跳过: {}", index + 1, e),
+            }
+        }
+        Ok(entries)
     }
 
     /// 通用 JSON 文件加载
-    async fn load_json_file<T: for<'de> Deserialize<'de> + Default>(&self, file_path: &Path) -> Result<T> {
-        match fs::read_to_string(file_path).await {
+    async fn load_json_file<T: for<'de> Deserialize<'de> + Default + ValidateData>(&self, file_path: &Path) -> Result<T> {
+        self.load_json_file_with_hash(file_path).await.map(|(data, _)| data)
+    }
+
+    /// 通用 JSON 文件加载，同时返回文件内容哈希
+    ///
+    /// 读取时持有数据目录锁，避免读到其他进程写入到一半的文件
+    async fn load_json_file_with_hash<T: for<'de> Deserialize<'de> + Default + ValidateData>(&self, file_path: &Path) -> Result<(T, String)> {
+        let content = {
+            let _lock = self.acquire_lock().await?;
+            fs::read_to_string(file_path).await
+        };
+        match content {
             Ok(content) => {
+                let hash = content_hash(content.as_bytes());
                 if content.trim().is_empty() {
-                    Ok(T::default())
+                    Ok((T::default(), hash))
                 } else {
-                    serde_json::from_str(&content).map_err(|e| {
+                    let data: T = serde_json::from_str(&content).map_err(|e| {
                         error!("JSON 解析错误 {:?}: {}", file_path, e);
                         CodeNexusError::SerializationError(e)
-                    })
+                    })?;
+                    data.validate(file_path).inspect_err(|e| error!("{}", e))?;
+                    Ok((data, hash))
                 }
             }
             Err(e) => {
@@ -120,11 +440,61 @@ impl JsonStorage {
         }
     }
 
-    /// 通用 JSON 文件保存
-    async fn save_json_file<T: Serialize>(&self, file_path: &Path, data: &T) -> Result<()> {
-        // 创建备份
+    /// 将数据文件标记为加载失败，恢复前拒绝写入
+    pub fn mark_unavailable(&self, file_name: &str) {
+        if let Ok(mut unavailable_files) = self.unavailable_files.lock() {
+            unavailable_files.insert(self.data_dir.join(file_name));
+        }
+    }
+
+    /// 解除数据文件的写入限制
+    pub fn mark_available(&self, file_name: &str) {
+        if let Ok(mut unavailable_files) = self.unavailable_files.lock() {
+            unavailable_files.remove(&self.data_dir.join(file_name));
+        }
+    }
+
+    /// 用指定版本的备份覆盖数据文件（0 为最近一次），当前文件先另存为 `.json.corrupt` 以便排查
+    pub async fn restore_backup(&self, file_name: &str, version: usize) -> Result<()> {
+        let file_path = self.data_dir.join(file_name);
+        let backup_path = backup_path(&file_path, version);
+        if !backup_path.exists() {
+            return Err(CodeNexusError::FileNotFound(backup_path.to_string_lossy().to_string()));
+        }
+
+        let _lock = self.acquire_lock().await?;
         if file_path.exists() {
-            let backup_path = file_path.with_extension("json.bak");
+            fs::copy(&file_path, file_path.with_extension("json.corrupt")).await?;
+        }
+        write_atomic(&file_path, &fs::read(&backup_path).await?).await?;
+        info!("已从备份恢复数据文件: {:?}", file_path);
+        Ok(())
+    }
+
+    /// 通用 JSON 文件保存，返回写入内容的哈希
+    async fn save_json_file<T: Serialize>(&self, file_path: &Path, data: &T) -> Result<String> {
+        if self
+            .unavailable_files
+            .lock()
+            .is_ok_and(|unavailable_files| unavailable_files.contains(file_path))
+        {
+            return Err(CodeNexusError::DataFileUnavailable(file_path.to_string_lossy().to_string()));
+        }
+
+        let _lock = self.acquire_lock().await?;
+
+        // 轮换并创建备份
+        let backup_count = self.backup_count.load(Ordering::Relaxed);
+        if backup_count > 0 && file_path.exists() {
+            for version in (1..backup_count).rev() {
+                let older = backup_path(file_path, version - 1);
+                if older.exists() {
+                    if let Err(e) = fs::rename(&older, backup_path(file_path, version)).await {
+                        error!("轮换备份失败 {:?}: {}", older, e);
+                    }
+                }
+            }
+            let backup_path = backup_path(file_path, 0);
             if let Err(e) = fs::copy(file_path, &backup_path).await {
                 error!("创建备份失败 {:?}: {}", backup_path, e);
             }
@@ -132,13 +502,30 @@ impl JsonStorage {
 
         // 保存数据
         let json_data = serde_json::to_string_pretty(data)?;
-        fs::write(file_path, json_data).await.map_err(|e| {
+        let hash = content_hash(json_data.as_bytes());
+        write_atomic(file_path, json_data.as_bytes()).await.map_err(|e| {
             error!("文件写入错误 {:?}: {}", file_path, e);
             CodeNexusError::StorageError(e)
         })?;
 
+        if let Ok(mut written_hashes) = self.written_hashes.lock() {
+            written_hashes.insert(file_path.to_path_buf(), hash.clone());
+        }
+
         debug!("数据已保存到: {:?}", file_path);
-        Ok(())
+        Ok(hash)
+    }
+
+    /// 判断文件当前内容是否为本进程最近一次写入的内容
+    pub async fn is_own_write(&self, file_path: &Path) -> bool {
+        let Ok(content) = fs::read(file_path).await else {
+            return false;
+        };
+        let hash = content_hash(&content);
+        self.written_hashes
+            .lock()
+            .map(|written_hashes| written_hashes.get(file_path) == Some(&hash))
+            .unwrap_or(false)
     }
 
     /// 获取数据目录路径
@@ -154,3 +541,93 @@ impl JsonStorage {
             && self.data_dir.join("relations.json").exists()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_save_waits_for_data_dir_lock() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = JsonStorage::new(temp_dir.path()).with_lock_timeout(Duration::from_millis(200));
+        storage.initialize().await.unwrap();
+
+        // 模拟另一个进程持有锁
+        let held = std::fs::File::create(temp_dir.path().join(".lock")).unwrap();
+        held.lock_exclusive().unwrap();
+        assert!(matches!(
+            storage.save_tags(&TagsData::default()).await,
+            Err(CodeNexusError::LockTimeout(_))
+        ));
+        // 读取同样等待写入方释放锁
+        assert!(matches!(storage.load_tags().await, Err(CodeNexusError::LockTimeout(_))));
+
+        drop(held);
+        storage.save_tags(&TagsData::default()).await.unwrap();
+        // 通过临时文件重命名写入，不留下临时文件
+        assert!(!temp_dir.path().join("tags.json.tmp").exists());
+    }
+
+    #[tokio::test]
+    async fn test_load_rejects_invalid_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = JsonStorage::new(temp_dir.path());
+        storage.initialize().await.unwrap();
+
+        std::fs::write(
+            temp_dir.path().join("relations.json"),
+            r#"{"file_relations":{"a.rs":[{"target":"b.rs","description":"调用"},{"target":"","description":"依赖"}]}}"#,
+        )
+        .unwrap();
+        match storage.load_relations().await {
+            Err(CodeNexusError::InvalidDataFile { path, key, .. }) => {
+                assert!(path.ends_with("relations.json"));
+                assert_eq!(key, "file_relations[\"a.rs\"][1]");
+            }
+            other => panic!("应返回 InvalidDataFile，实际为 {:?}", other),
+        }
+
+        std::fs::write(temp_dir.path().join("tags.json"), r#"{"file_tags":{"a.rs":["layer:api","api"]}}"#).unwrap();
+        match storage.load_tags().await {
+            Err(CodeNexusError::InvalidDataFile { key, reason, .. }) => {
+                assert_eq!(key, "file_tags[\"a.rs\"][1]");
+                assert_eq!(reason, Message::MalformedTag("api".to_string()));
+            }
+            other => panic!("应返回 InvalidDataFile，实际为 {:?}", other),
+        }
+
+        // 原因随语言输出
+        let err = storage.load_relations().await.unwrap_err();
+        assert!(err.message(crate::i18n::Locale::EnUs).ends_with(": Relation target must not be empty"));
+    }
+
+    #[tokio::test]
+    async fn test_backup_rotation_and_restore() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = JsonStorage::new(temp_dir.path());
+        storage.initialize().await.unwrap();
+        storage.set_backup_count(2);
+
+        let tags_of = |tag: &str| {
+            let mut data = TagsData::default();
+            data.file_tags.insert("a.rs".to_string(), vec![tag.to_string()]);
+            data
+        };
+        for tag in ["layer:v1", "layer:v2", "layer:v3"] {
+            storage.save_tags(&tags_of(tag)).await.unwrap();
+        }
+        // 只保留两个备份：v2 与 v1
+        assert!(!temp_dir.path().join("tags.json.bak.2").exists());
+
+        storage.restore_backup("tags.json", 1).await.unwrap();
+        let data = storage.load_tags().await.unwrap();
+        assert_eq!(data.file_tags["a.rs"], vec!["layer:v1"]);
+        assert!(temp_dir.path().join("tags.json.corrupt").exists());
+
+        assert!(matches!(
+            storage.restore_backup("tags.json", 2).await,
+            Err(CodeNexusError::FileNotFound(_))
+        ));
+    }
+}
@@ -1,3 +1,3 @@
 pub mod json_storage;
 
-pub use json_storage::{JsonStorage, TagsData, CommentsData, RelationsData};
+pub use json_storage::{JsonStorage, TagsData, CommentsData, RelationsData, TagIndexSnapshot, ContentHashesData};
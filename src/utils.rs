@@ -1,14 +1,41 @@
 use crate::error::{CodeNexusError, Result};
-use std::path::{Path, PathBuf};
+use crate::i18n::Message;
+use chrono::{DateTime, NaiveDate, Utc};
+use std::path::{Component, Path, PathBuf};
 use tracing::{debug, warn};
 
+/// 启用大小写折叠（项目配置 `path_case_fold`）时将相对路径转为小写
+fn fold_path_case(relative_path: String, case_fold: bool) -> String {
+    if case_fold {
+        relative_path.to_lowercase()
+    } else {
+        relative_path
+    }
+}
+
 /// 验证项目路径
+///
+/// 先展开开头的 `~` 以及 `$VAR`、`${VAR}`（Windows 上还有 `%VAR%`）形式的环境变量，引用未定义的变量时报错
 pub fn validate_project_path(project_path: &str) -> Result<PathBuf> {
     if project_path.trim().is_empty() {
-        return Err(CodeNexusError::ConfigError("项目路径不能为空".to_string()));
+        return Err(CodeNexusError::ConfigError(Message::EmptyProjectPath));
     }
 
-    let path = Path::new(project_path);
+    let unexpandable = |var: String, cause: String| {
+        CodeNexusError::ConfigError(Message::UnexpandableProjectPath {
+            path: project_path.to_string(),
+            var,
+            cause,
+        })
+    };
+    let windows_expanded = if cfg!(windows) {
+        expand_percent_vars(project_path)
+            .map_err(|var| unexpandable(var, std::env::VarError::NotPresent.to_string()))?
+    } else {
+        project_path.to_string()
+    };
+    let expanded = shellexpand::full(&windows_expanded).map_err(|e| unexpandable(e.var_name, e.cause.to_string()))?;
+    let path = Path::new(expanded.as_ref());
     
     // 检查路径是否存在
     if !path.exists() {
@@ -20,10 +47,7 @@ pub fn validate_project_path(project_path: &str) -> Result<PathBuf> {
 
     // 检查是否为目录
     if !path.is_dir() {
-        return Err(CodeNexusError::ConfigError(format!(
-            "项目路径必须是目录: {}",
-            project_path
-        )));
+        return Err(CodeNexusError::ConfigError(Message::ProjectPathNotDirectory(project_path.to_string())));
     }
 
     // 转换为绝对路径
@@ -38,10 +62,36 @@ pub fn validate_project_path(project_path: &str) -> Result<PathBuf> {
     Ok(absolute_path)
 }
 
+/// 展开 Windows 风格的 `%VAR%` 环境变量，引用未定义的变量时返回变量名
+///
+/// 没有成对 `%` 的部分原样保留
+fn expand_percent_vars(path: &str) -> std::result::Result<String, String> {
+    let mut expanded = String::with_capacity(path.len());
+    let mut rest = path;
+    while let Some(start) = rest.find('%') {
+        let Some(len) = rest[start + 1..].find('%') else {
+            break;
+        };
+        if len == 0 {
+            // `%%` 不是变量引用，保留第一个 `%` 后继续
+            expanded.push_str(&rest[..=start]);
+            rest = &rest[start + 1..];
+            continue;
+        }
+        let var = &rest[start + 1..start + 1 + len];
+        let value = std::env::var(var).map_err(|_| var.to_string())?;
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&value);
+        rest = &rest[start + len + 2..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
 /// 验证文件路径（相对于项目根目录）
 pub fn validate_file_path(project_path: &Path, file_path: &str) -> Result<PathBuf> {
     if file_path.trim().is_empty() {
-        return Err(CodeNexusError::ConfigError("文件路径不能为空".to_string()));
+        return Err(CodeNexusError::ConfigError(Message::EmptyFilePath));
     }
 
     // 构建完整的文件路径
@@ -57,10 +107,7 @@ pub fn validate_file_path(project_path: &Path, file_path: &str) -> Result<PathBu
 
     // 检查是否为文件
     if !full_path.is_file() {
-        return Err(CodeNexusError::ConfigError(format!(
-            "路径必须指向文件而不是目录: {}",
-            file_path
-        )));
+        return Err(CodeNexusError::ConfigError(Message::PathNotFile(file_path.to_string())));
     }
 
     // 确保文件在项目目录内（安全检查）
@@ -78,25 +125,200 @@ pub fn validate_file_path(project_path: &Path, file_path: &str) -> Result<PathBu
         ))
     })?;
 
-    if !canonical_full_path.starts_with(&canonical_project_path) {
+    if strip_project_prefix(&canonical_full_path, &canonical_project_path).is_none() {
         warn!("安全警告: 文件路径超出项目范围: {:?}", canonical_full_path);
-        return Err(CodeNexusError::ConfigError(format!(
-            "文件路径必须在项目目录内: {}",
-            file_path
-        )));
+        return Err(CodeNexusError::ConfigError(Message::FileOutsideProject(file_path.to_string())));
     }
 
     debug!("文件路径验证成功: {:?}", canonical_full_path);
     Ok(canonical_full_path)
 }
 
+/// 去掉路径的项目根目录前缀，路径不在项目内时返回 None
+///
+/// 两侧都应先 canonicalize（以消除符号链接）。按路径组件比较，
+/// 在大小写不敏感的平台（Windows、macOS）上忽略大小写。
+pub fn strip_project_prefix(path: &Path, project_path: &Path) -> Option<PathBuf> {
+    let mut path_components = path.components();
+    for root_component in project_path.components() {
+        let component = path_components.next()?;
+        if !path_component_eq(component.as_os_str(), root_component.as_os_str()) {
+            return None;
+        }
+    }
+    Some(path_components.as_path().to_path_buf())
+}
+
+/// 比较两个路径组件
+fn path_component_eq(a: &std::ffi::OsStr, b: &std::ffi::OsStr) -> bool {
+    if cfg!(any(windows, target_os = "macos")) {
+        a.to_string_lossy().to_lowercase() == b.to_string_lossy().to_lowercase()
+    } else {
+        a == b
+    }
+}
+
+/// 解析输出文件路径（相对于给定根目录），拒绝绝对路径和 `..` 以防写出根目录
+pub fn resolve_output_path(root: &Path, relative_path: &str) -> Result<PathBuf> {
+    if relative_path.trim().is_empty() {
+        return Err(CodeNexusError::ConfigError(Message::EmptyOutputPath));
+    }
+
+    let relative = Path::new(relative_path);
+    let is_safe = relative
+        .components()
+        .all(|component| matches!(component, std::path::Component::Normal(_) | std::path::Component::CurDir));
+    if !is_safe {
+        return Err(CodeNexusError::ConfigError(Message::OutputPathOutsideRoot(relative_path.to_string())));
+    }
+
+    Ok(root.join(relative))
+}
+
+/// 编译路径 glob 模式
+///
+/// 匹配对象为规范化后的相对路径（使用正斜杠分隔）；`*` 不跨越目录，`**` 递归匹配任意层级
+pub fn compile_path_glob(pattern: &str) -> Result<globset::GlobMatcher> {
+    globset::GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .map(|glob| glob.compile_matcher())
+        .map_err(|e| CodeNexusError::InvalidQuerySyntax(Message::InvalidPathGlob { pattern: pattern.to_string(), error: e.to_string() }))
+}
+
+/// 默认数据目录名称
+pub const DEFAULT_DATA_DIR_NAME: &str = ".codenexus";
+
+/// 项目内数据目录名称的环境变量
+pub const DATA_DIR_NAME_ENV_VAR: &str = "CODE_NEXUS_DATA_DIR_NAME";
+
+/// 共享数据目录位置的环境变量
+pub const DATA_DIR_OVERRIDE_ENV_VAR: &str = "CODE_NEXUS_DATA_DIR";
+
 /// 获取数据存储目录路径
+///
+/// 优先级（从高到低）：
+/// 1. `CODE_NEXUS_DATA_DIR`：所有项目的数据保存在该目录下，每个项目一个子目录（项目目录名加路径哈希）；
+/// 2. `CODE_NEXUS_DATA_DIR_NAME`：项目根目录下的数据目录名称，必须是单个目录名；
+/// 3. 默认的 `.codenexus`。
+///
+/// 环境变量为空时视为未设置。
 pub fn get_data_dir(project_path: &Path) -> PathBuf {
-    project_path.join(".codenexus")
+    let env = |name: &str| std::env::var(name).ok().filter(|value| !value.trim().is_empty());
+    let override_dir = env(DATA_DIR_OVERRIDE_ENV_VAR).map(PathBuf::from);
+    resolve_data_dir(project_path, env(DATA_DIR_NAME_ENV_VAR).as_deref(), override_dir.as_deref())
+}
+
+/// 按给定的目录名称和共享位置计算数据目录，规则见 `get_data_dir`
+pub fn resolve_data_dir(project_path: &Path, dir_name: Option<&str>, override_dir: Option<&Path>) -> PathBuf {
+    if let Some(base) = override_dir {
+        let project_name = project_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "project".to_string());
+        let path_hash = content_hash(project_path.to_string_lossy().as_bytes());
+        return base.join(format!("{}-{}", project_name, &path_hash[..8]));
+    }
+
+    let dir_name = match dir_name {
+        Some(name) if is_single_dir_name(name) => name,
+        Some(name) => {
+            warn!("数据目录名称无效，使用默认的 {}: {:?}", DEFAULT_DATA_DIR_NAME, name);
+            DEFAULT_DATA_DIR_NAME
+        }
+        None => DEFAULT_DATA_DIR_NAME,
+    };
+    project_path.join(dir_name)
+}
+
+/// 名称是否为单个普通路径组件
+fn is_single_dir_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(components.next(), Some(Component::Normal(_))) && components.next().is_none()
+}
+
+/// 扫描项目中的文件，返回相对于项目根目录的路径（已排序）
+///
+/// 遵循 .gitignore 等忽略规则，跳过隐藏文件和数据存储目录；`case_fold` 为 true 时路径转为小写
+pub fn scan_project_files(project_path: &Path, case_fold: bool) -> Result<Vec<String>> {
+    let (files, _) = scan_project_files_with(project_path, case_fold, None, None, |_| true)?;
+    Ok(files)
 }
 
-/// 规范化文件路径（转换为相对于项目根目录的路径）
-pub fn normalize_file_path(project_path: &Path, file_path: &Path) -> Result<String> {
+/// 扫描项目文件（遵循忽略规则），可限定到子目录 `under`，只保留 `keep` 返回 true 的文件
+///
+/// 收集到 `limit` 个文件后再遇到一个符合条件的文件即停止遍历，返回排序后的文件列表
+/// 以及结果是否被截断。目录按文件名顺序遍历，保证截断结果稳定。
+pub fn scan_project_files_with(
+    project_path: &Path,
+    case_fold: bool,
+    under: Option<&str>,
+    limit: Option<usize>,
+    mut keep: impl FnMut(&str) -> bool,
+) -> Result<(Vec<String>, bool)> {
+    let start = match under.map(|dir| dir.trim().trim_matches('/')).filter(|dir| !dir.is_empty()) {
+        Some(dir) => resolve_scan_dir(project_path, dir)?,
+        None => project_path.to_path_buf(),
+    };
+
+    let data_dir = get_data_dir(project_path);
+    let walker = ignore::WalkBuilder::new(&start)
+        .require_git(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(move |entry| entry.path() != data_dir)
+        .build();
+
+    let mut files = Vec::new();
+    let mut truncated = false;
+    for entry in walker {
+        let entry = entry.map_err(|e| CodeNexusError::FileSystemError(format!("扫描项目文件失败: {}", e)))?;
+        if !entry.file_type().is_some_and(|file_type| file_type.is_file()) {
+            continue;
+        }
+        let Ok(relative_path) = entry.path().strip_prefix(project_path) else {
+            continue;
+        };
+        let file_path = fold_path_case(relative_path.to_string_lossy().replace('\\', "/"), case_fold);
+        if !keep(&file_path) {
+            continue;
+        }
+        if limit.is_some_and(|limit| files.len() >= limit) {
+            truncated = true;
+            break;
+        }
+        files.push(file_path);
+    }
+
+    files.sort();
+    debug!("扫描到 {} 个项目文件（截断: {}）: {:?}", files.len(), truncated, start);
+    Ok((files, truncated))
+}
+
+/// 解析扫描的子目录，要求目录存在且位于项目目录内，返回以项目路径为前缀的目录路径
+fn resolve_scan_dir(project_path: &Path, dir: &str) -> Result<PathBuf> {
+    let full_path = project_path.join(dir);
+    if !full_path.is_dir() {
+        return Err(CodeNexusError::FileNotFound(format!("目录不存在: {} (完整路径: {:?})", dir, full_path)));
+    }
+
+    let canonical_dir = full_path
+        .canonicalize()
+        .map_err(|e| CodeNexusError::FileSystemError(format!("无法解析目录路径 {}: {}", dir, e)))?;
+    let canonical_project = project_path
+        .canonicalize()
+        .map_err(|e| CodeNexusError::FileSystemError(format!("无法解析项目路径 {:?}: {}", project_path, e)))?;
+
+    match strip_project_prefix(&canonical_dir, &canonical_project) {
+        Some(relative_dir) => Ok(project_path.join(relative_dir)),
+        None => {
+            warn!("安全警告: 目录路径超出项目范围: {:?}", canonical_dir);
+            Err(CodeNexusError::ConfigError(Message::DirectoryOutsideProject(dir.to_string())))
+        }
+    }
+}
+
+/// 规范化文件路径（转换为相对于项目根目录的路径），`case_fold` 为 true（项目启用 `path_case_fold`）时转为小写
+pub fn normalize_file_path(project_path: &Path, file_path: &Path, case_fold: bool) -> Result<String> {
     let canonical_project = project_path.canonicalize().map_err(|e| {
         CodeNexusError::FileSystemError(format!(
             "无法解析项目路径 {:?}: {}",
@@ -111,11 +333,8 @@ pub fn normalize_file_path(project_path: &Path, file_path: &Path) -> Result<Stri
         ))
     })?;
 
-    let relative_path = canonical_file.strip_prefix(&canonical_project).map_err(|_| {
-        CodeNexusError::ConfigError(format!(
-            "文件路径不在项目目录内: {:?}",
-            file_path
-        ))
+    let relative_path = strip_project_prefix(&canonical_file, &canonical_project).ok_or_else(|| {
+        CodeNexusError::ConfigError(Message::FileOutsideProject(file_path.display().to_string()))
     })?;
 
     // 转换为字符串，使用正斜杠作为分隔符（跨平台兼容）
@@ -123,11 +342,127 @@ pub fn normalize_file_path(project_path: &Path, file_path: &Path) -> Result<Stri
         .to_string_lossy()
         .replace('\\', "/");
 
-    Ok(normalized)
+    Ok(fold_path_case(normalized, case_fold))
+}
+
+/// 规范化文件路径，文件不存在或无法解析真实路径时退化为按词法规范化
+///
+/// 用于只读取元数据的操作（文件可能已从磁盘删除）。词法规范化会消去 `.` 和 `..`，
+/// 拒绝超出项目目录的路径。
+pub fn normalize_file_path_lenient(project_path: &Path, file_path: &str, case_fold: bool) -> Result<String> {
+    if file_path.trim().is_empty() {
+        return Err(CodeNexusError::ConfigError(Message::EmptyFilePath));
+    }
+
+    let full_path = project_path.join(file_path);
+    if full_path.exists() {
+        match normalize_file_path(project_path, &full_path, case_fold) {
+            // 无法解析真实路径（如权限不足）时退化为词法规范化
+            Err(CodeNexusError::FileSystemError(e)) => debug!("无法解析文件路径，按词法规范化: {}", e),
+            result => return result,
+        }
+    }
+
+    let relative_path = if Path::new(file_path).is_absolute() {
+        let canonical_project = project_path.canonicalize().unwrap_or_else(|_| project_path.to_path_buf());
+        Path::new(file_path)
+            .strip_prefix(&canonical_project)
+            .or_else(|_| Path::new(file_path).strip_prefix(project_path))
+            .map_err(|_| CodeNexusError::ConfigError(Message::FileOutsideProject(file_path.to_string())))?
+            .to_path_buf()
+    } else {
+        PathBuf::from(file_path)
+    };
+
+    let mut components: Vec<String> = Vec::new();
+    for component in relative_path.components() {
+        match component {
+            Component::Normal(part) => components.push(part.to_string_lossy().to_string()),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if components.pop().is_none() {
+                    return Err(CodeNexusError::ConfigError(Message::FileOutsideProject(file_path.to_string())));
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(CodeNexusError::ConfigError(Message::FileOutsideProject(file_path.to_string())));
+            }
+        }
+    }
+
+    if components.is_empty() {
+        return Err(CodeNexusError::ConfigError(Message::EmptyFilePath));
+    }
+    Ok(fold_path_case(components.join("/"), case_fold))
+}
+
+/// 计算内容哈希（FNV-1a 64 位），以十六进制字符串表示
+///
+/// 仅用于检测内容是否变化，不具备密码学安全性
+pub fn content_hash(bytes: &[u8]) -> String {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+
+    let hash = bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    });
+    format!("{:016x}", hash)
+}
+
+/// 计算关键词与候选文本的模糊相似度（Jaro-Winkler，大小写不敏感），范围 0.0 ~ 1.0
+pub fn fuzzy_score(keyword: &str, candidate: &str) -> f64 {
+    strsim::jaro_winkler(&keyword.to_lowercase(), &candidate.to_lowercase())
+}
+
+/// 轻量检查 Markdown 内容，返回警告列表（目前检测未闭合的围栏代码块）
+pub fn lint_markdown(text: &str) -> Vec<String> {
+    use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag};
+
+    let mut warnings = Vec::new();
+    for (event, range) in Parser::new(text).into_offset_iter() {
+        if let Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(_))) = event {
+            let block = &text[range.clone()];
+            let opening = block.trim_start();
+            let fence_char = opening.chars().next().unwrap_or('`');
+            let fence_len = opening.chars().take_while(|c| *c == fence_char).count();
+
+            // 未闭合的围栏代码块会一直延续到文档末尾，最后一行不是闭合围栏
+            let mut lines = block.trim_end().lines();
+            lines.next();
+            let closed = lines.last().is_some_and(|line| {
+                let line = line.trim();
+                line.chars().all(|c| c == fence_char) && line.chars().count() >= fence_len
+            });
+            if !closed {
+                let line = text[..range.start].matches('\n').count() + 1;
+                warnings.push(Message::UnclosedCodeBlock(line).localized());
+            }
+        }
+    }
+    warnings
+}
+
+/// 解析时间过滤边界：RFC 3339 时间原样使用；`YYYY-MM-DD` 日期按 UTC 取当天开始，
+/// `end_of_day` 为 true 时取当天结束，使结束日期包含当天全部记录
+pub fn parse_time_bound(value: &str, end_of_day: bool) -> Result<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+        CodeNexusError::ConfigError(Message::InvalidTimeBound(value.to_string()))
+    })?;
+    let time = if end_of_day {
+        date.and_hms_nano_opt(23, 59, 59, 999_999_999)
+    } else {
+        date.and_hms_opt(0, 0, 0)
+    };
+    Ok(time.expect("合法的时分秒").and_utc())
 }
 
 /// 创建项目错误信息
-pub fn project_path_error(message: String) -> CodeNexusError {
+pub fn project_path_error(message: Message) -> CodeNexusError {
     CodeNexusError::ConfigError(message)
 }
 
@@ -155,6 +490,35 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_validate_project_path_expands_variables() {
+        let temp_dir = TempDir::new().unwrap();
+        let expected = temp_dir.path().canonicalize().unwrap();
+
+        // 测试环境变量
+        std::env::set_var("CODENEXUS_TEST_PROJECT_ROOT", temp_dir.path());
+        assert_eq!(validate_project_path("$CODENEXUS_TEST_PROJECT_ROOT").unwrap(), expected);
+        assert_eq!(validate_project_path("${CODENEXUS_TEST_PROJECT_ROOT}/.").unwrap(), expected);
+
+        // 测试 ~
+        if let Some(home) = std::env::var_os("HOME").filter(|home| Path::new(home).is_dir()) {
+            assert_eq!(validate_project_path("~").unwrap(), Path::new(&home).canonicalize().unwrap());
+        }
+
+        // 测试未定义的环境变量
+        let err = validate_project_path("$CODENEXUS_TEST_UNDEFINED_VAR/app").unwrap_err();
+        assert!(matches!(&err, CodeNexusError::ConfigError(Message::UnexpandableProjectPath { var, .. }) if var == "CODENEXUS_TEST_UNDEFINED_VAR"));
+    }
+
+    #[test]
+    fn test_expand_percent_vars() {
+        std::env::set_var("CODENEXUS_TEST_PERCENT_ROOT", "C:\\Users\\dev");
+        assert_eq!(expand_percent_vars("%CODENEXUS_TEST_PERCENT_ROOT%\\proj").unwrap(), "C:\\Users\\dev\\proj");
+        assert_eq!(expand_percent_vars("100%").unwrap(), "100%");
+        assert_eq!(expand_percent_vars("a%%CODENEXUS_TEST_PERCENT_ROOT%").unwrap(), "a%C:\\Users\\dev");
+        assert_eq!(expand_percent_vars("%CODENEXUS_TEST_UNDEFINED_VAR%\\app").unwrap_err(), "CODENEXUS_TEST_UNDEFINED_VAR");
+    }
+
     #[test]
     fn test_validate_file_path() {
         let temp_dir = TempDir::new().unwrap();
@@ -177,6 +541,169 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_strip_project_prefix() {
+        let root = Path::new("/project/app");
+        assert_eq!(strip_project_prefix(Path::new("/project/app/src/a.rs"), root), Some(PathBuf::from("src/a.rs")));
+        assert_eq!(strip_project_prefix(root, root), Some(PathBuf::new()));
+        // 仅前缀字符相同的兄弟目录不算在项目内
+        assert_eq!(strip_project_prefix(Path::new("/project/application/a.rs"), root), None);
+        assert_eq!(strip_project_prefix(Path::new("/project/a.rs"), root), None);
+        assert_eq!(
+            strip_project_prefix(Path::new("/Project/App/a.rs"), root).is_some(),
+            cfg!(any(windows, target_os = "macos"))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_project_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let real_root = temp_dir.path().join("real");
+        fs::create_dir_all(real_root.join("src")).unwrap();
+        fs::write(real_root.join("src/a.rs"), "").unwrap();
+        fs::write(temp_dir.path().join("outside.rs"), "").unwrap();
+        let linked_root = temp_dir.path().join("linked");
+        std::os::unix::fs::symlink(&real_root, &linked_root).unwrap();
+        // 项目内指向项目外的符号链接
+        std::os::unix::fs::symlink(temp_dir.path().join("outside.rs"), real_root.join("escape.rs")).unwrap();
+
+        let full_path = validate_file_path(&linked_root, "src/a.rs").unwrap();
+        assert_eq!(normalize_file_path(&linked_root, &full_path, false).unwrap(), "src/a.rs");
+        assert_eq!(normalize_file_path(&linked_root, &linked_root.join("src/a.rs"), false).unwrap(), "src/a.rs");
+
+        assert!(validate_file_path(&linked_root, "../outside.rs").is_err());
+        assert!(validate_file_path(&linked_root, "src/../../outside.rs").is_err());
+        assert!(validate_file_path(&linked_root, "escape.rs").is_err());
+        assert!(normalize_file_path(&linked_root, &linked_root.join("../outside.rs"), false).is_err());
+    }
+
+    #[test]
+    fn test_resolve_data_dir() {
+        let project = Path::new("/work/app");
+        assert_eq!(resolve_data_dir(project, None, None), PathBuf::from("/work/app/.codenexus"));
+        assert_eq!(resolve_data_dir(project, Some("codenexus-data"), None), PathBuf::from("/work/app/codenexus-data"));
+        assert_eq!(resolve_data_dir(project, Some("../outside"), None), PathBuf::from("/work/app/.codenexus"));
+
+        // 共享位置优先于目录名称，不同项目互不冲突
+        let shared = resolve_data_dir(project, Some("ignored"), Some(Path::new("/var/codenexus")));
+        assert!(shared.starts_with("/var/codenexus"));
+        assert!(shared.file_name().unwrap().to_string_lossy().starts_with("app-"));
+        assert_ne!(shared, resolve_data_dir(Path::new("/other/app"), None, Some(Path::new("/var/codenexus"))));
+    }
+
+    #[test]
+    fn test_resolve_output_path() {
+        let root = Path::new("/project");
+        assert_eq!(resolve_output_path(root, "out/tags.csv").unwrap(), root.join("out/tags.csv"));
+        assert!(resolve_output_path(root, "../tags.csv").is_err());
+        assert!(resolve_output_path(root, "/tmp/tags.csv").is_err());
+        assert!(resolve_output_path(root, "").is_err());
+    }
+
+    #[test]
+    fn test_compile_path_glob() {
+        let matcher = compile_path_glob("src/api/**").unwrap();
+        assert!(matcher.is_match("src/api/user.rs"));
+        assert!(matcher.is_match("src/api/v1/user.rs"));
+        assert!(!matcher.is_match("src/db/user.rs"));
+
+        let matcher = compile_path_glob("src/*.rs").unwrap();
+        assert!(matcher.is_match("src/main.rs"));
+        assert!(!matcher.is_match("src/api/user.rs"));
+
+        assert!(compile_path_glob("src/[").is_err());
+    }
+
+    #[test]
+    fn test_lint_markdown() {
+        assert!(lint_markdown("说明\n\n```rust\nfn main() {}\n```\n").is_empty());
+        assert!(lint_markdown("````\n```\n````").is_empty());
+        assert!(lint_markdown("纯文本注释").is_empty());
+        assert_eq!(lint_markdown("说明\n\n```rust\nfn main() {}\n"), vec!["第 3 行开始的代码块未闭合".to_string()]);
+        assert_eq!(lint_markdown("~~~\n内容").len(), 1);
+    }
+
+    #[test]
+    fn test_scan_project_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::create_dir_all(get_data_dir(root)).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(root.join("src/main.rs"), "").unwrap();
+        fs::write(root.join("README.md"), "").unwrap();
+        fs::write(root.join("debug.log"), "").unwrap();
+        fs::write(root.join("target/out.rs"), "").unwrap();
+        fs::write(get_data_dir(root).join("tags.json"), "{}").unwrap();
+
+        assert_eq!(scan_project_files(root, false).unwrap(), vec!["README.md".to_string(), "src/main.rs".to_string()]);
+    }
+
+    #[test]
+    fn test_scan_project_files_with_limit_and_under() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src/nested")).unwrap();
+        for name in ["a.rs", "b.rs", "c.rs", "nested/d.rs"] {
+            fs::write(root.join("src").join(name), "").unwrap();
+        }
+        fs::write(root.join("README.md"), "").unwrap();
+
+        let (files, truncated) = scan_project_files_with(root, false, Some("src"), None, |_| true).unwrap();
+        assert_eq!(files, vec!["src/a.rs", "src/b.rs", "src/c.rs", "src/nested/d.rs"]);
+        assert!(!truncated);
+
+        let (files, truncated) = scan_project_files_with(root, false, Some("src/"), Some(2), |_| true).unwrap();
+        assert_eq!(files, vec!["src/a.rs", "src/b.rs"]);
+        assert!(truncated);
+
+        let (files, truncated) = scan_project_files_with(root, false, None, Some(1), |path| path == "README.md").unwrap();
+        assert_eq!(files, vec!["README.md"]);
+        assert!(!truncated);
+
+        assert!(scan_project_files_with(root, false, Some("missing"), None, |_| true).is_err());
+        assert!(scan_project_files_with(root, false, Some(".."), None, |_| true).is_err());
+    }
+
+    #[test]
+    fn test_normalize_file_path_lenient() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/main.rs"), "").unwrap();
+
+        assert_eq!(normalize_file_path_lenient(root, "src/main.rs", false).unwrap(), "src/main.rs");
+        // 已删除的文件按词法规范化
+        assert_eq!(normalize_file_path_lenient(root, "./src/old/../removed.rs", false).unwrap(), "src/removed.rs");
+        let absolute = root.canonicalize().unwrap().join("src/removed.rs");
+        assert_eq!(normalize_file_path_lenient(root, absolute.to_str().unwrap(), false).unwrap(), "src/removed.rs");
+        assert!(normalize_file_path_lenient(root, "../outside.rs", false).is_err());
+        assert!(normalize_file_path_lenient(root, "/elsewhere/removed.rs", false).is_err());
+        assert!(normalize_file_path_lenient(root, "", false).is_err());
+
+        // 大小写折叠只作用于启用的调用
+        fs::write(root.join("src/Lib.rs"), "").unwrap();
+        assert_eq!(normalize_file_path_lenient(root, "src/Lib.rs", true).unwrap(), "src/lib.rs");
+        assert_eq!(normalize_file_path_lenient(root, "src/Lib.rs", false).unwrap(), "src/Lib.rs");
+        assert_eq!(normalize_file_path_lenient(root, "src/Removed.rs", true).unwrap(), "src/removed.rs");
+    }
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(content_hash(b""), "cbf29ce484222325");
+        assert_eq!(content_hash(b"tags"), content_hash(b"tags"));
+        assert_ne!(content_hash(b"tags"), content_hash(b"tag"));
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("API", "api"), 1.0);
+        assert!(fuzzy_score("controler", "controller") > 0.9);
+        assert!(fuzzy_score("api", "database") < 0.6);
+    }
+
     #[test]
     fn test_normalize_file_path() {
         let temp_dir = TempDir::new().unwrap();
@@ -187,8 +714,20 @@ mod tests {
         fs::create_dir_all(test_file.parent().unwrap()).unwrap();
         fs::write(&test_file, "fn main() {}").unwrap();
 
-        let result = normalize_file_path(project_path, &test_file);
+        let result = normalize_file_path(project_path, &test_file, false);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), "src/main.rs");
     }
+
+    #[test]
+    fn test_parse_time_bound() {
+        let start = parse_time_bound("2024-03-01", false).unwrap();
+        assert_eq!(start.to_rfc3339(), "2024-03-01T00:00:00+00:00");
+        let end = parse_time_bound("2024-03-01", true).unwrap();
+        assert!(end > start && end < parse_time_bound("2024-03-02", false).unwrap());
+
+        let exact = parse_time_bound("2024-03-01T08:00:00+08:00", true).unwrap();
+        assert_eq!(exact, start);
+        assert!(parse_time_bound("03/01/2024", false).is_err());
+    }
 }
@@ -1,4 +1,9 @@
 use code_nexus::CodeNexusServer;
+use code_nexus::config::ProjectConfig;
+use code_nexus::error::CodeNexusError;
+use code_nexus::mcp::undo::UndoScope;
+use code_nexus::models::{BackupTarget, ImportMode, ProjectExport, Relation};
+use serde_json::json;
 use std::fs;
 use tempfile::TempDir;
 
@@ -30,3 +35,470 @@ async fn test_server_initialization() {
     assert!(data_dir.join("comments.json").exists(), "注释文件应该被创建");
     assert!(data_dir.join("relations.json").exists(), "关联关系文件应该被创建");
 }
+
+#[tokio::test]
+async fn test_invalid_project_path_keeps_error_code() {
+    let temp_dir = TempDir::new().unwrap();
+    let missing = temp_dir.path().join("missing");
+
+    let server = CodeNexusServer::new().await.unwrap();
+    let Err(error) = server.get_or_create_project(missing.to_str().unwrap()).await else {
+        panic!("不存在的项目路径应该创建失败");
+    };
+
+    let body: serde_json::Value =
+        serde_json::from_str(&code_nexus::error::format_error_data_response(&error)).unwrap();
+    assert_eq!(body["error"]["code"], "FILE_NOT_FOUND");
+    assert!(body["error"]["suggestion"].is_string());
+}
+
+#[tokio::test]
+async fn test_list_and_unload_projects() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = temp_dir.path().to_str().unwrap();
+
+    let server = CodeNexusServer::new().await.unwrap();
+    assert!(server.loaded_projects().await.is_empty());
+
+    let canonical = temp_dir.path().canonicalize().unwrap().to_str().unwrap().to_string();
+    let project = server.get_or_create_project(project_path).await.unwrap();
+    assert_eq!(server.loaded_projects().await, vec![canonical]);
+
+    // 同一项目的不同写法共用一个管理器
+    std::fs::create_dir(temp_dir.path().join("sub")).unwrap();
+    std::env::set_var("CODENEXUS_TEST_LOADED_ROOT", temp_dir.path());
+    for alias in [format!("{}/sub/..", project_path), "$CODENEXUS_TEST_LOADED_ROOT".to_string()] {
+        let aliased = server.get_or_create_project(&alias).await.unwrap();
+        assert!(std::sync::Arc::ptr_eq(&project, &aliased), "{} 应该复用已加载的管理器", alias);
+    }
+    assert_eq!(server.loaded_projects().await.len(), 1);
+
+    assert!(server.remove_project("${CODENEXUS_TEST_LOADED_ROOT}/.").await, "已加载的项目应该被卸载");
+    assert!(server.loaded_projects().await.is_empty());
+    assert!(!server.remove_project(project_path).await, "重复卸载应该返回 false");
+}
+
+#[tokio::test]
+async fn test_export_and_import_project() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = temp_dir.path().to_str().unwrap();
+    fs::write(temp_dir.path().join("a.rs"), "").unwrap();
+    fs::write(temp_dir.path().join("b.rs"), "").unwrap();
+
+    let server = CodeNexusServer::new().await.unwrap();
+    let project = server.get_or_create_project(project_path).await.unwrap();
+    let pm = project.lock().await;
+
+    let mut bundle = ProjectExport::default();
+    bundle.tags.insert("a.rs".to_string(), vec!["layer:api".to_string()]);
+    bundle.tags.insert("missing.rs".to_string(), vec!["layer:db".to_string()]);
+    bundle.comments.insert("a.rs".to_string(), "入口文件".to_string());
+    bundle.relations.insert("a.rs".to_string(), vec![Relation {
+        target: "b.rs".to_string(),
+        description: "调用".to_string(),
+        weight: None,
+    }]);
+
+    // 校验文件存在时跳过缺失文件
+    let summary = pm.import_all(bundle.clone(), ImportMode::Merge, true).await.unwrap();
+    assert_eq!((summary.tags, summary.comments, summary.relations), (1, 1, 1));
+    assert_eq!(summary.skipped_files, vec!["missing.rs".to_string()]);
+
+    let exported = pm.export_all().await;
+    assert_eq!(exported.tags.get("a.rs"), Some(&vec!["layer:api".to_string()]));
+    assert_eq!(exported.comments.get("a.rs").map(String::as_str), Some("入口文件"));
+    assert_eq!(exported.relations.get("a.rs").map(Vec::len), Some(1));
+
+    // 导出到数据目录下的文件
+    let path = pm.export_to_file("snapshot.json").await.unwrap();
+    assert!(path.starts_with(temp_dir.path().join(".codenexus/exports")));
+    let from_file: ProjectExport = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+    assert_eq!(from_file.tags, exported.tags);
+    assert!(pm.export_to_file("../tags.json").await.is_err());
+
+    // 序列化时按文件路径排序，多次导出内容一致
+    let mut many = ProjectExport::default();
+    for file in ["c.rs", "a.rs", "e.rs", "b.rs", "d.rs"] {
+        many.comments.insert(file.to_string(), file.to_string());
+    }
+    let json = serde_json::to_string(&many).unwrap();
+    assert!(json.starts_with(r#"{"tags":{},"comments":{"a.rs":"a.rs","b.rs":"b.rs","c.rs""#));
+
+    // 非法标签导致整体拒绝
+    let mut invalid = ProjectExport::default();
+    invalid.tags.insert("b.rs".to_string(), vec!["invalid".to_string()]);
+    assert!(pm.import_all(invalid, ImportMode::Replace, false).await.is_err());
+    assert!(pm.export_all().await.tags.contains_key("a.rs"));
+
+    // 替换模式清空原有数据
+    let mut replacement = ProjectExport::default();
+    replacement.tags.insert("b.rs".to_string(), vec!["layer:db".to_string()]);
+    pm.import_all(replacement, ImportMode::Replace, false).await.unwrap();
+    let exported = pm.export_all().await;
+    assert_eq!(exported.tags.keys().collect::<Vec<_>>(), vec!["b.rs"]);
+    assert!(exported.comments.is_empty());
+    assert!(exported.relations.is_empty());
+}
+
+#[tokio::test]
+async fn test_copy_file_metadata() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = temp_dir.path().to_str().unwrap();
+    for file in ["a.rs", "b.rs", "c.rs"] {
+        fs::write(temp_dir.path().join(file), "").unwrap();
+    }
+
+    let server = CodeNexusServer::new().await.unwrap();
+    let project = server.get_or_create_project(project_path).await.unwrap();
+    let pm = project.lock().await;
+
+    let mut bundle = ProjectExport::default();
+    bundle.tags.insert("a.rs".to_string(), vec!["layer:api".to_string(), "status:done".to_string()]);
+    bundle.tags.insert("b.rs".to_string(), vec!["layer:api".to_string()]);
+    bundle.comments.insert("a.rs".to_string(), "入口文件".to_string());
+    bundle.comments.insert("b.rs".to_string(), "已有注释".to_string());
+    bundle.relations.insert("a.rs".to_string(), vec![
+        Relation { target: "b.rs".to_string(), description: "调用".to_string(), weight: None },
+        Relation { target: "c.rs".to_string(), description: "依赖".to_string(), weight: None },
+    ]);
+    bundle.relations.insert("c.rs".to_string(), vec![
+        Relation { target: "a.rs".to_string(), description: "引用".to_string(), weight: None },
+    ]);
+    pm.import_all(bundle, ImportMode::Replace, false).await.unwrap();
+
+    // 不覆盖已有注释，跳过指向自身的关联，不复制入向关联
+    let summary = pm.copy_metadata("a.rs", "b.rs", false).await.unwrap();
+    assert_eq!((summary.tags, summary.comment, summary.relations), (1, false, 1));
+    let exported = pm.export_all().await;
+    assert_eq!(exported.tags["b.rs"], vec!["layer:api".to_string(), "status:done".to_string()]);
+    assert_eq!(exported.comments["b.rs"], "已有注释");
+    assert_eq!(exported.relations["b.rs"].iter().map(|r| r.target.as_str()).collect::<Vec<_>>(), vec!["c.rs"]);
+    assert!(exported.relations["c.rs"].iter().all(|r| r.target != "b.rs"));
+
+    // 覆盖注释，重复复制不产生新标签和关联
+    let summary = pm.copy_metadata("a.rs", "b.rs", true).await.unwrap();
+    assert_eq!((summary.tags, summary.comment, summary.relations), (0, true, 0));
+    assert_eq!(pm.export_all().await.comments["b.rs"], "入口文件");
+
+    let err = pm.copy_metadata("missing.rs", "b.rs", false).await.unwrap_err();
+    assert_eq!(err.error_code(), "NO_METADATA");
+    let err = pm.copy_metadata("a.rs", "missing.rs", false).await.unwrap_err();
+    assert_eq!(err.error_code(), "FILE_NOT_FOUND");
+}
+
+#[tokio::test]
+async fn test_get_orphan_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = temp_dir.path().to_str().unwrap();
+    for file in ["tagged.rs", "commented.rs", "source.rs", "target.rs", "orphan.rs"] {
+        fs::write(temp_dir.path().join(file), "").unwrap();
+    }
+
+    let server = CodeNexusServer::new().await.unwrap();
+    let project = server.get_or_create_project(project_path).await.unwrap();
+    let pm = project.lock().await;
+
+    let mut bundle = ProjectExport::default();
+    bundle.tags.insert("tagged.rs".to_string(), vec!["layer:api".to_string()]);
+    bundle.comments.insert("commented.rs".to_string(), "只有注释".to_string());
+    bundle.relations.insert("source.rs".to_string(), vec![Relation {
+        target: "target.rs".to_string(),
+        description: "调用".to_string(),
+        weight: None,
+    }]);
+    pm.import_all(bundle, ImportMode::Replace, false).await.unwrap();
+
+    // 数据目录中的文件不计入
+    assert_eq!(pm.get_orphan_files().await.unwrap(), vec!["orphan.rs".to_string()]);
+}
+
+#[tokio::test]
+async fn test_reload_on_external_change() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = temp_dir.path().to_str().unwrap();
+    let data_dir = temp_dir.path().join(".codenexus");
+    fs::create_dir_all(&data_dir).unwrap();
+    fs::write(data_dir.join("config.json"), r#"{"watch_data_files": true}"#).unwrap();
+
+    let server = CodeNexusServer::new().await.unwrap();
+    let project = server.get_or_create_project(project_path).await.unwrap();
+    assert!(project.lock().await.is_watching());
+
+    // 模拟手动编辑或 git 同步
+    fs::write(data_dir.join("tags.json"), r#"{"file_tags": {"a.rs": ["layer:api"]}}"#).unwrap();
+
+    let mut reloaded = false;
+    for _ in 0..50 {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        if project.lock().await.export_all().await.tags.contains_key("a.rs") {
+            reloaded = true;
+            break;
+        }
+    }
+    assert!(reloaded, "外部修改后应自动重新加载标签");
+
+    // 手动重新加载
+    fs::write(data_dir.join("comments.json"), r#"{"file_comments": {"a.rs": "手动添加"}}"#).unwrap();
+    let summary = project.lock().await.reload().await.unwrap();
+    assert_eq!((summary.tagged_files, summary.total_tags, summary.commented_files), (1, 1, 1));
+    assert_eq!(project.lock().await.export_all().await.comments["a.rs"], "手动添加");
+}
+
+#[tokio::test]
+async fn test_rekey_paths() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = temp_dir.path().to_str().unwrap();
+    fs::create_dir_all(temp_dir.path().join("src")).unwrap();
+    fs::write(temp_dir.path().join("src/a.rs"), "").unwrap();
+    fs::write(temp_dir.path().join("src/b.rs"), "").unwrap();
+
+    let server = CodeNexusServer::new().await.unwrap();
+    let project = server.get_or_create_project(project_path).await.unwrap();
+    let pm = project.lock().await;
+
+    // 模拟旧版本或其他机器上写入的非规范路径（导入时会规范化，因此直接写数据文件）
+    let data_dir = temp_dir.path().join(".codenexus");
+    fs::write(data_dir.join("tags.json"), json!({
+        "file_tags": { "src/a.rs": ["layer:api"], "./src/a.rs": ["status:done"] }
+    }).to_string()).unwrap();
+    fs::write(data_dir.join("comments.json"), json!({
+        "file_comments": { "src/a.rs": "规范注释", "src/../src/a.rs": "重复注释" }
+    }).to_string()).unwrap();
+    fs::write(data_dir.join("relations.json"), json!({
+        "file_relations": { "./src/b.rs": [{ "target": "src/./a.rs", "description": "调用" }] }
+    }).to_string()).unwrap();
+    pm.reload().await.unwrap();
+
+    assert_eq!(pm.rekey_paths().await.unwrap(), 4);
+    let exported = pm.export_all().await;
+    assert_eq!(exported.tags.keys().collect::<Vec<_>>(), vec!["src/a.rs"]);
+    assert_eq!(exported.tags["src/a.rs"], vec!["layer:api".to_string(), "status:done".to_string()]);
+    assert_eq!(exported.comments.len(), 1);
+    assert_eq!(exported.comments["src/a.rs"], "规范注释");
+    assert_eq!(exported.relations["src/b.rs"][0].target, "src/a.rs");
+
+    // 再次执行没有变化
+    assert_eq!(pm.rekey_paths().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_undo_last_operation() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = temp_dir.path().to_str().unwrap();
+    for file in ["a.rs", "b.rs"] {
+        fs::write(temp_dir.path().join(file), "").unwrap();
+    }
+
+    let server = CodeNexusServer::new().await.unwrap();
+    let project = server.get_or_create_project(project_path).await.unwrap();
+    let pm = project.lock().await;
+
+    let mut bundle = ProjectExport::default();
+    bundle.tags.insert("a.rs".to_string(), vec!["layer:api".to_string()]);
+    bundle.comments.insert("a.rs".to_string(), "入口文件".to_string());
+    pm.import_all(bundle, ImportMode::Replace, false).await.unwrap();
+    let before = pm.export_all().await;
+
+    let snapshot = pm.snapshot("copy_file_metadata", UndoScope::All).await;
+    pm.copy_metadata("a.rs", "b.rs", false).await.unwrap();
+    pm.push_undo(snapshot).await;
+    assert!(pm.export_all().await.tags.contains_key("b.rs"));
+
+    assert_eq!(pm.undo_last().await.unwrap().as_deref(), Some("copy_file_metadata"));
+    let after = pm.export_all().await;
+    assert_eq!(after.tags, before.tags);
+    assert_eq!(after.comments, before.comments);
+    assert!(pm.undo_last().await.unwrap().is_none());
+
+    // 撤销结果已持久化
+    drop(pm);
+    server.remove_project(project_path).await;
+    let project = server.get_or_create_project(project_path).await.unwrap();
+    assert!(!project.lock().await.export_all().await.tags.contains_key("b.rs"));
+}
+
+#[tokio::test]
+async fn test_set_config() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = temp_dir.path().to_str().unwrap();
+
+    let server = CodeNexusServer::new().await.unwrap();
+    let project = server.get_or_create_project(project_path).await.unwrap();
+    let mut pm = project.lock().await;
+    assert_eq!(*pm.config(), ProjectConfig::default());
+
+    let config = pm.set_config(json!({"max_relations_per_file": 5, "strict_tag_values": true})).await.unwrap();
+    assert_eq!(config.max_relations_per_file, Some(5));
+    assert!(config.strict_tag_values);
+    assert_eq!(config.undo_depth, ProjectConfig::default().undo_depth);
+
+    // 未知字段和无效取值被拒绝，配置保持不变
+    assert!(pm.set_config(json!({"max_relation_per_file": 1})).await.is_err());
+    assert!(pm.set_config(json!({"relation_description_pattern": "["})).await.is_err());
+    assert_eq!(pm.config(), config);
+
+    // 重新加载后保留
+    drop(pm);
+    server.remove_project(project_path).await;
+    let project = server.get_or_create_project(project_path).await.unwrap();
+    assert_eq!(project.lock().await.config(), config);
+}
+
+#[tokio::test]
+async fn test_audit_log() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = temp_dir.path().to_str().unwrap();
+
+    let server = CodeNexusServer::new().await.unwrap();
+    let project = server.get_or_create_project(project_path).await.unwrap();
+    let mut pm = project.lock().await;
+
+    // 未启用时不记录
+    pm.record_audit("add_file_tags", &["a.rs".to_string()], None).await;
+    assert_eq!(pm.get_audit_log(None, None, 0, None).await.unwrap().total, 0);
+
+    pm.set_config(json!({"audit_log": true})).await.unwrap();
+    pm.record_audit("add_file_tags", &["a.rs".to_string()], None).await;
+    let error = CodeNexusError::RelationNotFound { from: "a.rs".to_string(), to: "b.rs".to_string() };
+    pm.record_audit("remove_file_relation", &["a.rs".to_string(), "b.rs".to_string()], Some(&error)).await;
+    assert!(temp_dir.path().join(".codenexus/audit.jsonl").exists());
+
+    let log = pm.get_audit_log(None, None, 0, None).await.unwrap();
+    assert_eq!(log.total, 2);
+    assert_eq!(log.entries[0].operation, "add_file_tags");
+    assert!(log.entries[0].success && log.entries[0].error.is_none());
+    assert_eq!(log.entries[1].files, vec!["a.rs", "b.rs"]);
+    assert!(!log.entries[1].success && log.entries[1].error.is_some());
+
+    // 分页和时间过滤
+    let page = pm.get_audit_log(None, None, 1, Some(1)).await.unwrap();
+    assert_eq!(page.total, 2);
+    assert_eq!(page.entries[0].operation, "remove_file_relation");
+    let future = log.entries[1].timestamp + chrono::Duration::seconds(1);
+    assert_eq!(pm.get_audit_log(Some(future), None, 0, None).await.unwrap().total, 0);
+    assert_eq!(pm.get_audit_log(None, Some(future), 0, None).await.unwrap().total, 2);
+}
+
+#[tokio::test]
+async fn test_corrupt_data_file_recovery() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = temp_dir.path().to_str().unwrap();
+    fs::write(temp_dir.path().join("a.rs"), "").unwrap();
+    let data_dir = temp_dir.path().join(".codenexus");
+
+    let server = CodeNexusServer::new().await.unwrap();
+    {
+        let project = server.get_or_create_project(project_path).await.unwrap();
+        let pm = project.lock().await;
+        let mut bundle = ProjectExport::default();
+        bundle.tags.insert("a.rs".to_string(), vec!["layer:api".to_string()]);
+        bundle.comments.insert("a.rs".to_string(), "入口文件".to_string());
+        pm.import_all(bundle, ImportMode::Merge, false).await.unwrap();
+    }
+    server.remove_project(project_path).await;
+    fs::copy(data_dir.join("tags.json"), data_dir.join("tags.json.bak")).unwrap();
+    fs::write(data_dir.join("tags.json"), "{ broken").unwrap();
+
+    // 其余文件正常加载，损坏的文件被标记
+    let project = server.get_or_create_project(project_path).await.unwrap();
+    let pm = project.lock().await;
+    let exported = pm.export_all().await;
+    assert!(exported.tags.is_empty());
+    assert_eq!(exported.comments.get("a.rs").map(String::as_str), Some("入口文件"));
+    let errors = pm.data_file_errors().await;
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].file, "tags.json");
+    let report = pm.check_integrity(false).await.unwrap();
+    assert!(report.issues.iter().any(|issue| issue.file == "tags.json"));
+
+    // 恢复前写入被拒绝，磁盘上的原文件保持不变
+    let mut bundle = ProjectExport::default();
+    bundle.tags.insert("a.rs".to_string(), vec!["layer:db".to_string()]);
+    let error = pm.import_all(bundle, ImportMode::Merge, false).await.unwrap_err();
+    assert!(matches!(error, CodeNexusError::DataFileUnavailable(_)));
+    assert_eq!(fs::read_to_string(data_dir.join("tags.json")).unwrap(), "{ broken");
+
+    assert!(pm.recover_from_backup(Some("other.json")).await.is_err());
+    assert_eq!(pm.recover_from_backup(None).await.unwrap(), vec!["tags.json"]);
+    assert!(pm.data_file_errors().await.is_empty());
+    assert!(data_dir.join("tags.json.corrupt").exists());
+    assert_eq!(pm.export_all().await.tags.get("a.rs"), Some(&vec!["layer:api".to_string()]));
+}
+
+#[tokio::test]
+async fn test_restore_backup_version() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = temp_dir.path().to_str().unwrap();
+    fs::write(temp_dir.path().join("a.rs"), "").unwrap();
+
+    let server = CodeNexusServer::new().await.unwrap();
+    let project = server.get_or_create_project(project_path).await.unwrap();
+    let mut pm = project.lock().await;
+    pm.set_config(json!({"backup_count": 3})).await.unwrap();
+
+    for comment in ["第一版", "第二版", "错误的修改"] {
+        let mut bundle = ProjectExport::default();
+        bundle.comments.insert("a.rs".to_string(), comment.to_string());
+        pm.import_all(bundle, ImportMode::Merge, false).await.unwrap();
+    }
+
+    let summary = pm.restore_backup(BackupTarget::Comments, 0).await.unwrap();
+    assert_eq!(summary.commented_files, 1);
+    assert_eq!(pm.export_all().await.comments.get("a.rs").map(String::as_str), Some("第二版"));
+
+    // 恢复不会轮换备份，更早的版本仍可用
+    pm.restore_backup(BackupTarget::Comments, 1).await.unwrap();
+    assert_eq!(pm.export_all().await.comments.get("a.rs").map(String::as_str), Some("第一版"));
+    assert!(pm.restore_backup(BackupTarget::Comments, 5).await.is_err());
+}
+
+#[tokio::test]
+async fn test_fold_path_case() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = temp_dir.path().to_str().unwrap();
+
+    let server = CodeNexusServer::new().await.unwrap();
+    let project = server.get_or_create_project(project_path).await.unwrap();
+    let mut pm = project.lock().await;
+
+    let mut bundle = ProjectExport::default();
+    bundle.tags.insert("Src/Main.rs".to_string(), vec!["layer:api".to_string()]);
+    bundle.tags.insert("src/main.rs".to_string(), vec!["layer:core".to_string()]);
+    bundle.comments.insert("Src/Main.rs".to_string(), "旧的大小写".to_string());
+    bundle.comments.insert("src/main.rs".to_string(), "入口文件".to_string());
+    bundle.relations.insert("src/lib.rs".to_string(), vec![Relation {
+        target: "Src/Main.rs".to_string(),
+        description: "调用".to_string(),
+        weight: None,
+    }]);
+    pm.import_all(bundle, ImportMode::Merge, false).await.unwrap();
+
+    // 未启用配置时拒绝折叠
+    assert!(matches!(pm.fold_path_case().await, Err(CodeNexusError::ConfigError(_))));
+
+    pm.set_config(json!({"path_case_fold": true})).await.unwrap();
+    assert_eq!(pm.normalize_path_lenient("Src/Other.rs").unwrap(), "src/other.rs");
+
+    // 设置只作用于本项目
+    let other_dir = TempDir::new().unwrap();
+    let other = server.get_or_create_project(other_dir.path().to_str().unwrap()).await.unwrap();
+    assert_eq!(other.lock().await.normalize_path_lenient("Src/Other.rs").unwrap(), "Src/Other.rs");
+
+    assert_eq!(pm.fold_path_case().await.unwrap(), 1);
+    let exported = pm.export_all().await;
+    assert_eq!(exported.tags.keys().collect::<Vec<_>>(), vec!["src/main.rs"]);
+    assert_eq!(exported.tags["src/main.rs"].len(), 2);
+    assert_eq!(exported.comments["src/main.rs"], "入口文件");
+    assert_eq!(exported.relations["src/lib.rs"][0].target, "src/main.rs");
+
+    pm.set_config(json!({"path_case_fold": false})).await.unwrap();
+    assert_eq!(pm.normalize_path_lenient("Src/Other.rs").unwrap(), "Src/Other.rs");
+}
+
+#[test]
+fn test_output_schemas_match_tools() {
+    let mut tools = CodeNexusServer::tool_names();
+    tools.retain(|tool| tool != "describe_outputs");
+    let described: Vec<String> = code_nexus::mcp::outputs::output_schemas().keys().map(|tool| tool.to_string()).collect();
+    assert_eq!(described, tools, "每个工具都应声明输出结构");
+}